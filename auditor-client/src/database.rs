@@ -9,13 +9,41 @@ use std::fmt;
 use std::path::{Path, PathBuf};
 use std::str::FromStr;
 
-use auditor::domain::{RecordAdd, RecordUpdate};
+use auditor::domain::{Component, MetaMode, RecordAdd, RecordUpdate, ValidMeta, ValidName};
+use chrono::{DateTime, Utc};
 
 use sqlx::{QueryBuilder, Sqlite, SqlitePool, sqlite::SqliteJournalMode};
 
 // See https://docs.rs/sqlx/latest/sqlx/struct.QueryBuilder.html#method.push_bind
 const BULK_SIZE: usize = 16384;
 
+/// Deserializes an update of the "updates" queue.
+///
+/// Updates queued before `meta_mode` was added to [`RecordUpdate`] lack this field and are
+/// applied with [`MetaMode::Merge`], as they were before.
+fn deserialize_update(record: &[u8]) -> RecordUpdate {
+    #[derive(serde::Deserialize)]
+    struct QueuedUpdate {
+        record_id: ValidName,
+        meta: Option<ValidMeta>,
+        components: Vec<Component>,
+        start_time: Option<DateTime<Utc>>,
+        stop_time: DateTime<Utc>,
+    }
+
+    bincode::deserialize::<RecordUpdate>(record).unwrap_or_else(|_| {
+        let update = bincode::deserialize::<QueuedUpdate>(record).unwrap();
+        RecordUpdate {
+            record_id: update.record_id,
+            meta: update.meta,
+            components: update.components,
+            start_time: update.start_time,
+            stop_time: update.stop_time,
+            meta_mode: MetaMode::Merge,
+        }
+    })
+}
+
 fn is_path_valid(path: &Path) -> bool {
     path.to_str().is_some_and(|s| !s.is_empty()) && path.try_exists().is_ok()
 }
@@ -133,6 +161,18 @@ impl Database {
         Ok(())
     }
 
+    /// Delete several records from the "update" queue
+    #[tracing::instrument(name = "Deleting records from database", level = "debug", skip(self))]
+    pub(crate) async fn delete_updates(&self, rowids: &[i64]) -> Result<(), sqlx::Error> {
+        let mut transaction = self.db_pool.begin().await?;
+        for rowid in rowids {
+            sqlx::query!(r#"DELETE FROM updates WHERE rowid=$1"#, rowid)
+                .execute(&mut *transaction)
+                .await?;
+        }
+        transaction.commit().await
+    }
+
     /// Returns all records in the "insert" queue along with their rowids
     #[tracing::instrument(
         name = "Getting insert records from database",
@@ -178,12 +218,7 @@ impl Database {
         .await?;
         let records = rows
             .into_iter()
-            .map(|Row { rowid, record }| {
-                (
-                    rowid,
-                    bincode::deserialize::<RecordUpdate>(&record).unwrap(),
-                )
-            })
+            .map(|Row { rowid, record }| (rowid, deserialize_update(&record)))
             .collect();
         Ok(records)
     }
@@ -211,12 +246,7 @@ impl Database {
         .await?;
         let records = rows
             .into_iter()
-            .map(|Row { rowid, record }| {
-                (
-                    rowid,
-                    bincode::deserialize::<RecordUpdate>(&record).unwrap(),
-                )
-            })
+            .map(|Row { rowid, record }| (rowid, deserialize_update(&record)))
             .collect();
         Ok(records)
     }
@@ -277,6 +307,38 @@ mod tests {
         assert_eq!(Record::from(res), Record::from(rec));
     }
 
+    #[tokio::test]
+    async fn updates_queued_without_meta_mode_are_merged() {
+        #[derive(serde::Serialize)]
+        struct QueuedUpdate<'a> {
+            record_id: &'a ValidName,
+            meta: &'a Option<ValidMeta>,
+            components: &'a Vec<Component>,
+            start_time: &'a Option<DateTime<Utc>>,
+            stop_time: &'a DateTime<Utc>,
+        }
+
+        let db = Database::new("sqlite://:memory:").await.unwrap();
+        let rec: RecordUpdate = record();
+        let queued = bincode::serialize(&QueuedUpdate {
+            record_id: &rec.record_id,
+            meta: &rec.meta,
+            components: &rec.components,
+            start_time: &rec.start_time,
+            stop_time: &rec.stop_time,
+        })
+        .unwrap();
+        sqlx::query("INSERT INTO updates (record) VALUES ($1)")
+            .bind(queued)
+            .execute(&db.db_pool)
+            .await
+            .unwrap();
+
+        let (_, res) = db.get_updates().await.unwrap().pop().unwrap();
+        assert_eq!(res.meta_mode, MetaMode::Merge);
+        assert_eq!(Record::from(res), Record::from(rec));
+    }
+
     #[tokio::test]
     async fn insert_many_get() {
         let db = Database::new("sqlite://:memory:").await.unwrap();
@@ -346,6 +408,27 @@ mod tests {
             .for_each(|(a, b)| assert_eq!(Record::from(a), Record::from(b)));
     }
 
+    #[tokio::test]
+    async fn updates_delete() {
+        let db = Database::new("sqlite://:memory:").await.unwrap();
+        let mut recs: Vec<_> = (0..10).map(|_| record()).collect();
+
+        for r in recs.iter() {
+            db.update(r).await.unwrap()
+        }
+        db.delete_updates(&[2, 5, 6]).await.unwrap();
+        let res = db.get_updates().await.unwrap();
+
+        recs.remove(5);
+        recs.remove(4);
+        recs.remove(1);
+        assert_eq!(res.len(), 7);
+        res.into_iter()
+            .map(|(_, r)| r)
+            .zip(recs)
+            .for_each(|(a, b)| assert_eq!(Record::from(a), Record::from(b)));
+    }
+
     #[tokio::test]
     async fn update_rowid() {
         let db = Database::new("sqlite://:memory:").await.unwrap();
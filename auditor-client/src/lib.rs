@@ -142,12 +142,43 @@
 //! # }
 //! ```
 //!
+//! If a single record already exists, `bulk_insert` rejects all records. Alternatively,
+//! [`AuditorClient::bulk_insert_with_report`] inserts the records individually and reports the
+//! status of each record. Existing records are either skipped or updated:
+//!
+//! ```no_run
+//! # use auditor_client::{AuditorClientBuilder, ClientError};
+//! # use auditor::domain::{InsertStatus, OnConflict, RecordAdd};
+//! # use chrono::{DateTime, TimeZone, Utc};
+//! # use std::collections::HashMap;
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), anyhow::Error> {
+//! # let client = AuditorClientBuilder::new()
+//! #     .address(&"localhost", 8000)
+//! #     .timeout(20)
+//! #     .build()?;
+//! # let start_time: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 1, 1, 0, 0, 0).unwrap();
+//! # let records: Vec<RecordAdd> = (0..5)
+//! #    .map(|i| RecordAdd::new(&format!("record-{}", i), HashMap::new(), vec![], start_time))
+//! #    .collect::<Result<_, _>>()?;
+//! let report = client.bulk_insert_with_report(&records, OnConflict::Skip).await?;
+//! println!(
+//!     "{} records inserted, {} already existed",
+//!     report.count(InsertStatus::Inserted),
+//!     report.count(InsertStatus::Duplicate),
+//! );
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Updating records in Auditor
 //!
 //! Auditor accepts incomplete records. In particular, the stop time can be missing.
 //! These records can be updated at a later time, by adding the same record which includes a stop time.
 //! Note that the `record_id` must match the one already in the database!
-//! Fields other than the stop time cannot be updated.
+//! An update can also correct the start time, add or replace meta keys and add or replace
+//! components (matched by name). Meta keys and components that are not part of the update are
+//! left untouched.
 //!
 //! ```no_run
 //! # use auditor_client::{AuditorClientBuilder, ClientError};
@@ -254,6 +285,7 @@
 //!| `runtime`    | Runtime of the event (in seconds)                                      | `gt`, `gte`, `lt`, `lte`               | `runtime[gt]=<u64>`                        |
 //!| `meta`       | Meta information (<meta_key>, MetaOperator(<meta_value>))              | `c`, `dnc`                             | `meta[<meta_key>][c]=<meta_value>`         |
 //!| `component`  | Component identifier (<component_name>, Operator(<component_amount>))  | `gt`, `gte`, `lt`, `lte`, `equals`     | `component[<component_name>][gt]=<amount>` |
+//!| `score`      | Score of a component (<component_name>, <score_name>, Operator(<value>)) | `gt`, `gte`, `lt`, `lte`, `equals`   | `component[<component_name>][score][<score_name>][gte]=<value>` |
 //!| `sort_by`    | Sort query results (SortBy(<column_name>))                             | `asc`, `desc`                          | `sort_by[desc]=<column_name>`              |
 //!| `limit`      | limit query records (number)                                           |                                        | `limit=5000`                               |
 //!
@@ -262,7 +294,12 @@
 //! contained or is not contained for the specific Metakey.
 //!
 //! Component field can be used to query records by specifying the component name (CPU) and ['Operator'] must be used
-//! to specify the amount.
+//! to specify the amount. A record matches if any of its components with this name satisfies all
+//! operators given for the component. Several components can be queried at once, in which case
+//! a record has to match all of them.
+//!
+//! Scores of a component can be queried with [`ComponentQuery::score_operator`]. The operators
+//! on the amount and on the scores of a component have to be satisfied by the same component.
 //!
 //! To query records based on a range, specify the field with two operators
 //! Either with gt or gte and lt or lte.
@@ -459,6 +496,37 @@
 //! GET records?component[CPU][equals]=count
 //! ```
 //!
+//! Records whose CPUs have a HEPSPEC06 score of at least 10 can be queried with:
+//!
+//! ```no_run
+//! use auditor_client::{QueryBuilder, Operator, ComponentQuery, AuditorClientBuilder, ClientError};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ClientError> {
+//! # let client = AuditorClientBuilder::new()
+//! #     .address(&"localhost", 8000)
+//! #     .timeout(20)
+//! #     .build()?;
+//! let records = QueryBuilder::new()
+//!     .with_component_query(
+//!         ComponentQuery::new().score_operator(
+//!             "CPU".to_string(),
+//!             "HEPSPEC06".to_string(),
+//!             Operator::default().gte(10.0.into()),
+//!         )
+//!     )
+//!     .get(client)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The query string would look like
+//!
+//! ```text
+//! GET records?component[CPU][score][HEPSPEC06][gte]=10
+//! ```
+//!
 //!//! ### Example 6:
 //!
 //! Constructs a QueryBuilder which sorts the record in descending order by stop_time and limits the query results by 500 records
@@ -513,12 +581,139 @@
 //! GET record/record-1
 //! ```
 //!
+//! ## Aggregating resource usage
+//!
+//! Instead of retrieving individual records, the summed usage of all records matching a query
+//! can be computed by Auditor. The records can be grouped by the values of one or more meta keys.
+//! Each [`UsageAggregate`](auditor::domain::UsageAggregate) contains the number of records, the
+//! summed runtime and, per component, the summed amount, the summed amount × runtime and the
+//! summed amount × runtime × score.
+//!
+//! ```no_run
+//! use auditor_client::{QueryBuilder, MetaQuery, MetaOperator, AuditorClientBuilder, ClientError};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ClientError> {
+//! # let client = AuditorClientBuilder::new()
+//! #     .address(&"localhost", 8000)
+//! #     .timeout(20)
+//! #     .build()?;
+//! let usage_per_user = QueryBuilder::new()
+//!     .with_meta_query(
+//!         MetaQuery::new().meta_operator(
+//!             "site_id".to_string(),
+//!             MetaOperator::default().contains("site1".to_string()),
+//!         )
+//!     )
+//!     .aggregate(&["group_id", "user_id"], client)
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The query string would look like
+//!
+//! ```text
+//! GET aggregate?group_by[0]=group_id&group_by[1]=user_id&meta[site_id][c]=site1
+//! ```
+//!
+//! The usage can also be split into hourly, daily or monthly buckets. Records that overlap with
+//! several buckets only contribute the part of their runtime that lies within the respective
+//! bucket.
+//!
+//! ```no_run
+//! use auditor::domain::BucketSize;
+//! use auditor_client::{QueryBuilder, AuditorClientBuilder, ClientError};
+//! use chrono::{TimeZone, Utc};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ClientError> {
+//! # let client = AuditorClientBuilder::new()
+//! #     .address(&"localhost", 8000)
+//! #     .timeout(20)
+//! #     .build()?;
+//! let daily_usage_per_site = QueryBuilder::new()
+//!     .histogram(
+//!         BucketSize::Day,
+//!         Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+//!         Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+//!         &["site_id"],
+//!         client,
+//!     )
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! The query string would look like
+//!
+//! ```text
+//! GET histogram?bucket=day&from=2024-01-01T00%3A00%3A00Z&to=2024-02-01T00%3A00%3A00Z&group_by[0]=site_id
+//! ```
+//!
+//! Occupancy curves can be retrieved with [`QueryBuilder::concurrency`], which sums up the
+//! records that were running at each of the given [`SamplePoints`]. Records without a
+//! `stop_time` are considered to be still running.
+//!
+//! ```no_run
+//! use auditor_client::{QueryBuilder, AuditorClientBuilder, ClientError, SamplePoints};
+//! use chrono::{Duration, TimeZone, Utc};
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ClientError> {
+//! # let client = AuditorClientBuilder::new()
+//! #     .address(&"localhost", 8000)
+//! #     .timeout(20)
+//! #     .build()?;
+//! let running_per_site = QueryBuilder::new()
+//!     .concurrency(
+//!         SamplePoints::Range {
+//!             from: Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+//!             to: Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 0).unwrap(),
+//!             step: Duration::minutes(15),
+//!         },
+//!         &["site_id"],
+//!         client,
+//!     )
+//!     .await?;
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Warning
 //! `equals` operator is only available for querying components. It cannot be used for time based
 //! queries
 //!
 //! If the query is directly appended to the URL, please make sure that the datetime value is urlencoded
 //!
+//! ## Reading records in pages
+//!
+//! Large result sets can be read in pages. If a `limit` is set, the AUDITOR server returns the
+//! cursor of the next page in the `X-Next-Cursor` header, which is passed back with
+//! [`QueryBuilder::after`]. [`AuditorClient::paginate`] follows these cursors automatically and
+//! returns a stream of all records:
+//!
+//! ```no_run
+//! # use auditor_client::{AuditorClientBuilder, ClientError, QueryBuilder, SortBy};
+//! use futures::TryStreamExt;
+//!
+//! # #[tokio::main]
+//! # async fn main() -> Result<(), ClientError> {
+//! # let client = AuditorClientBuilder::new()
+//! #     .address(&"localhost", 8000)
+//! #     .timeout(20)
+//! #     .build()?;
+//! let mut records = QueryBuilder::new()
+//!     .sort_by(SortBy::new().ascending("start_time".to_string()))
+//!     .paginate(1000, client);
+//!
+//! while let Some(record) = records.try_next().await? {
+//!     println!("{}", record.record_id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+//!
 //! ## Checking the health of Auditor
 //!
 //! The health of Auditor can be checked with
@@ -543,13 +738,17 @@
 
 mod constants;
 use auditor::{
-    constants::ERR_RECORD_EXISTS,
-    domain::{Record, RecordAdd, RecordUpdate},
+    constants::{ERR_RECORD_EXISTS, HEADER_NEXT_CURSOR},
+    domain::{
+        ApiVersion, BucketSize, BulkInsertReport, BulkUpdateReport, ConcurrentUsage, DailyUsage,
+        DailyUsageQuery, DeletedRecords, InsertResult, InsertStatus, OnConflict, Record, RecordAdd,
+        RecordFrame, RecordUpdate, UpdateStatus, UsageAggregate, UsageBucket,
+    },
 };
 use constants::ERR_INVALID_TIME_INTERVAL;
 
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, Mutex};
+use std::sync::{Arc, Mutex, OnceLock};
 
 use chrono::{DateTime, Duration, Utc};
 use serde::Serialize;
@@ -560,20 +759,31 @@ use urlencoding::encode;
 mod database;
 use database::Database;
 
+use reqwest::header::{AUTHORIZATION, HeaderMap, HeaderValue};
 use reqwest::{Certificate, Identity};
 use std::fs;
 
-use futures::TryStreamExt;
+use futures::stream::BoxStream;
+use futures::{StreamExt, TryStreamExt};
 use reqwest_streams::*;
 use serde_json::Deserializer;
 use std::io::BufReader;
 
+/// Maximum number of queued updates sent to Auditor in a single request.
+const UPDATE_BATCH_SIZE: usize = 1000;
+
+/// Timeout of subscriptions, which replaces the timeout of the client.
+const SUBSCRIPTION_TIMEOUT: std::time::Duration =
+    std::time::Duration::from_secs(100 * 365 * 24 * 3600);
+
 static APP_USER_AGENT: &str = concat!(env!("CARGO_PKG_NAME"), "/", env!("CARGO_PKG_VERSION"),);
 
 #[derive(Debug, thiserror::Error)]
 #[non_exhaustive]
 pub enum ClientError {
     RecordExists,
+    /// A different record with the same `record_id` is stored. Holds the stored record.
+    RecordConflict(Box<Record>),
     InvalidTimeInterval,
     ReqwestError(reqwest::Error),
     DatabaseError(sqlx::Error),
@@ -587,6 +797,10 @@ impl std::fmt::Display for ClientError {
             "{}",
             match self {
                 ClientError::RecordExists => ERR_RECORD_EXISTS.to_string(),
+                ClientError::RecordConflict(record) => format!(
+                    "A different record with record_id {} exists",
+                    record.record_id
+                ),
                 ClientError::InvalidTimeInterval => ERR_INVALID_TIME_INTERVAL.to_string(),
                 ClientError::ReqwestError(e) => format!("Reqwest Error: {e}"),
                 ClientError::DatabaseError(e) => format!("Database Error: {e}"),
@@ -602,6 +816,12 @@ impl From<reqwest::Error> for ClientError {
     }
 }
 
+impl From<reqwest_streams::error::StreamBodyError> for ClientError {
+    fn from(error: reqwest_streams::error::StreamBodyError) -> Self {
+        ClientError::Other(format!("Incomplete response: {error}"))
+    }
+}
+
 impl From<chrono::OutOfRangeError> for ClientError {
     fn from(_: chrono::OutOfRangeError) -> Self {
         ClientError::InvalidTimeInterval
@@ -620,6 +840,28 @@ impl From<anyhow::Error> for ClientError {
     }
 }
 
+/// Parses a Server-Sent Event of a subscription. Returns the record of `record` events and
+/// `None` for comments.
+fn parse_record_event(event: &str) -> Result<Option<Record>, ClientError> {
+    let mut name = "message";
+    let mut data = vec![];
+    for line in event.lines() {
+        if let Some(value) = line.strip_prefix("event:") {
+            name = value.trim();
+        } else if let Some(value) = line.strip_prefix("data:") {
+            data.push(value.strip_prefix(' ').unwrap_or(value));
+        }
+    }
+    let data = data.join("\n");
+    match name {
+        "record" => serde_json::from_str(&data)
+            .map(Some)
+            .map_err(|e| ClientError::Other(format!("Invalid record in subscription: {e}"))),
+        "error" => Err(ClientError::Other(format!("Subscription ended: {data}"))),
+        _ => Ok(None),
+    }
+}
+
 /// The `AuditorClientBuilder` is used to build an instance of
 /// [`AuditorClient`], [`AuditorClientBlocking`] or [`QueuedAuditorClient`].
 ///
@@ -658,6 +900,7 @@ pub struct AuditorClientBuilder {
     timeout: Duration,
     send_interval: Duration,
     tls_config: Option<TlsConfig>,
+    bearer_token: Option<String>,
 }
 
 impl AuditorClientBuilder {
@@ -669,6 +912,7 @@ impl AuditorClientBuilder {
             timeout: Duration::try_seconds(30).expect("This should never fail"),
             send_interval: Duration::try_seconds(60).expect("This should never fail"),
             tls_config: None,
+            bearer_token: None,
         }
     }
 
@@ -764,12 +1008,37 @@ impl AuditorClientBuilder {
         self
     }
 
+    /// Set an API token which is sent as bearer token with every request.
+    /// This is required if authentication is enabled on the Auditor server.
+    ///
+    /// # Arguments
+    ///
+    /// * `token` - API token.
+    #[must_use]
+    pub fn bearer_token<T: AsRef<str>>(mut self, token: &T) -> Self {
+        self.bearer_token = Some(token.as_ref().into());
+        self
+    }
+
+    /// Returns the headers which are sent with every request.
+    fn default_headers(&self) -> Result<HeaderMap, ClientError> {
+        let mut headers = HeaderMap::new();
+        if let Some(token) = &self.bearer_token {
+            let mut value = HeaderValue::from_str(&format!("Bearer {token}"))
+                .map_err(|e| ClientError::Other(format!("Invalid bearer token: {e}")))?;
+            value.set_sensitive(true);
+            headers.insert(AUTHORIZATION, value);
+        }
+        Ok(headers)
+    }
+
     /// Build an [`AuditorClient`] from `AuditorClientBuilder`.
     ///
     /// # Errors
     ///
     /// * [`ClientError::InvalidTimeInterval`] - If the timeout duration is less than zero.
     /// * [`ClientError::ReqwestError`] - If there was an error building the HTTP client.
+    /// * [`ClientError::Other`] - If the bearer token is not a valid header value.
     pub fn build(self) -> Result<AuditorClient, ClientError> {
         let client = match self.tls_config.clone() {
             Some(tls_config) => reqwest::ClientBuilder::new()
@@ -781,10 +1050,12 @@ impl AuditorClientBuilder {
                         .ca_certificate
                         .expect("Error while setting up the root certificate"),
                 )
+                .default_headers(self.default_headers()?)
                 .timeout(self.timeout.to_std()?)
                 .build()?,
             None => reqwest::ClientBuilder::new()
                 .user_agent(APP_USER_AGENT)
+                .default_headers(self.default_headers()?)
                 .timeout(self.timeout.to_std()?)
                 .build()?,
         };
@@ -803,7 +1074,11 @@ impl AuditorClientBuilder {
             format!("{}://{}", scheme, self.address)
         };
 
-        Ok(AuditorClient { address, client })
+        Ok(AuditorClient {
+            address,
+            client,
+            version: Arc::new(OnceLock::new()),
+        })
     }
 
     /// Build a [`QueuedAuditorClient`] from `AuditorClientBuilder`.
@@ -838,6 +1113,7 @@ impl AuditorClientBuilder {
     ///
     /// * [`ClientError::InvalidTimeInterval`] - If the timeout duration is less than zero.
     /// * [`ClientError::ReqwestError`] - If there was an error building the HTTP client.
+    /// * [`ClientError::Other`] - If the bearer token is not a valid header value.
     ///
     /// # Panics
     ///
@@ -853,10 +1129,12 @@ impl AuditorClientBuilder {
                         .ca_certificate
                         .expect("Error while setting up the root certificate"),
                 )
+                .default_headers(self.default_headers()?)
                 .timeout(self.timeout.to_std()?)
                 .build()?,
             None => reqwest::blocking::ClientBuilder::new()
                 .user_agent(APP_USER_AGENT)
+                .default_headers(self.default_headers()?)
                 .timeout(self.timeout.to_std()?)
                 .build()?,
         };
@@ -940,6 +1218,9 @@ pub struct QueryParameters {
     pub sort_by: Option<SortBy>,
     /// Specifies the number of query records to be returned
     pub limit: Option<u64>,
+    /// Specifies the cursor after which records are returned. Cursors are returned by
+    /// [`AuditorClient::get_page`].
+    pub after: Option<String>,
 }
 
 impl Default for QueryBuilder {
@@ -959,6 +1240,8 @@ pub enum Value {
     Runtime(u64),
     /// Represents a count value
     Count(u8),
+    /// Represents a score value
+    Score(f64),
 }
 
 /// Implementation of the `Serialize` trait for the `Value` enum.
@@ -971,6 +1254,7 @@ impl Serialize for Value {
             Value::Datetime(datetime) => datetime.serialize(serializer),
             Value::Runtime(runtime) => runtime.serialize(serializer),
             Value::Count(count) => count.serialize(serializer),
+            Value::Score(score) => score.serialize(serializer),
         }
     }
 }
@@ -1045,6 +1329,13 @@ impl From<u8> for Value {
     }
 }
 
+/// Conversion from f64 to Value::Score.
+impl From<f64> for Value {
+    fn from(item: f64) -> Self {
+        Value::Score(item)
+    }
+}
+
 /// The `QueryBuilder` is used to construct `QueryParameters` using the builder pattern.
 /// It is used to fetch records using query parameters such as start_time, stop_time etc.
 ///
@@ -1090,6 +1381,7 @@ impl QueryBuilder {
                 component: None,
                 sort_by: None,
                 limit: None,
+                after: None,
             },
         }
     }
@@ -1141,6 +1433,13 @@ impl QueryBuilder {
         self
     }
 
+    /// Only returns the records following the cursor. The cursor must have been returned for a
+    /// query with the same sort order.
+    pub fn after(mut self, cursor: String) -> Self {
+        self.query_params.after = Some(cursor);
+        self
+    }
+
     // Executes an asynchronous query using the built parameters.
     ///
     /// # Arguments
@@ -1156,12 +1455,140 @@ impl QueryBuilder {
         client.advanced_query(query_string).await
     }
 
+    /// Executes an asynchronous query for a single page of records. The size of the page is set
+    /// with [`QueryBuilder::limit`].
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - An instance of the `AuditorClient` used to perform the query.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the page if successful, or a `ClientError` if an error occurs.
+    pub async fn get_page(&self, client: AuditorClient) -> Result<RecordPage, ClientError> {
+        client.get_page(self.build()).await
+    }
+
+    /// Returns a stream of all records matching the built parameters, which are fetched in pages
+    /// of `page_size` records. See [`AuditorClient::paginate`].
+    pub fn paginate(
+        &self,
+        page_size: u64,
+        client: AuditorClient,
+    ) -> BoxStream<'static, Result<Record, ClientError>> {
+        client.paginate(self.clone(), page_size)
+    }
+
+    /// Returns a stream of the records matching the built parameters which are inserted or
+    /// updated from now on. See [`AuditorClient::subscribe`].
+    pub fn subscribe(
+        &self,
+        client: AuditorClient,
+    ) -> BoxStream<'static, Result<Record, ClientError>> {
+        client.subscribe(self.clone())
+    }
+
+    /// Deletes all records matching the built parameters, including records without a stop
+    /// time.
+    ///
+    /// # Arguments
+    ///
+    /// * `client` - An instance of the `AuditorClient` used to perform the deletion.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the number of deleted records if successful, or a `ClientError` if an error occurs.
+    pub async fn delete(&self, client: AuditorClient) -> Result<u64, ClientError> {
+        client.delete_records(self.build()).await
+    }
+
+    /// Executes an asynchronous aggregation of the records matching the built parameters.
+    ///
+    /// Sorting and limits are not supported and are rejected by Auditor.
+    ///
+    /// # Arguments
+    ///
+    /// * `group_by` - Meta keys whose values are used to group the records.
+    /// * `client` - An instance of the `AuditorClient` used to perform the query.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the vector of aggregates if successful, or a `ClientError` if an error occurs.
+    pub async fn aggregate(
+        &self,
+        group_by: &[&str],
+        client: AuditorClient,
+    ) -> Result<Vec<UsageAggregate>, ClientError> {
+        client.aggregate(group_by, self.build()).await
+    }
+
+    /// Executes an asynchronous computation of the usage histogram of the records matching the
+    /// built parameters.
+    ///
+    /// Sorting and limits are not supported and are rejected by Auditor.
+    ///
+    /// # Arguments
+    ///
+    /// * `bucket` - Size of the buckets.
+    /// * `from` - Start of the time range (inclusive).
+    /// * `to` - End of the time range (exclusive).
+    /// * `group_by` - Meta keys whose values are used to group the records within each bucket.
+    /// * `client` - An instance of the `AuditorClient` used to perform the query.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the vector of buckets if successful, or a `ClientError` if an error occurs.
+    pub async fn histogram(
+        &self,
+        bucket: BucketSize,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        group_by: &[&str],
+        client: AuditorClient,
+    ) -> Result<Vec<UsageBucket>, ClientError> {
+        client
+            .histogram(bucket, from, to, group_by, self.build())
+            .await
+    }
+
+    /// Executes an asynchronous computation of the usage of the records matching the built
+    /// parameters that were running at the given sample points.
+    ///
+    /// Sorting and limits are not supported and are rejected by Auditor.
+    ///
+    /// # Arguments
+    ///
+    /// * `samples` - Points in time at which the running records are summed up.
+    /// * `group_by` - Meta keys whose values are used to group the records at each sample point.
+    /// * `client` - An instance of the `AuditorClient` used to perform the query.
+    ///
+    /// # Returns
+    ///
+    /// A `Result` containing the usage per sample point if successful, or a `ClientError` if an error occurs.
+    pub async fn concurrency(
+        &self,
+        samples: SamplePoints,
+        group_by: &[&str],
+        client: AuditorClient,
+    ) -> Result<Vec<ConcurrentUsage>, ClientError> {
+        client.concurrency(samples, group_by, self.build()).await
+    }
+
     /// Builds and returns the serialized query string
     pub fn build(&self) -> String {
         serde_qs::to_string(&self.query_params).expect("Failed to serialize query parameters")
     }
 }
 
+/// A page of records returned by [`AuditorClient::get_page`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct RecordPage {
+    /// Records of the page.
+    pub records: Vec<Record>,
+    /// Cursor pointing at the last record of the page. It is `None` if this is the last page.
+    pub next: Option<String>,
+}
+
 /// The `MetaQuery` struct represents a set of metadata queries associated with specific query IDs
 /// It is used to filter records based on metadata conditions.
 #[derive(serde::Deserialize, Debug, Default, Clone)]
@@ -1250,6 +1677,10 @@ impl MetaOperator {
 pub struct ComponentQuery {
     /// HashMap containing query IDs and corresponding component operators.
     pub component_query: HashMap<String, Option<Operator>>,
+    /// HashMap containing query IDs and the operators on the scores of the component, keyed by
+    /// score name.
+    #[serde(default)]
+    pub score_query: HashMap<String, HashMap<String, Operator>>,
 }
 
 impl ComponentQuery {
@@ -1257,6 +1688,7 @@ impl ComponentQuery {
     pub fn new() -> Self {
         ComponentQuery {
             component_query: HashMap::new(),
+            score_query: HashMap::new(),
         }
     }
 
@@ -1275,6 +1707,25 @@ impl ComponentQuery {
             .insert(query_id.to_string(), Some(operator));
         self
     }
+
+    /// Adds a new operator on a score of the component with the given query ID.
+    ///
+    /// # Arguments
+    ///
+    /// * `query_id` - A unique identifier for the component query.
+    /// * `score` - The name of the score, e.g. `HEPSPEC06`.
+    /// * `operator` - The operator containing conditions on the score value.
+    ///
+    /// # Returns
+    ///
+    /// A new `ComponentQuery` instance with the added score operator.
+    pub fn score_operator(mut self, query_id: String, score: String, operator: Operator) -> Self {
+        self.score_query
+            .entry(query_id)
+            .or_default()
+            .insert(score, operator);
+        self
+    }
 }
 
 /// Implementation of the `Serialize` trait for the `ComponentQuery` struct.
@@ -1284,7 +1735,59 @@ impl Serialize for ComponentQuery {
     where
         S: serde::Serializer,
     {
-        self.component_query.serialize(serializer)
+        use serde::ser::SerializeMap;
+
+        let query_ids: std::collections::BTreeSet<&String> = self
+            .component_query
+            .keys()
+            .chain(self.score_query.keys())
+            .collect();
+        let mut map = serializer.serialize_map(Some(query_ids.len()))?;
+        for query_id in query_ids {
+            map.serialize_entry(
+                query_id,
+                &ComponentFilter {
+                    operator: self.component_query.get(query_id).and_then(Option::as_ref),
+                    score: self.score_query.get(query_id),
+                },
+            )?;
+        }
+        map.end()
+    }
+}
+
+/// Operators on the amount and on the scores of a single component, serialized as
+/// `[gt]=<amount>&[score][<score_name>][gt]=<value>`.
+struct ComponentFilter<'a> {
+    operator: Option<&'a Operator>,
+    score: Option<&'a HashMap<String, Operator>>,
+}
+
+impl Serialize for ComponentFilter<'_> {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        if let Some(operator) = self.operator {
+            for (key, value) in [
+                ("gt", &operator.gt),
+                ("lt", &operator.lt),
+                ("gte", &operator.gte),
+                ("lte", &operator.lte),
+                ("equals", &operator.equals),
+            ] {
+                if let Some(value) = value {
+                    map.serialize_entry(key, value)?;
+                }
+            }
+        }
+        if let Some(score) = self.score {
+            map.serialize_entry("score", score)?;
+        }
+        map.end()
     }
 }
 
@@ -1333,24 +1836,148 @@ impl SortBy {
     }
 }
 
+/// `SamplePoints` specifies the points in time at which the concurrently running records are
+/// summed up with [`AuditorClient::concurrency`].
+#[derive(Debug, Clone, PartialEq)]
+pub enum SamplePoints {
+    /// A single point in time.
+    At(DateTime<Utc>),
+    /// All points in time from `from` to `to` (both inclusive) that are `step` apart.
+    Range {
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        step: Duration,
+    },
+}
+
+impl Serialize for SamplePoints {
+    fn serialize<S>(&self, serializer: S) -> Result<S::Ok, S::Error>
+    where
+        S: serde::Serializer,
+    {
+        use serde::ser::SerializeMap;
+
+        let mut map = serializer.serialize_map(None)?;
+        match self {
+            SamplePoints::At(at) => map.serialize_entry("at", &DateTimeUtcWrapper(*at))?,
+            SamplePoints::Range { from, to, step } => {
+                map.serialize_entry("from", &DateTimeUtcWrapper(*from))?;
+                map.serialize_entry("to", &DateTimeUtcWrapper(*to))?;
+                map.serialize_entry("step", &step.num_seconds())?;
+            }
+        }
+        map.end()
+    }
+}
+
 /// The `AuditorClient` handles the interaction with the Auditor instances and allows one to add
 /// records to the database, update records in the database and retrieve the records from the
 /// database.
 ///
 /// It is constructed using the [`AuditorClientBuilder`].
+///
+/// The version of the REST API is negotiated with the first request: the client uses
+/// [`ApiVersion::V2`] if the server supports it, and the unversioned paths of older servers
+/// otherwise.
 #[derive(Clone)]
 pub struct AuditorClient {
     address: String,
     client: reqwest::Client,
+    version: Arc<OnceLock<ApiVersion>>,
 }
 
 impl AuditorClient {
-    /// Returns ``true`` if the Auditor instance is healthy, ``false`` otherwise.
-    #[tracing::instrument(name = "Checking health of AUDITOR server.", skip(self))]
-    pub async fn health_check(&self) -> bool {
-        match self
-            .client
-            .get(format!("{}/health_check", &self.address))
+    /// Returns the version of the REST API used to talk to the Auditor instance.
+    ///
+    /// The server is asked for [`ApiVersion::V2`] on the first call, the result is remembered
+    /// for all clones of the client.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(name = "Negotiating API version with AUDITOR server.", skip(self))]
+    pub async fn api_version(&self) -> Result<ApiVersion, ClientError> {
+        if let Some(version) = self.version.get() {
+            return Ok(*version);
+        }
+        let response = self
+            .client
+            .get(format!(
+                "{}{}/health_check",
+                &self.address,
+                ApiVersion::V2.prefix()
+            ))
+            .send()
+            .await?;
+        // Servers without versioned paths do not know the route
+        let version = if response.status() == reqwest::StatusCode::NOT_FOUND {
+            ApiVersion::V1
+        } else {
+            response.error_for_status()?;
+            ApiVersion::V2
+        };
+        Ok(*self.version.get_or_init(|| version))
+    }
+
+    /// Returns the URL of `path` in the negotiated version of the API. `V1` uses the unversioned
+    /// paths, which are also understood by servers predating the versioning.
+    async fn url(&self, path: &str) -> Result<String, ClientError> {
+        Ok(match self.api_version().await? {
+            ApiVersion::V1 => format!("{}{}", &self.address, path),
+            version => format!("{}{}{}", &self.address, version.prefix(), path),
+        })
+    }
+
+    /// Gets all records of `url`, which are framed in [`ApiVersion::V2`].
+    async fn get_records(&self, url: String) -> Result<Vec<Record>, ClientError> {
+        if self.api_version().await? == ApiVersion::V1 {
+            let response = self
+                .client
+                .get(url)
+                .send()
+                .await?
+                .error_for_status()?
+                .json_array_stream::<Record>(10000);
+            return Ok(response.try_collect().await?);
+        }
+
+        let mut frames = self
+            .client
+            .get(url)
+            .header("Accept", "application/x-ndjson")
+            .send()
+            .await?
+            .error_for_status()?
+            .json_nl_stream::<RecordFrame>(10000);
+        let mut records = vec![];
+        while let Some(frame) = frames.try_next().await? {
+            match frame {
+                RecordFrame::Record(record) => records.push(*record),
+                RecordFrame::End { count } if count == records.len() as u64 => {
+                    return Ok(records);
+                }
+                RecordFrame::End { count } => {
+                    return Err(ClientError::Other(format!(
+                        "Incomplete response: expected {count} records, got {}",
+                        records.len()
+                    )));
+                }
+                RecordFrame::Error(error) => {
+                    return Err(ClientError::Other(format!("Incomplete response: {error}")));
+                }
+            }
+        }
+        Err(ClientError::Other(
+            "Incomplete response: the stream ended without end frame".to_string(),
+        ))
+    }
+
+    /// Returns ``true`` if the Auditor instance is healthy, ``false`` otherwise.
+    #[tracing::instrument(name = "Checking health of AUDITOR server.", skip(self))]
+    pub async fn health_check(&self) -> bool {
+        match self
+            .client
+            .get(format!("{}/health_check", &self.address))
             .send()
             .await
         {
@@ -1374,12 +2001,19 @@ impl AuditorClient {
     pub async fn add(&self, record: &RecordAdd) -> Result<(), ClientError> {
         let response = self
             .client
-            .post(format!("{}/record", &self.address))
+            .post(self.url("/record").await?)
             .header("Content-Type", "application/json")
             .json(record)
             .send()
             .await?;
 
+        if self.api_version().await? >= ApiVersion::V2 {
+            if response.status() == reqwest::StatusCode::CONFLICT {
+                return Err(ClientError::RecordExists);
+            }
+            response.error_for_status()?;
+            return Ok(());
+        }
         if response.text().await? == ERR_RECORD_EXISTS {
             Err(ClientError::RecordExists)
         } else {
@@ -1387,6 +2021,45 @@ impl AuditorClient {
         }
     }
 
+    /// Push a record to the Auditor instance, tolerating records which already exist.
+    ///
+    /// Adding a record which is identical to the stored one succeeds and returns
+    /// [`InsertStatus::Duplicate`], so that sending a record again is safe. If the stored record
+    /// differs, it is updated if `on_conflict` is [`OnConflict::Update`].
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::RecordConflict`] - If a different record with the same `record_id`
+    ///   exists and `on_conflict` is [`OnConflict::Skip`].
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(
+        name = "Sending a record idempotently to AUDITOR server.",
+        skip(self, record),
+        fields(record_id = %record.record_id),
+        level = "debug"
+    )]
+    pub async fn add_idempotent(
+        &self,
+        record: &RecordAdd,
+        on_conflict: OnConflict,
+    ) -> Result<InsertStatus, ClientError> {
+        let response = self
+            .client
+            .post(self.url("/record").await?)
+            .query(&[("on_conflict", on_conflict.as_str())])
+            .header("Content-Type", "application/json")
+            .json(record)
+            .send()
+            .await?;
+
+        if response.status() == reqwest::StatusCode::CONFLICT {
+            let stored: Record = response.json().await?;
+            return Err(ClientError::RecordConflict(Box::new(stored)));
+        }
+        let result: InsertResult = response.error_for_status()?.json().await?;
+        Ok(result.status)
+    }
+
     /// Push multiple record to the Auditor instance as a vec.
     ///
     /// # Errors
@@ -1400,12 +2073,19 @@ impl AuditorClient {
     pub async fn bulk_insert(&self, records: &Vec<RecordAdd>) -> Result<(), ClientError> {
         let response = self
             .client
-            .post(format!("{}/records", &self.address))
+            .post(self.url("/records").await?)
             .header("Content-Type", "application/json")
             .json(records)
             .send()
             .await?;
 
+        if self.api_version().await? >= ApiVersion::V2 {
+            if response.status() == reqwest::StatusCode::CONFLICT {
+                return Err(ClientError::RecordExists);
+            }
+            response.error_for_status()?;
+            return Ok(());
+        }
         if response.text().await? == ERR_RECORD_EXISTS {
             Err(ClientError::RecordExists)
         } else {
@@ -1413,6 +2093,37 @@ impl AuditorClient {
         }
     }
 
+    /// Push multiple records to the Auditor instance and report the status of each record.
+    ///
+    /// Records whose `record_id` already exists are skipped or update the stored record,
+    /// depending on `on_conflict`, instead of rejecting all records.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(
+        name = "Sending multiple records to AUDITOR server with status report.",
+        skip(self, records)
+    )]
+    pub async fn bulk_insert_with_report(
+        &self,
+        records: &[RecordAdd],
+        on_conflict: OnConflict,
+    ) -> Result<BulkInsertReport, ClientError> {
+        let report = self
+            .client
+            .post(self.url("/records").await?)
+            .query(&[("on_conflict", on_conflict.as_str())])
+            .header("Content-Type", "application/json")
+            .json(records)
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(report)
+    }
+
     /// Update an existing record in the Auditor instance.
     ///
     ///
@@ -1426,7 +2137,7 @@ impl AuditorClient {
     )]
     pub async fn update(&self, record: &RecordUpdate) -> Result<(), ClientError> {
         self.client
-            .put(format!("{}/record", &self.address))
+            .put(self.url("/record").await?)
             .header("Content-Type", "application/json")
             .json(record)
             .send()
@@ -1435,25 +2146,83 @@ impl AuditorClient {
         Ok(())
     }
 
-    /// Gets all records from the Auditors database.
+    /// Update several existing records in the Auditor instance at once.
+    ///
+    /// The updates are applied in a single transaction in the order of `records`. Updates of
+    /// records which do not exist are reported as [`UpdateStatus::Unknown`] and do not affect the
+    /// other updates.
     ///
     /// # Errors
     ///
     /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
-    #[tracing::instrument(name = "Getting all records from AUDITOR server.", skip(self))]
-    pub async fn get(&self) -> Result<Vec<Record>, ClientError> {
-        let response = self
+    #[tracing::instrument(
+        name = "Sending multiple record updates to AUDITOR server.",
+        skip(self, records)
+    )]
+    pub async fn bulk_update(
+        &self,
+        records: &[RecordUpdate],
+    ) -> Result<BulkUpdateReport, ClientError> {
+        let report = self
             .client
-            .get(format!("{}/records", &self.address))
+            .put(self.url("/records").await?)
+            .header("Content-Type", "application/json")
+            .json(records)
             .send()
             .await?
             .error_for_status()?
-            .json_array_stream::<Record>(10000);
-        let records: Vec<Record> = response
-            .try_collect()
-            .await
-            .expect("Unexpected error while processing the stream");
-        Ok(records)
+            .json()
+            .await?;
+        Ok(report)
+    }
+
+    /// Delete the record with `record_id` from the Auditor instance.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request, or if
+    ///   the record does not exist.
+    #[tracing::instrument(name = "Deleting a record from AUDITOR server.", skip(self))]
+    pub async fn delete(&self, record_id: &str) -> Result<(), ClientError> {
+        let url = self.url("/record").await?;
+        self.client
+            .delete(format!("{url}/{}", encode(record_id)))
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+
+    /// Delete all records matching `query_string` from the Auditor instance, including records
+    /// without a stop time. Returns the number of deleted records.
+    ///
+    /// Auditor refuses to delete records if `query_string` does not contain any filter.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(name = "Deleting records from AUDITOR server.", skip(self))]
+    pub async fn delete_records(&self, query_string: String) -> Result<u64, ClientError> {
+        let deleted: DeletedRecords = self
+            .client
+            .delete(format!("{}?{}", self.url("/records").await?, query_string))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?;
+        Ok(deleted.deleted)
+    }
+
+    /// Gets all records from the Auditors database.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    /// * [`ClientError::Other`] - If the response is incomplete, e.g. because the server aborted it.
+    #[tracing::instrument(name = "Getting all records from AUDITOR server.", skip(self))]
+    pub async fn get(&self) -> Result<Vec<Record>, ClientError> {
+        self.get_records(self.url("/records").await?).await
     }
 
     /// Get all records in the database with a started timestamp after ``since``.
@@ -1461,6 +2230,7 @@ impl AuditorClient {
     /// # Errors
     ///
     /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    /// * [`ClientError::Other`] - If the response is incomplete, e.g. because the server aborted it.
     #[tracing::instrument(
         name = "Getting all records started since a given date from AUDITOR server.",
         skip(self),
@@ -1474,21 +2244,9 @@ impl AuditorClient {
         dbg!(since.to_rfc3339());
         let since_str = since.to_rfc3339();
         let encoded_since = encode(&since_str);
-        let response = self
-            .client
-            .get(format!(
-                "{}/records?start_time[gte]={}",
-                &self.address, encoded_since
-            ))
-            .send()
-            .await?
-            .error_for_status()?
-            .json_array_stream::<Record>(10000);
-        let records: Vec<Record> = response
-            .try_collect()
+        let url = self.url("/records").await?;
+        self.get_records(format!("{url}?start_time[gte]={encoded_since}"))
             .await
-            .expect("Unexpected error while processing the stream");
-        Ok(records)
     }
 
     /// Get all records in the database with a stopped timestamp after ``since``.
@@ -1496,6 +2254,7 @@ impl AuditorClient {
     /// # Errors
     ///
     /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    /// * [`ClientError::Other`] - If the response is incomplete, e.g. because the server aborted it.
     #[tracing::instrument(
         name = "Getting all records stopped since a given date from AUDITOR server.",
         skip(self),
@@ -1508,21 +2267,9 @@ impl AuditorClient {
     ) -> Result<Vec<Record>, ClientError> {
         let since_str = since.to_rfc3339();
         let encoded_since = encode(&since_str);
-        let response = self
-            .client
-            .get(format!(
-                "{}/records?stop_time[gte]={}",
-                &self.address, encoded_since
-            ))
-            .send()
-            .await?
-            .error_for_status()?
-            .json_array_stream::<Record>(10000);
-        let records: Vec<Record> = response
-            .try_collect()
+        let url = self.url("/records").await?;
+        self.get_records(format!("{url}?stop_time[gte]={encoded_since}"))
             .await
-            .expect("Unexpected error while processing the stream");
-        Ok(records)
     }
 
     /// Get records from AUDITOR server using custom query.
@@ -1530,94 +2277,331 @@ impl AuditorClient {
     /// # Errors
     ///
     /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    /// * [`ClientError::Other`] - If the response is incomplete, e.g. because the server aborted it.
     #[tracing::instrument(
         name = "Getting records from AUDITOR server using custom query",
         skip(self)
     )]
     pub async fn advanced_query(&self, query_string: String) -> Result<Vec<Record>, ClientError> {
+        let url = self.url("/records").await?;
+        self.get_records(format!("{url}?{query_string}")).await
+    }
+
+    /// Get a single page of records from AUDITOR server using custom query. The query has to
+    /// contain a `limit`, which is the size of the page.
+    ///
+    /// The returned cursor can be passed to [`QueryBuilder::after`] to get the next page.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(name = "Getting a page of records from AUDITOR server", skip(self))]
+    pub async fn get_page(&self, query_string: String) -> Result<RecordPage, ClientError> {
         let response = self
             .client
-            .get(format!("{}/records?{}", &self.address, query_string))
+            .get(format!("{}?{}", self.url("/records").await?, query_string))
             .send()
             .await?
-            .error_for_status()?
-            .json_array_stream::<Record>(10000);
-        let records: Vec<Record> = response
-            .try_collect()
-            .await
-            .expect("Unexpected error while processing the stream");
-        Ok(records)
+            .error_for_status()?;
+        let next = response
+            .headers()
+            .get(HEADER_NEXT_CURSOR)
+            .and_then(|cursor| cursor.to_str().ok())
+            .map(String::from);
+        let records = response.json().await?;
+        Ok(RecordPage { records, next })
     }
 
-    /// Get single record from AUDITOR server using record_id.
+    /// Returns a stream of all records matching `query`. The records are fetched in pages of
+    /// `page_size` records, and the cursor of each page is followed automatically until the last
+    /// page has been read.
+    ///
+    /// Unlike [`AuditorClient::advanced_query`], only a single page of records is held in memory.
+    /// Any `limit` set in `query` is replaced by `page_size`.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending one of the HTTP requests.
+    ///   The stream ends after the first error.
+    pub fn paginate(
+        &self,
+        query: QueryBuilder,
+        page_size: u64,
+    ) -> BoxStream<'static, Result<Record, ClientError>> {
+        let client = self.clone();
+        futures::stream::try_unfold(Some(query.limit(page_size)), move |query| {
+            let client = client.clone();
+            async move {
+                let Some(query) = query else {
+                    return Ok(None);
+                };
+                let page = client.get_page(query.build()).await?;
+                let next = page.next.map(|cursor| query.after(cursor));
+                Ok::<_, ClientError>(Some((page.records, next)))
+            }
+        })
+        .map_ok(|records| futures::stream::iter(records.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
+
+    /// Returns a stream of the records matching `query` which are inserted or updated from now
+    /// on. The records are pushed by the server as soon as they are stored.
+    ///
+    /// The subscription is not affected by the timeout of the client and lasts until the stream
+    /// is dropped. Sorting and pagination options of `query` are not supported.
     ///
     /// # Errors
     ///
     /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
-    #[tracing::instrument(
-        name = "Getting a single record from AUDITOR server using record_id",
-        skip(self)
-    )]
-    pub async fn get_single_record(&self, record_id: String) -> Result<Record, ClientError> {
+    /// * [`ClientError::Other`] - If the server reports that changes might have been missed. The
+    ///   stream ends after the first error. Missed records can be queried with
+    ///   [`AuditorClient::advanced_query`] before subscribing again.
+    pub fn subscribe(
+        &self,
+        query: QueryBuilder,
+    ) -> BoxStream<'static, Result<Record, ClientError>> {
+        let client = self.clone();
+        async_stream::try_stream! {
+            let url = client.url("/records/subscribe").await?;
+            let request = client
+                .client
+                .get(format!("{url}?{}", query.build()))
+                .timeout(SUBSCRIPTION_TIMEOUT);
+            let mut body = request.send().await?.error_for_status()?.bytes_stream();
+            let mut buffer = Vec::new();
+            while let Some(chunk) = body.next().await {
+                buffer.extend_from_slice(&chunk?);
+                while let Some(end) = buffer.windows(2).position(|w| w == b"\n\n") {
+                    let event: Vec<u8> = buffer.drain(..end + 2).collect();
+                    if let Some(record) = parse_record_event(&String::from_utf8_lossy(&event))? {
+                        yield record;
+                    }
+                }
+            }
+        }
+        .boxed()
+    }
+
+    /// Get the summed resource usage of the records matching `query_string`, grouped by the
+    /// values of the meta keys in `group_by`.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(name = "Getting aggregated records from AUDITOR server", skip(self))]
+    pub async fn aggregate(
+        &self,
+        group_by: &[&str],
+        query_string: String,
+    ) -> Result<Vec<UsageAggregate>, ClientError> {
+        let group_by = serde_qs::to_string(&HashMap::from([("group_by", group_by)]))
+            .map_err(|e| ClientError::Other(e.to_string()))?;
+        let query_string = [group_by, query_string]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("&");
         Ok(self
             .client
-            .get(format!("{}/record/{}", &self.address, record_id))
+            .get(format!(
+                "{}?{}",
+                self.url("/aggregate").await?,
+                query_string
+            ))
             .send()
             .await?
             .error_for_status()?
             .json()
             .await?)
     }
-}
 
-/// The `QueuedAuditorClient` handles the interaction with the Auditor instances. All
-/// data to be sent is transparently saved in a persistent local database.
-///
-/// It is constructed using [`AuditorClientBuilder::build_queued`] and provides the same
-/// interface as [`AuditorClient`].
-///
-/// When records are sent to Auditor, this client will transparently buffer them in a
-/// (persistent) local database.
-/// A background task will then periodically send records from the local database to
-/// Auditor, deleting them from the local database only after they have been successfully
-/// send to Auditor.
-///
-/// # Notes
-/// There are some quirks that need to be observed when using this client:
-/// - Since sending and updating records is delayed, there is no guarantee that a record
-///   can be retrieved from Auditor right after it has been "sent" by this client.
-/// - The background task of this client should be stopped by invoking [`QueuedAuditorClient::stop`]
-///   before the client is dropped.
-/// - Since methods for sending records like `QueuedAuditorClient::add` only push the records to
-///   the local queue, they can only ever raise database errors.
-///   Errors like `ClientError::ReqwestError` or `ClientError::RecordExists` can only be triggered
-///   by the background send task and will be logged.
-///
-/// # Examples
-/// ```
-/// # use auditor_client::{AuditorClientBuilder, ClientError};
-/// # use auditor::domain::{RecordAdd, RecordTest};
-/// #
-/// # async fn foo() -> Result<(), ClientError> {
-/// # let record = RecordAdd::try_from(RecordTest::default()).unwrap();
-/// let mut client = AuditorClientBuilder::new()
-///     .address(&"localhost", 8000)
-///     .database_path("sqlite://:memory:")
-///     .send_interval(60)
-///     .build_queued()
-///     .await?;
-/// client.add(&record).await?;
-/// client.stop().await?;
-/// # Ok(())
-/// # }
-/// ```
-#[derive(Clone)]
-pub struct QueuedAuditorClient {
-    database: Database,
-    client: AuditorClient,
-    shutdown_tx: Arc<Mutex<Option<oneshot::Sender<()>>>>,
-    task_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
-}
+    /// Get the summed resource usage of the records matching `query_string` in buckets of size
+    /// `bucket` between `from` and `to`, grouped by the values of the meta keys in `group_by`.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(name = "Getting usage histogram from AUDITOR server", skip(self))]
+    pub async fn histogram(
+        &self,
+        bucket: BucketSize,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        group_by: &[&str],
+        query_string: String,
+    ) -> Result<Vec<UsageBucket>, ClientError> {
+        #[derive(Serialize)]
+        struct HistogramParameters<'a> {
+            bucket: BucketSize,
+            from: DateTime<Utc>,
+            to: DateTime<Utc>,
+            group_by: &'a [&'a str],
+        }
+
+        let parameters = serde_qs::to_string(&HistogramParameters {
+            bucket,
+            from,
+            to,
+            group_by,
+        })
+        .map_err(|e| ClientError::Other(e.to_string()))?;
+        let query_string = [parameters, query_string]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("&");
+        Ok(self
+            .client
+            .get(format!(
+                "{}?{}",
+                self.url("/histogram").await?,
+                query_string
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Get the summed resource usage of the records matching `query_string` that were running at
+    /// each of the `samples`, grouped by the values of the meta keys in `group_by`.
+    ///
+    /// A record is running at time `t` if `start_time <= t < stop_time`. Records without a
+    /// `stop_time` are considered to be still running.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(name = "Getting concurrent usage from AUDITOR server", skip(self))]
+    pub async fn concurrency(
+        &self,
+        samples: SamplePoints,
+        group_by: &[&str],
+        query_string: String,
+    ) -> Result<Vec<ConcurrentUsage>, ClientError> {
+        let samples =
+            serde_qs::to_string(&samples).map_err(|e| ClientError::Other(e.to_string()))?;
+        let group_by = serde_qs::to_string(&HashMap::from([("group_by", group_by)]))
+            .map_err(|e| ClientError::Other(e.to_string()))?;
+        let query_string = [samples, group_by, query_string]
+            .into_iter()
+            .filter(|s| !s.is_empty())
+            .collect::<Vec<_>>()
+            .join("&");
+        Ok(self
+            .client
+            .get(format!(
+                "{}?{}",
+                self.url("/concurrency").await?,
+                query_string
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Get the daily usage summary matching `query`, which is maintained by the server and
+    /// much cheaper to query than aggregating the records.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(name = "Getting daily usage from AUDITOR server", skip(self))]
+    pub async fn daily_usage(
+        &self,
+        query: &DailyUsageQuery,
+    ) -> Result<Vec<DailyUsage>, ClientError> {
+        let query_string =
+            serde_qs::to_string(query).map_err(|e| ClientError::Other(e.to_string()))?;
+        Ok(self
+            .client
+            .get(format!(
+                "{}?{}",
+                self.url("/daily_usage").await?,
+                query_string
+            ))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+
+    /// Get single record from AUDITOR server using record_id.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request, or if
+    ///   the record does not exist.
+    #[tracing::instrument(
+        name = "Getting a single record from AUDITOR server using record_id",
+        skip(self)
+    )]
+    pub async fn get_single_record(&self, record_id: String) -> Result<Record, ClientError> {
+        let url = self.url("/record").await?;
+        Ok(self
+            .client
+            .get(format!("{url}/{record_id}"))
+            .send()
+            .await?
+            .error_for_status()?
+            .json()
+            .await?)
+    }
+}
+
+/// The `QueuedAuditorClient` handles the interaction with the Auditor instances. All
+/// data to be sent is transparently saved in a persistent local database.
+///
+/// It is constructed using [`AuditorClientBuilder::build_queued`] and provides the same
+/// interface as [`AuditorClient`].
+///
+/// When records are sent to Auditor, this client will transparently buffer them in a
+/// (persistent) local database.
+/// A background task will then periodically send records from the local database to
+/// Auditor, deleting them from the local database only after they have been successfully
+/// send to Auditor.
+///
+/// # Notes
+/// There are some quirks that need to be observed when using this client:
+/// - Since sending and updating records is delayed, there is no guarantee that a record
+///   can be retrieved from Auditor right after it has been "sent" by this client.
+/// - The background task of this client should be stopped by invoking [`QueuedAuditorClient::stop`]
+///   before the client is dropped.
+/// - Since methods for sending records like `QueuedAuditorClient::add` only push the records to
+///   the local queue, they can only ever raise database errors.
+///   Errors like `ClientError::ReqwestError` or `ClientError::RecordExists` can only be triggered
+///   by the background send task and will be logged.
+///
+/// # Examples
+/// ```
+/// # use auditor_client::{AuditorClientBuilder, ClientError};
+/// # use auditor::domain::{RecordAdd, RecordTest};
+/// #
+/// # async fn foo() -> Result<(), ClientError> {
+/// # let record = RecordAdd::try_from(RecordTest::default()).unwrap();
+/// let mut client = AuditorClientBuilder::new()
+///     .address(&"localhost", 8000)
+///     .database_path("sqlite://:memory:")
+///     .send_interval(60)
+///     .build_queued()
+///     .await?;
+/// client.add(&record).await?;
+/// client.stop().await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct QueuedAuditorClient {
+    database: Database,
+    client: AuditorClient,
+    shutdown_tx: Arc<Mutex<Option<oneshot::Sender<()>>>>,
+    task_handle: Arc<Mutex<Option<tokio::task::JoinHandle<()>>>>,
+}
 
 impl QueuedAuditorClient {
     /// Constructs the `QueuedAuditorClient` and starts the background send task
@@ -1675,18 +2659,38 @@ impl QueuedAuditorClient {
 
         // Send updates
         if let Some(maxid) = update_rowid {
-            let updates = database.get_updates().await?;
-            for (rowid, u) in updates {
-                if rowid > maxid {
-                    continue;
-                };
-                match client.update(&u).await {
-                    Ok(_) => {
-                        tracing::info!("Successfully updated record {}", u.record_id);
-                        database.delete_update(rowid).await?;
+            let updates = database.get_updates_le(maxid).await?;
+            for batch in updates.chunks(UPDATE_BATCH_SIZE) {
+                let records: Vec<RecordUpdate> = batch.iter().map(|(_, u)| u.clone()).collect();
+                let report = match client.bulk_update(&records).await {
+                    Ok(report) => report,
+                    // Servers without bulk updates only accept updates one by one
+                    Err(ClientError::ReqwestError(e))
+                        if e.status() == Some(reqwest::StatusCode::METHOD_NOT_ALLOWED) =>
+                    {
+                        for (rowid, u) in batch {
+                            client.update(u).await?;
+                            tracing::info!("Successfully updated record {}", u.record_id);
+                            database.delete_update(*rowid).await?;
+                        }
+                        continue;
                     }
                     Err(e) => return Err(e),
+                };
+
+                let mut updated = vec![];
+                for ((rowid, u), result) in batch.iter().zip(report.records) {
+                    match result.status {
+                        UpdateStatus::Updated => updated.push(*rowid),
+                        // Kept in the queue in case the record is added later
+                        UpdateStatus::Unknown => tracing::warn!(
+                            "Failed updating record {}. Record does not exist.",
+                            u.record_id
+                        ),
+                    }
                 }
+                database.delete_updates(&updated).await?;
+                tracing::info!("Successfully updated {} records", updated.len());
             }
         };
         Ok(())
@@ -1764,6 +2768,17 @@ impl QueuedAuditorClient {
         Ok(())
     }
 
+    /// Same as [`AuditorClient::delete`]. The request is sent immediately and is not queued.
+    pub async fn delete(&self, record_id: &str) -> Result<(), ClientError> {
+        self.client.delete(record_id).await
+    }
+
+    /// Same as [`AuditorClient::delete_records`]. The request is sent immediately and is not
+    /// queued.
+    pub async fn delete_records(&self, query_string: String) -> Result<u64, ClientError> {
+        self.client.delete_records(query_string).await
+    }
+
     /// Same as [`AuditorClient::get`]
     pub async fn get(&self) -> Result<Vec<Record>, ClientError> {
         self.client.get().await
@@ -1774,6 +2789,63 @@ impl QueuedAuditorClient {
         self.client.advanced_query(query_string).await
     }
 
+    /// Same as [`AuditorClient::get_page`]
+    pub async fn get_page(&self, query_string: String) -> Result<RecordPage, ClientError> {
+        self.client.get_page(query_string).await
+    }
+
+    /// Same as [`AuditorClient::paginate`]
+    pub fn paginate(
+        &self,
+        query: QueryBuilder,
+        page_size: u64,
+    ) -> BoxStream<'static, Result<Record, ClientError>> {
+        self.client.paginate(query, page_size)
+    }
+
+    /// Same as [`AuditorClient::aggregate`]
+    pub async fn aggregate(
+        &self,
+        group_by: &[&str],
+        query_string: String,
+    ) -> Result<Vec<UsageAggregate>, ClientError> {
+        self.client.aggregate(group_by, query_string).await
+    }
+
+    /// Same as [`AuditorClient::histogram`]
+    pub async fn histogram(
+        &self,
+        bucket: BucketSize,
+        from: DateTime<Utc>,
+        to: DateTime<Utc>,
+        group_by: &[&str],
+        query_string: String,
+    ) -> Result<Vec<UsageBucket>, ClientError> {
+        self.client
+            .histogram(bucket, from, to, group_by, query_string)
+            .await
+    }
+
+    /// Same as [`AuditorClient::concurrency`]
+    pub async fn concurrency(
+        &self,
+        samples: SamplePoints,
+        group_by: &[&str],
+        query_string: String,
+    ) -> Result<Vec<ConcurrentUsage>, ClientError> {
+        self.client
+            .concurrency(samples, group_by, query_string)
+            .await
+    }
+
+    /// Same as [`AuditorClient::daily_usage`]
+    pub async fn daily_usage(
+        &self,
+        query: &DailyUsageQuery,
+    ) -> Result<Vec<DailyUsage>, ClientError> {
+        self.client.daily_usage(query).await
+    }
+
     /// Same as [`AuditorClient::get_single_record`]
     pub async fn get_single_record(&self, record_id: String) -> Result<Record, ClientError> {
         self.client.get_single_record(record_id).await
@@ -1844,6 +2916,44 @@ impl AuditorClientBlocking {
         }
     }
 
+    /// Push a record to the Auditor instance, tolerating records which already exist.
+    ///
+    /// Adding a record which is identical to the stored one succeeds and returns
+    /// [`InsertStatus::Duplicate`], so that sending a record again is safe. If the stored record
+    /// differs, it is updated if `on_conflict` is [`OnConflict::Update`].
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::RecordConflict`] - If a different record with the same `record_id`
+    ///   exists and `on_conflict` is [`OnConflict::Skip`].
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(
+        name = "Sending a record idempotently to AUDITOR server.",
+        skip(self, record),
+        fields(record_id = %record.record_id),
+        level = "debug"
+    )]
+    pub fn add_idempotent(
+        &self,
+        record: &RecordAdd,
+        on_conflict: OnConflict,
+    ) -> Result<InsertStatus, ClientError> {
+        let response = self
+            .client
+            .post(format!("{}/record", &self.address))
+            .query(&[("on_conflict", on_conflict.as_str())])
+            .header("Content-Type", "application/json")
+            .json(record)
+            .send()?;
+
+        if response.status() == reqwest::StatusCode::CONFLICT {
+            let stored: Record = response.json()?;
+            return Err(ClientError::RecordConflict(Box::new(stored)));
+        }
+        let result: InsertResult = response.error_for_status()?.json()?;
+        Ok(result.status)
+    }
+
     /// Push multiple records to the Auditor instance as vec.
     ///
     /// # Errors
@@ -1868,6 +2978,36 @@ impl AuditorClientBlocking {
             Ok(())
         }
     }
+
+    /// Push multiple records to the Auditor instance and report the status of each record.
+    ///
+    /// Records whose `record_id` already exists are skipped or update the stored record,
+    /// depending on `on_conflict`, instead of rejecting all records.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(
+        name = "Sending multiple records to AUDITOR server with status report.",
+        skip(self, records)
+    )]
+    pub fn bulk_insert_with_report(
+        &self,
+        records: &[RecordAdd],
+        on_conflict: OnConflict,
+    ) -> Result<BulkInsertReport, ClientError> {
+        let report = self
+            .client
+            .post(format!("{}/records", &self.address))
+            .query(&[("on_conflict", on_conflict.as_str())])
+            .header("Content-Type", "application/json")
+            .json(records)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(report)
+    }
+
     /// Update an existing record in the Auditor instance.
     ///
     /// # Errors
@@ -1888,6 +3028,31 @@ impl AuditorClientBlocking {
         Ok(())
     }
 
+    /// Update several existing records in the Auditor instance at once.
+    ///
+    /// The updates are applied in a single transaction in the order of `records`. Updates of
+    /// records which do not exist are reported as [`UpdateStatus::Unknown`] and do not affect the
+    /// other updates.
+    ///
+    /// # Errors
+    ///
+    /// * [`ClientError::ReqwestError`] - If there was an error sending the HTTP request.
+    #[tracing::instrument(
+        name = "Sending multiple record updates to AUDITOR server.",
+        skip(self, records)
+    )]
+    pub fn bulk_update(&self, records: &[RecordUpdate]) -> Result<BulkUpdateReport, ClientError> {
+        let report = self
+            .client
+            .put(format!("{}/records", &self.address))
+            .header("Content-Type", "application/json")
+            .json(records)
+            .send()?
+            .error_for_status()?
+            .json()?;
+        Ok(report)
+    }
+
     /// Gets all records from the Auditors database.
     ///
     /// # Errors
@@ -2011,12 +3176,15 @@ impl AuditorClientBlocking {
 #[cfg(test)]
 mod tests {
     use super::*;
-    use auditor::domain::RecordTest;
-    use chrono::TimeZone;
+    use auditor::domain::{RecordTest, UpdateResult};
+    use chrono::{NaiveDate, TimeZone};
     use claim::assert_err;
     use fake::{Fake, Faker};
+    use futures::StreamExt;
     use tokio::time::sleep;
-    use wiremock::matchers::{any, body_json, header, method, path, query_param};
+    use wiremock::matchers::{
+        any, body_json, header, method, path, query_param, query_param_is_missing,
+    };
     use wiremock::{Mock, MockServer, ResponseTemplate};
 
     fn record<T: TryFrom<RecordTest>>() -> T
@@ -2026,6 +3194,17 @@ mod tests {
         T::try_from(Faker.fake::<RecordTest>()).unwrap()
     }
 
+    /// Lets `mock_server` answer the negotiation of the API version like a server which
+    /// supports at most `version`.
+    async fn mount_api_version(mock_server: &MockServer, version: ApiVersion) {
+        let status = if version >= ApiVersion::V2 { 200 } else { 404 };
+        Mock::given(method("GET"))
+            .and(path("/v2/health_check"))
+            .respond_with(ResponseTemplate::new(status))
+            .mount(mock_server)
+            .await;
+    }
+
     #[tokio::test]
     async fn get_succeeds() {
         let mock_server = MockServer::start().await;
@@ -2052,6 +3231,87 @@ mod tests {
             .count();
     }
 
+    #[tokio::test]
+    async fn get_reads_framed_records_with_v2() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V2).await;
+
+        let records: Vec<Record> = vec![record(), record()];
+        let body: String = records
+            .iter()
+            .map(|r| RecordFrame::Record(Box::new(r.clone())))
+            .chain([RecordFrame::End { count: 2 }])
+            .map(|frame| serde_json::to_string(&frame).unwrap() + "\n")
+            .collect();
+
+        Mock::given(method("GET"))
+            .and(path("/v2/records"))
+            .and(header("Accept", "application/x-ndjson"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_eq!(client.get().await.unwrap(), records);
+    }
+
+    #[tokio::test]
+    async fn get_fails_on_unterminated_frames_with_v2() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V2).await;
+
+        let record: Record = record();
+        for frames in [
+            vec![RecordFrame::Record(Box::new(record.clone()))],
+            vec![
+                RecordFrame::Record(Box::new(record.clone())),
+                RecordFrame::Error("Database failed".to_string()),
+            ],
+            vec![
+                RecordFrame::Record(Box::new(record.clone())),
+                RecordFrame::End { count: 2 },
+            ],
+        ] {
+            let body: String = frames
+                .iter()
+                .map(|frame| serde_json::to_string(frame).unwrap() + "\n")
+                .collect();
+            let _guard = Mock::given(method("GET"))
+                .and(path("/v2/records"))
+                .respond_with(ResponseTemplate::new(200).set_body_string(body))
+                .mount_as_scoped(&mock_server)
+                .await;
+
+            assert!(matches!(client.get().await, Err(ClientError::Other(_))));
+        }
+    }
+
+    #[tokio::test]
+    async fn get_fails_on_invalid_stream() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .respond_with(ResponseTemplate::new(200).set_body_string(r#"[{"record_id": 1},"#))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(matches!(client.get().await, Err(ClientError::Other(_))));
+    }
+
     #[tokio::test]
     async fn blocking_get_succeeds() {
         let mock_server = MockServer::start().await;
@@ -2131,39 +3391,97 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn health_check_fails_on_timeout() {
+    async fn bearer_token_is_sent() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
-            .timeout(1)
+            .bearer_token(&"secret")
             .build()
             .unwrap();
 
         Mock::given(method("GET"))
-            .and(path("/health_check"))
-            .respond_with(
-                ResponseTemplate::new(200).set_delay(
-                    Duration::try_seconds(180)
-                        .expect("This should never fail")
-                        .to_std()
-                        .expect("This should never fail"),
-                ),
-            )
+            .and(path("/records"))
+            .and(header("Authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<Record>::new()))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        assert!(!client.health_check().await);
+        assert!(client.get().await.unwrap().is_empty());
     }
 
     #[tokio::test]
-    async fn blocking_health_check_fails_on_timeout() {
+    async fn blocking_bearer_token_is_sent() {
         let mock_server = MockServer::start().await;
         let uri = mock_server.uri();
         let client = tokio::task::spawn_blocking(move || {
             AuditorClientBuilder::new()
                 .connection_string(&uri)
-                .timeout(1)
+                .bearer_token(&"secret")
+                .build_blocking()
+                .unwrap()
+        })
+        .await
+        .unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(header("Authorization", "Bearer secret"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(Vec::<Record>::new()))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let response = tokio::task::spawn_blocking(move || client.get())
+            .await
+            .unwrap();
+
+        assert!(response.unwrap().is_empty());
+    }
+
+    #[test]
+    fn invalid_bearer_token_fails() {
+        let result = AuditorClientBuilder::new()
+            .bearer_token(&"line\nbreak")
+            .build();
+
+        assert!(matches!(result, Err(ClientError::Other(_))));
+    }
+
+    #[tokio::test]
+    async fn health_check_fails_on_timeout() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .timeout(1)
+            .build()
+            .unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/health_check"))
+            .respond_with(
+                ResponseTemplate::new(200).set_delay(
+                    Duration::try_seconds(180)
+                        .expect("This should never fail")
+                        .to_std()
+                        .expect("This should never fail"),
+                ),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(!client.health_check().await);
+    }
+
+    #[tokio::test]
+    async fn blocking_health_check_fails_on_timeout() {
+        let mock_server = MockServer::start().await;
+        let uri = mock_server.uri();
+        let client = tokio::task::spawn_blocking(move || {
+            AuditorClientBuilder::new()
+                .connection_string(&uri)
+                .timeout(1)
                 .build_blocking()
                 .unwrap()
         })
@@ -2325,6 +3643,7 @@ mod tests {
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V1).await;
 
         let record: RecordAdd = record();
 
@@ -2337,6 +3656,109 @@ mod tests {
         assert_err!(client.add(&record).await);
     }
 
+    #[tokio::test]
+    async fn api_version_is_negotiated_once() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        Mock::given(method("GET"))
+            .and(path("/v2/health_check"))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_eq!(client.api_version().await.unwrap(), ApiVersion::V2);
+        assert_eq!(client.clone().api_version().await.unwrap(), ApiVersion::V2);
+    }
+
+    #[tokio::test]
+    async fn api_version_falls_back_to_v1() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        // Servers without versioned paths respond with 404 to unknown paths
+        assert_eq!(client.api_version().await.unwrap(), ApiVersion::V1);
+    }
+
+    #[tokio::test]
+    async fn add_uses_v2_if_supported() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V2).await;
+
+        let record: RecordAdd = record();
+
+        Mock::given(method("POST"))
+            .and(path("/v2/record"))
+            .and(body_json(&record))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        client.add(&record).await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn add_fails_on_existing_record_with_v2() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V2).await;
+
+        let record: RecordAdd = record();
+
+        Mock::given(method("POST"))
+            .and(path("/v2/record"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(serde_json::json!({
+                "error": ERR_RECORD_EXISTS
+            })))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(matches!(
+            client.add(&record).await,
+            Err(ClientError::RecordExists)
+        ));
+    }
+
+    #[tokio::test]
+    async fn add_fails_on_500_with_v2() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V2).await;
+
+        let record: RecordAdd = record();
+
+        Mock::given(method("POST"))
+            .and(path("/v2/record"))
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert!(matches!(
+            client.add(&record).await,
+            Err(ClientError::ReqwestError(_))
+        ));
+    }
+
     #[tokio::test]
     async fn blocking_add_fails_on_existing_record() {
         let mock_server = MockServer::start().await;
@@ -2365,100 +3787,589 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn update_succeeds() {
+    async fn update_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let record: RecordUpdate = record();
+
+        Mock::given(method("PUT"))
+            .and(path("/record"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(&record))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _res = client.update(&record).await;
+    }
+
+    #[tokio::test]
+    async fn queued_update_succeeds() {
+        let mock_server = MockServer::start().await;
+        let mut client_builder = AuditorClientBuilder::new().connection_string(&mock_server.uri());
+        client_builder.send_interval = chrono::Duration::try_milliseconds(50).unwrap();
+        let mut client = client_builder.build_queued().await.unwrap();
+
+        let record: RecordUpdate = record();
+
+        Mock::given(method("PUT"))
+            .and(path("/records"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(vec![&record]))
+            .respond_with(ResponseTemplate::new(200).set_body_json(BulkUpdateReport {
+                records: vec![UpdateResult {
+                    record_id: record.record_id.to_string(),
+                    status: UpdateStatus::Updated,
+                }],
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _res = client.update(&record).await;
+        sleep(std::time::Duration::from_millis(100)).await;
+        client.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn bulk_update_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let records: Vec<RecordUpdate> = (0..2).map(|_| record()).collect();
+        let report = BulkUpdateReport {
+            records: vec![
+                UpdateResult {
+                    record_id: records[0].record_id.to_string(),
+                    status: UpdateStatus::Updated,
+                },
+                UpdateResult {
+                    record_id: records[1].record_id.to_string(),
+                    status: UpdateStatus::Unknown,
+                },
+            ],
+        };
+
+        Mock::given(method("PUT"))
+            .and(path("/records"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(&records))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&report))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_eq!(client.bulk_update(&records).await.unwrap(), report);
+    }
+
+    #[tokio::test]
+    async fn queued_update_keeps_unknown_records_queued() {
+        let mock_server = MockServer::start().await;
+        let mut client_builder = AuditorClientBuilder::new().connection_string(&mock_server.uri());
+        client_builder.send_interval = chrono::Duration::try_milliseconds(50).unwrap();
+        let mut client = client_builder.build_queued().await.unwrap();
+
+        let record: RecordUpdate = record();
+
+        // The update is sent again in every interval as long as the record is unknown
+        Mock::given(method("PUT"))
+            .and(path("/records"))
+            .and(body_json(vec![&record]))
+            .respond_with(ResponseTemplate::new(200).set_body_json(BulkUpdateReport {
+                records: vec![UpdateResult {
+                    record_id: record.record_id.to_string(),
+                    status: UpdateStatus::Unknown,
+                }],
+            }))
+            .expect(2..)
+            .mount(&mock_server)
+            .await;
+
+        let _res = client.update(&record).await;
+        sleep(std::time::Duration::from_millis(200)).await;
+        client.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn queued_update_falls_back_to_single_updates() {
+        let mock_server = MockServer::start().await;
+        let mut client_builder = AuditorClientBuilder::new().connection_string(&mock_server.uri());
+        client_builder.send_interval = chrono::Duration::try_milliseconds(50).unwrap();
+        let mut client = client_builder.build_queued().await.unwrap();
+
+        let record: RecordUpdate = record();
+
+        Mock::given(method("PUT"))
+            .and(path("/records"))
+            .respond_with(ResponseTemplate::new(405))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+        Mock::given(method("PUT"))
+            .and(path("/record"))
+            .and(body_json(&record))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _res = client.update(&record).await;
+        sleep(std::time::Duration::from_millis(200)).await;
+        client.stop().await.unwrap();
+    }
+
+    #[tokio::test]
+    async fn blocking_update_succeeds() {
+        let mock_server = MockServer::start().await;
+        let uri = mock_server.uri();
+        let client = tokio::task::spawn_blocking(move || {
+            AuditorClientBuilder::new()
+                .connection_string(&uri)
+                .build_blocking()
+                .unwrap()
+        })
+        .await
+        .unwrap();
+
+        let record: RecordUpdate = record();
+
+        Mock::given(method("PUT"))
+            .and(path("/record"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(&record))
+            .respond_with(ResponseTemplate::new(200))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let _res = tokio::task::spawn_blocking(move || client.update(&record))
+            .await
+            .unwrap();
+    }
+
+    #[tokio::test]
+    async fn update_fails_on_500() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V1).await;
+
+        let record: RecordUpdate = record();
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        assert_err!(client.update(&record).await);
+    }
+
+    #[tokio::test]
+    async fn blocking_update_fails_on_500() {
+        let mock_server = MockServer::start().await;
+        let uri = mock_server.uri();
+        let client = tokio::task::spawn_blocking(move || {
+            AuditorClientBuilder::new()
+                .connection_string(&uri)
+                .build_blocking()
+                .unwrap()
+        })
+        .await
+        .unwrap();
+
+        let record: RecordUpdate = record();
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let res = tokio::task::spawn_blocking(move || client.update(&record))
+            .await
+            .unwrap();
+        assert_err!(res);
+    }
+
+    #[tokio::test]
+    async fn get_advanced_queries_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let body: Vec<Record> = vec![record()];
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let datetime_utc = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let response = QueryBuilder::new()
+            .with_start_time(Operator::default().gte(datetime_utc.into()))
+            .get(client)
+            .await
+            .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
+    }
+
+    #[tokio::test]
+    async fn get_record_query_with_start_time_and_stop_time_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let body: Vec<Record> = vec![record()];
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .and(query_param("stop_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let datetime_utc = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let response = QueryBuilder::new()
+            .with_start_time(Operator::default().gte(datetime_utc.into()))
+            .with_stop_time(Operator::default().gte(datetime_utc.into()))
+            .get(client)
+            .await
+            .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
+    }
+
+    #[tokio::test]
+    async fn get_record_query_with_start_time_gte_and_start_time_lte_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let body: Vec<Record> = vec![record()];
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
+        let response = QueryBuilder::new()
+            .with_start_time(
+                Operator::default()
+                    .gte(datetime_utc_gte.into())
+                    .lte(datetime_utc_lte.into()),
+            )
+            .get(client)
+            .await
+            .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
+    }
+
+    #[tokio::test]
+    async fn get_record_query_with_start_time_gte_and_start_time_lte_runtime_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let body: Vec<Record> = vec![record()];
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
+            .and(query_param("runtime[gte]", "100000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
+        let runtime: u64 = 100000;
+        let response = QueryBuilder::new()
+            .with_start_time(
+                Operator::default()
+                    .gte(datetime_utc_gte.into())
+                    .lte(datetime_utc_lte.into()),
+            )
+            .with_runtime(Operator::default().gte(runtime.into()))
+            .get(client)
+            .await
+            .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
+    }
+
+    #[tokio::test]
+    async fn get_record_query_with_start_time_stop_time_and_runtime_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let body: Vec<Record> = vec![record()];
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
+            .and(query_param("runtime[gte]", "100000"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
+        let runtime_gte: u64 = 100000;
+        let runtime_lte: u64 = 200000;
+        let response = QueryBuilder::new()
+            .with_start_time(
+                Operator::default()
+                    .gte(datetime_utc_gte.into())
+                    .lte(datetime_utc_lte.into()),
+            )
+            .with_stop_time(
+                Operator::default()
+                    .gte(datetime_utc_gte.into())
+                    .lte(datetime_utc_lte.into()),
+            )
+            .with_runtime(
+                Operator::default()
+                    .gte(runtime_gte.into())
+                    .lte(runtime_lte.into()),
+            )
+            .get(client)
+            .await
+            .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
+    }
+
+    #[tokio::test]
+    async fn get_advanced_queries_fails_on_500() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V1).await;
+
+        Mock::given(any())
+            .respond_with(ResponseTemplate::new(500))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+
+        assert_err!(
+            QueryBuilder::new()
+                .with_stop_time(Operator::default().gte(datetime_utc_gte.into()))
+                .get(client)
+                .await
+        );
+    }
+
+    #[tokio::test]
+    async fn get_meta_queries_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let record: RecordUpdate = record();
+        let body: Vec<Record> = vec![record()];
 
-        Mock::given(method("PUT"))
-            .and(path("/record"))
-            .and(header("Content-Type", "application/json"))
-            .and(body_json(&record))
-            .respond_with(ResponseTemplate::new(200))
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("meta[site_id][c]", "group_1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let _res = client.update(&record).await;
+        let response = QueryBuilder::new()
+            .with_meta_query(MetaQuery::new().meta_operator(
+                "site_id".to_string(),
+                MetaOperator::default().contains("group_1".to_string()),
+            ))
+            .get(client)
+            .await
+            .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
     }
 
     #[tokio::test]
-    async fn queued_update_succeeds() {
+    async fn get_meta_queries_and_start_time_succeeds() {
         let mock_server = MockServer::start().await;
-        let mut client_builder = AuditorClientBuilder::new().connection_string(&mock_server.uri());
-        client_builder.send_interval = chrono::Duration::try_milliseconds(50).unwrap();
-        let mut client = client_builder.build_queued().await.unwrap();
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
 
-        let record: RecordUpdate = record();
+        let body: Vec<Record> = vec![record()];
 
-        Mock::given(method("PUT"))
-            .and(path("/record"))
-            .and(header("Content-Type", "application/json"))
-            .and(body_json(&record))
-            .respond_with(ResponseTemplate::new(200))
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("meta[site_id][c]", "group_1"))
+            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let _res = client.update(&record).await;
-        sleep(std::time::Duration::from_millis(100)).await;
-        client.stop().await.unwrap();
+        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
+        let response = QueryBuilder::new()
+            .with_meta_query(MetaQuery::new().meta_operator(
+                "site_id".to_string(),
+                MetaOperator::default().contains("group_1".to_string()),
+            ))
+            .with_start_time(Operator::default().lte(datetime_utc_lte.into()))
+            .get(client)
+            .await
+            .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
     }
 
     #[tokio::test]
-    async fn blocking_update_succeeds() {
+    async fn get_component_queries_succeeds() {
         let mock_server = MockServer::start().await;
-        let uri = mock_server.uri();
-        let client = tokio::task::spawn_blocking(move || {
-            AuditorClientBuilder::new()
-                .connection_string(&uri)
-                .build_blocking()
-                .unwrap()
-        })
-        .await
-        .unwrap();
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
 
-        let record: RecordUpdate = record();
+        let body: Vec<Record> = vec![record()];
 
-        Mock::given(method("PUT"))
-            .and(path("/record"))
-            .and(header("Content-Type", "application/json"))
-            .and(body_json(&record))
-            .respond_with(ResponseTemplate::new(200))
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("component[cpu][equals]", "4"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let _res = tokio::task::spawn_blocking(move || client.update(&record))
-            .await
-            .unwrap();
+        let count: u8 = 4;
+        let response =
+            QueryBuilder::new()
+                .with_component_query(ComponentQuery::new().component_operator(
+                    "cpu".to_string(),
+                    Operator::default().equals(count.into()),
+                ))
+                .get(client)
+                .await
+                .unwrap();
+
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
     }
 
     #[tokio::test]
-    async fn update_fails_on_500() {
+    async fn get_score_queries_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let record: RecordUpdate = record();
+        let body: Vec<Record> = vec![record()];
 
-        Mock::given(any())
-            .respond_with(ResponseTemplate::new(500))
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("component[cpu][gte]", "4"))
+            .and(query_param("component[cpu][score][HEPSPEC06][gte]", "10.5"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        assert_err!(client.update(&record).await);
+        let count: u8 = 4;
+        let response = QueryBuilder::new()
+            .with_component_query(
+                ComponentQuery::new()
+                    .component_operator("cpu".to_string(), Operator::default().gte(count.into()))
+                    .score_operator(
+                        "cpu".to_string(),
+                        "HEPSPEC06".to_string(),
+                        Operator::default().gte(10.5.into()),
+                    ),
+            )
+            .get(client)
+            .await
+            .unwrap();
+
+        assert_eq!(response.len(), 1);
+        assert_eq!(response[0], body[0]);
     }
 
     #[tokio::test]
-    async fn blocking_update_fails_on_500() {
+    async fn blocking_advanced_queries_succeeds() {
         let mock_server = MockServer::start().await;
         let uri = mock_server.uri();
         let client = tokio::task::spawn_blocking(move || {
@@ -2470,22 +4381,36 @@ mod tests {
         .await
         .unwrap();
 
-        let record: RecordUpdate = record();
+        let body: Vec<Record> = vec![record()];
 
-        Mock::given(any())
-            .respond_with(ResponseTemplate::new(500))
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("stop_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let res = tokio::task::spawn_blocking(move || client.update(&record))
+        let datetime_utc = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let query_string = QueryBuilder::new()
+            .with_stop_time(Operator::default().gte(datetime_utc.into()))
+            .build();
+
+        let response = tokio::task::spawn_blocking(move || client.advanced_query(query_string))
             .await
+            .unwrap()
             .unwrap();
-        assert_err!(res);
+
+        println!("{:?}", &response);
+        response
+            .into_iter()
+            .zip(body)
+            .map(|(rr, br)| assert_eq!(rr, br))
+            .count();
     }
 
     #[tokio::test]
-    async fn get_advanced_queries_succeeds() {
+    async fn get_sort_by_query_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
@@ -2496,15 +4421,14 @@ mod tests {
 
         Mock::given(method("GET"))
             .and(path("/records"))
-            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .and(query_param("sort_by[asc]", "start_time"))
             .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
         let response = QueryBuilder::new()
-            .with_start_time(Operator::default().gte(datetime_utc.into()))
+            .sort_by(SortBy::new().ascending("start_time".to_string()))
             .get(client)
             .await
             .unwrap();
@@ -2517,7 +4441,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn get_record_query_with_start_time_and_stop_time_succeeds() {
+    async fn limit_get_query_records_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
@@ -2528,17 +4452,16 @@ mod tests {
 
         Mock::given(method("GET"))
             .and(path("/records"))
-            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
-            .and(query_param("stop_time[gte]", "2022-08-03T09:47:00+00:00"))
+            .and(query_param("limit", "500"))
             .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let number: u64 = 500;
         let response = QueryBuilder::new()
-            .with_start_time(Operator::default().gte(datetime_utc.into()))
-            .with_stop_time(Operator::default().gte(datetime_utc.into()))
+            .sort_by(SortBy::new().ascending("start_time".to_string()))
+            .limit(number)
             .get(client)
             .await
             .unwrap();
@@ -2551,7 +4474,7 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn get_record_query_with_start_time_gte_and_start_time_lte_succeeds() {
+    async fn get_exact_record_using_record_id_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
@@ -2562,21 +4485,14 @@ mod tests {
 
         Mock::given(method("GET"))
             .and(path("/records"))
-            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
-            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
+            .and(query_param("record_id", "r1"))
             .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
-        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
         let response = QueryBuilder::new()
-            .with_start_time(
-                Operator::default()
-                    .gte(datetime_utc_gte.into())
-                    .lte(datetime_utc_lte.into()),
-            )
+            .with_record_id("r1".to_string())
             .get(client)
             .await
             .unwrap();
@@ -2589,362 +4505,402 @@ mod tests {
     }
 
     #[tokio::test]
-    async fn get_record_query_with_start_time_gte_and_start_time_lte_runtime_succeeds() {
+    async fn aggregate_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let body: Vec<Record> = vec![record()];
+        let body = vec![UsageAggregate {
+            group: [("site_id".to_string(), Some("site1".to_string()))].into(),
+            record_count: 2,
+            runtime: 7200,
+            components: vec![],
+        }];
 
         Mock::given(method("GET"))
-            .and(path("/records"))
-            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
-            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
-            .and(query_param("runtime[gte]", "100000"))
+            .and(path("/aggregate"))
+            .and(query_param("group_by[0]", "site_id"))
+            .and(query_param("meta[site_id][c]", "site1"))
             .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
-        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
-        let runtime: u64 = 100000;
         let response = QueryBuilder::new()
-            .with_start_time(
-                Operator::default()
-                    .gte(datetime_utc_gte.into())
-                    .lte(datetime_utc_lte.into()),
-            )
-            .with_runtime(Operator::default().gte(runtime.into()))
-            .get(client)
+            .with_meta_query(MetaQuery::new().meta_operator(
+                "site_id".to_string(),
+                MetaOperator::default().contains("site1".to_string()),
+            ))
+            .aggregate(&["site_id"], client)
             .await
             .unwrap();
 
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_eq!(body, response);
     }
 
     #[tokio::test]
-    async fn get_record_query_with_start_time_stop_time_and_runtime_succeeds() {
+    async fn histogram_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let body: Vec<Record> = vec![record()];
+        let from = Utc.with_ymd_and_hms(2022, 10, 1, 0, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2022, 10, 2, 0, 0, 0).unwrap();
+        let body = vec![UsageBucket {
+            start: from,
+            stop: to,
+            usage: vec![UsageAggregate {
+                group: [("site_id".to_string(), Some("site1".to_string()))].into(),
+                record_count: 1,
+                runtime: 3600,
+                components: vec![],
+            }],
+        }];
 
         Mock::given(method("GET"))
-            .and(path("/records"))
-            .and(query_param("start_time[gte]", "2022-08-03T09:47:00+00:00"))
-            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
-            .and(query_param("runtime[gte]", "100000"))
+            .and(path("/histogram"))
+            .and(query_param("bucket", "day"))
+            .and(query_param("from", "2022-10-01T00:00:00Z"))
+            .and(query_param("to", "2022-10-02T00:00:00Z"))
+            .and(query_param("group_by[0]", "site_id"))
             .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
-        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
-        let runtime_gte: u64 = 100000;
-        let runtime_lte: u64 = 200000;
         let response = QueryBuilder::new()
-            .with_start_time(
-                Operator::default()
-                    .gte(datetime_utc_gte.into())
-                    .lte(datetime_utc_lte.into()),
-            )
-            .with_stop_time(
-                Operator::default()
-                    .gte(datetime_utc_gte.into())
-                    .lte(datetime_utc_lte.into()),
-            )
-            .with_runtime(
-                Operator::default()
-                    .gte(runtime_gte.into())
-                    .lte(runtime_lte.into()),
+            .histogram(BucketSize::Day, from, to, &["site_id"], client)
+            .await
+            .unwrap();
+
+        assert_eq!(body, response);
+    }
+
+    #[tokio::test]
+    async fn concurrency_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let from = Utc.with_ymd_and_hms(2022, 10, 1, 12, 0, 0).unwrap();
+        let to = Utc.with_ymd_and_hms(2022, 10, 1, 13, 0, 0).unwrap();
+        let body = vec![
+            ConcurrentUsage {
+                time: from,
+                usage: vec![],
+            },
+            ConcurrentUsage {
+                time: to,
+                usage: vec![],
+            },
+        ];
+
+        Mock::given(method("GET"))
+            .and(path("/concurrency"))
+            .and(query_param("from", "2022-10-01T12:00:00+00:00"))
+            .and(query_param("to", "2022-10-01T13:00:00+00:00"))
+            .and(query_param("step", "3600"))
+            .and(query_param("group_by[0]", "site_id"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let response = QueryBuilder::new()
+            .concurrency(
+                SamplePoints::Range {
+                    from,
+                    to,
+                    step: Duration::hours(1),
+                },
+                &["site_id"],
+                client,
             )
-            .get(client)
             .await
             .unwrap();
 
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_eq!(body, response);
     }
 
     #[tokio::test]
-    async fn get_advanced_queries_fails_on_500() {
+    async fn daily_usage_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        Mock::given(any())
-            .respond_with(ResponseTemplate::new(500))
+        let from = NaiveDate::from_ymd_opt(2022, 10, 1).unwrap();
+        let to = NaiveDate::from_ymd_opt(2022, 11, 1).unwrap();
+        let body = vec![DailyUsage {
+            date: from,
+            site_id: Some("site1".to_string()),
+            group_id: None,
+            user_id: None,
+            component: Some("CPU".to_string()),
+            amount_runtime: 7200.0,
+            record_count: 2,
+        }];
+
+        Mock::given(method("GET"))
+            .and(path("/daily_usage"))
+            .and(query_param("from", "2022-10-01"))
+            .and(query_param("to", "2022-11-01"))
+            .and(query_param("bucket", "month"))
+            .and(query_param("site_id", "site1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc_gte = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
+        let query = DailyUsageQuery::new(from, to)
+            .with_bucket(BucketSize::Month)
+            .with_site_id("site1");
+        let response = client.daily_usage(&query).await.unwrap();
 
-        assert_err!(
-            QueryBuilder::new()
-                .with_stop_time(Operator::default().gte(datetime_utc_gte.into()))
-                .get(client)
-                .await
-        );
+        assert_eq!(body, response);
     }
 
     #[tokio::test]
-    async fn get_meta_queries_succeeds() {
+    async fn delete_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let body: Vec<Record> = vec![record()];
-
-        Mock::given(method("GET"))
-            .and(path("/records"))
-            .and(query_param("meta[site_id][c]", "group_1"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+        Mock::given(method("DELETE"))
+            .and(path("/record/r1"))
+            .respond_with(ResponseTemplate::new(200))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let response = QueryBuilder::new()
-            .with_meta_query(MetaQuery::new().meta_operator(
-                "site_id".to_string(),
-                MetaOperator::default().contains("group_1".to_string()),
-            ))
-            .get(client)
-            .await
-            .unwrap();
-
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        client.delete("r1").await.unwrap();
     }
 
     #[tokio::test]
-    async fn get_meta_queries_and_start_time_succeeds() {
+    async fn delete_fails_on_unknown_record() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let body: Vec<Record> = vec![record()];
-
-        Mock::given(method("GET"))
-            .and(path("/records"))
-            .and(query_param("meta[site_id][c]", "group_1"))
-            .and(query_param("start_time[lte]", "2022-08-04T09:47:00+00:00"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+        Mock::given(method("DELETE"))
+            .and(path("/record/r1"))
+            .respond_with(ResponseTemplate::new(404))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc_lte = Utc.with_ymd_and_hms(2022, 8, 4, 9, 47, 0).unwrap();
-        let response = QueryBuilder::new()
-            .with_meta_query(MetaQuery::new().meta_operator(
-                "site_id".to_string(),
-                MetaOperator::default().contains("group_1".to_string()),
-            ))
-            .with_start_time(Operator::default().lte(datetime_utc_lte.into()))
-            .get(client)
-            .await
-            .unwrap();
-
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_err!(client.delete("r1").await);
     }
 
     #[tokio::test]
-    async fn get_component_queries_succeeds() {
+    async fn delete_records_succeeds() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let body: Vec<Record> = vec![record()];
-
-        Mock::given(method("GET"))
+        Mock::given(method("DELETE"))
             .and(path("/records"))
-            .and(query_param("component[cpu][equals]", "4"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .and(query_param("meta[site_id][c]", "site1"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(DeletedRecords { deleted: 3 }))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let count: u8 = 4;
-        let response =
-            QueryBuilder::new()
-                .with_component_query(ComponentQuery::new().component_operator(
-                    "cpu".to_string(),
-                    Operator::default().equals(count.into()),
-                ))
-                .get(client)
-                .await
-                .unwrap();
+        let deleted = QueryBuilder::new()
+            .with_meta_query(MetaQuery::new().meta_operator(
+                "site_id".to_string(),
+                MetaOperator::default().contains("site1".to_string()),
+            ))
+            .delete(client)
+            .await
+            .unwrap();
 
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_eq!(3, deleted);
     }
 
     #[tokio::test]
-    async fn blocking_advanced_queries_succeeds() {
+    async fn get_page_succeeds() {
         let mock_server = MockServer::start().await;
-        let uri = mock_server.uri();
-        let client = tokio::task::spawn_blocking(move || {
-            AuditorClientBuilder::new()
-                .connection_string(&uri)
-                .build_blocking()
-                .unwrap()
-        })
-        .await
-        .unwrap();
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
 
-        let body: Vec<Record> = vec![record()];
+        let body: Vec<Record> = vec![record(), record()];
 
         Mock::given(method("GET"))
             .and(path("/records"))
-            .and(query_param("stop_time[gte]", "2022-08-03T09:47:00+00:00"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .and(query_param("limit", "2"))
+            .and(query_param("after", "cursor1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(&body)
+                    .insert_header(HEADER_NEXT_CURSOR, "cursor2"),
+            )
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let datetime_utc = Utc.with_ymd_and_hms(2022, 8, 3, 9, 47, 0).unwrap();
-        let query_string = QueryBuilder::new()
-            .with_stop_time(Operator::default().gte(datetime_utc.into()))
-            .build();
-
-        let response = tokio::task::spawn_blocking(move || client.advanced_query(query_string))
+        let page = QueryBuilder::new()
+            .limit(2)
+            .after("cursor1".to_string())
+            .get_page(client)
             .await
-            .unwrap()
             .unwrap();
 
-        println!("{:?}", &response);
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_eq!(
+            page,
+            RecordPage {
+                records: body,
+                next: Some("cursor2".to_string())
+            }
+        );
     }
 
     #[tokio::test]
-    async fn get_sort_by_query_succeeds() {
+    async fn paginate_follows_cursors() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let body: Vec<Record> = vec![record()];
+        let pages: Vec<Vec<Record>> = vec![
+            vec![record(), record()],
+            vec![record(), record()],
+            vec![record()],
+        ];
 
         Mock::given(method("GET"))
             .and(path("/records"))
-            .and(query_param("sort_by[asc]", "start_time"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .and(query_param("limit", "2"))
+            .and(query_param("sort_by[desc]", "runtime"))
+            .and(query_param_is_missing("after"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(&pages[0])
+                    .insert_header(HEADER_NEXT_CURSOR, "cursor1"),
+            )
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let response = QueryBuilder::new()
-            .sort_by(SortBy::new().ascending("start_time".to_string()))
-            .get(client)
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("limit", "2"))
+            .and(query_param("sort_by[desc]", "runtime"))
+            .and(query_param("after", "cursor1"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(&pages[1])
+                    .insert_header(HEADER_NEXT_CURSOR, "cursor2"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param("limit", "2"))
+            .and(query_param("sort_by[desc]", "runtime"))
+            .and(query_param("after", "cursor2"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&pages[2]))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let records: Vec<Record> = QueryBuilder::new()
+            .sort_by(SortBy::new().descending("runtime".to_string()))
+            .limit(1000)
+            .paginate(2, client)
+            .try_collect()
             .await
             .unwrap();
 
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_eq!(records, pages.concat());
     }
 
     #[tokio::test]
-    async fn limit_get_query_records_succeeds() {
+    async fn subscribe_yields_records_until_error() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
-
-        let body: Vec<Record> = vec![record()];
+        let records: Vec<Record> = (0..2).map(|_| record()).collect();
+        let body = format!(
+            ": subscribed\n\nevent: record\ndata: {}\n\n: keep-alive\n\nevent: record\ndata: {}\n\nevent: error\ndata: Changes of records might have been missed\n\n",
+            serde_json::to_string(&records[0]).unwrap(),
+            serde_json::to_string(&records[1]).unwrap(),
+        );
 
         Mock::given(method("GET"))
-            .and(path("/records"))
-            .and(query_param("limit", "500"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .and(path("/records/subscribe"))
+            .and(query_param("meta[site_id][c]", "site1"))
+            .respond_with(ResponseTemplate::new(200).set_body_raw(body, "text/event-stream"))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let number: u64 = 500;
-        let response = QueryBuilder::new()
-            .sort_by(SortBy::new().ascending("start_time".to_string()))
-            .limit(number)
-            .get(client)
-            .await
-            .unwrap();
+        let query = QueryBuilder::new().with_meta_query(MetaQuery::new().meta_operator(
+            "site_id".to_string(),
+            MetaOperator::default().contains("site1".to_string()),
+        ));
+        let results: Vec<Result<Record, ClientError>> = query.subscribe(client).collect().await;
 
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_eq!(results.len(), 3);
+        assert_eq!(results[0].as_ref().unwrap(), &records[0]);
+        assert_eq!(results[1].as_ref().unwrap(), &records[1]);
+        assert!(matches!(results[2], Err(ClientError::Other(_))));
     }
 
     #[tokio::test]
-    async fn get_exact_record_using_record_id_succeeds() {
+    async fn paginate_stops_on_error() {
         let mock_server = MockServer::start().await;
         let client = AuditorClientBuilder::new()
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
 
-        let body: Vec<Record> = vec![record()];
+        Mock::given(method("GET"))
+            .and(path("/records"))
+            .and(query_param_is_missing("after"))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(vec![record::<Record>()])
+                    .insert_header(HEADER_NEXT_CURSOR, "cursor1"),
+            )
+            .expect(1)
+            .mount(&mock_server)
+            .await;
 
         Mock::given(method("GET"))
             .and(path("/records"))
-            .and(query_param("record_id", "r1"))
-            .respond_with(ResponseTemplate::new(200).set_body_json(&body))
+            .and(query_param("after", "cursor1"))
+            .respond_with(ResponseTemplate::new(500))
             .expect(1)
             .mount(&mock_server)
             .await;
 
-        let response = QueryBuilder::new()
-            .with_record_id("r1".to_string())
-            .get(client)
-            .await
-            .unwrap();
+        let results: Vec<Result<Record, ClientError>> =
+            client.paginate(QueryBuilder::new(), 1).collect().await;
 
-        response
-            .into_iter()
-            .zip(body)
-            .map(|(rr, br)| assert_eq!(rr, br))
-            .count();
+        assert_eq!(results.len(), 2);
+        assert!(results[0].is_ok());
+        assert!(results[1].is_err());
     }
 
     #[tokio::test]
@@ -3013,6 +4969,7 @@ mod tests {
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V1).await;
 
         let record_id: &str = "r3";
 
@@ -3151,6 +5108,7 @@ mod tests {
             .connection_string(&mock_server.uri())
             .build()
             .unwrap();
+        mount_api_version(&mock_server, ApiVersion::V1).await;
 
         let records: Vec<RecordAdd> = (0..10).map(|_| record()).collect();
 
@@ -3163,6 +5121,101 @@ mod tests {
         assert_err!(client.bulk_insert(&records).await);
     }
 
+    #[tokio::test]
+    async fn bulk_insert_with_report_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let records: Vec<RecordAdd> = (0..2).map(|_| record()).collect();
+        let report = BulkInsertReport {
+            records: vec![
+                InsertResult {
+                    record_id: Some(records[0].record_id.to_string()),
+                    status: InsertStatus::Inserted,
+                    error: None,
+                },
+                InsertResult {
+                    record_id: Some(records[1].record_id.to_string()),
+                    status: InsertStatus::Duplicate,
+                    error: None,
+                },
+            ],
+        };
+
+        Mock::given(method("POST"))
+            .and(path("/records"))
+            .and(query_param("on_conflict", "skip"))
+            .and(header("Content-Type", "application/json"))
+            .and(body_json(&records))
+            .respond_with(ResponseTemplate::new(200).set_body_json(&report))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let response = client
+            .bulk_insert_with_report(&records, OnConflict::Skip)
+            .await
+            .unwrap();
+        assert_eq!(response, report);
+    }
+
+    #[tokio::test]
+    async fn add_idempotent_succeeds() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let record: RecordAdd = record();
+
+        Mock::given(method("POST"))
+            .and(path("/record"))
+            .and(query_param("on_conflict", "update"))
+            .and(body_json(&record))
+            .respond_with(ResponseTemplate::new(200).set_body_json(InsertResult {
+                record_id: Some(record.record_id.to_string()),
+                status: InsertStatus::Updated,
+                error: None,
+            }))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        let status = client
+            .add_idempotent(&record, OnConflict::Update)
+            .await
+            .unwrap();
+        assert_eq!(status, InsertStatus::Updated);
+    }
+
+    #[tokio::test]
+    async fn add_idempotent_returns_conflicting_record() {
+        let mock_server = MockServer::start().await;
+        let client = AuditorClientBuilder::new()
+            .connection_string(&mock_server.uri())
+            .build()
+            .unwrap();
+
+        let stored: Record = record();
+
+        Mock::given(method("POST"))
+            .and(path("/record"))
+            .and(query_param("on_conflict", "skip"))
+            .respond_with(ResponseTemplate::new(409).set_body_json(&stored))
+            .expect(1)
+            .mount(&mock_server)
+            .await;
+
+        match client.add_idempotent(&record(), OnConflict::Skip).await {
+            Err(ClientError::RecordConflict(conflicting)) => assert_eq!(*conflicting, stored),
+            other => panic!("Expected a conflict, got {other:?}"),
+        }
+    }
+
     #[tokio::test]
     async fn blocking_bulk_insert_fails_on_existing_record() {
         let mock_server = MockServer::start().await;
@@ -0,0 +1,171 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Administrative commands of the `auditor` binary, which are run instead of the server.
+
+use crate::auth::Authenticator;
+use crate::configuration::Settings;
+use crate::metrics::DatabaseMetricsWatcher;
+use crate::migrations::{latest_applied_version, latest_version};
+use crate::partitions::PartitionManager;
+use crate::retention::RetentionWatcher;
+use crate::rollup::DailyUsageRollup;
+use crate::webhooks::WebhookDispatcher;
+use chrono::{DateTime, Utc};
+use sqlx::postgres::PgPoolOptions;
+use sqlx::{Executor, PgPool};
+use std::fmt;
+
+/// Checks the configuration in the same way as the server does on startup, without connecting
+/// to the database.
+pub fn check_config(config: &Settings) -> Result<(), anyhow::Error> {
+    let pool = PgPoolOptions::new().connect_lazy_with(config.database.with_db());
+
+    for (name, frequency) in [
+        (
+            "metrics.database.frequency",
+            config.metrics.database.frequency,
+        ),
+        ("retention.frequency", config.retention.frequency),
+        ("partitioning.frequency", config.partitioning.frequency),
+        ("daily_usage.frequency", config.daily_usage.frequency),
+        ("webhooks.frequency", config.webhooks.frequency),
+    ] {
+        if frequency <= chrono::Duration::zero() {
+            anyhow::bail!("{name} must be positive");
+        }
+    }
+    DatabaseMetricsWatcher::new(pool.clone(), config)?;
+    PartitionManager::new(pool.clone(), config)?;
+    RetentionWatcher::new(pool.clone(), config)?;
+    DailyUsageRollup::new(pool.clone(), config)?;
+    WebhookDispatcher::new(pool.clone(), config)?;
+    Authenticator::new(pool, &config.auth)?;
+
+    if let Some(tls) = config.tls_config.as_ref()
+        && tls.use_tls
+    {
+        tls.validate_tls_paths().map_err(anyhow::Error::msg)?;
+        for path in [
+            &tls.ca_cert_path,
+            &tls.server_cert_path,
+            &tls.server_key_path,
+        ]
+        .into_iter()
+        .flatten()
+        {
+            if !std::path::Path::new(path).is_file() {
+                anyhow::bail!("{path} does not exist");
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Statistics about the stored records, as printed by `auditor stats`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Stats {
+    pub records: i64,
+    /// Records without `stop_time`.
+    pub open_records: i64,
+    pub earliest_start_time: Option<DateTime<Utc>>,
+    pub latest_start_time: Option<DateTime<Utc>>,
+    /// Number of partitions of the records table, including the default partition.
+    pub partitions: i64,
+    /// Size of the records table including all partitions and indexes.
+    pub size_bytes: i64,
+    pub applied_migration: Option<i64>,
+    pub latest_migration: Option<i64>,
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn or_none<T: ToString>(value: Option<T>) -> String {
+            value.map_or("none".to_string(), |value| value.to_string())
+        }
+
+        writeln!(f, "Records:             {}", self.records)?;
+        writeln!(f, "Open records:        {}", self.open_records)?;
+        writeln!(
+            f,
+            "Earliest start time: {}",
+            or_none(self.earliest_start_time)
+        )?;
+        writeln!(
+            f,
+            "Latest start time:   {}",
+            or_none(self.latest_start_time)
+        )?;
+        writeln!(f, "Partitions:          {}", self.partitions)?;
+        writeln!(f, "Size:                {} bytes", self.size_bytes)?;
+        writeln!(
+            f,
+            "Applied migration:   {}",
+            or_none(self.applied_migration)
+        )?;
+        write!(f, "Latest migration:    {}", or_none(self.latest_migration))
+    }
+}
+
+/// Collects [`Stats`] about the stored records.
+#[tracing::instrument(name = "Collecting statistics", skip(pool))]
+pub async fn stats(pool: &PgPool) -> Result<Stats, sqlx::Error> {
+    let (records, open_records, earliest_start_time, latest_start_time): (
+        i64,
+        i64,
+        Option<DateTime<Utc>>,
+        Option<DateTime<Utc>>,
+    ) = sqlx::query_as(
+        "SELECT count(*),
+                count(*) FILTER (WHERE stop_time IS NULL),
+                min(start_time),
+                max(start_time)
+         FROM auditor_accounting",
+    )
+    .persistent(false)
+    .fetch_one(pool)
+    .await?;
+
+    let (partitions, size_bytes): (i64, i64) = sqlx::query_as(
+        "SELECT count(*) FILTER (WHERE isleaf),
+                coalesce(sum(pg_total_relation_size(relid)), 0)::bigint
+         FROM pg_partition_tree('auditor_accounting')",
+    )
+    .persistent(false)
+    .fetch_one(pool)
+    .await?;
+
+    Ok(Stats {
+        records,
+        open_records,
+        earliest_start_time,
+        latest_start_time,
+        partitions,
+        size_bytes,
+        applied_migration: latest_applied_version(pool).await?,
+        latest_migration: latest_version(),
+    })
+}
+
+/// Applies the retention policies once and vacuums the records table afterwards, so that the
+/// space of the deleted records can be reused. Returns the number of deleted records.
+#[tracing::instrument(name = "Vacuuming according to retention policies", skip(pool, config))]
+pub async fn vacuum_retention(pool: &PgPool, config: &Settings) -> Result<u64, anyhow::Error> {
+    let deleted = RetentionWatcher::new(pool.clone(), config)?.apply().await?;
+    // VACUUM cannot run inside a transaction, hence it is sent as simple query
+    pool.execute("VACUUM (ANALYZE) auditor_accounting").await?;
+    Ok(deleted)
+}
+
+/// Removes the registered webhooks which are not in the configuration, together with their
+/// pending deliveries. Returns the number of removed webhooks.
+#[tracing::instrument(name = "Pruning webhooks", skip(pool, config))]
+pub async fn prune_webhooks(pool: &PgPool, config: &Settings) -> Result<u64, anyhow::Error> {
+    Ok(WebhookDispatcher::new(pool.clone(), config)?
+        .prune()
+        .await?)
+}
@@ -0,0 +1,566 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Token based authentication and role based authorization of the REST API.
+//!
+//! Clients authenticate with an `Authorization: Bearer <token>` header. Tokens are either listed
+//! in the configuration or stored in the `api_tokens` table. In both cases only the SHA-256 hash
+//! of a token is known to AUDITOR.
+//!
+//! Clients connecting with mutual TLS can alternatively be authenticated by the subject or a
+//! subject alternative name of their certificate.
+
+use crate::configuration::{AuthSettings, ClientCertificateSettings};
+use crate::domain::{ApiVersion, ValidMeta};
+use actix_tls::accept::rustls_0_23::TlsStream;
+use actix_web::body::MessageBody;
+use actix_web::dev::{Extensions, ServiceRequest, ServiceResponse};
+use actix_web::http::{Method, StatusCode, header};
+use actix_web::middleware::Next;
+use actix_web::rt::net::TcpStream;
+use actix_web::{HttpMessage, HttpRequest, HttpResponse, ResponseError, web};
+use base64::Engine;
+use rand::RngCore;
+use sha2::{Digest, Sha256};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::any::Any;
+use std::collections::HashMap;
+use std::net::IpAddr;
+use std::sync::Arc;
+use x509_cert::der::Decode;
+use x509_cert::ext::pkix::SubjectAltName;
+use x509_cert::ext::pkix::name::GeneralName;
+
+/// Meta key holding the site of a record. Clients can be restricted to the records of some sites.
+pub const SITE_META_KEY: &str = "site_id";
+
+/// Role of an API token.
+#[derive(
+    serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq, Hash, utoipa::ToSchema,
+)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    /// May add and update records.
+    Collector,
+    /// May query records.
+    Reader,
+    /// May do everything, including deleting records and managing tokens.
+    Admin,
+}
+
+impl Role {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Role::Collector => "collector",
+            Role::Reader => "reader",
+            Role::Admin => "admin",
+        }
+    }
+
+    /// Returns `true` if the role grants `access`.
+    pub fn permits(&self, access: Access) -> bool {
+        matches!(
+            (self, access),
+            (Role::Admin, _) | (Role::Collector, Access::Write) | (Role::Reader, Access::Read)
+        )
+    }
+}
+
+impl std::fmt::Display for Role {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.as_str())
+    }
+}
+
+impl TryFrom<String> for Role {
+    type Error = String;
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        match s.as_str() {
+            "collector" => Ok(Role::Collector),
+            "reader" => Ok(Role::Reader),
+            "admin" => Ok(Role::Admin),
+            other => Err(format!("{other} is not a valid role")),
+        }
+    }
+}
+
+/// Kind of access a request needs.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Access {
+    Read,
+    Write,
+    Admin,
+}
+
+impl Access {
+    /// Determines the access needed for a request. Returns `None` for endpoints which do not
+    /// require authentication.
+    pub fn required_for(method: &Method, path: &str) -> Option<Access> {
+        let (_, path) = ApiVersion::from_path(path);
+        if matches!(
+            path,
+            "/health_check" | "/livez" | "/readyz" | "/metrics" | "/openapi.json"
+        ) || path.starts_with("/swagger-ui/")
+        {
+            return None;
+        }
+        if path == "/tokens" || path.starts_with("/tokens/") {
+            return Some(Access::Admin);
+        }
+        match *method {
+            Method::GET | Method::HEAD => Some(Access::Read),
+            Method::POST | Method::PUT => Some(Access::Write),
+            _ => Some(Access::Admin),
+        }
+    }
+}
+
+/// The authenticated client of a request. It is stored in the request extensions by
+/// [`authorize`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Principal {
+    /// Name of the token or subject of the client certificate.
+    pub name: String,
+    pub role: Role,
+    /// Sites whose records the client may access. `None` if the client is not restricted.
+    pub sites: Option<Vec<String>>,
+}
+
+impl Principal {
+    /// Returns the principal of an authenticated request.
+    pub fn of(request: &HttpRequest) -> Option<Principal> {
+        request.extensions().get::<Principal>().cloned()
+    }
+
+    /// Returns `true` if the principal may access records of the given `sites`. A restricted
+    /// principal may only access records with at least one site, all of which have to be
+    /// permitted.
+    pub fn permits_sites<S: AsRef<str>>(&self, sites: &[S]) -> bool {
+        match &self.sites {
+            None => true,
+            Some(allowed) => {
+                !sites.is_empty()
+                    && sites
+                        .iter()
+                        .all(|site| allowed.iter().any(|a| a == site.as_ref()))
+            }
+        }
+    }
+
+    /// Returns `true` if the principal may access records with the meta information `meta`.
+    pub fn permits_meta(&self, meta: Option<&ValidMeta>) -> bool {
+        let sites = meta
+            .and_then(|meta| {
+                meta.0
+                    .iter()
+                    .find(|(key, _)| key.as_ref() == SITE_META_KEY)
+                    .map(|(_, sites)| sites.as_slice())
+            })
+            .unwrap_or_default();
+        self.permits_sites(sites)
+    }
+
+    /// Returns `true` if the stored records with the given `record_ids` only belong to sites
+    /// the principal may access. Unknown records are ignored.
+    pub async fn permits_stored_records(
+        &self,
+        record_ids: &[&str],
+        pool: &PgPool,
+    ) -> Result<bool, sqlx::Error> {
+        let Some(sites) = &self.sites else {
+            return Ok(true);
+        };
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+            "SELECT NOT EXISTS (SELECT 1 FROM auditor_accounting WHERE record_id = ANY(",
+        );
+        query.push_bind(record_ids);
+        query.push(") AND NOT ");
+        push_site_condition(&mut query, sites);
+        query.push(")");
+        query.build_query_scalar().fetch_one(pool).await
+    }
+}
+
+/// Appends a condition which is true if a record has at least one site and all of its sites are
+/// contained in `sites`.
+pub(crate) fn push_site_condition(query: &mut QueryBuilder<'_, Postgres>, sites: &[String]) {
+    query.push(format!(
+        "COALESCE(jsonb_array_length(meta -> '{SITE_META_KEY}') > 0 \
+         AND meta -> '{SITE_META_KEY}' <@ to_jsonb("
+    ));
+    query.push_bind(sites.to_vec());
+    query.push("::text[]), false)");
+}
+
+/// Subject and subject alternative names of a client certificate.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct ClientCertificate {
+    /// Subject distinguished name in the format of RFC 4514, e.g. `CN=collector,O=Site A`.
+    pub subject: String,
+    /// DNS names, email addresses, URIs and IP addresses of the certificate.
+    pub alt_names: Vec<String>,
+}
+
+impl ClientCertificate {
+    /// Parses a DER encoded certificate.
+    pub fn from_der(der: &[u8]) -> Result<ClientCertificate, x509_cert::der::Error> {
+        let certificate = x509_cert::Certificate::from_der(der)?;
+        let alt_names = match certificate.tbs_certificate.get::<SubjectAltName>()? {
+            Some((_, SubjectAltName(names))) => names
+                .iter()
+                .filter_map(|name| match name {
+                    GeneralName::DnsName(name)
+                    | GeneralName::Rfc822Name(name)
+                    | GeneralName::UniformResourceIdentifier(name) => Some(name.to_string()),
+                    GeneralName::IpAddress(ip) => match ip.as_bytes().len() {
+                        4 => <[u8; 4]>::try_from(ip.as_bytes())
+                            .ok()
+                            .map(|ip| IpAddr::from(ip).to_string()),
+                        _ => <[u8; 16]>::try_from(ip.as_bytes())
+                            .ok()
+                            .map(|ip| IpAddr::from(ip).to_string()),
+                    },
+                    _ => None,
+                })
+                .collect(),
+            None => vec![],
+        };
+        Ok(ClientCertificate {
+            subject: certificate.tbs_certificate.subject.to_string(),
+            alt_names,
+        })
+    }
+
+    /// Returns `true` if the certificate satisfies the subject and subject alternative name of
+    /// `settings`.
+    fn matches(&self, settings: &ClientCertificateSettings) -> bool {
+        settings
+            .subject
+            .as_ref()
+            .is_none_or(|subject| *subject == self.subject)
+            && settings
+                .san
+                .as_ref()
+                .is_none_or(|san| self.alt_names.contains(san))
+    }
+}
+
+/// Stores the [`ClientCertificate`] of a TLS connection in the connection data. Meant to be
+/// passed to [`actix_web::HttpServer::on_connect`].
+pub fn extract_client_certificate(connection: &dyn Any, data: &mut Extensions) {
+    let Some(stream) = connection.downcast_ref::<TlsStream<TcpStream>>() else {
+        return;
+    };
+    let Some(certificate) = stream
+        .get_ref()
+        .1
+        .peer_certificates()
+        .and_then(|certificates| certificates.first())
+    else {
+        return;
+    };
+    match ClientCertificate::from_der(certificate.as_ref()) {
+        Ok(certificate) => {
+            data.insert(certificate);
+        }
+        Err(e) => tracing::warn!("Cannot parse client certificate: {e}"),
+    }
+}
+
+/// Returns the hex encoded SHA-256 hash of `token`.
+pub fn hash_token(token: &str) -> String {
+    format!("{:x}", Sha256::digest(token.as_bytes()))
+}
+
+/// Generates a new random token.
+pub fn generate_token() -> String {
+    let mut bytes = [0u8; 32];
+    rand::thread_rng().fill_bytes(&mut bytes);
+    base64::engine::general_purpose::URL_SAFE_NO_PAD.encode(bytes)
+}
+
+/// `Authenticator` looks up the [`Principal`] belonging to a bearer token or client certificate.
+#[derive(Clone)]
+pub struct Authenticator {
+    enabled: bool,
+    tokens: Arc<HashMap<String, Principal>>,
+    certificates: Arc<Vec<ClientCertificateSettings>>,
+    db_pool: PgPool,
+}
+
+impl Authenticator {
+    pub fn new(pool: PgPool, config: &AuthSettings) -> Result<Authenticator, anyhow::Error> {
+        let mut tokens = HashMap::new();
+        for token in config.tokens.iter() {
+            token.validate().map_err(anyhow::Error::msg)?;
+            tokens.insert(
+                token.token_sha256.to_lowercase(),
+                Principal {
+                    name: token.name.clone(),
+                    role: token.role,
+                    sites: token.sites.clone(),
+                },
+            );
+        }
+        for certificate in config.certificates.iter() {
+            certificate.validate().map_err(anyhow::Error::msg)?;
+        }
+
+        Ok(Authenticator {
+            enabled: config.enabled,
+            tokens: Arc::new(tokens),
+            certificates: Arc::new(config.certificates.clone()),
+            db_pool: pool,
+        })
+    }
+
+    /// Returns the [`Principal`] of `token`, or `None` if the token is unknown. Tokens from the
+    /// configuration take precedence over tokens stored in the database.
+    #[tracing::instrument(name = "Authenticating a token", skip_all)]
+    pub async fn authenticate(&self, token: &str) -> Result<Option<Principal>, sqlx::Error> {
+        let hash = hash_token(token);
+        if let Some(principal) = self.tokens.get(&hash) {
+            return Ok(Some(principal.clone()));
+        }
+
+        let row: Option<(String, String, Option<Vec<String>>)> =
+            sqlx::query_as("SELECT name, role, sites FROM api_tokens WHERE token_hash = $1")
+                .bind(&hash)
+                .fetch_optional(&self.db_pool)
+                .await?;
+        Ok(row.and_then(|(name, role, sites)| {
+            Role::try_from(role)
+                .map(|role| Principal { name, role, sites })
+                .ok()
+        }))
+    }
+
+    /// Returns the [`Principal`] of the first configured certificate mapping which matches
+    /// `certificate`, or `None` if there is no such mapping.
+    pub fn authenticate_certificate(&self, certificate: &ClientCertificate) -> Option<Principal> {
+        self.certificates
+            .iter()
+            .find(|settings| certificate.matches(settings))
+            .map(|settings| Principal {
+                name: certificate.subject.clone(),
+                role: settings.role,
+                sites: settings.sites.clone(),
+            })
+    }
+}
+
+#[derive(thiserror::Error)]
+pub enum AuthError {
+    #[error("A valid bearer token is required")]
+    Unauthorized,
+    #[error("The role {0} is not allowed to access this resource")]
+    Forbidden(Role),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+debug_for_error!(AuthError);
+
+impl ResponseError for AuthError {
+    fn status_code(&self) -> StatusCode {
+        match self {
+            AuthError::Unauthorized => StatusCode::UNAUTHORIZED,
+            AuthError::Forbidden(_) => StatusCode::FORBIDDEN,
+            AuthError::UnexpectedError(_) => StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        if let AuthError::Unauthorized = self {
+            response.insert_header((header::WWW_AUTHENTICATE, "Bearer"));
+        }
+        response.body(self.to_string())
+    }
+}
+
+/// Middleware which authenticates the bearer token or the client certificate of a request and
+/// checks whether the role of the client grants the access needed for the request. Does nothing
+/// if authentication is disabled.
+pub async fn authorize(
+    authenticator: web::Data<Authenticator>,
+    request: ServiceRequest,
+    next: Next<impl MessageBody>,
+) -> Result<ServiceResponse<impl MessageBody>, actix_web::Error> {
+    if !authenticator.enabled {
+        return next.call(request).await;
+    }
+    let Some(access) = Access::required_for(request.method(), request.path()) else {
+        return next.call(request).await;
+    };
+
+    let token = request
+        .headers()
+        .get(header::AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("Bearer "))
+        .map(str::trim);
+
+    // A bearer token takes precedence over the client certificate
+    let principal = match token {
+        Some(token) => authenticator
+            .authenticate(token)
+            .await
+            .map_err(|e| AuthError::UnexpectedError(e.into()))?,
+        None => request
+            .conn_data::<ClientCertificate>()
+            .and_then(|certificate| authenticator.authenticate_certificate(certificate)),
+    }
+    .ok_or(AuthError::Unauthorized)?;
+
+    if !principal.role.permits(access) {
+        tracing::warn!(
+            "Client {} with role {} was denied {access:?} access to {} {}",
+            principal.name,
+            principal.role,
+            request.method(),
+            request.path()
+        );
+        return Err(AuthError::Forbidden(principal.role).into());
+    }
+
+    request.extensions_mut().insert(principal);
+    next.call(request).await
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roles_grant_access() {
+        assert!(Role::Admin.permits(Access::Read));
+        assert!(Role::Admin.permits(Access::Write));
+        assert!(Role::Admin.permits(Access::Admin));
+        assert!(Role::Collector.permits(Access::Write));
+        assert!(!Role::Collector.permits(Access::Read));
+        assert!(!Role::Collector.permits(Access::Admin));
+        assert!(Role::Reader.permits(Access::Read));
+        assert!(!Role::Reader.permits(Access::Write));
+        assert!(!Role::Reader.permits(Access::Admin));
+    }
+
+    #[test]
+    fn access_depends_on_method_and_path() {
+        assert_eq!(Access::required_for(&Method::GET, "/health_check"), None);
+        assert_eq!(Access::required_for(&Method::GET, "/livez"), None);
+        assert_eq!(Access::required_for(&Method::GET, "/readyz"), None);
+        assert_eq!(Access::required_for(&Method::GET, "/openapi.json"), None);
+        assert_eq!(Access::required_for(&Method::GET, "/metrics"), None);
+        assert_eq!(
+            Access::required_for(&Method::GET, "/records"),
+            Some(Access::Read)
+        );
+        assert_eq!(
+            Access::required_for(&Method::POST, "/record"),
+            Some(Access::Write)
+        );
+        assert_eq!(
+            Access::required_for(&Method::PUT, "/records"),
+            Some(Access::Write)
+        );
+        assert_eq!(
+            Access::required_for(&Method::DELETE, "/record/r1"),
+            Some(Access::Admin)
+        );
+        assert_eq!(
+            Access::required_for(&Method::GET, "/tokens"),
+            Some(Access::Admin)
+        );
+    }
+
+    #[test]
+    fn access_does_not_depend_on_api_version() {
+        assert_eq!(Access::required_for(&Method::GET, "/v2/health_check"), None);
+        assert_eq!(
+            Access::required_for(&Method::POST, "/v1/record"),
+            Some(Access::Write)
+        );
+        assert_eq!(
+            Access::required_for(&Method::GET, "/v2/tokens"),
+            Some(Access::Admin)
+        );
+        assert_eq!(
+            Access::required_for(&Method::POST, "/v2/tokens/collector"),
+            Some(Access::Admin)
+        );
+    }
+
+    #[test]
+    fn tokens_are_hashed_with_sha256() {
+        assert_eq!(
+            hash_token("secret"),
+            "2bb80d537b1da3e38bd30361aa855686bde0eacd7162fef6a25fe97bf527a25b"
+        );
+        assert_ne!(generate_token(), generate_token());
+    }
+
+    #[test]
+    fn client_certificates_are_parsed_and_matched() {
+        let key = rcgen::KeyPair::generate().unwrap();
+        let mut params = rcgen::CertificateParams::new(vec![
+            "collector.site-a.example.org".to_string(),
+            "10.0.0.1".to_string(),
+        ])
+        .unwrap();
+        params
+            .distinguished_name
+            .push(rcgen::DnType::OrganizationName, "Site A");
+        params
+            .distinguished_name
+            .push(rcgen::DnType::CommonName, "collector");
+        let der = params.self_signed(&key).unwrap().der().to_vec();
+
+        let certificate = ClientCertificate::from_der(&der).unwrap();
+
+        assert_eq!(certificate.subject, "O=Site A,CN=collector");
+        assert_eq!(
+            certificate.alt_names,
+            vec!["collector.site-a.example.org", "10.0.0.1"]
+        );
+
+        let settings = |subject: Option<&str>, san: Option<&str>| ClientCertificateSettings {
+            subject: subject.map(String::from),
+            san: san.map(String::from),
+            role: Role::Collector,
+            sites: None,
+        };
+        assert!(certificate.matches(&settings(Some("O=Site A,CN=collector"), None)));
+        assert!(certificate.matches(&settings(None, Some("10.0.0.1"))));
+        assert!(certificate.matches(&settings(
+            Some("O=Site A,CN=collector"),
+            Some("collector.site-a.example.org")
+        )));
+        assert!(!certificate.matches(&settings(Some("CN=collector"), None)));
+        assert!(!certificate.matches(&settings(
+            Some("O=Site A,CN=collector"),
+            Some("site-b.example.org")
+        )));
+    }
+
+    #[test]
+    fn principals_can_be_restricted_to_sites() {
+        let principal = |sites: Option<Vec<&str>>| Principal {
+            name: "client".to_string(),
+            role: Role::Reader,
+            sites: sites.map(|sites| sites.into_iter().map(String::from).collect()),
+        };
+
+        assert!(principal(None).permits_sites::<&str>(&[]));
+        assert!(principal(None).permits_sites(&["site-b"]));
+        assert!(principal(Some(vec!["site-a"])).permits_sites(&["site-a"]));
+        assert!(principal(Some(vec!["site-a", "site-b"])).permits_sites(&["site-a", "site-b"]));
+        assert!(!principal(Some(vec!["site-a"])).permits_sites(&["site-a", "site-b"]));
+        assert!(!principal(Some(vec!["site-a"])).permits_sites::<&str>(&[]));
+        assert!(!principal(Some(vec!["site-a"])).permits_meta(None));
+    }
+}
@@ -0,0 +1,158 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Command line arguments of the `auditor` binary.
+
+use std::path::PathBuf;
+
+pub const USAGE: &str = "\
+Usage: auditor [COMMAND] [OPTIONS] [CONFIG_FILE]
+
+Commands:
+  serve             Start the server (default)
+  migrate           Create the database if necessary and apply all pending migrations
+  check-config      Validate the configuration without starting the server
+  stats             Print statistics about the stored records
+  vacuum-retention  Apply the retention policies once and vacuum the records table
+  prune-webhooks    Remove registered webhooks which are not configured
+  help              Print this message
+
+Options:
+  --migrate-on-start  Apply all pending migrations before starting the server
+  -h, --help          Print this message";
+
+/// Command to be executed by the `auditor` binary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Command {
+    #[default]
+    Serve,
+    Migrate,
+    CheckConfig,
+    Stats,
+    VacuumRetention,
+    PruneWebhooks,
+    Help,
+}
+
+impl Command {
+    fn from_name(name: &str) -> Option<Command> {
+        match name {
+            "serve" => Some(Command::Serve),
+            "migrate" => Some(Command::Migrate),
+            "check-config" => Some(Command::CheckConfig),
+            "stats" => Some(Command::Stats),
+            "vacuum-retention" => Some(Command::VacuumRetention),
+            "prune-webhooks" => Some(Command::PruneWebhooks),
+            "help" => Some(Command::Help),
+            _ => None,
+        }
+    }
+}
+
+/// Parsed command line arguments.
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct Cli {
+    pub command: Command,
+    /// Configuration file, which takes precedence over the files in the `configuration`
+    /// directory.
+    pub config_file: Option<PathBuf>,
+    /// Apply pending migrations before starting the server.
+    pub migrate_on_start: bool,
+}
+
+impl Cli {
+    /// Parses the command line arguments `args`, excluding the name of the binary.
+    ///
+    /// For backwards compatibility, `auditor <CONFIG_FILE>` still starts the server.
+    pub fn parse(args: impl IntoIterator<Item = String>) -> Result<Cli, anyhow::Error> {
+        let mut cli = Cli::default();
+        let mut command = None;
+        for arg in args {
+            match arg.as_str() {
+                "-h" | "--help" => command = Some(Command::Help),
+                "--migrate-on-start" => cli.migrate_on_start = true,
+                option if option.starts_with('-') => {
+                    anyhow::bail!("Unknown option '{option}'")
+                }
+                name if command.is_none() && cli.config_file.is_none() => {
+                    match Command::from_name(name) {
+                        Some(name) => command = Some(name),
+                        None => cli.config_file = Some(PathBuf::from(arg)),
+                    }
+                }
+                _ if cli.config_file.is_none() => cli.config_file = Some(PathBuf::from(arg)),
+                _ => anyhow::bail!("Unexpected argument '{arg}'"),
+            }
+        }
+        cli.command = command.unwrap_or_default();
+
+        if cli.migrate_on_start && !matches!(cli.command, Command::Serve | Command::Help) {
+            anyhow::bail!("--migrate-on-start can only be used when starting the server");
+        }
+        Ok(cli)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(args: &[&str]) -> Result<Cli, anyhow::Error> {
+        Cli::parse(args.iter().map(|arg| arg.to_string()))
+    }
+
+    #[test]
+    fn server_is_started_by_default() {
+        assert_eq!(parse(&[]).unwrap(), Cli::default());
+        assert_eq!(
+            parse(&["config.yaml"]).unwrap(),
+            Cli {
+                command: Command::Serve,
+                config_file: Some(PathBuf::from("config.yaml")),
+                migrate_on_start: false,
+            }
+        );
+        assert_eq!(
+            parse(&["--migrate-on-start", "config.yaml"]).unwrap(),
+            Cli {
+                command: Command::Serve,
+                config_file: Some(PathBuf::from("config.yaml")),
+                migrate_on_start: true,
+            }
+        );
+    }
+
+    #[test]
+    fn commands_are_parsed() {
+        for (name, command) in [
+            ("serve", Command::Serve),
+            ("migrate", Command::Migrate),
+            ("check-config", Command::CheckConfig),
+            ("stats", Command::Stats),
+            ("vacuum-retention", Command::VacuumRetention),
+            ("prune-webhooks", Command::PruneWebhooks),
+            ("help", Command::Help),
+            ("--help", Command::Help),
+        ] {
+            let cli = parse(&[name, "config.yaml"]).unwrap();
+            assert_eq!(cli.command, command, "{name}");
+            assert_eq!(
+                cli.config_file,
+                Some(PathBuf::from("config.yaml")),
+                "{name}"
+            );
+        }
+        assert_eq!(parse(&["stats"]).unwrap().config_file, None);
+    }
+
+    #[test]
+    fn invalid_arguments_are_rejected() {
+        assert!(parse(&["--unknown"]).is_err());
+        assert!(parse(&["migrate", "a.yaml", "b.yaml"]).is_err());
+        assert!(parse(&["stats", "--migrate-on-start"]).is_err());
+    }
+}
@@ -11,6 +11,7 @@ use secrecy::{ExposeSecret, Secret};
 use serde_aux::field_attributes::deserialize_number_from_string;
 use sqlx::ConnectOptions;
 use sqlx::postgres::{PgConnectOptions, PgSslMode};
+use std::collections::HashMap;
 use tracing_subscriber::filter::LevelFilter;
 
 #[derive(serde::Deserialize, Debug)]
@@ -22,6 +23,16 @@ pub struct Settings {
     pub application: AuditorSettings,
     #[serde(default = "default_metrics")]
     pub metrics: MetricsSettings,
+    #[serde(default = "default_retention")]
+    pub retention: RetentionSettings,
+    #[serde(default = "default_partitioning")]
+    pub partitioning: PartitionSettings,
+    #[serde(default = "default_daily_usage")]
+    pub daily_usage: DailyUsageSettings,
+    #[serde(default = "default_auth")]
+    pub auth: AuthSettings,
+    #[serde(default = "default_webhooks")]
+    pub webhooks: WebhookSettings,
     #[serde(default = "default_log_level")]
     #[serde(deserialize_with = "deserialize_log_level")]
     pub log_level: LevelFilter,
@@ -124,7 +135,8 @@ pub struct DatabaseMetricsSettings {
     #[serde(default = "default_db_metrics_frequency")]
     #[serde_as(as = "serde_with::DurationSeconds<i64>")]
     pub frequency: chrono::Duration,
-    pub metrics: Vec<crate::metrics::DatabaseMetricsOptions>,
+    #[serde(deserialize_with = "crate::metrics::deserialize_database_metrics")]
+    pub metrics: Vec<crate::metrics::DatabaseMetric>,
 }
 
 fn default_db_metrics_frequency() -> chrono::Duration {
@@ -140,6 +152,251 @@ fn default_metrics() -> MetricsSettings {
     }
 }
 
+#[serde_with::serde_as]
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionSettings {
+    #[serde(default = "default_retention_frequency")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub frequency: chrono::Duration,
+    #[serde(default)]
+    pub policies: Vec<RetentionPolicy>,
+}
+
+/// Records whose `stop_time` lies further in the past than the sum of `older_than_months` and
+/// `older_than_days` are deleted. If `meta` is given, only records which contain at least one
+/// of the listed values for each of the meta keys are deleted.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct RetentionPolicy {
+    #[serde(default)]
+    pub older_than_months: u32,
+    #[serde(default)]
+    pub older_than_days: u32,
+    #[serde(default)]
+    pub meta: HashMap<String, Vec<String>>,
+}
+
+impl RetentionPolicy {
+    /// Checks that the policy does not delete records immediately.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.older_than_months == 0 && self.older_than_days == 0 {
+            return Err(
+                "Either older_than_months or older_than_days has to be set for a retention policy"
+                    .to_string(),
+            );
+        }
+        if self.meta.values().any(|values| values.is_empty()) {
+            return Err("The meta keys of a retention policy need at least one value".to_string());
+        }
+        Ok(())
+    }
+}
+
+fn default_retention_frequency() -> chrono::Duration {
+    chrono::Duration::try_hours(1).expect("This should never fail")
+}
+
+fn default_retention() -> RetentionSettings {
+    RetentionSettings {
+        frequency: default_retention_frequency(),
+        policies: vec![],
+    }
+}
+
+/// The records are partitioned by the month of their `stop_time`. Partitions are created for the
+/// current month and `months_ahead` upcoming months every `frequency`.
+#[serde_with::serde_as]
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct PartitionSettings {
+    #[serde(default = "default_partitioning_frequency")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub frequency: chrono::Duration,
+    #[serde(default = "default_partitioning_months_ahead")]
+    pub months_ahead: u32,
+}
+
+fn default_partitioning_frequency() -> chrono::Duration {
+    chrono::Duration::try_hours(1).expect("This should never fail")
+}
+
+fn default_partitioning_months_ahead() -> u32 {
+    3
+}
+
+fn default_partitioning() -> PartitionSettings {
+    PartitionSettings {
+        frequency: default_partitioning_frequency(),
+        months_ahead: default_partitioning_months_ahead(),
+    }
+}
+
+#[serde_with::serde_as]
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct DailyUsageSettings {
+    /// Interval in which the daily usage of changed days is computed again.
+    #[serde(default = "default_daily_usage_frequency")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub frequency: chrono::Duration,
+}
+
+fn default_daily_usage_frequency() -> chrono::Duration {
+    chrono::Duration::try_seconds(60).expect("This should never fail")
+}
+
+fn default_daily_usage() -> DailyUsageSettings {
+    DailyUsageSettings {
+        frequency: default_daily_usage_frequency(),
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct AuthSettings {
+    #[serde(default)]
+    pub enabled: bool,
+    #[serde(default)]
+    pub tokens: Vec<ApiTokenSettings>,
+    #[serde(default)]
+    pub certificates: Vec<ClientCertificateSettings>,
+}
+
+/// An API token listed in the configuration. Only the hex encoded SHA-256 hash of the token is
+/// configured, e.g. the output of `echo -n <token> | sha256sum`.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ApiTokenSettings {
+    pub name: String,
+    pub role: crate::auth::Role,
+    pub token_sha256: String,
+    /// Restricts the token to the records of these sites.
+    #[serde(default)]
+    pub sites: Option<Vec<String>>,
+}
+
+impl ApiTokenSettings {
+    /// Checks that the configured hash is a SHA-256 hash.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.token_sha256.len() != 64
+            || !self.token_sha256.chars().all(|c| c.is_ascii_hexdigit())
+        {
+            return Err(format!(
+                "The token_sha256 of API token {} is not a hex encoded SHA-256 hash",
+                self.name
+            ));
+        }
+        validate_sites(&self.sites)
+    }
+}
+
+/// Maps client certificates to a role. A certificate matches if it has the given `subject` and
+/// the subject alternative name `san`, whichever of both are set. The first matching mapping is
+/// applied.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct ClientCertificateSettings {
+    /// Subject distinguished name in the format of RFC 4514, e.g. `CN=collector,O=Site A`.
+    #[serde(default)]
+    pub subject: Option<String>,
+    #[serde(default)]
+    pub san: Option<String>,
+    pub role: crate::auth::Role,
+    /// Restricts the certificate to the records of these sites.
+    #[serde(default)]
+    pub sites: Option<Vec<String>>,
+}
+
+impl ClientCertificateSettings {
+    /// Checks that the mapping does not match every certificate.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.subject.is_none() && self.san.is_none() {
+            return Err(
+                "Either subject or san has to be set for a client certificate mapping".to_string(),
+            );
+        }
+        validate_sites(&self.sites)
+    }
+}
+
+/// Checks that `sites`, if set, lists at least one site.
+pub(crate) fn validate_sites(sites: &Option<Vec<String>>) -> Result<(), String> {
+    if sites.as_ref().is_some_and(Vec::is_empty) {
+        return Err("At least one site has to be listed if sites is set".to_string());
+    }
+    Ok(())
+}
+
+fn default_auth() -> AuthSettings {
+    AuthSettings {
+        enabled: false,
+        tokens: vec![],
+        certificates: vec![],
+    }
+}
+
+#[serde_with::serde_as]
+#[derive(serde::Deserialize, Debug, Clone)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookSettings {
+    /// Interval in which new events are delivered.
+    #[serde(default = "default_webhook_frequency")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub frequency: chrono::Duration,
+    /// Timeout of a single delivery.
+    #[serde(default = "default_webhook_timeout")]
+    #[serde_as(as = "serde_with::DurationSeconds<i64>")]
+    pub timeout: chrono::Duration,
+    /// Number of attempts after which a delivery is given up and deleted.
+    #[serde(default = "default_webhook_max_attempts")]
+    pub max_attempts: u32,
+    #[serde(default)]
+    pub targets: Vec<WebhookTarget>,
+}
+
+/// A webhook to which the matching records are posted when the `events` occur.
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct WebhookTarget {
+    pub name: String,
+    pub url: String,
+    /// Query string with the filters of `GET /records`, e.g. `meta[site_id][c]=site1`. Only
+    /// records matching the filters are posted.
+    #[serde(default)]
+    pub filter: Option<String>,
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<crate::webhooks::WebhookEvent>,
+}
+
+fn default_webhook_frequency() -> chrono::Duration {
+    chrono::Duration::try_seconds(10).expect("This should never fail")
+}
+
+fn default_webhook_timeout() -> chrono::Duration {
+    chrono::Duration::try_seconds(10).expect("This should never fail")
+}
+
+fn default_webhook_max_attempts() -> u32 {
+    10
+}
+
+fn default_webhook_events() -> Vec<crate::webhooks::WebhookEvent> {
+    vec![
+        crate::webhooks::WebhookEvent::RecordAdded,
+        crate::webhooks::WebhookEvent::RecordCompleted,
+    ]
+}
+
+fn default_webhooks() -> WebhookSettings {
+    WebhookSettings {
+        frequency: default_webhook_frequency(),
+        timeout: default_webhook_timeout(),
+        max_attempts: default_webhook_max_attempts(),
+        targets: vec![],
+    }
+}
+
 impl DatabaseSettings {
     /// Returns the connection options for the PostgreSQL database without database name
     pub fn without_db(&self) -> PgConnectOptions {
@@ -164,8 +421,16 @@ impl DatabaseSettings {
     }
 }
 
-/// Loads the configuration from a file `configuration.{yaml,json,toml,...}`
+/// Loads the configuration from a file `configuration.{yaml,json,toml,...}`, using the first
+/// command line argument as configuration file.
 pub fn get_configuration() -> Result<Settings, config::ConfigError> {
+    get_configuration_from(std::env::args().nth(1).as_deref().map(std::path::Path::new))
+}
+
+/// Same as [`get_configuration`], but with an explicitly given configuration `file`.
+pub fn get_configuration_from(
+    file: Option<&std::path::Path>,
+) -> Result<Settings, config::ConfigError> {
     let base_path = std::env::current_dir().expect("Failed to determine the current directory");
     let configuration_directory = base_path.join("configuration");
 
@@ -190,9 +455,9 @@ pub fn get_configuration() -> Result<Settings, config::ConfigError> {
             config::File::from(configuration_directory.join(environment.as_str())).required(false),
         );
 
-    let settings = match std::env::args().nth(1) {
+    let settings = match file {
         Some(file) => settings.add_source(
-            config::File::from(file.as_ref())
+            config::File::from(file)
                 .required(false)
                 .format(config::FileFormat::Yaml),
         ),
@@ -7,3 +7,6 @@
 
 pub const ERR_RECORD_EXISTS: &str = "RECORD_EXISTS";
 pub const ERR_UNEXPECTED_ERROR: &str = "UNEXPECTED_ERROR";
+
+/// Response header of `GET /records` holding the cursor of the next page.
+pub const HEADER_NEXT_CURSOR: &str = "X-Next-Cursor";
@@ -0,0 +1,71 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Types used for serializing and deserializing aggregated resource usage.
+
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// `UsageAggregate` holds the summed resource usage of all records that share the same values
+/// for the meta keys that were used for grouping.
+///
+/// A record that has several values for a meta key used for grouping is accounted for in the
+/// group of each of these values.
+///
+/// # Example
+///
+/// Retrieve the usage per site:
+///
+/// ```ignore
+/// # use auditor_client::{AuditorClientBuilder, ClientError, QueryBuilder};
+/// #
+/// # async fn foo() -> Result<(), ClientError> {
+/// let client = AuditorClientBuilder::new()
+///     .address(&"localhost", 8000)
+///     .build()?;
+///
+/// let usage = QueryBuilder::new()
+///     .aggregate(&["site_id"], client)
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct UsageAggregate {
+    /// Values of the meta keys the records were grouped by. The value is `None` for records
+    /// that do not have the meta key.
+    pub group: BTreeMap<String, Option<String>>,
+    /// Number of records in this group.
+    pub record_count: i64,
+    /// Summed runtime (in seconds) of all records in this group.
+    pub runtime: i64,
+    /// Summed usage per component name.
+    pub components: Vec<ComponentAggregate>,
+}
+
+/// Summed usage of all components with the same name within a [`UsageAggregate`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ComponentAggregate {
+    /// Name of the component.
+    pub name: String,
+    /// Sum of the amounts.
+    pub amount: f64,
+    /// Sum of the amounts multiplied by the runtime of the respective record.
+    pub amount_runtime: f64,
+    /// Score weighted usage per score name.
+    pub scores: Vec<ScoreAggregate>,
+}
+
+/// Score weighted usage of a component within a [`ComponentAggregate`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ScoreAggregate {
+    /// Name of the score.
+    pub name: String,
+    /// Sum of the amounts multiplied by the runtime of the respective record and the score value.
+    pub amount_runtime_score: f64,
+}
@@ -0,0 +1,114 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Versions of the REST API and the frames of streamed responses.
+
+use serde::{Deserialize, Serialize};
+
+use super::Record;
+
+/// Version of the REST API.
+///
+/// Both versions are served side by side. Requests to paths without a version prefix are
+/// answered by [`ApiVersion::V1`], so that deployed collectors keep working.
+///
+/// `V2` differs from `V1` in that
+/// * all errors are returned as JSON object `{"error": "<message>"}`,
+/// * adding a record which already exists fails with `409 Conflict` instead of
+///   `500 Internal Server Error` and the body `RECORD_EXISTS`,
+/// * requesting a single record which does not exist fails with `404 Not Found` instead of
+///   returning `null`,
+/// * records in the `application/x-ndjson` format are sent as [`RecordFrame`]s.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, PartialOrd, Ord)]
+#[serde(rename_all = "lowercase")]
+pub enum ApiVersion {
+    #[default]
+    V1,
+    V2,
+}
+
+impl ApiVersion {
+    /// Returns the path prefix of the version, e.g. `/v2`.
+    pub fn prefix(&self) -> &'static str {
+        match self {
+            ApiVersion::V1 => "/v1",
+            ApiVersion::V2 => "/v2",
+        }
+    }
+
+    /// Splits `path` into the version it addresses and the path within the version.
+    pub fn from_path(path: &str) -> (ApiVersion, &str) {
+        for version in [ApiVersion::V1, ApiVersion::V2] {
+            if let Some(rest) = path.strip_prefix(version.prefix())
+                && (rest.is_empty() || rest.starts_with('/'))
+            {
+                return (version, rest);
+            }
+        }
+        (ApiVersion::V1, path)
+    }
+}
+
+/// Frame of a stream of records in the `application/x-ndjson` format of [`ApiVersion::V2`].
+///
+/// Every frame is a JSON object on a line of its own. The records are followed by exactly one
+/// `end` or `error` frame, so that clients can tell a complete stream from a truncated one:
+///
+/// ```text
+/// {"record":{"record_id":"r1",...}}
+/// {"record":{"record_id":"r2",...}}
+/// {"end":{"count":2}}
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum RecordFrame {
+    Record(Box<Record>),
+    /// The stream is complete and contained `count` records.
+    End {
+        count: u64,
+    },
+    /// The stream was aborted, records may be missing.
+    Error(String),
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn versions_are_split_from_paths() {
+        assert_eq!(
+            ApiVersion::from_path("/records"),
+            (ApiVersion::V1, "/records")
+        );
+        assert_eq!(
+            ApiVersion::from_path("/v1/records"),
+            (ApiVersion::V1, "/records")
+        );
+        assert_eq!(
+            ApiVersion::from_path("/v2/record/r1"),
+            (ApiVersion::V2, "/record/r1")
+        );
+        assert_eq!(ApiVersion::from_path("/v2"), (ApiVersion::V2, ""));
+        assert_eq!(
+            ApiVersion::from_path("/v2records"),
+            (ApiVersion::V1, "/v2records")
+        );
+    }
+
+    #[test]
+    fn frames_are_externally_tagged() {
+        assert_eq!(
+            serde_json::to_string(&RecordFrame::End { count: 2 }).unwrap(),
+            r#"{"end":{"count":2}}"#
+        );
+        assert_eq!(
+            serde_json::to_string(&RecordFrame::Error("failed".to_string())).unwrap(),
+            r#"{"error":"failed"}"#
+        );
+    }
+}
@@ -0,0 +1,68 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use serde::{Deserialize, Serialize};
+
+/// Determines what happens to added records whose `record_id` already exists.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum OnConflict {
+    /// The stored record is kept and the new record is reported as duplicate. A single record
+    /// added with `POST /record` which differs from the stored one is rejected as conflict.
+    Skip,
+    /// The stored record is replaced by the new record.
+    Update,
+}
+
+impl OnConflict {
+    /// Returns the value of the `on_conflict` query parameter.
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            OnConflict::Skip => "skip",
+            OnConflict::Update => "update",
+        }
+    }
+}
+
+/// Outcome of inserting a single record of a bulk insert.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum InsertStatus {
+    /// The record was stored.
+    Inserted,
+    /// An existing record with the same `record_id` was replaced.
+    Updated,
+    /// A record with the same `record_id` already exists or occurs earlier in the same request.
+    Duplicate,
+    /// The record could not be parsed.
+    Invalid,
+}
+
+/// Status of a single record of a bulk insert.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct InsertResult {
+    /// `record_id` of the record. Only missing if an invalid record has no `record_id`.
+    pub record_id: Option<String>,
+    pub status: InsertStatus,
+    /// Reason why the record is invalid.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// `BulkInsertReport` is returned by Auditor after inserting records with `on_conflict` set.
+/// It holds the status of every record in the order of the request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct BulkInsertReport {
+    pub records: Vec<InsertResult>,
+}
+
+impl BulkInsertReport {
+    /// Returns the number of records with the given status.
+    pub fn count(&self, status: InsertStatus) -> usize {
+        self.records.iter().filter(|r| r.status == status).count()
+    }
+}
@@ -0,0 +1,39 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use serde::{Deserialize, Serialize};
+
+/// Outcome of a single update of a bulk update.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum UpdateStatus {
+    /// The record was updated.
+    Updated,
+    /// No record with this `record_id` exists.
+    Unknown,
+}
+
+/// Status of a single update of a bulk update.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct UpdateResult {
+    pub record_id: String,
+    pub status: UpdateStatus,
+}
+
+/// `BulkUpdateReport` is returned by Auditor after updating several records at once.
+/// It holds the status of every update in the order of the request.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, utoipa::ToSchema)]
+pub struct BulkUpdateReport {
+    pub records: Vec<UpdateResult>,
+}
+
+impl BulkUpdateReport {
+    /// Returns the number of updates with the given status.
+    pub fn count(&self, status: UpdateStatus) -> usize {
+        self.records.iter().filter(|r| r.status == status).count()
+    }
+}
@@ -33,8 +33,20 @@ use sqlx::{
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Debug, PartialEq, Eq, Serialize, Deserialize, sqlx::Encode, Clone, PartialOrd, Ord)]
+#[derive(
+    Debug,
+    PartialEq,
+    Eq,
+    Serialize,
+    Deserialize,
+    sqlx::Encode,
+    Clone,
+    PartialOrd,
+    Ord,
+    utoipa::ToSchema,
+)]
 #[sqlx(type_name = "component")]
+#[schema(description = "Component of a record that is accounted for, e.g. CPU cores or memory.")]
 pub struct Component {
     /// Name of the component.
     pub name: ValidName,
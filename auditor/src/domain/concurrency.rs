@@ -0,0 +1,69 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Types used for serializing and deserializing the usage of concurrently running records.
+
+use std::collections::BTreeMap;
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::UsageAggregate;
+
+/// `ConcurrentUsage` holds the records that were running at a sample point in time.
+///
+/// A record is running at time `t` if `start_time <= t < stop_time`. Records without a
+/// `stop_time` are considered to be still running.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ConcurrentUsage {
+    /// The sample point in time.
+    pub time: DateTime<Utc>,
+    /// Usage of the running records, one entry per group. Groups without running records are
+    /// omitted.
+    pub usage: Vec<ConcurrentAggregate>,
+}
+
+/// Summed usage of all records running at a sample point that share the same values for the
+/// meta keys that were used for grouping.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ConcurrentAggregate {
+    /// Values of the meta keys the records were grouped by. The value is `None` for records
+    /// that do not have the meta key.
+    pub group: BTreeMap<String, Option<String>>,
+    /// Number of running records in this group.
+    pub record_count: i64,
+    /// Summed amount per component name.
+    pub components: Vec<ConcurrentComponent>,
+}
+
+/// Summed amount of all components with the same name within a [`ConcurrentAggregate`].
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct ConcurrentComponent {
+    /// Name of the component.
+    pub name: String,
+    /// Sum of the amounts.
+    pub amount: f64,
+}
+
+impl From<UsageAggregate> for ConcurrentAggregate {
+    /// Keeps the number of records and the amounts of `usage`, which are the values that can be
+    /// summed up at a point in time.
+    fn from(usage: UsageAggregate) -> Self {
+        ConcurrentAggregate {
+            group: usage.group,
+            record_count: usage.record_count,
+            components: usage
+                .components
+                .into_iter()
+                .map(|component| ConcurrentComponent {
+                    name: component.name,
+                    amount: component.amount,
+                })
+                .collect(),
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Types used for serializing and deserializing the daily usage summary.
+
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+use super::BucketSize;
+
+/// Parameters of a query of the daily usage summary.
+///
+/// Returns the usage of the days from `from` (inclusive) to `to` (exclusive), summed up per
+/// `bucket`, which is either [`BucketSize::Day`] (default) or [`BucketSize::Month`]. The optional
+/// fields restrict the usage to the given site, group, user or component.
+///
+/// # Example
+///
+/// Retrieve the monthly usage of site-x in 2024:
+///
+/// ```ignore
+/// # use auditor_client::{AuditorClientBuilder, ClientError};
+/// # use auditor::domain::{BucketSize, DailyUsageQuery};
+/// # use chrono::NaiveDate;
+/// #
+/// # async fn foo() -> Result<(), ClientError> {
+/// let client = AuditorClientBuilder::new()
+///     .address(&"localhost", 8000)
+///     .build()?;
+///
+/// let query = DailyUsageQuery::new(
+///     NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+///     NaiveDate::from_ymd_opt(2025, 1, 1).unwrap(),
+/// )
+/// .with_bucket(BucketSize::Month)
+/// .with_site_id("site-x");
+/// let usage = client.daily_usage(&query).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct DailyUsageQuery {
+    /// First day (inclusive).
+    pub from: NaiveDate,
+    /// Last day (exclusive).
+    pub to: NaiveDate,
+    /// Size of the buckets the usage is summed up in, `day` (default) or `month`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub bucket: Option<BucketSize>,
+    /// Only returns the usage of this site.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub site_id: Option<String>,
+    /// Only returns the usage of this group.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub group_id: Option<String>,
+    /// Only returns the usage of this user.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub user_id: Option<String>,
+    /// Only returns the usage of this component.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub component: Option<String>,
+}
+
+impl DailyUsageQuery {
+    pub fn new(from: NaiveDate, to: NaiveDate) -> DailyUsageQuery {
+        DailyUsageQuery {
+            from,
+            to,
+            bucket: None,
+            site_id: None,
+            group_id: None,
+            user_id: None,
+            component: None,
+        }
+    }
+
+    pub fn with_bucket(mut self, bucket: BucketSize) -> Self {
+        self.bucket = Some(bucket);
+        self
+    }
+
+    pub fn with_site_id<T: AsRef<str>>(mut self, site_id: T) -> Self {
+        self.site_id = Some(site_id.as_ref().to_string());
+        self
+    }
+
+    pub fn with_group_id<T: AsRef<str>>(mut self, group_id: T) -> Self {
+        self.group_id = Some(group_id.as_ref().to_string());
+        self
+    }
+
+    pub fn with_user_id<T: AsRef<str>>(mut self, user_id: T) -> Self {
+        self.user_id = Some(user_id.as_ref().to_string());
+        self
+    }
+
+    pub fn with_component<T: AsRef<str>>(mut self, component: T) -> Self {
+        self.component = Some(component.as_ref().to_string());
+        self
+    }
+}
+
+/// Usage of the records that stopped on one day (or in one month), summed up per site, group,
+/// user and component.
+///
+/// The site, group and user are the values of the meta keys `site_id`, `group_id` and
+/// `user_id`, which are `None` for records without the meta key. A record with several values
+/// for one of the meta keys is accounted for in each of them. Records without components are
+/// summed up with `component` being `None`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, sqlx::FromRow, utoipa::ToSchema)]
+pub struct DailyUsage {
+    /// The day, or the first day of the month.
+    pub date: NaiveDate,
+    pub site_id: Option<String>,
+    pub group_id: Option<String>,
+    pub user_id: Option<String>,
+    pub component: Option<String>,
+    /// Sum of the amounts of the component multiplied by the runtime of the respective record.
+    pub amount_runtime: f64,
+    /// Number of records.
+    pub record_count: i64,
+}
@@ -0,0 +1,90 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Types used for serializing and deserializing time-bucketed resource usage.
+
+use chrono::{DateTime, Datelike, Months, NaiveTime, TimeDelta, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+use super::UsageAggregate;
+
+/// Width of the buckets of a usage histogram. Buckets are aligned to full hours, days or months
+/// in UTC.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum BucketSize {
+    Hour,
+    Day,
+    Month,
+}
+
+impl BucketSize {
+    /// Returns the start of the bucket that contains `time`.
+    pub fn truncate(&self, time: DateTime<Utc>) -> DateTime<Utc> {
+        let midnight = time.date_naive().and_time(NaiveTime::MIN);
+        match self {
+            BucketSize::Hour => (midnight + TimeDelta::hours(time.hour().into())).and_utc(),
+            BucketSize::Day => midnight.and_utc(),
+            BucketSize::Month => midnight
+                .with_day(1)
+                .expect("The first day exists in every month")
+                .and_utc(),
+        }
+    }
+
+    /// Returns the start of the bucket following the bucket that starts at `start`.
+    pub fn next(&self, start: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        match self {
+            BucketSize::Hour => start.checked_add_signed(TimeDelta::hours(1)),
+            BucketSize::Day => start.checked_add_signed(TimeDelta::days(1)),
+            BucketSize::Month => start.checked_add_months(Months::new(1)),
+        }
+    }
+}
+
+/// `UsageBucket` holds the resource usage within one bucket of a usage histogram.
+///
+/// Records that overlap with several buckets are split proportionally: only the part of the
+/// runtime that lies within the bucket is accounted for. A job that runs from 23:00 to 01:00
+/// therefore contributes one hour of runtime to each of the two days.
+///
+/// # Example
+///
+/// Retrieve the daily usage per site in January 2024:
+///
+/// ```ignore
+/// # use auditor_client::{AuditorClientBuilder, ClientError, QueryBuilder};
+/// # use auditor::domain::BucketSize;
+/// # use chrono::{TimeZone, Utc};
+/// #
+/// # async fn foo() -> Result<(), ClientError> {
+/// let client = AuditorClientBuilder::new()
+///     .address(&"localhost", 8000)
+///     .build()?;
+///
+/// let histogram = QueryBuilder::new()
+///     .histogram(
+///         BucketSize::Day,
+///         Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap(),
+///         Utc.with_ymd_and_hms(2024, 2, 1, 0, 0, 0).unwrap(),
+///         &["site_id"],
+///         client,
+///     )
+///     .await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct UsageBucket {
+    /// Start of the bucket (inclusive).
+    pub start: DateTime<Utc>,
+    /// End of the bucket (exclusive).
+    pub stop: DateTime<Utc>,
+    /// Usage within the bucket, one entry per group. `record_count` is the number of records
+    /// overlapping with the bucket and `runtime` the summed runtime within the bucket.
+    pub usage: Vec<UsageAggregate>,
+}
@@ -0,0 +1,39 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+
+/// Kind of change of a record.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ChangeOperation {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// A change of a record, as returned by `GET /record/<record_id>/history`.
+///
+/// The values are keyed by the name of the field (`start_time`, `stop_time`, `runtime`, `meta`
+/// and `components`). Inserts contain all fields of the new record, updates only the fields
+/// which changed. Deletes contain all fields of the deleted record in `old` and no new values.
+/// Records deleted by a retention policy only keep their deletion, without any values.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, utoipa::ToSchema)]
+pub struct RecordChange {
+    pub operation: ChangeOperation,
+    /// Values before the change. `None` for inserts and purged deletes.
+    #[schema(value_type = Option<Object>)]
+    pub old: Option<Map<String, Value>>,
+    /// Values after the change.
+    #[schema(value_type = Object)]
+    pub new: Map<String, Value>,
+    pub changed_at: DateTime<Utc>,
+    /// Name of the client that made the change. `None` if authentication is disabled.
+    pub changed_by: Option<String>,
+}
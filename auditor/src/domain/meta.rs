@@ -11,7 +11,8 @@ use serde::{Deserialize, Serialize};
 
 use super::ValidName;
 
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default)]
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, utoipa::ToSchema)]
+#[schema(description = "Meta information of a record, which maps keys to lists of values.")]
 pub struct ValidMeta(pub HashMap<ValidName, Vec<ValidName>>);
 
 impl ValidMeta {
@@ -101,7 +102,10 @@ impl TryFrom<Meta> for ValidMeta {
 /// meta.insert("site_id".to_string(), vec!["site1".to_string()]);
 /// meta.insert("features".to_string(), vec!["ssd".to_string(), "gpu".to_string()]);
 /// ```
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, sqlx::FromRow)]
+#[derive(
+    Serialize, Deserialize, Clone, Debug, PartialEq, Eq, Default, sqlx::FromRow, utoipa::ToSchema,
+)]
+#[schema(description = "Meta information of a record, which maps keys to lists of values.")]
 pub struct Meta(pub HashMap<String, Vec<String>>);
 
 impl Meta {
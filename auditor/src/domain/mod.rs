@@ -5,7 +5,16 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+mod aggregate;
+mod api_version;
+mod bulk_insert;
+mod bulk_update;
 mod component;
+mod concurrency;
+mod daily_usage;
+mod deleted;
+mod histogram;
+mod history;
 mod meta;
 mod record;
 mod score;
@@ -14,9 +23,18 @@ mod validname;
 mod validvalue;
 
 use actix_web::{ResponseError, http::StatusCode};
+pub use aggregate::{ComponentAggregate, ScoreAggregate, UsageAggregate};
+pub use api_version::{ApiVersion, RecordFrame};
+pub use bulk_insert::{BulkInsertReport, InsertResult, InsertStatus, OnConflict};
+pub use bulk_update::{BulkUpdateReport, UpdateResult, UpdateStatus};
 pub use component::{Component, ComponentTest};
+pub use concurrency::{ConcurrentAggregate, ConcurrentComponent, ConcurrentUsage};
+pub use daily_usage::{DailyUsage, DailyUsageQuery};
+pub use deleted::DeletedRecords;
+pub use histogram::{BucketSize, UsageBucket};
+pub use history::{ChangeOperation, RecordChange};
 pub use meta::{Meta, ValidMeta};
-pub use record::{Record, RecordAdd, RecordDatabase, RecordTest, RecordUpdate};
+pub use record::{MetaMode, Record, RecordAdd, RecordDatabase, RecordTest, RecordUpdate};
 pub use score::{Score, ScoreTest};
 pub use validamount::ValidAmount;
 pub use validname::ValidName;
@@ -79,7 +79,8 @@ use serde::{Deserialize, Serialize};
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, utoipa::ToSchema)]
+#[schema(description = "Record that is added to Auditor.")]
 pub struct RecordAdd {
     /// Unique identifier of the record.
     pub record_id: ValidName,
@@ -93,12 +94,12 @@ pub struct RecordAdd {
     pub stop_time: Option<DateTime<Utc>>,
 }
 
-/// `RecordUpdate` represents a single accountable unit that is used to set the `stop_time` of a
+/// `RecordUpdate` represents a single accountable unit that is used to update an existing
 /// [`Record`].
 ///
 /// Initially, records are added to Auditor by pushing a [`RecordAdd`], where the `stop_time` field
-/// is optional. To later set the `stop_time` of the record, push a `RecordUpdate` with the same
-/// `record_id` to auditor.
+/// is optional. To later set the `stop_time` of the record, or to add further information to it,
+/// push a `RecordUpdate` with the same `record_id` to auditor.
 ///
 /// Use the constructor to build a new record.
 ///
@@ -109,8 +110,13 @@ pub struct RecordAdd {
 /// the record is already valid in terms of all checks that
 /// Auditor performs when receiving it.
 ///
-/// Currently, only the `stop_time` can be updated.
-/// Setting other fields such as `meta` or `components` has no effect.
+/// Updates are applied with PATCH semantics:
+/// * The `stop_time` is always set and the runtime of the record is recomputed.
+/// * The `start_time` is only corrected if it is set (see [`RecordUpdate::with_start_time`]).
+/// * The `meta` information is merged into the stored one. The values of meta keys that are
+///   already present in the record are replaced, all other meta keys are left untouched.
+/// * Each of the `components` replaces the stored component with the same name, or is added to
+///   the record if there is no such component yet.
 ///
 /// # Examples
 ///
@@ -127,8 +133,31 @@ pub struct RecordAdd {
 /// # Ok(())
 /// # }
 /// ```
+///
+/// Add a memory component and a meta key to an existing record and correct its start time
+///
+/// ```
+/// # use auditor::domain::{Component, RecordUpdate};
+/// # use std::collections::HashMap;
+/// use chrono::{DateTime, TimeZone, Utc};
+///
+/// # fn main() -> Result<(), anyhow::Error> {
+/// let start_time: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 1, 1, 11, 0, 0).unwrap();
+/// let stop_time: DateTime<Utc> = Utc.with_ymd_and_hms(2023, 1, 1, 12, 0, 0).unwrap();
+/// let memory = Component::new("MEM", 4096)?;
+/// let meta = HashMap::from([("queue", vec!["long"])]);
+/// let record = RecordUpdate::new("123456", meta, vec![memory], stop_time)?
+///     .with_start_time(start_time);
+/// # Ok(())
+/// # }
+/// ```
 
-#[derive(Serialize, Deserialize, Clone, Debug)]
+#[derive(Serialize, Deserialize, Clone, Debug, utoipa::ToSchema)]
+#[schema(
+    description = "Update of an existing record. The `stop_time` is set, `meta` is merged \
+    into or replaces the stored one depending on `meta_mode`, `components` replace the stored \
+    components with the same name and the `start_time` is corrected if given."
+)]
 pub struct RecordUpdate {
     /// Unique identifier of the record.
     pub record_id: ValidName,
@@ -140,6 +169,23 @@ pub struct RecordUpdate {
     pub start_time: Option<DateTime<Utc>>,
     /// Stop time of the record.
     pub stop_time: DateTime<Utc>,
+    /// How `meta` is applied to the stored meta information.
+    // Last field, so that updates queued by the client before it was added can be told apart.
+    #[serde(default)]
+    pub meta_mode: MetaMode,
+}
+
+/// Determines how the `meta` of a [`RecordUpdate`] is applied to the stored meta information.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, utoipa::ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum MetaMode {
+    /// The values of the keys in `meta` replace the stored values of these keys, all other keys
+    /// are kept.
+    #[default]
+    Merge,
+    /// The stored meta information is replaced by `meta`. Keys which are missing in `meta` are
+    /// removed, an update without `meta` removes all meta information.
+    Replace,
 }
 
 /// A `Record` represents a single accountable unit.
@@ -147,7 +193,7 @@ pub struct RecordUpdate {
 /// Records can be sent to and received from Auditor with the
 /// [`AuditorClient`](../../auditor_client/index.html) crate.
 /// When initially inserting a record in Auditor, the record is represented as [`RecordAdd`].
-/// The `stop_time` can be updated at a later time by pushing a [`RecordUpdate`] to Auditor.
+/// The record can be updated at a later time by pushing a [`RecordUpdate`] to Auditor.
 ///
 /// Records that are retrieved from Auditor are returned as `Record`.
 ///
@@ -170,7 +216,10 @@ pub struct RecordUpdate {
 /// # Ok(())
 /// # }
 /// ```
-#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+#[derive(
+    Serialize, Deserialize, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, utoipa::ToSchema,
+)]
+#[schema(description = "Record as stored in Auditor.")]
 pub struct Record {
     /// Unique identifier of the record.
     pub record_id: String,
@@ -270,8 +319,23 @@ impl RecordUpdate {
             components,
             start_time: None,
             stop_time,
+            meta_mode: MetaMode::Merge,
         })
     }
+
+    /// Set the corrected start time of the record.
+    #[must_use]
+    pub fn with_start_time(mut self, start_time: DateTime<Utc>) -> Self {
+        self.start_time = Some(start_time);
+        self
+    }
+
+    /// Set how the meta information is applied to the stored one.
+    #[must_use]
+    pub fn with_meta_mode(mut self, meta_mode: MetaMode) -> Self {
+        self.meta_mode = meta_mode;
+        self
+    }
 }
 
 impl RecordTest {
@@ -519,6 +583,7 @@ impl TryFrom<RecordTest> for RecordUpdate {
                 .collect::<Result<Vec<_>, _>>()?,
             start_time: value.start_time,
             stop_time: value.stop_time.unwrap(),
+            meta_mode: MetaMode::Merge,
         })
     }
 }
@@ -574,6 +639,7 @@ impl TryFrom<Record> for RecordUpdate {
                 .collect::<Result<Vec<_>, _>>()?,
             start_time: value.start_time,
             stop_time: value.stop_time.unwrap(),
+            meta_mode: MetaMode::Merge,
         })
     }
 }
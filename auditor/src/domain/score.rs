@@ -31,9 +31,10 @@ use std::cmp::Ordering;
 /// let score =  Score::new("HEPSPEC06", 9.2)?;
 /// # Ok(())
 /// # }
-#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone)]
+#[derive(Debug, Serialize, Deserialize, sqlx::Type, Clone, utoipa::ToSchema)]
 #[sqlx(type_name = "score")]
 #[sqlx(no_pg_array)]
+#[schema(description = "Score of a component, e.g. a benchmark value of the CPU.")]
 pub struct Score {
     pub name: ValidName,
     pub value: ValidValue,
@@ -105,7 +106,7 @@ impl Eq for Score {}
 
 impl PartialOrd for Score {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(self.name.as_ref().cmp(other.name.as_ref()))
+        Some(self.cmp(other))
     }
 }
 
@@ -142,12 +143,7 @@ impl ScoreTest {
 
 impl PartialOrd for ScoreTest {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
-        Some(
-            self.name
-                .as_ref()
-                .unwrap()
-                .cmp(other.name.as_ref().unwrap()),
-        )
+        Some(self.cmp(other))
     }
 }
 
@@ -9,6 +9,8 @@ use crate::domain::ValidationError;
 use anyhow::Context;
 use sqlx::{Postgres, Type, postgres::PgTypeInfo};
 use std::fmt;
+use utoipa::openapi::RefOr;
+use utoipa::openapi::schema::{self, KnownFormat, ObjectBuilder, Schema, SchemaFormat};
 
 // never turn this into `ValidAmount(pub i64)`. By keeping the inner field private, it is not
 // possible to create this type outside of this module, hence enforcing the use of `parse`. This
@@ -28,6 +30,18 @@ impl ValidAmount {
     }
 }
 
+impl utoipa::PartialSchema for ValidAmount {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(schema::Type::Integer)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Int64)))
+            .minimum(Some(0))
+            .into()
+    }
+}
+
+impl utoipa::ToSchema for ValidAmount {}
+
 impl AsRef<i64> for ValidAmount {
     fn as_ref(&self) -> &i64 {
         &self.0
@@ -9,6 +9,8 @@ use crate::domain::ValidationError;
 use anyhow::Context;
 use std::fmt;
 use unicode_segmentation::UnicodeSegmentation;
+use utoipa::openapi::RefOr;
+use utoipa::openapi::schema::{ObjectBuilder, Schema, Type};
 
 // never turn this into `ValidName(pub String)`. By keeping the inner field private, it is not
 // possible to create this type outside of this module, hence enforcing the use of `parse`. This
@@ -33,6 +35,21 @@ impl ValidName {
     }
 }
 
+impl utoipa::PartialSchema for ValidName {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(Type::String)
+            .min_length(Some(1))
+            .max_length(Some(256))
+            .description(Some(
+                "Name which is neither empty nor longer than 256 characters",
+            ))
+            .into()
+    }
+}
+
+impl utoipa::ToSchema for ValidName {}
+
 impl AsRef<str> for ValidName {
     fn as_ref(&self) -> &str {
         &self.0
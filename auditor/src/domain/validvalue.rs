@@ -9,6 +9,8 @@ use crate::domain::ValidationError;
 use anyhow::Context;
 use sqlx::{Postgres, Type, postgres::PgTypeInfo};
 use std::fmt;
+use utoipa::openapi::RefOr;
+use utoipa::openapi::schema::{self, KnownFormat, ObjectBuilder, Schema, SchemaFormat};
 
 // never turn this into `ValidValue(pub f64)`. By keeping the inner field private, it is not
 // possible to create this type outside of this module, hence enforcing the use of `parse`. This
@@ -28,6 +30,18 @@ impl ValidValue {
     }
 }
 
+impl utoipa::PartialSchema for ValidValue {
+    fn schema() -> RefOr<Schema> {
+        ObjectBuilder::new()
+            .schema_type(schema::Type::Number)
+            .format(Some(SchemaFormat::KnownFormat(KnownFormat::Double)))
+            .minimum(Some(0.0))
+            .into()
+    }
+}
+
+impl utoipa::ToSchema for ValidValue {}
+
 impl AsRef<f64> for ValidValue {
     fn as_ref(&self) -> &f64 {
         &self.0
@@ -16,10 +16,29 @@ pub mod domain;
 pub mod error;
 #[cfg(feature = "server")]
 pub mod metrics;
+#[cfg(feature = "server")]
+pub mod migrations;
 #[macro_use]
 mod macros;
+// Uses the macros, hence declared after them
+#[cfg(feature = "server")]
+pub mod admin;
+#[cfg(feature = "server")]
+pub mod auth;
+#[cfg(feature = "server")]
+pub mod cli;
+#[cfg(feature = "server")]
+pub mod notifications;
+#[cfg(feature = "server")]
+pub mod partitions;
+#[cfg(feature = "server")]
+pub mod retention;
+#[cfg(feature = "server")]
+pub mod rollup;
 #[cfg(feature = "server")]
 pub mod routes;
 #[cfg(feature = "server")]
 pub mod startup;
 pub mod telemetry;
+#[cfg(feature = "server")]
+pub mod webhooks;
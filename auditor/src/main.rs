@@ -5,10 +5,17 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use auditor::configuration::{TLSParams, get_configuration};
+use auditor::admin;
+use auditor::cli::{Cli, Command, USAGE};
+use auditor::configuration::{Settings, TLSParams, get_configuration_from};
 use auditor::metrics::DatabaseMetricsWatcher;
+use auditor::migrations;
+use auditor::partitions::PartitionManager;
+use auditor::retention::RetentionWatcher;
+use auditor::rollup::DailyUsageRollup;
 use auditor::startup::run;
 use auditor::telemetry::{get_subscriber, init_subscriber};
+use auditor::webhooks::WebhookDispatcher;
 use sqlx::postgres::PgPoolOptions;
 
 use rustls::{RootCertStore, ServerConfig, pki_types::PrivateKeyDer, server::WebPkiClientVerifier};
@@ -20,13 +27,72 @@ use std::env;
 
 #[tokio::main]
 async fn main() -> Result<(), anyhow::Error> {
+    let cli = match Cli::parse(env::args().skip(1)) {
+        Ok(cli) => cli,
+        Err(e) => {
+            eprintln!("{e}\n\n{USAGE}");
+            std::process::exit(2);
+        }
+    };
+    if cli.command == Command::Help {
+        println!("{USAGE}");
+        return Ok(());
+    }
+
     // Read in configuration
-    let configuration = get_configuration().expect("Failed to read configuration.");
+    let configuration = get_configuration_from(cli.config_file.as_deref())?;
 
     // Set up logging
     let subscriber = get_subscriber("AUDITOR".into(), configuration.log_level, std::io::stdout);
     init_subscriber(subscriber);
 
+    match cli.command {
+        Command::Serve | Command::Help => {
+            if cli.migrate_on_start {
+                migrations::run(&configuration.database).await?;
+            }
+            serve(configuration).await
+        }
+        Command::Migrate => {
+            let version = migrations::run(&configuration.database).await?;
+            println!(
+                "The database is migrated to version {}",
+                version.map_or("none".to_string(), |version| version.to_string())
+            );
+            Ok(())
+        }
+        Command::CheckConfig => {
+            admin::check_config(&configuration)?;
+            println!("The configuration is valid");
+            Ok(())
+        }
+        Command::Stats => {
+            let pool = PgPoolOptions::new()
+                .connect_with(configuration.database.with_db())
+                .await?;
+            println!("{}", admin::stats(&pool).await?);
+            Ok(())
+        }
+        Command::VacuumRetention => {
+            let pool = PgPoolOptions::new()
+                .connect_with(configuration.database.with_db())
+                .await?;
+            let deleted = admin::vacuum_retention(&pool, &configuration).await?;
+            println!("Deleted {deleted} records");
+            Ok(())
+        }
+        Command::PruneWebhooks => {
+            let pool = PgPoolOptions::new()
+                .connect_with(configuration.database.with_db())
+                .await?;
+            let removed = admin::prune_webhooks(&pool, &configuration).await?;
+            println!("Removed {removed} webhooks");
+            Ok(())
+        }
+    }
+}
+
+async fn serve(configuration: Settings) -> Result<(), anyhow::Error> {
     // Create a connection pool for the PostgreSQL database
     let connection_pool = PgPoolOptions::new()
         .acquire_timeout(std::time::Duration::from_secs(2))
@@ -35,9 +101,38 @@ async fn main() -> Result<(), anyhow::Error> {
     // Start background task
     let db_metrics_watcher = DatabaseMetricsWatcher::new(connection_pool.clone(), &configuration)?;
     let db_metrics_watcher_task = db_metrics_watcher.clone();
-    // TODO: Don't panic!
     tokio::spawn(async move {
-        db_metrics_watcher_task.monitor().await.unwrap();
+        if let Err(e) = db_metrics_watcher_task.monitor().await {
+            tracing::error!("Database metrics are not monitored: {e:?}");
+        }
+    });
+
+    let partition_manager = PartitionManager::new(connection_pool.clone(), &configuration)?;
+    tokio::spawn(async move {
+        if let Err(e) = partition_manager.manage().await {
+            tracing::error!("Partitions are not managed: {e:?}");
+        }
+    });
+
+    let retention_watcher = RetentionWatcher::new(connection_pool.clone(), &configuration)?;
+    tokio::spawn(async move {
+        if let Err(e) = retention_watcher.enforce().await {
+            tracing::error!("Retention policies are not enforced: {e:?}");
+        }
+    });
+
+    let daily_usage_rollup = DailyUsageRollup::new(connection_pool.clone(), &configuration)?;
+    tokio::spawn(async move {
+        if let Err(e) = daily_usage_rollup.maintain().await {
+            tracing::error!("Daily usage is not maintained: {e:?}");
+        }
+    });
+
+    let webhook_dispatcher = WebhookDispatcher::new(connection_pool.clone(), &configuration)?;
+    tokio::spawn(async move {
+        if let Err(e) = webhook_dispatcher.dispatch().await {
+            tracing::error!("Webhooks are not dispatched: {e:?}");
+        }
     });
 
     if let Some(tls) = configuration.tls_config {
@@ -105,6 +200,7 @@ async fn main() -> Result<(), anyhow::Error> {
                 connection_pool,
                 db_metrics_watcher,
                 Some(tls_params),
+                configuration.auth,
             )?
             .await?;
         } else {
@@ -116,6 +212,7 @@ async fn main() -> Result<(), anyhow::Error> {
                 connection_pool,
                 db_metrics_watcher,
                 None,
+                configuration.auth,
             )?
             .await?;
         }
@@ -128,6 +225,7 @@ async fn main() -> Result<(), anyhow::Error> {
             connection_pool,
             db_metrics_watcher,
             None,
+            configuration.auth,
         )?
         .await?;
     }
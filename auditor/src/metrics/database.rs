@@ -8,45 +8,267 @@
 use crate::configuration::Settings;
 use prometheus::core::{Collector, Desc};
 use prometheus::proto::MetricFamily;
-use prometheus::{IntGauge, IntGaugeVec, Opts};
-use sqlx::PgPool;
-use std::collections::HashMap;
+use prometheus::{GaugeVec, Opts};
+use serde::de::{self, Deserializer, MapAccess, Visitor};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+use std::fmt;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Instant;
 
-struct AggregatedColumns {
-    name: String,
-    num: i64,
-}
-
-impl AggregatedColumns {
-    fn into_tuple(self) -> (String, i64) {
-        (self.name, self.num)
-    }
-}
+/// Values of the label combinations of a metric.
+type MetricValues = Vec<(Vec<String>, f64)>;
 
 #[derive(Clone)]
 pub struct DatabaseMetricsWatcher {
     db_pool: PgPool,
-    data: Arc<Mutex<DatabaseMetricsData>>,
+    data: Arc<Mutex<Vec<Option<MetricValues>>>>,
     desc: Desc,
     frequency: chrono::Duration,
-    metrics: Vec<DatabaseMetricsOptions>,
+    metrics: Vec<DatabaseMetric>,
+    /// Time of the last iteration of [`DatabaseMetricsWatcher::monitor`].
+    heartbeat: Arc<Mutex<Option<Instant>>>,
+}
+
+/// How the records of a [`DatabaseMetric`] are aggregated.
+#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum DatabaseMetricAggregation {
+    /// Number of records.
+    Count,
+    /// Sum of the runtime of the records in seconds.
+    Runtime,
+    /// Sum of the amount of `component` multiplied by the runtime.
+    ComponentRuntime,
+    /// Sum of the amount of `component` multiplied by the runtime and the value of `score`.
+    ScoreComponentRuntime,
 }
 
-struct DatabaseMetricsData {
-    num_records: Option<i64>,
-    num_records_per_site: Option<HashMap<String, i64>>,
-    num_records_per_group: Option<HashMap<String, i64>>,
-    num_records_per_user: Option<HashMap<String, i64>>,
+/// A metric computed from the records in the database, which is exported as Prometheus gauge.
+///
+/// The records are grouped by the values of the meta keys in `group_by`, which become the labels
+/// of the gauge. Records without one of the meta keys are not included, records with several
+/// values for a meta key are accounted for in each of them. If `window` is given, only records
+/// which stopped within the window are included.
+///
+/// Instead of a definition, one of the names `RecordCount`, `RecordCountPerSite`,
+/// `RecordCountPerGroup` and `RecordCountPerUser` can be given, which count all records in total
+/// or per `site_id`, `group_id` and `user_id`, respectively.
+#[serde_with::serde_as]
+#[derive(serde::Deserialize, Debug, Clone, PartialEq, Eq)]
+#[serde(deny_unknown_fields)]
+pub struct DatabaseMetric {
+    pub name: String,
+    #[serde(default)]
+    pub help: Option<String>,
+    pub aggregation: DatabaseMetricAggregation,
+    #[serde(default)]
+    pub component: Option<String>,
+    #[serde(default)]
+    pub score: Option<String>,
+    #[serde(default)]
+    pub group_by: Vec<String>,
+    /// Names of the labels, one for each meta key in `group_by`. Defaults to the meta keys.
+    #[serde(default)]
+    pub labels: Option<Vec<String>>,
+    #[serde(default)]
+    #[serde_as(as = "Option<serde_with::DurationSeconds<i64>>")]
+    pub window: Option<chrono::Duration>,
 }
 
-#[derive(serde::Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
-pub enum DatabaseMetricsOptions {
-    RecordCount,
-    RecordCountPerSite,
-    RecordCountPerGroup,
-    RecordCountPerUser,
+impl DatabaseMetric {
+    fn record_count(name: &str, meta_key: Option<(&str, &str)>) -> DatabaseMetric {
+        DatabaseMetric {
+            name: name.to_string(),
+            help: Some("Number of records in the Auditor database".to_string()),
+            aggregation: DatabaseMetricAggregation::Count,
+            component: None,
+            score: None,
+            group_by: meta_key.iter().map(|(key, _)| key.to_string()).collect(),
+            labels: meta_key.map(|(_, label)| vec![label.to_string()]),
+            window: None,
+        }
+    }
+
+    fn from_preset(preset: &str) -> Option<DatabaseMetric> {
+        match preset {
+            "RecordCount" => Some(Self::record_count("num_records_database", None)),
+            "RecordCountPerSite" => Some(Self::record_count(
+                "num_records_database_per_site",
+                Some(("site_id", "site")),
+            )),
+            "RecordCountPerGroup" => Some(Self::record_count(
+                "num_records_database_per_group",
+                Some(("group_id", "group_id")),
+            )),
+            "RecordCountPerUser" => Some(Self::record_count(
+                "num_records_database_per_user",
+                Some(("user_id", "user_id")),
+            )),
+            _ => None,
+        }
+    }
+
+    fn labels(&self) -> &[String] {
+        self.labels.as_deref().unwrap_or(&self.group_by)
+    }
+
+    fn gauge(&self) -> Result<GaugeVec, prometheus::Error> {
+        let help = self
+            .help
+            .clone()
+            .unwrap_or_else(|| format!("{} of records in the Auditor database", self.name));
+        let labels: Vec<&str> = self.labels().iter().map(String::as_str).collect();
+        GaugeVec::new(Opts::new(&self.name, help), &labels)
+    }
+
+    /// Checks that the metric can be computed and exported.
+    fn validate(&self) -> Result<(), anyhow::Error> {
+        let name = &self.name;
+        match self.aggregation {
+            DatabaseMetricAggregation::Count | DatabaseMetricAggregation::Runtime => {
+                if self.component.is_some() || self.score.is_some() {
+                    anyhow::bail!("Metric {name} does not use a component or score");
+                }
+            }
+            DatabaseMetricAggregation::ComponentRuntime => {
+                if self.component.is_none() || self.score.is_some() {
+                    anyhow::bail!("Metric {name} needs a component and no score");
+                }
+            }
+            DatabaseMetricAggregation::ScoreComponentRuntime => {
+                if self.component.is_none() || self.score.is_none() {
+                    anyhow::bail!("Metric {name} needs a component and a score");
+                }
+            }
+        }
+        if self.labels().len() != self.group_by.len() {
+            anyhow::bail!("Metric {name} needs one label for each meta key in group_by");
+        }
+        if self
+            .window
+            .is_some_and(|window| window <= chrono::Duration::zero())
+        {
+            anyhow::bail!("The window of metric {name} has to be positive");
+        }
+        self.gauge()
+            .map_err(|e| anyhow::anyhow!("Invalid name or labels of metric {name}: {e}"))?;
+        Ok(())
+    }
+
+    fn query(&self) -> QueryBuilder<'_, Postgres> {
+        let mut query: QueryBuilder<Postgres> = QueryBuilder::new("SELECT ARRAY[");
+        let mut separated = query.separated(", ");
+        for index in 0..self.group_by.len() {
+            separated.push(format!("g{index}.value"));
+        }
+        query.push("]::text[] AS labels, ");
+        query.push(match self.aggregation {
+            DatabaseMetricAggregation::Count => "count(*)::double precision",
+            DatabaseMetricAggregation::Runtime => "COALESCE(sum(a.runtime), 0)::double precision",
+            DatabaseMetricAggregation::ComponentRuntime => {
+                "COALESCE(sum((c.value->>'amount')::numeric * a.runtime), 0)::double precision"
+            }
+            DatabaseMetricAggregation::ScoreComponentRuntime => {
+                "COALESCE(
+                     sum((c.value->>'amount')::numeric * a.runtime * (s.value->>'value')::numeric),
+                     0
+                 )::double precision"
+            }
+        });
+        query.push(" AS value FROM auditor_accounting AS a");
+        for (index, key) in self.group_by.iter().enumerate() {
+            query.push(" CROSS JOIN LATERAL jsonb_array_elements_text(a.meta->");
+            query.push_bind(key);
+            query.push(format!(") AS g{index}(value)"));
+        }
+        if self.component.is_some() {
+            query.push(" CROSS JOIN LATERAL jsonb_array_elements(a.components) AS c(value)");
+        }
+        if self.score.is_some() {
+            query.push(" CROSS JOIN LATERAL jsonb_array_elements(c.value->'scores') AS s(value)");
+        }
+        query.push(" WHERE true");
+        if let Some(ref component) = self.component {
+            query.push(" AND c.value->>'name' = ");
+            query.push_bind(component);
+        }
+        if let Some(ref score) = self.score {
+            query.push(" AND s.value->>'name' = ");
+            query.push_bind(score);
+        }
+        if let Some(window) = self.window {
+            query.push(" AND a.stop_time >= now() - make_interval(secs => ");
+            query.push_bind(window.num_milliseconds() as f64 / 1000.0);
+            query.push(")");
+        }
+        // Without grouping, the aggregation returns a single row even if there are no records
+        if !self.group_by.is_empty() {
+            query.push(" GROUP BY 1");
+        }
+        query
+    }
+}
+
+/// Entry of the list of database metrics in the configuration, which is either the name of a
+/// predefined metric or a [`DatabaseMetric`].
+struct DatabaseMetricEntry(DatabaseMetric);
+
+impl<'de> serde::Deserialize<'de> for DatabaseMetricEntry {
+    fn deserialize<D>(deserializer: D) -> Result<Self, D::Error>
+    where
+        D: Deserializer<'de>,
+    {
+        struct DatabaseMetricVisitor;
+
+        impl<'de> Visitor<'de> for DatabaseMetricVisitor {
+            type Value = DatabaseMetricEntry;
+
+            fn expecting(&self, formatter: &mut fmt::Formatter) -> fmt::Result {
+                formatter.write_str("the name of a predefined metric or a metric definition")
+            }
+
+            fn visit_str<E>(self, value: &str) -> Result<DatabaseMetricEntry, E>
+            where
+                E: de::Error,
+            {
+                DatabaseMetric::from_preset(value)
+                    .map(DatabaseMetricEntry)
+                    .ok_or_else(|| {
+                        E::unknown_variant(
+                            value,
+                            &[
+                                "RecordCount",
+                                "RecordCountPerSite",
+                                "RecordCountPerGroup",
+                                "RecordCountPerUser",
+                            ],
+                        )
+                    })
+            }
+
+            fn visit_map<M>(self, map: M) -> Result<DatabaseMetricEntry, M::Error>
+            where
+                M: MapAccess<'de>,
+            {
+                serde::Deserialize::deserialize(de::value::MapAccessDeserializer::new(map))
+                    .map(DatabaseMetricEntry)
+            }
+        }
+
+        deserializer.deserialize_any(DatabaseMetricVisitor)
+    }
+}
+
+/// Deserializes the list of database metrics, see [`DatabaseMetric`].
+pub fn deserialize_database_metrics<'de, D>(
+    deserializer: D,
+) -> Result<Vec<DatabaseMetric>, D::Error>
+where
+    D: Deserializer<'de>,
+{
+    let entries: Vec<DatabaseMetricEntry> = serde::Deserialize::deserialize(deserializer)?;
+    Ok(entries.into_iter().map(|entry| entry.0).collect())
 }
 
 impl DatabaseMetricsWatcher {
@@ -58,17 +280,21 @@ impl DatabaseMetricsWatcher {
             std::collections::HashMap::new(),
         )?;
 
+        let metrics = config.metrics.database.metrics.clone();
+        for (index, metric) in metrics.iter().enumerate() {
+            metric.validate()?;
+            if metrics[..index].iter().any(|m| m.name == metric.name) {
+                anyhow::bail!("The name {} is used by several metrics", metric.name);
+            }
+        }
+
         Ok(DatabaseMetricsWatcher {
             db_pool: pool,
-            data: Arc::new(Mutex::new(DatabaseMetricsData {
-                num_records: None,
-                num_records_per_site: None,
-                num_records_per_group: None,
-                num_records_per_user: None,
-            })),
+            data: Arc::new(Mutex::new(vec![None; metrics.len()])),
             desc,
             frequency: config.metrics.database.frequency,
-            metrics: config.metrics.database.metrics.clone(),
+            metrics,
+            heartbeat: Arc::new(Mutex::new(None)),
         })
     }
 
@@ -77,97 +303,62 @@ impl DatabaseMetricsWatcher {
         let mut interval = tokio::time::interval(self.frequency.to_std()?);
         loop {
             interval.tick().await;
-            for metric in self.metrics.iter() {
-                match metric {
-                    DatabaseMetricsOptions::RecordCount => self.update_record_count().await?,
-                    DatabaseMetricsOptions::RecordCountPerSite => {
-                        self.update_record_count_per_site().await?
-                    }
-                    DatabaseMetricsOptions::RecordCountPerGroup => {
-                        self.update_record_count_per_group().await?
-                    }
-                    DatabaseMetricsOptions::RecordCountPerUser => {
-                        self.update_record_count_per_user().await?
-                    }
-                };
+            *self.heartbeat.lock().unwrap() = Some(Instant::now());
+            if let Err(e) = self.update().await {
+                tracing::error!("Failed to update database metrics: {e:?}");
             }
         }
     }
 
-    #[tracing::instrument(name = "Update record count for database metrics", skip(self))]
-    async fn update_record_count(&self) -> Result<(), anyhow::Error> {
-        let num = sqlx::query_scalar!(r#"SELECT count(*) as "count!" FROM auditor_accounting;"#)
-            .fetch_one(&self.db_pool)
-            .await?;
-        let mut data_lock = self.data.lock().unwrap();
-        data_lock.num_records = Some(num);
-        Ok(())
+    /// Returns whether [`DatabaseMetricsWatcher::monitor`] is running, i.e. it has woken up
+    /// within twice the update frequency.
+    pub fn is_alive(&self) -> bool {
+        let Ok(frequency) = self.frequency.to_std() else {
+            return false;
+        };
+        self.heartbeat
+            .lock()
+            .unwrap()
+            .is_some_and(|heartbeat| heartbeat.elapsed() <= 2 * frequency)
     }
 
-    #[tracing::instrument(name = "Update record count per site for database metrics", skip(self))]
-    async fn update_record_count_per_site(&self) -> Result<(), anyhow::Error> {
-        let per_site: HashMap<String, i64> = sqlx::query_as!(
-            AggregatedColumns,
-            r#"
-            SELECT jsonb_array_elements_text(meta->'site_id') AS "name!", COUNT(*) AS "num!"
-            FROM auditor_accounting
-            GROUP BY jsonb_array_elements_text(meta->'site_id');
-            "#
-        )
-        .fetch_all(&self.db_pool)
-        .await?
-        .into_iter()
-        .map(AggregatedColumns::into_tuple)
-        .collect();
-
-        let mut data_lock = self.data.lock().unwrap();
-        data_lock.num_records_per_site = Some(per_site);
-        Ok(())
-    }
+    /// Computes all metrics. Metrics which cannot be computed keep their previous values, the
+    /// remaining metrics are updated nonetheless.
+    #[tracing::instrument(name = "Updating database metrics", skip(self))]
+    pub async fn update(&self) -> Result<(), anyhow::Error> {
+        let mut failed = 0;
+        for (index, metric) in self.metrics.iter().enumerate() {
+            let values = match self.compute(metric).await {
+                Ok(values) => values,
+                Err(e) => {
+                    tracing::error!("Failed to update database metric {}: {e:?}", metric.name);
+                    failed += 1;
+                    continue;
+                }
+            };
 
-    #[tracing::instrument(
-        name = "Update record count per group for database metrics",
-        skip(self)
-    )]
-    async fn update_record_count_per_group(&self) -> Result<(), anyhow::Error> {
-        let per_group: HashMap<String, i64> = sqlx::query_as!(
-            AggregatedColumns,
-            r#"
-            SELECT jsonb_array_elements_text(meta->'group_id') AS "name!", COUNT(*) AS "num!"
-            FROM auditor_accounting
-            GROUP BY jsonb_array_elements_text(meta->'group_id');
-            "#
-        )
-        .fetch_all(&self.db_pool)
-        .await?
-        .into_iter()
-        .map(AggregatedColumns::into_tuple)
-        .collect();
-
-        let mut data_lock = self.data.lock().unwrap();
-        data_lock.num_records_per_group = Some(per_group);
+            let mut data_lock = self.data.lock().unwrap();
+            data_lock[index] = Some(values);
+        }
+        if failed > 0 {
+            anyhow::bail!(
+                "{failed} of {} database metrics could not be updated",
+                self.metrics.len()
+            );
+        }
         Ok(())
     }
 
-    #[tracing::instrument(name = "Update record count per user for database metrics", skip(self))]
-    async fn update_record_count_per_user(&self) -> Result<(), anyhow::Error> {
-        let per_user: HashMap<String, i64> = sqlx::query_as!(
-            AggregatedColumns,
-            r#"
-            SELECT jsonb_array_elements_text(meta->'user_id') AS "name!", COUNT(*) AS "num!"
-            FROM auditor_accounting
-            GROUP BY jsonb_array_elements_text(meta->'user_id');
-            "#
-        )
-        .fetch_all(&self.db_pool)
-        .await?
-        .into_iter()
-        .map(AggregatedColumns::into_tuple)
-        .collect();
-
-        let mut data_lock = self.data.lock().unwrap();
-        data_lock.num_records_per_user = Some(per_user);
-        Ok(())
+    async fn compute(&self, metric: &DatabaseMetric) -> Result<MetricValues, sqlx::Error> {
+        metric
+            .query()
+            .build()
+            .persistent(false)
+            .fetch_all(&self.db_pool)
+            .await?
+            .iter()
+            .map(|row| Ok((row.try_get("labels")?, row.try_get("value")?)))
+            .collect()
     }
 
     #[tracing::instrument(
@@ -180,63 +371,16 @@ impl DatabaseMetricsWatcher {
 
         let data_lock = self.data.lock().unwrap();
 
-        if let Some(num_records) = data_lock.num_records {
-            let gauge = IntGauge::new(
-                "num_records_database",
-                "Number of records in the Auditor database",
-            )?;
-            gauge.set(num_records);
-            out.extend(gauge.collect());
-        }
-
-        if let Some(ref num_records_per_site) = data_lock.num_records_per_site {
-            let gauge_vec = IntGaugeVec::new(
-                Opts::new(
-                    "num_records_database_per_site",
-                    "Number of records in the Auditor database",
-                ),
-                &["site"],
-            )?;
-
-            num_records_per_site
-                .iter()
-                .map(|(name, &num)| gauge_vec.with_label_values(&[&name[..]]).set(num))
-                .count();
-
-            out.extend(gauge_vec.collect());
-        }
-
-        if let Some(ref num_records_per_group) = data_lock.num_records_per_group {
-            let gauge_vec = IntGaugeVec::new(
-                Opts::new(
-                    "num_records_database_per_group",
-                    "Number of records in the Auditor database",
-                ),
-                &["group_id"],
-            )?;
-
-            num_records_per_group
-                .iter()
-                .map(|(name, &num)| gauge_vec.with_label_values(&[&name[..]]).set(num))
-                .count();
-
-            out.extend(gauge_vec.collect());
-        }
-
-        if let Some(ref num_records_per_user) = data_lock.num_records_per_user {
-            let gauge_vec = IntGaugeVec::new(
-                Opts::new(
-                    "num_records_database_per_user",
-                    "Number of records in the Auditor database",
-                ),
-                &["user_id"],
-            )?;
-
-            num_records_per_user
-                .iter()
-                .map(|(name, &num)| gauge_vec.with_label_values(&[&name[..]]).set(num))
-                .count();
-
+        for (metric, values) in self.metrics.iter().zip(data_lock.iter()) {
+            // Gauges without values cannot be encoded
+            let Some(values) = values.as_ref().filter(|values| !values.is_empty()) else {
+                continue;
+            };
+            let gauge_vec = metric.gauge()?;
+            for (labels, value) in values {
+                let labels: Vec<&str> = labels.iter().map(String::as_str).collect();
+                gauge_vec.with_label_values(&labels).set(*value);
+            }
             out.extend(gauge_vec.collect());
         }
 
@@ -0,0 +1,76 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+//! Migrations of the database schema, which are embedded into the binary.
+
+use crate::configuration::DatabaseSettings;
+use sqlx::migrate::Migrator;
+use sqlx::{Connection, Executor, PgConnection, PgPool};
+
+/// Migrations of the `migrations` directory of the repository.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../migrations");
+
+/// Returns the version of the latest migration known to this version of Auditor.
+pub fn latest_version() -> Option<i64> {
+    MIGRATOR
+        .iter()
+        .filter(|migration| !migration.migration_type.is_down_migration())
+        .map(|migration| migration.version)
+        .max()
+}
+
+/// Returns the version of the latest migration which was applied successfully to the database,
+/// or `None` if the database has not been migrated yet.
+pub async fn latest_applied_version(pool: &PgPool) -> Result<Option<i64>, sqlx::Error> {
+    let version = sqlx::query_scalar("SELECT max(version) FROM _sqlx_migrations WHERE success")
+        .persistent(false)
+        .fetch_one(pool)
+        .await;
+    match version {
+        Ok(version) => Ok(version),
+        // The table is created when the first migration is applied
+        Err(sqlx::Error::Database(e)) if e.code().as_deref() == Some("42P01") => Ok(None),
+        Err(e) => Err(e),
+    }
+}
+
+/// Creates the database of `config` if it does not exist yet and applies all pending
+/// migrations. Returns the version of the latest applied migration.
+#[tracing::instrument(name = "Migrating the database", skip(config))]
+pub async fn run(config: &DatabaseSettings) -> Result<Option<i64>, anyhow::Error> {
+    create_database(config).await?;
+    let pool = PgPool::connect_with(config.with_db()).await?;
+    MIGRATOR.run(&pool).await?;
+    Ok(latest_applied_version(&pool).await?)
+}
+
+/// Creates the database of `config` if it does not exist yet. Returns whether the database was
+/// created.
+async fn create_database(config: &DatabaseSettings) -> Result<bool, sqlx::Error> {
+    // Connect to the maintenance database, as the configured one might not exist
+    let mut connection =
+        PgConnection::connect_with(&config.without_db().database("postgres")).await?;
+    let exists: bool =
+        sqlx::query_scalar("SELECT EXISTS(SELECT 1 FROM pg_database WHERE datname = $1)")
+            .bind(&config.database_name)
+            .persistent(false)
+            .fetch_one(&mut connection)
+            .await?;
+    if !exists {
+        tracing::info!("Creating database {}", config.database_name);
+        connection
+            .execute(
+                format!(
+                    r#"CREATE DATABASE "{}""#,
+                    config.database_name.replace('"', r#""""#)
+                )
+                .as_str(),
+            )
+            .await?;
+    }
+    Ok(!exists)
+}
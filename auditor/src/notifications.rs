@@ -0,0 +1,129 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use futures::StreamExt;
+use sqlx::PgPool;
+use sqlx::postgres::PgListener;
+use std::sync::Arc;
+use tokio::sync::{broadcast, watch};
+
+/// Channel on which the database notifies about inserted and updated records.
+pub const RECORDS_CHANNEL: &str = "auditor_records";
+
+/// Number of batches of notifications a subscriber may fall behind before it is disconnected.
+const CAPACITY: usize = 1024;
+/// Maximum number of notifications forwarded to the subscribers at once.
+const MAX_BATCH_SIZE: usize = 1000;
+const RECONNECT_DELAY: std::time::Duration = std::time::Duration::from_secs(5);
+
+/// Notification about a change of the stored records.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum RecordNotification {
+    /// The record with this `record_id` was inserted or updated.
+    Changed(String),
+    /// The connection to the database was lost, notifications may have been missed.
+    Lost,
+}
+
+/// `RecordNotifications` listens for changes of records in the database and forwards them to
+/// all subscribers.
+///
+/// A single database connection is used for all subscribers. Notifications arriving at the same
+/// time are forwarded together, so that subscribers can look up the changed records at once.
+#[derive(Clone)]
+pub struct RecordNotifications {
+    sender: broadcast::Sender<Arc<[RecordNotification]>>,
+    listening: watch::Receiver<bool>,
+}
+
+impl RecordNotifications {
+    /// Starts listening in a background task. Must be called from within a Tokio runtime.
+    pub fn start(pool: PgPool) -> RecordNotifications {
+        let (sender, _) = broadcast::channel(CAPACITY);
+        let (listening_tx, listening) = watch::channel(false);
+        tokio::spawn(listen(pool, sender.clone(), listening_tx));
+        RecordNotifications { sender, listening }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<Arc<[RecordNotification]>> {
+        self.sender.subscribe()
+    }
+
+    /// Waits until the listener is connected to the database. Changes made after this point in
+    /// time are guaranteed to be forwarded to existing subscribers, unless
+    /// [`RecordNotification::Lost`] is sent.
+    pub async fn listening(&self) {
+        let mut listening = self.listening.clone();
+        // The sender is only dropped together with the background task, which runs forever
+        let _ = listening.wait_for(|listening| *listening).await;
+    }
+}
+
+#[tracing::instrument(name = "Listening for record notifications", skip_all)]
+async fn listen(
+    pool: PgPool,
+    sender: broadcast::Sender<Arc<[RecordNotification]>>,
+    listening: watch::Sender<bool>,
+) {
+    let mut reconnecting = false;
+    loop {
+        let mut listener = match connect(&pool).await {
+            Ok(listener) => listener,
+            Err(e) => {
+                tracing::error!("Failed to listen for record notifications: {e:?}");
+                tokio::time::sleep(RECONNECT_DELAY).await;
+                continue;
+            }
+        };
+        if reconnecting {
+            // Subscribers that connected before the connection was lost might have missed changes
+            let _ = sender.send(Arc::new([RecordNotification::Lost]));
+        }
+        reconnecting = true;
+        listening.send_replace(true);
+
+        let notifications = async_stream::stream! {
+            loop {
+                match listener.try_recv().await {
+                    Ok(Some(notification)) => {
+                        yield Ok(RecordNotification::Changed(notification.payload().to_string()))
+                    }
+                    // The listener reconnects by itself
+                    Ok(None) => yield Ok(RecordNotification::Lost),
+                    Err(e) => {
+                        yield Err(e);
+                        break;
+                    }
+                }
+            }
+        };
+        // Notifications which are already received are forwarded together
+        let mut batches = std::pin::pin!(notifications.ready_chunks(MAX_BATCH_SIZE));
+        while let Some(batch) = batches.next().await {
+            let (notifications, errors): (Vec<_>, Vec<_>) =
+                batch.into_iter().partition(Result::is_ok);
+            let notifications: Vec<_> = notifications.into_iter().flatten().collect();
+            if !notifications.is_empty() {
+                // Sending only fails if there are no subscribers
+                let _ = sender.send(notifications.into());
+            }
+            if let Some(Err(e)) = errors.into_iter().next() {
+                tracing::error!("Lost connection while listening for record notifications: {e:?}");
+                break;
+            }
+        }
+
+        listening.send_replace(false);
+        tokio::time::sleep(RECONNECT_DELAY).await;
+    }
+}
+
+async fn connect(pool: &PgPool) -> Result<PgListener, sqlx::Error> {
+    let mut listener = PgListener::connect_with(pool).await?;
+    listener.listen(RECORDS_CHANNEL).await?;
+    Ok(listener)
+}
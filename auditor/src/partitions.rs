@@ -0,0 +1,79 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::configuration::Settings;
+use chrono::{DateTime, Utc};
+use sqlx::PgPool;
+
+/// `PartitionManager` periodically creates the monthly partitions of the records.
+///
+/// Partitions are created for the current month and the configured number of upcoming months.
+/// Records of other months are stored in the default partition until their partition is created,
+/// which happens with the next run as well.
+#[derive(Clone)]
+pub struct PartitionManager {
+    db_pool: PgPool,
+    frequency: chrono::Duration,
+    months_ahead: u32,
+}
+
+impl PartitionManager {
+    pub fn new(pool: PgPool, config: &Settings) -> Result<PartitionManager, anyhow::Error> {
+        Ok(PartitionManager {
+            db_pool: pool,
+            frequency: config.partitioning.frequency,
+            months_ahead: config.partitioning.months_ahead,
+        })
+    }
+
+    /// Creates the missing partitions every `frequency`.
+    #[tracing::instrument(name = "Managing partitions", skip(self))]
+    pub async fn manage(&self) -> Result<(), anyhow::Error> {
+        let mut interval = tokio::time::interval(self.frequency.to_std()?);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.create_partitions().await {
+                tracing::error!("Failed to create partitions: {e:?}");
+            }
+        }
+    }
+
+    /// Creates the partitions of the current and upcoming months, as well as the partitions of
+    /// records in the default partition. Returns the names of the created partitions.
+    #[tracing::instrument(name = "Creating partitions", skip(self))]
+    pub async fn create_partitions(&self) -> Result<Vec<String>, sqlx::Error> {
+        // Partitions cannot be attached while the default partition is read by the same query,
+        // hence the months are collected first.
+        let months: Vec<DateTime<Utc>> = sqlx::query_scalar(
+            "SELECT date_trunc('month', stop_time, 'UTC') AS month
+             FROM auditor_accounting_default
+             WHERE stop_time IS NOT NULL
+             UNION
+             SELECT (date_trunc('month', now() AT TIME ZONE 'UTC') + make_interval(months => n))
+                        AT TIME ZONE 'UTC'
+             FROM generate_series(0, $1) AS n
+             ORDER BY month",
+        )
+        .bind(i32::try_from(self.months_ahead).unwrap_or(i32::MAX))
+        .fetch_all(&self.db_pool)
+        .await?;
+
+        let mut created = vec![];
+        for month in months {
+            let partition: Option<String> =
+                sqlx::query_scalar("SELECT auditor_accounting_create_partition($1)")
+                    .bind(month)
+                    .fetch_one(&self.db_pool)
+                    .await?;
+            if let Some(partition) = partition {
+                tracing::info!("Created partition {partition}");
+                created.push(partition);
+            }
+        }
+        Ok(created)
+    }
+}
@@ -0,0 +1,126 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::configuration::{RetentionPolicy, Settings};
+use crate::routes::set_history_client;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+
+/// `RetentionWatcher` periodically deletes records according to the configured retention
+/// policies.
+#[derive(Clone)]
+pub struct RetentionWatcher {
+    db_pool: PgPool,
+    frequency: chrono::Duration,
+    policies: Vec<RetentionPolicy>,
+}
+
+impl RetentionWatcher {
+    pub fn new(pool: PgPool, config: &Settings) -> Result<RetentionWatcher, anyhow::Error> {
+        for policy in config.retention.policies.iter() {
+            policy.validate().map_err(anyhow::Error::msg)?;
+        }
+
+        Ok(RetentionWatcher {
+            db_pool: pool,
+            frequency: config.retention.frequency,
+            policies: config.retention.policies.clone(),
+        })
+    }
+
+    /// Applies the retention policies every `frequency`. Returns immediately if no policies are
+    /// configured.
+    #[tracing::instrument(name = "Enforcing retention policies", skip(self))]
+    pub async fn enforce(&self) -> Result<(), anyhow::Error> {
+        if self.policies.is_empty() {
+            return Ok(());
+        }
+
+        let mut interval = tokio::time::interval(self.frequency.to_std()?);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.apply().await {
+                tracing::error!("Failed to apply retention policies: {e:?}");
+            }
+        }
+    }
+
+    /// Applies all retention policies once. Returns the number of deleted records.
+    #[tracing::instrument(name = "Applying retention policies", skip(self))]
+    pub async fn apply(&self) -> Result<u64, anyhow::Error> {
+        let mut deleted = 0;
+        for policy in self.policies.iter() {
+            let num = apply_policy(policy, &self.db_pool).await?;
+            if num > 0 {
+                tracing::info!("Retention policy {policy:?} deleted {num} records");
+            }
+            deleted += num;
+        }
+        Ok(deleted)
+    }
+}
+
+/// Name of the client to which deletions by retention policies are attributed in the history.
+pub const RETENTION_CLIENT: &str = "retention";
+
+/// Deletes the records matching `policy` and purges the values of the deleted records from
+/// their history, so that only the time of their deletion is kept.
+#[tracing::instrument(name = "Applying a retention policy", skip(pool))]
+async fn apply_policy(policy: &RetentionPolicy, pool: &PgPool) -> Result<u64, sqlx::Error> {
+    let mut transaction = pool.begin().await?;
+    set_history_client(&mut transaction, Some(RETENTION_CLIENT)).await?;
+
+    let months = i32::try_from(policy.older_than_months).unwrap_or(i32::MAX);
+    let days = i32::try_from(policy.older_than_days).unwrap_or(i32::MAX);
+
+    // Policies without meta filter delete all records of old months, whose partitions are
+    // dropped as a whole instead of deleting their records one by one.
+    let mut dropped = 0;
+    if policy.meta.is_empty() {
+        let num: i64 = sqlx::query_scalar(
+            "SELECT auditor_accounting_drop_partitions(
+                 now() - make_interval(months => $1, days => $2)
+             )",
+        )
+        .bind(months)
+        .bind(days)
+        .fetch_one(&mut *transaction)
+        .await?;
+        dropped = num as u64;
+    }
+
+    let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+        "DELETE FROM auditor_accounting WHERE stop_time < now() - make_interval(months => ",
+    );
+    query.push_bind(months);
+    query.push(", days => ");
+    query.push_bind(days);
+    query.push(")");
+    for (key, values) in policy.meta.iter() {
+        query.push(" AND meta -> ");
+        query.push_bind(key.clone());
+        query.push(" ?| ");
+        query.push_bind(values.clone());
+        query.push("::text[]");
+    }
+
+    query.push(" RETURNING record_id");
+    let record_ids: Vec<String> = query
+        .build_query_scalar()
+        .persistent(false)
+        .fetch_all(&mut *transaction)
+        .await?;
+
+    // The deletion entries are added by the trigger at the end of the delete statement
+    sqlx::query("SELECT auditor_accounting_purge_history($1)")
+        .bind(&record_ids)
+        .persistent(false)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+
+    Ok(dropped + record_ids.len() as u64)
+}
@@ -0,0 +1,108 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::configuration::Settings;
+use chrono::NaiveDate;
+use sqlx::PgPool;
+
+/// Number of days whose usage is computed in one transaction.
+const BATCH_SIZE: i64 = 31;
+
+/// `DailyUsageRollup` periodically updates the daily usage summary.
+///
+/// Changes of records add the days of the records to `daily_usage_changes` in the database. The
+/// usage of these days is then computed again from the records.
+#[derive(Clone)]
+pub struct DailyUsageRollup {
+    db_pool: PgPool,
+    frequency: chrono::Duration,
+}
+
+impl DailyUsageRollup {
+    pub fn new(pool: PgPool, config: &Settings) -> Result<DailyUsageRollup, anyhow::Error> {
+        Ok(DailyUsageRollup {
+            db_pool: pool,
+            frequency: config.daily_usage.frequency,
+        })
+    }
+
+    /// Updates the daily usage every `frequency`.
+    #[tracing::instrument(name = "Maintaining daily usage", skip(self))]
+    pub async fn maintain(&self) -> Result<(), anyhow::Error> {
+        let mut interval = tokio::time::interval(self.frequency.to_std()?);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.update().await {
+                tracing::error!("Failed to update daily usage: {e:?}");
+            }
+        }
+    }
+
+    /// Computes the usage of all changed days. Returns the number of updated days, which is zero
+    /// if another instance of AUDITOR is updating the daily usage at the same time.
+    #[tracing::instrument(name = "Updating daily usage", skip(self))]
+    pub async fn update(&self) -> Result<u64, sqlx::Error> {
+        let mut updated = 0;
+        loop {
+            let mut transaction = self.db_pool.begin().await?;
+            let locked: bool =
+                sqlx::query_scalar("SELECT pg_try_advisory_xact_lock(hashtext('daily_usage'))")
+                    .fetch_one(&mut *transaction)
+                    .await?;
+            if !locked {
+                return Ok(updated);
+            }
+
+            let days: Vec<NaiveDate> = sqlx::query_scalar(
+                "SELECT DISTINCT day FROM daily_usage_changes ORDER BY day LIMIT $1",
+            )
+            .bind(BATCH_SIZE)
+            .fetch_all(&mut *transaction)
+            .await?;
+            if days.is_empty() {
+                return Ok(updated);
+            }
+
+            // Only the changes visible now are deleted. Changes of transactions which commit
+            // later are kept, so that their days are computed again with the next update.
+            sqlx::query("DELETE FROM daily_usage_changes WHERE day = ANY($1)")
+                .bind(&days)
+                .execute(&mut *transaction)
+                .await?;
+            sqlx::query("DELETE FROM daily_usage WHERE day = ANY($1)")
+                .bind(&days)
+                .execute(&mut *transaction)
+                .await?;
+            sqlx::query(
+                "INSERT INTO daily_usage (
+                     day, site_id, group_id, user_id, component, amount_runtime, record_count
+                 )
+                 SELECT d.day, s.value, g.value, u.value, c.value->>'name',
+                        COALESCE(sum((c.value->>'amount')::numeric * a.runtime), 0)
+                            ::double precision,
+                        count(DISTINCT a.id)
+                 FROM UNNEST($1::date[]) AS d(day)
+                 JOIN auditor_accounting AS a
+                     ON a.stop_time >= d.day::timestamp AT TIME ZONE 'UTC'
+                     AND a.stop_time < (d.day + 1)::timestamp AT TIME ZONE 'UTC'
+                 LEFT JOIN LATERAL jsonb_array_elements_text(a.meta -> 'site_id') AS s(value)
+                     ON true
+                 LEFT JOIN LATERAL jsonb_array_elements_text(a.meta -> 'group_id') AS g(value)
+                     ON true
+                 LEFT JOIN LATERAL jsonb_array_elements_text(a.meta -> 'user_id') AS u(value)
+                     ON true
+                 LEFT JOIN LATERAL jsonb_array_elements(a.components) AS c(value) ON true
+                 GROUP BY d.day, s.value, g.value, u.value, c.value->>'name'",
+            )
+            .bind(&days)
+            .execute(&mut *transaction)
+            .await?;
+            transaction.commit().await?;
+            updated += days.len() as u64;
+        }
+    }
+}
@@ -5,16 +5,25 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use crate::auth::{Principal, SITE_META_KEY};
 use crate::constants::{ERR_RECORD_EXISTS, ERR_UNEXPECTED_ERROR};
-use crate::domain::RecordAdd;
-use actix_web::{HttpResponse, ResponseError, web};
-use chrono::Utc;
+use crate::domain::{
+    ApiVersion, BulkInsertReport, InsertResult, InsertStatus, OnConflict, Record, RecordAdd,
+};
+use crate::routes::{api_version, record_from_row, set_history_client};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
+use chrono::{DateTime, Utc};
 use serde_json::Value;
-use sqlx::PgPool;
+use sqlx::{PgConnection, PgPool, Row};
+use std::collections::HashSet;
 
 #[derive(thiserror::Error)]
 pub enum AddError {
-    RecordExists,
+    /// The record exists already. The status code depends on the version of the API.
+    RecordExists(ApiVersion),
+    Conflict(Box<Record>),
+    InvalidRequest(String),
+    Forbidden(String),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
     // UnexpectedError,
@@ -29,7 +38,8 @@ impl std::fmt::Display for AddError {
             f,
             "{}",
             match self {
-                AddError::RecordExists => ERR_RECORD_EXISTS,
+                AddError::RecordExists(_) | AddError::Conflict(_) => ERR_RECORD_EXISTS,
+                AddError::InvalidRequest(msg) | AddError::Forbidden(msg) => msg,
                 AddError::UnexpectedError(_) => ERR_UNEXPECTED_ERROR,
             }
         )
@@ -40,35 +50,91 @@ impl ResponseError for AddError {
     fn status_code(&self) -> actix_web::http::StatusCode {
         match self {
             AddError::UnexpectedError(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
-            AddError::RecordExists => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+            AddError::RecordExists(ApiVersion::V1) => {
+                actix_web::http::StatusCode::INTERNAL_SERVER_ERROR
+            }
+            AddError::RecordExists(_) => actix_web::http::StatusCode::CONFLICT,
+            AddError::Conflict(_) => actix_web::http::StatusCode::CONFLICT,
+            AddError::InvalidRequest(_) => actix_web::http::StatusCode::BAD_REQUEST,
+            AddError::Forbidden(_) => actix_web::http::StatusCode::FORBIDDEN,
         }
     }
 
     fn error_response(&self) -> HttpResponse {
         let message = match self {
             AddError::UnexpectedError(_) => ERR_UNEXPECTED_ERROR,
-            AddError::RecordExists => ERR_RECORD_EXISTS,
+            AddError::RecordExists(_) => ERR_RECORD_EXISTS,
+            // The stored record is returned, so that clients can inspect the differences
+            AddError::Conflict(record) => {
+                return HttpResponse::build(self.status_code()).json(record);
+            }
+            AddError::InvalidRequest(msg) | AddError::Forbidden(msg) => msg,
         };
 
-        HttpResponse::build(self.status_code()).body(message)
+        HttpResponse::build(self.status_code()).body(message.to_string())
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/record",
+    tag = "records",
+    params(InsertQuery),
+    request_body = RecordAdd,
+    responses(
+        (status = 200, description = "The record was added. With `on_conflict`, the status of the \
+            record is returned", body = Option<InsertResult>),
+        (status = 400, description = "The record or query is invalid", body = String),
+        (status = 403, description = "The client may not write the record", body = String),
+        (status = 409, description = "A different record with the same `record_id` exists, which \
+            is returned (only with `on_conflict=skip`). In v2, a record with the same \
+            `record_id` exists", body = Record),
+        (status = 500, description = "A record with the same `record_id` exists \
+            (`RECORD_EXISTS`, only in v1) or an unexpected error occurred", body = String),
+    )
+)]
 #[tracing::instrument(
     name = "Adding a record to the database",
     skip(record, pool),
     fields(record_id = %record.record_id)
 )]
 pub async fn add(
+    request: HttpRequest,
     record: web::Json<RecordAdd>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AddError> {
-    add_record(&record, &pool)
+    let query: InsertQuery = serde_qs::from_str(request.query_string())
+        .map_err(|e| AddError::InvalidRequest(e.to_string()))?;
+    authorize_write(
+        &request,
+        &[record.record_id.as_ref()],
+        |principal| principal.permits_meta(record.meta.as_ref()),
+        &pool,
+    )
+    .await?;
+    let client = Principal::of(&request).map(|principal| principal.name);
+
+    if let Some(on_conflict) = query.on_conflict {
+        let status = match add_record_idempotent(&record, on_conflict, client.as_deref(), &pool)
+            .await
+            .map_err(|e| AddError::UnexpectedError(e.into()))?
+        {
+            IdempotentAdd::Written(status) => status,
+            IdempotentAdd::Conflict(stored) => return Err(AddError::Conflict(Box::new(stored))),
+        };
+        return Ok(HttpResponse::Ok().json(InsertResult {
+            record_id: Some(record.record_id.as_ref().to_string()),
+            status,
+            error: None,
+        }));
+    }
+
+    add_record(&record, client.as_deref(), &pool)
         .await
         .map_err(|e| match e.0.as_database_error() {
             Some(db_err) => match db_err.code().as_ref() {
                 Some(code) => match code.as_ref() {
-                    "23505" => AddError::RecordExists,
+                    "23505" => AddError::RecordExists(api_version(&request)),
                     _ => AddError::UnexpectedError(e.into()),
                 },
                 _ => AddError::UnexpectedError(e.into()),
@@ -79,7 +145,11 @@ pub async fn add(
 }
 
 #[tracing::instrument(name = "Inserting record into database", skip(record, pool))]
-pub async fn add_record(record: &RecordAdd, pool: &PgPool) -> Result<(), AddRecordError> {
+pub async fn add_record(
+    record: &RecordAdd,
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<(), AddRecordError> {
     let runtime = match record.stop_time.as_ref() {
         Some(&stop) => Some((stop - record.start_time).num_seconds()),
         _ => None,
@@ -89,6 +159,9 @@ pub async fn add_record(record: &RecordAdd, pool: &PgPool) -> Result<(), AddReco
         Ok(transaction) => transaction,
         Err(e) => return Err(AddRecordError(e)),
     };
+    set_history_client(&mut transaction, client)
+        .await
+        .map_err(AddRecordError)?;
 
     sqlx::query_unchecked!(
         r#"
@@ -118,17 +191,190 @@ pub async fn add_record(record: &RecordAdd, pool: &PgPool) -> Result<(), AddReco
     }
 }
 
+/// Query parameters of `POST /record` and `POST /records`.
+#[derive(serde::Deserialize, Debug, Default, utoipa::IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct InsertQuery {
+    /// If set, existing records are skipped or updated and the status of the records is
+    /// returned. Otherwise an existing record is reported as error, which rejects all records
+    /// of a bulk insert.
+    pub on_conflict: Option<OnConflict>,
+}
+
+/// Outcome of [`add_record_idempotent`].
+#[derive(Debug)]
+pub enum IdempotentAdd {
+    /// The record was inserted or updated, or an identical record already exists.
+    Written(InsertStatus),
+    /// A different record with the same `record_id` exists, which was left untouched.
+    Conflict(Record),
+}
+
+/// Inserts `record` unless a record with the same `record_id` exists.
+///
+/// Adding a record which is identical to the stored one succeeds and reports it as duplicate,
+/// which makes retries safe. If the stored record differs, it is replaced if `on_conflict` is
+/// [`OnConflict::Update`], and returned as conflict otherwise.
+#[tracing::instrument(
+    name = "Inserting record into database idempotently",
+    skip(record, pool)
+)]
+pub async fn add_record_idempotent(
+    record: &RecordAdd,
+    on_conflict: OnConflict,
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<IdempotentAdd, AddRecordError> {
+    let runtime = record
+        .stop_time
+        .map(|stop| (stop - record.start_time).num_seconds());
+    let meta = serde_json::to_value(&record.meta).unwrap_or(Value::Null);
+    let components = serde_json::to_value(&record.components).unwrap_or(Value::Null);
+
+    let mut transaction = pool.begin().await.map_err(AddRecordError)?;
+    set_history_client(&mut transaction, client)
+        .await
+        .map_err(AddRecordError)?;
+    skip_existing_records(&mut transaction)
+        .await
+        .map_err(AddRecordError)?;
+
+    let inserted = sqlx::query(
+        "INSERT INTO auditor_accounting (
+             record_id, start_time, stop_time, meta, components, runtime, updated_at
+         )
+         VALUES ($1, $2, $3, $4, $5, $6, $7)
+         RETURNING id",
+    )
+    .bind(record.record_id.as_ref())
+    .bind(record.start_time)
+    .bind(record.stop_time)
+    .bind(&meta)
+    .bind(&components)
+    .bind(runtime)
+    .bind(Utc::now())
+    .fetch_optional(&mut *transaction)
+    .await
+    .map_err(AddRecordError)?;
+
+    let outcome = if inserted.is_some() {
+        IdempotentAdd::Written(InsertStatus::Inserted)
+    } else {
+        // The values are compared in the database, so that they are compared with the same
+        // precision and JSON semantics they are stored with.
+        let stored = sqlx::query(
+            "SELECT record_id, meta, components, start_time, stop_time, runtime,
+                    (start_time = $2
+                     AND stop_time IS NOT DISTINCT FROM $3
+                     AND meta IS NOT DISTINCT FROM $4
+                     AND components IS NOT DISTINCT FROM $5) AS identical
+             FROM auditor_accounting
+             WHERE record_id = $1
+             FOR UPDATE",
+        )
+        .bind(record.record_id.as_ref())
+        .bind(record.start_time)
+        .bind(record.stop_time)
+        .bind(&meta)
+        .bind(&components)
+        .fetch_one(&mut *transaction)
+        .await
+        .map_err(AddRecordError)?;
+
+        if stored.try_get("identical").map_err(AddRecordError)? {
+            IdempotentAdd::Written(InsertStatus::Duplicate)
+        } else if on_conflict == OnConflict::Update {
+            sqlx::query(
+                "UPDATE auditor_accounting
+                 SET start_time = $2, stop_time = $3, meta = $4, components = $5, runtime = $6,
+                     updated_at = $7
+                 WHERE record_id = $1",
+            )
+            .bind(record.record_id.as_ref())
+            .bind(record.start_time)
+            .bind(record.stop_time)
+            .bind(&meta)
+            .bind(&components)
+            .bind(runtime)
+            .bind(Utc::now())
+            .execute(&mut *transaction)
+            .await
+            .map_err(AddRecordError)?;
+            IdempotentAdd::Written(InsertStatus::Updated)
+        } else {
+            IdempotentAdd::Conflict(record_from_row(&stored))
+        }
+    };
+
+    transaction.commit().await.map_err(AddRecordError)?;
+    Ok(outcome)
+}
+
+#[utoipa::path(
+    post,
+    path = "/records",
+    tag = "records",
+    params(InsertQuery),
+    request_body = Vec<RecordAdd>,
+    responses(
+        (status = 200, description = "The records were added. With `on_conflict`, the status of \
+            each record is returned and invalid records are reported instead of rejecting all \
+            records", body = Option<BulkInsertReport>),
+        (status = 400, description = "The records or query are invalid", body = String),
+        (status = 403, description = "The client may not write the records", body = String),
+        (status = 409, description = "A record with the same `record_id` exists (only in v2)",
+            body = String),
+        (status = 500, description = "A record with the same `record_id` exists \
+            (`RECORD_EXISTS`, only in v1) or an unexpected error occurred", body = String),
+    )
+)]
 #[tracing::instrument(name = "Adding multiple records to the database", skip(records, pool))]
 pub async fn bulk_add(
-    records: web::Json<Vec<RecordAdd>>,
+    request: HttpRequest,
+    records: web::Json<Vec<Value>>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, AddError> {
-    bulk_insert(&records, &pool)
+    let query: InsertQuery = serde_qs::from_str(request.query_string())
+        .map_err(|e| AddError::InvalidRequest(e.to_string()))?;
+    let record_ids: Vec<&str> = records
+        .iter()
+        .filter_map(|record| record.get("record_id").and_then(Value::as_str))
+        .collect();
+    authorize_write(
+        &request,
+        &record_ids,
+        |principal| {
+            records.iter().all(|record| {
+                let sites: Vec<&str> = record
+                    .pointer(&format!("/meta/{SITE_META_KEY}"))
+                    .and_then(Value::as_array)
+                    .map(|sites| sites.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                principal.permits_sites(&sites)
+            })
+        },
+        &pool,
+    )
+    .await?;
+    let client = Principal::of(&request).map(|principal| principal.name);
+
+    if let Some(on_conflict) = query.on_conflict {
+        let report =
+            bulk_insert_partial(records.into_inner(), on_conflict, client.as_deref(), &pool)
+                .await
+                .map_err(|e| AddError::UnexpectedError(e.into()))?;
+        return Ok(HttpResponse::Ok().json(report));
+    }
+
+    let records: Vec<RecordAdd> = serde_json::from_value(Value::Array(records.into_inner()))
+        .map_err(|e| AddError::InvalidRequest(format!("Json deserialize error: {e}")))?;
+    bulk_insert(&records, client.as_deref(), &pool)
         .await
         .map_err(|e| match e.0.as_database_error() {
             Some(db_err) => match db_err.code().as_ref() {
                 Some(code) => match code.as_ref() {
-                    "23505" => AddError::RecordExists,
+                    "23505" => AddError::RecordExists(api_version(&request)),
                     _ => AddError::UnexpectedError(e.into()),
                 },
                 _ => AddError::UnexpectedError(e.into()),
@@ -138,33 +384,47 @@ pub async fn bulk_add(
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Checks that the client of `request` may write records with the given `record_ids`. The sites
+/// of the new records are checked by `permits`. Stored records with the same `record_ids` have to
+/// belong to permitted sites as well, as they might be overwritten.
+async fn authorize_write(
+    request: &HttpRequest,
+    record_ids: &[&str],
+    permits: impl Fn(&Principal) -> bool,
+    pool: &PgPool,
+) -> Result<(), AddError> {
+    let Some(principal) = Principal::of(request) else {
+        return Ok(());
+    };
+    if !permits(&principal)
+        || !principal
+            .permits_stored_records(record_ids, pool)
+            .await
+            .map_err(|e| AddError::UnexpectedError(e.into()))?
+    {
+        return Err(AddError::Forbidden(format!(
+            "{} is not permitted to write records of other sites",
+            principal.name
+        )));
+    }
+    Ok(())
+}
+
 #[tracing::instrument(name = "Inserting bulk records into database", skip(records, pool))]
-pub async fn bulk_insert(records: &[RecordAdd], pool: &PgPool) -> Result<(), AddRecordError> {
+pub async fn bulk_insert(
+    records: &[RecordAdd],
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<(), AddRecordError> {
     let mut transaction = match pool.begin().await {
         Ok(transaction) => transaction,
         Err(e) => return Err(AddRecordError(e)),
     };
+    set_history_client(&mut transaction, client)
+        .await
+        .map_err(AddRecordError)?;
 
-    let record_ids: Vec<_> = records
-        .iter()
-        .map(|r| r.record_id.as_ref().to_string())
-        .collect();
-    let start_times: Vec<_> = records.iter().map(|r| r.start_time).collect();
-    let stop_times: Vec<_> = records.iter().map(|r| r.stop_time).collect();
-    let runtimes: Vec<_> = records
-        .iter()
-        .map(|r| r.stop_time.map(|stop| (stop - r.start_time).num_seconds()))
-        .collect();
-    let updated_at_vec: Vec<_> = std::iter::repeat_n(Utc::now(), records.len()).collect();
-
-    let meta_values: Vec<Value> = records
-        .iter()
-        .map(|r| serde_json::to_value(&r.meta).unwrap_or(serde_json::Value::Null))
-        .collect();
-    let component_values: Vec<Value> = records
-        .iter()
-        .map(|r| serde_json::to_value(&r.components).unwrap_or(serde_json::Value::Null))
-        .collect();
+    let columns = RecordColumns::new(records.iter());
 
     sqlx::query_unchecked!(
         r#"
@@ -174,13 +434,13 @@ pub async fn bulk_insert(records: &[RecordAdd], pool: &PgPool) -> Result<(), Add
         SELECT * FROM UNNEST($1::text[], $2::timestamptz[], $3::timestamptz[], $4::jsonb[], $5::jsonb[],  $6::bigint[], $7::timestamptz[])
         RETURNING id;
         "#,
-        &record_ids[..],
-        &start_times[..],
-        &stop_times[..],
-        &meta_values[..],
-        &component_values[..],
-        &runtimes[..],
-        &updated_at_vec[..],
+        &columns.record_ids[..],
+        &columns.start_times[..],
+        &columns.stop_times[..],
+        &columns.meta[..],
+        &columns.components[..],
+        &columns.runtimes[..],
+        &columns.updated_at[..],
     )
     .fetch_all(&mut *transaction)
     .await
@@ -193,6 +453,167 @@ pub async fn bulk_insert(records: &[RecordAdd], pool: &PgPool) -> Result<(), Add
     }
 }
 
+/// Columns of records passed to `UNNEST` when inserting several records at once.
+struct RecordColumns {
+    record_ids: Vec<String>,
+    start_times: Vec<DateTime<Utc>>,
+    stop_times: Vec<Option<DateTime<Utc>>>,
+    meta: Vec<Value>,
+    components: Vec<Value>,
+    runtimes: Vec<Option<i64>>,
+    updated_at: Vec<DateTime<Utc>>,
+}
+
+impl RecordColumns {
+    fn new<'a>(records: impl ExactSizeIterator<Item = &'a RecordAdd> + Clone) -> Self {
+        let updated_at = std::iter::repeat_n(Utc::now(), records.len()).collect();
+        RecordColumns {
+            record_ids: records
+                .clone()
+                .map(|r| r.record_id.as_ref().to_string())
+                .collect(),
+            start_times: records.clone().map(|r| r.start_time).collect(),
+            stop_times: records.clone().map(|r| r.stop_time).collect(),
+            meta: records
+                .clone()
+                .map(|r| serde_json::to_value(&r.meta).unwrap_or(Value::Null))
+                .collect(),
+            components: records
+                .clone()
+                .map(|r| serde_json::to_value(&r.components).unwrap_or(Value::Null))
+                .collect(),
+            runtimes: records
+                .map(|r| r.stop_time.map(|stop| (stop - r.start_time).num_seconds()))
+                .collect(),
+            updated_at,
+        }
+    }
+}
+
+/// Inserts the valid records of `records` and reports the status of each record. Records whose
+/// `record_id` already exists are skipped or replaced, depending on `on_conflict`.
+#[tracing::instrument(
+    name = "Inserting bulk records into database individually",
+    skip(records, pool)
+)]
+pub async fn bulk_insert_partial(
+    records: Vec<Value>,
+    on_conflict: OnConflict,
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<BulkInsertReport, AddRecordError> {
+    let mut results = Vec::with_capacity(records.len());
+    // Records to insert together with their position in `results`
+    let mut valid = vec![];
+    let mut seen = HashSet::new();
+    for value in records {
+        let record_id = value
+            .get("record_id")
+            .and_then(Value::as_str)
+            .map(str::to_string);
+        let (status, error) = match serde_json::from_value::<RecordAdd>(value) {
+            Ok(record) if seen.insert(record.record_id.as_ref().to_string()) => {
+                valid.push((results.len(), record));
+                // Replaced once the record has been written
+                (InsertStatus::Duplicate, None)
+            }
+            Ok(_) => (InsertStatus::Duplicate, None),
+            Err(e) => (InsertStatus::Invalid, Some(e.to_string())),
+        };
+        results.push(InsertResult {
+            record_id,
+            status,
+            error,
+        });
+    }
+
+    let columns = RecordColumns::new(valid.iter().map(|(_, record)| record));
+
+    let mut transaction = pool.begin().await.map_err(AddRecordError)?;
+    set_history_client(&mut transaction, client)
+        .await
+        .map_err(AddRecordError)?;
+    skip_existing_records(&mut transaction)
+        .await
+        .map_err(AddRecordError)?;
+    let inserted: HashSet<String> = sqlx::query_scalar(
+        "INSERT INTO auditor_accounting (
+             record_id, start_time, stop_time, meta, components, runtime, updated_at
+         )
+         SELECT * FROM UNNEST($1::text[], $2::timestamptz[], $3::timestamptz[], $4::jsonb[],
+                              $5::jsonb[], $6::bigint[], $7::timestamptz[])
+         RETURNING record_id",
+    )
+    .bind(&columns.record_ids)
+    .bind(&columns.start_times)
+    .bind(&columns.stop_times)
+    .bind(&columns.meta)
+    .bind(&columns.components)
+    .bind(&columns.runtimes)
+    .bind(&columns.updated_at)
+    .fetch_all(&mut *transaction)
+    .await
+    .map_err(AddRecordError)?
+    .into_iter()
+    .collect();
+
+    // The records which were skipped as they exist already
+    let updated: HashSet<String> = if on_conflict == OnConflict::Update {
+        sqlx::query_scalar(
+            "UPDATE auditor_accounting AS a
+             SET start_time = r.start_time,
+                 stop_time = r.stop_time,
+                 meta = r.meta,
+                 components = r.components,
+                 runtime = r.runtime,
+                 updated_at = r.updated_at
+             FROM UNNEST($1::text[], $2::timestamptz[], $3::timestamptz[], $4::jsonb[],
+                         $5::jsonb[], $6::bigint[], $7::timestamptz[])
+                  AS r(record_id, start_time, stop_time, meta, components, runtime, updated_at)
+             WHERE a.record_id = r.record_id AND r.record_id <> ALL($8)
+             RETURNING a.record_id",
+        )
+        .bind(&columns.record_ids)
+        .bind(&columns.start_times)
+        .bind(&columns.stop_times)
+        .bind(&columns.meta)
+        .bind(&columns.components)
+        .bind(&columns.runtimes)
+        .bind(&columns.updated_at)
+        .bind(inserted.iter().collect::<Vec<_>>())
+        .fetch_all(&mut *transaction)
+        .await
+        .map_err(AddRecordError)?
+        .into_iter()
+        .collect()
+    } else {
+        HashSet::new()
+    };
+    transaction.commit().await.map_err(AddRecordError)?;
+
+    for (index, record) in valid {
+        let record_id = record.record_id.as_ref();
+        results[index].status = if inserted.contains(record_id) {
+            InsertStatus::Inserted
+        } else if updated.contains(record_id) {
+            InsertStatus::Updated
+        } else {
+            InsertStatus::Duplicate
+        };
+    }
+
+    Ok(BulkInsertReport { records: results })
+}
+
+/// Lets inserts of the current transaction skip records whose `record_id` exists already,
+/// instead of failing with a unique violation.
+async fn skip_existing_records(connection: &mut PgConnection) -> Result<(), sqlx::Error> {
+    sqlx::query("SELECT set_config('auditor.skip_existing', 'on', true)")
+        .execute(connection)
+        .await?;
+    Ok(())
+}
+
 pub struct AddRecordError(sqlx::Error);
 
 debug_for_error!(AddRecordError);
@@ -5,29 +5,53 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use crate::domain::{Record, RecordDatabase, ValidAmount, ValidName};
+use crate::auth::push_site_condition;
+use crate::domain::{Record, RecordDatabase, ValidAmount, ValidName, ValidValue};
+use crate::routes::{Cursor, deserialize_cursor, sort_order};
 use chrono::{DateTime, Utc};
 use core::fmt::Debug;
-use sqlx::{PgPool, QueryBuilder, Row};
+use sqlx::postgres::PgRow;
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
 use std::collections::HashMap;
 use std::fmt::Display;
 
-use actix_web::web::Bytes;
-use async_stream::stream;
-use futures::Stream;
-use futures_util::TryStreamExt;
-
-#[derive(serde::Deserialize, Debug, Clone)]
+/// Filters of the records, which are given as query string, e.g.
+/// `start_time[gte]=2024-01-01T00:00:00Z&meta[site_id][c]=site1&sort_by[desc]=stop_time`.
+#[derive(serde::Deserialize, Debug, Clone, utoipa::IntoParams)]
 #[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query, style = DeepObject)]
 pub struct Filters {
+    /// Only selects the record with this `record_id`.
+    #[param(style = Form)]
     pub record_id: Option<ValidName>,
+    /// Operators on the `start_time`, e.g. `start_time[gte]=2024-01-01T00:00:00Z`.
+    #[param(value_type = Option<Operator<String>>)]
     pub start_time: Option<Operator<DateTime<Utc>>>,
+    /// Operators on the `stop_time`, e.g. `stop_time[lt]=2024-02-01T00:00:00Z`.
+    #[param(value_type = Option<Operator<String>>)]
     pub stop_time: Option<Operator<DateTime<Utc>>>,
+    /// Operators on the `runtime` in seconds, e.g. `runtime[gt]=3600`.
     pub runtime: Option<Operator<ValidAmount>>,
+    /// Values that a meta key contains (`c`) or does not contain (`dnc`), e.g.
+    /// `meta[site_id][c]=site1`.
     pub meta: Option<HashMap<ValidName, MetaOperator>>,
-    pub component: Option<HashMap<ValidName, Operator<ValidAmount>>>,
+    /// Operators on the amount and scores of a component, e.g. `component[CPU][gte]=4`.
+    pub component: Option<HashMap<ValidName, ComponentOperator>>,
+    /// Sort order of the records, e.g. `sort_by[desc]=stop_time`.
     pub sort_by: Option<SortOption>,
+    /// Maximum number of records. The cursor of the next page is returned in the
+    /// `X-Next-Cursor` header.
+    #[param(style = Form)]
     pub limit: Option<ValidAmount>,
+    /// Only returns the records following the record the cursor points at.
+    #[serde(default, deserialize_with = "deserialize_cursor")]
+    #[param(style = Form, value_type = Option<String>)]
+    pub after: Option<Cursor>,
+    /// Only selects records of these sites. This is not part of the query string, but set for
+    /// clients which are restricted to some sites.
+    #[serde(skip)]
+    #[param(ignore)]
+    pub sites: Option<Vec<String>>,
 }
 
 impl Filters {
@@ -40,10 +64,71 @@ impl Filters {
             && self.component.is_none()
             && self.sort_by.is_none()
             && self.limit.is_none()
+            && self.after.is_none()
+    }
+
+    /// Parses `query_string` and validates the filters with [`Filters::validate`].
+    pub fn parse(query_string: &str) -> Result<Filters, String> {
+        let filters: Filters = serde_qs::from_str(query_string).map_err(|err| err.to_string())?;
+        filters.validate()?;
+        Ok(filters)
+    }
+
+    /// Checks that every filter which is set translates into a condition. Filters that would
+    /// otherwise be ignored, e.g. `start_time[equals]` or `meta[site_id]` without operator, are
+    /// rejected, as ignoring them selects more records than intended.
+    pub fn validate(&self) -> Result<(), String> {
+        if let Some(operator) = &self.start_time {
+            validate_operator("start_time", operator, false)?;
+        }
+        if let Some(operator) = &self.stop_time {
+            validate_operator("stop_time", operator, false)?;
+        }
+        if let Some(operator) = &self.runtime {
+            validate_operator("runtime", operator, true)?;
+        }
+        for (key, operator) in self.meta.iter().flatten() {
+            if operator.c.is_none() && operator.dnc.is_none() {
+                return Err(format!("meta[{key}] requires the operator c or dnc"));
+            }
+        }
+        for (name, operator) in self.component.iter().flatten() {
+            let scores = operator.score.as_ref().filter(|scores| !scores.is_empty());
+            if scores.is_none() || !operator.amount().is_empty() {
+                validate_operator(&format!("component[{name}]"), &operator.amount(), true)?;
+            }
+            for (score, operator) in scores.into_iter().flatten() {
+                validate_operator(
+                    &format!("component[{name}][score][{score}]"),
+                    operator,
+                    true,
+                )?;
+            }
+        }
+        Ok(())
     }
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+/// Checks that `operator` of the filter `name` contains at least one usable comparison.
+fn validate_operator<T>(name: &str, operator: &Operator<T>, equals: bool) -> Result<(), String> {
+    if operator.gt.is_some() && operator.gte.is_some() {
+        return Err(format!("{name} must not contain both gt and gte"));
+    }
+    if operator.lt.is_some() && operator.lte.is_some() {
+        return Err(format!("{name} must not contain both lt and lte"));
+    }
+    if operator.equals.is_some() && !equals {
+        return Err(format!(
+            "{name}[equals] is not supported, use gte and lte instead"
+        ));
+    }
+    if operator.is_empty() {
+        return Err(format!("{name} requires an operator"));
+    }
+    Ok(())
+}
+
+#[derive(serde::Deserialize, Debug, Clone, utoipa::ToSchema)]
 #[serde(deny_unknown_fields)]
 pub struct Operator<T> {
     pub gt: Option<T>,
@@ -53,20 +138,57 @@ pub struct Operator<T> {
     pub equals: Option<T>,
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+impl<T> Operator<T> {
+    fn is_empty(&self) -> bool {
+        self.gt.is_none()
+            && self.lt.is_none()
+            && self.gte.is_none()
+            && self.lte.is_none()
+            && self.equals.is_none()
+    }
+}
+
+/// Operators on the amount of a component together with operators on the values of its
+/// scores, e.g. `component[CPU][gte]=4&component[CPU][score][HEPSPEC06][gte]=10`.
+#[derive(serde::Deserialize, Debug, Clone, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct ComponentOperator {
+    pub gt: Option<ValidAmount>,
+    pub lt: Option<ValidAmount>,
+    pub gte: Option<ValidAmount>,
+    pub lte: Option<ValidAmount>,
+    pub equals: Option<ValidAmount>,
+    pub score: Option<HashMap<ValidName, Operator<ValidValue>>>,
+}
+
+impl ComponentOperator {
+    /// Returns the operators on the amount of the component.
+    fn amount(&self) -> Operator<ValidAmount> {
+        Operator {
+            gt: self.gt,
+            lt: self.lt,
+            gte: self.gte,
+            lte: self.lte,
+            equals: self.equals,
+        }
+    }
+}
+
+#[derive(serde::Deserialize, Debug, Clone, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
 pub struct MetaOperator {
     pub c: Option<ValidName>,
     pub dnc: Option<ValidName>,
 }
 
-#[derive(serde::Deserialize, Debug, Clone)]
+#[derive(serde::Deserialize, Debug, Clone, utoipa::ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SortOption {
     ASC(SortField),
     DESC(SortField),
 }
 
-#[derive(serde::Deserialize, Debug, PartialEq, Eq, Clone)]
+#[derive(serde::Deserialize, serde::Serialize, Debug, PartialEq, Eq, Clone, utoipa::ToSchema)]
 #[serde(rename_all = "lowercase")]
 pub enum SortField {
     #[serde(rename = "start_time")]
@@ -90,202 +212,363 @@ impl Display for SortField {
     }
 }
 
-#[tracing::instrument(name = "Getting records using custom query", skip(filters, pool))]
-pub async fn advanced_record_filtering(
-    filters: Filters,
-    pool: PgPool,
-) -> impl Stream<Item = Result<Bytes, anyhow::Error>> {
-    let mut query = QueryBuilder::new(
-        "SELECT record_id,
-                  meta,
-                  components,
-                  start_time,
-                  stop_time,
-                  runtime
-           FROM auditor_accounting
-               ",
-    );
+/// Splits a query string into the parameters named in `keys` and all remaining parameters.
+///
+/// This allows endpoints to accept their own parameters next to the ones that are deserialized
+/// into [`Filters`], which denies unknown fields.
+pub(crate) fn split_query_string(query_string: &str, keys: &[&str]) -> (String, String) {
+    let (selected, remaining): (Vec<&str>, Vec<&str>) = query_string
+        .split('&')
+        .filter(|pair| !pair.is_empty())
+        .partition(|pair| {
+            let name = pair.split(['=', '[']).next().unwrap_or_default();
+            let name = match name.find("%5B").or_else(|| name.find("%5b")) {
+                Some(index) => &name[..index],
+                None => name,
+            };
+            keys.contains(&name)
+        });
+    (selected.join("&"), remaining.join("&"))
+}
+
+/// Appends the `WHERE` clause described by `filters` to `query`.
+///
+/// Nothing is appended if none of the filtering fields are set. Sorting and limiting are not
+/// handled here, as they only make sense for queries returning individual records.
+///
+/// If any filter is set, only records that have a `stop_time` are selected.
+///
+/// Returns whether a condition of any of the filtering fields was appended. The cursor and the
+/// restriction to `sites` do not count as such.
+pub(crate) fn push_filters(query: &mut QueryBuilder<'_, Postgres>, filters: &Filters) -> bool {
+    push_filter_clause(query, filters, false)
+}
+
+/// Same as [`push_filters`], but records without a `stop_time` are selected as well.
+pub(crate) fn push_filters_including_open(
+    query: &mut QueryBuilder<'_, Postgres>,
+    filters: &Filters,
+) -> bool {
+    push_filter_clause(query, filters, true)
+}
 
-    if filters.start_time.is_some()
+fn push_filter_clause(
+    query: &mut QueryBuilder<'_, Postgres>,
+    filters: &Filters,
+    include_open: bool,
+) -> bool {
+    let filtered = filters.start_time.is_some()
         || filters.stop_time.is_some()
         || filters.runtime.is_some()
         || filters.meta.is_some()
         || filters.component.is_some()
-        || filters.record_id.is_some()
-    {
-        query.push(" WHERE ".to_string());
-        if let Some(record_id) = &filters.record_id {
-            // query string -> a.record_id = '{}' and
-            query.push(" record_id = ".to_string());
-            query.push_bind(record_id.clone());
-            query.push(" and ".to_string());
-        }
+        || filters.record_id.is_some();
+    if !filtered && filters.after.is_none() && filters.sites.is_none() {
+        return false;
+    }
+    let mut conditions = false;
 
-        if let Some(start_time_filters) = &filters.start_time {
-            if let Some(operators) = get_operator(start_time_filters) {
-                for operator in operators {
-                    // query string -> a.start_time {} '{}' and
-                    query.push(format!(" start_time {} ", operator.0));
-                    query.push_bind(*operator.1);
-                    query.push(" and ".to_string());
-                }
-            }
-        }
+    query.push(" WHERE ".to_string());
+    if let Some(cursor) = &filters.after {
+        cursor.push_condition(query);
+        query.push(" and ".to_string());
+    }
+    if let Some(record_id) = &filters.record_id {
+        // query string -> a.record_id = '{}' and
+        query.push(" record_id = ".to_string());
+        query.push_bind(record_id.clone());
+        query.push(" and ".to_string());
+        conditions = true;
+    }
 
-        if let Some(stop_time_filters) = &filters.stop_time {
-            if let Some(operators) = get_operator(stop_time_filters) {
-                for operator in operators {
-                    // query string -> a.stop_time {} '{}' and
-                    query.push(format!(" stop_time {} ", operator.0));
-                    query.push_bind(*operator.1);
-                    query.push(" and ".to_string());
-                }
-            }
+    if let Some(operators) = filters.start_time.as_ref().and_then(get_operator) {
+        for operator in operators {
+            // query string -> a.start_time {} '{}' and
+            query.push(format!(" start_time {} ", operator.0));
+            query.push_bind(*operator.1);
+            query.push(" and ".to_string());
+            conditions = true;
         }
+    }
 
-        if let Some(meta_filters) = &filters.meta {
-            for (key, meta_operator) in meta_filters {
-                if let Some(c) = &meta_operator.c {
-                    // query string -> meta -> "site_id" @> jsonb_build_array("site1") and
-
-                    query.push(" meta ->  ".to_string());
-                    query.push_bind(key.clone());
-                    query.push(" @> jsonb_build_array(".to_string());
-                    query.push_bind(c.clone());
-                    query.push(") ");
-                    query.push(" and ");
-                }
-                if let Some(dnc) = &meta_operator.dnc {
-                    // query string -> NOT (meta -> "site_id" @> jsonb_build_array("site_1")) and
-
-                    query.push(" NOT (meta ->  ".to_string());
-                    query.push_bind(key.clone());
-                    query.push(" @> jsonb_build_array(".to_string());
-                    query.push_bind(dnc.clone());
-                    query.push(") ) ");
-                    query.push(" and ");
-                }
-            }
+    if let Some(operators) = filters.stop_time.as_ref().and_then(get_operator) {
+        for operator in operators {
+            // query string -> a.stop_time {} '{}' and
+            query.push(format!(" stop_time {} ", operator.0));
+            query.push_bind(*operator.1);
+            query.push(" and ".to_string());
+            conditions = true;
         }
+    }
 
-        if let Some(component_filters) = &filters.component {
-            for (key, component_operator) in component_filters {
-                if let Some(operators) = get_operator(component_operator) {
-                    for operator in operators {
-                        // query string -> components->0->>'name' = "CPU" AND
-                        // (components->0->>'amount')::int >10  and
-
-                        query.push("components->0->>'name' = ");
-                        query.push_bind(key.clone());
-                        query.push(format!(
-                            " AND (components->0->>'amount')::int {} ",
-                            &operator.0
-                        ));
-                        query.push_bind(*operator.1);
-
-                        query.push(" and ".to_string());
-                    }
-                }
+    if let Some(meta_filters) = &filters.meta {
+        for (key, meta_operator) in meta_filters {
+            if let Some(c) = &meta_operator.c {
+                // query string -> meta -> "site_id" @> jsonb_build_array("site1") and
+
+                query.push(" meta ->  ".to_string());
+                query.push_bind(key.clone());
+                query.push(" @> jsonb_build_array(".to_string());
+                query.push_bind(c.clone());
+                query.push(") ");
+                query.push(" and ");
+                conditions = true;
+            }
+            if let Some(dnc) = &meta_operator.dnc {
+                // query string -> NOT (meta -> "site_id" @> jsonb_build_array("site_1")) and
+
+                query.push(" NOT (meta ->  ".to_string());
+                query.push_bind(key.clone());
+                query.push(" @> jsonb_build_array(".to_string());
+                query.push_bind(dnc.clone());
+                query.push(") ) ");
+                query.push(" and ");
+                conditions = true;
             }
         }
+    }
 
-        // The previous implementation of get and get_since is replicated. Getting all records also includes
-        // the records whose runtime IS NOT NULL. But while querying with the start_time or stop_time,
-        // we also specify the query to only include the records whose runtime is NOT NULL
-
-        if let Some(runtime_filters) = &filters.runtime {
-            if let Some(operators) = get_operator(runtime_filters) {
-                for operator in operators {
-                    // query string ->  a.runtime {} {} and
-                    query.push(format!(" runtime {} ", operator.0));
-                    query.push_bind(*operator.1);
-                    query.push(" and ".to_string());
-                }
+    if let Some(component_filters) = &filters.component {
+        for (name, component_operator) in component_filters {
+            let amount = component_operator.amount();
+            let amount = get_operator(&amount).unwrap_or_default();
+            let scores: Vec<_> = component_operator
+                .score
+                .iter()
+                .flatten()
+                .filter_map(|(score, operator)| Some((score, get_operator(operator)?)))
+                .collect();
+            if amount.is_empty() && scores.is_empty() {
+                continue;
             }
-        } else {
-            query.push(" runtime IS NOT NULL".to_string());
+            // query string -> jsonb_path_exists(
+            //     components,
+            //     '$[*] ? (@.name == $name && @.amount > $a0
+            //              && exists(@.scores[*] ? (@.name == $s0 && @.value >= $s0v0)))',
+            //     jsonb_build_object('name', 'CPU', 'a0', 10, 's0', 'HEPSPEC06', 's0v0', 10)
+            // ) and
+            push_component_filter(query, name, &amount, &scores);
+            query.push(" and ".to_string());
+            conditions = true;
         }
     }
 
-    if let Some(sort_by) = &filters.sort_by {
-        if let SortOption::ASC(asc) = sort_by {
-            query.push(format!(" ORDER BY {} ASC", &asc.to_string()));
-        }
-        if let SortOption::DESC(desc) = sort_by {
-            query.push(format!(" ORDER BY {} DESC", &desc.to_string()));
+    // The previous implementation of get and get_since is replicated. Getting all records also includes
+    // the records whose runtime IS NOT NULL. But while querying with the start_time or stop_time,
+    // we also specify the query to only include the records whose runtime is NOT NULL
+
+    if let Some(operators) = filters.runtime.as_ref().and_then(get_operator) {
+        for operator in operators {
+            // query string ->  a.runtime {} {} and
+            query.push(format!(" runtime {} ", operator.0));
+            query.push_bind(*operator.1);
+            query.push(" and ".to_string());
+            conditions = true;
         }
+    }
+    if let Some(sites) = &filters.sites {
+        push_site_condition(query, sites);
+        query.push(" and ".to_string());
+    }
+    // A cursor alone does not exclude records without a `stop_time`, otherwise the first page
+    // would contain records that are missing from the following pages
+    if include_open || !filtered {
+        query.push(" true".to_string());
     } else {
-        query.push(" ORDER BY stop_time ".to_string());
+        query.push(" runtime IS NOT NULL".to_string());
     }
+    conditions
+}
 
-    if let Some(limit) = &filters.limit {
-        query.push(" LIMIT ".to_string());
-        query.push_bind(*limit);
+/// Appends a condition which is true if any of the components named `name` satisfies all of
+/// the `amount` operators and has scores satisfying the `scores` operators. All components of a
+/// record are searched, not only the first one.
+fn push_component_filter(
+    query: &mut QueryBuilder<'_, Postgres>,
+    name: &ValidName,
+    amount: &[(&str, &ValidAmount)],
+    scores: &[(&ValidName, Vec<(&str, &ValidValue)>)],
+) {
+    let mut predicate: String = amount
+        .iter()
+        .enumerate()
+        .map(|(i, (operator, _))| format!(" && @.amount {} $a{i}", jsonpath_operator(operator)))
+        .collect();
+    for (i, (_, operators)) in scores.iter().enumerate() {
+        let score_predicate: String = operators
+            .iter()
+            .enumerate()
+            .map(|(j, (operator, _))| {
+                format!(" && @.value {} $s{i}v{j}", jsonpath_operator(operator))
+            })
+            .collect();
+        predicate.push_str(&format!(
+            " && exists(@.scores[*] ? (@.name == $s{i}{score_predicate}))"
+        ));
     }
 
-    fn get_operator<T>(operator: &Operator<T>) -> Option<Vec<(&str, &T)>>
-    where
-        T: 'static,
-    {
-        let mut operators: Vec<(&str, &T)> = Vec::new();
-
-        if operator.gt.is_some() && operator.gte.is_some()
-            || operator.lt.is_some() && operator.lte.is_some()
-        {
-            return None;
+    query.push(format!(
+        "jsonb_path_exists(components, '$[*] ? (@.name == $name{predicate})', jsonb_build_object('name', "
+    ));
+    query.push_bind(name.clone());
+    query.push("::text");
+    for (i, (_, value)) in amount.iter().enumerate() {
+        query.push(format!(", 'a{i}', "));
+        query.push_bind(**value);
+    }
+    for (i, (score, operators)) in scores.iter().enumerate() {
+        query.push(format!(", 's{i}', "));
+        query.push_bind((*score).clone());
+        query.push("::text");
+        for (j, (_, value)) in operators.iter().enumerate() {
+            query.push(format!(", 's{i}v{j}', "));
+            query.push_bind(**value);
         }
+    }
+    query.push("))");
+}
 
-        if let Some(gt) = &operator.gt {
-            operators.push((">", gt));
-        }
-        if let Some(lt) = &operator.lt {
-            operators.push(("<", lt));
-        }
-        if let Some(gte) = &operator.gte {
-            operators.push((">=", gte));
-        }
-        if let Some(lte) = &operator.lte {
-            operators.push(("<=", lte));
-        }
-        if let Some(equals) = &operator.equals {
-            if !is_datetime::<T>() {
-                operators.push(("=", equals));
-            }
-        }
-        if !operators.is_empty() {
-            Some(operators)
-        } else {
-            None
-        }
+/// Translates an SQL comparison operator into the corresponding SQL/JSON path operator.
+fn jsonpath_operator(operator: &str) -> &str {
+    match operator {
+        "=" => "==",
+        operator => operator,
     }
+}
+
+fn get_operator<T>(operator: &Operator<T>) -> Option<Vec<(&str, &T)>>
+where
+    T: 'static,
+{
+    let mut operators: Vec<(&str, &T)> = Vec::new();
 
-    // Helper function to check if T is Datetime
-    fn is_datetime<T: 'static>() -> bool {
-        std::any::TypeId::of::<T>() == std::any::TypeId::of::<DateTime<Utc>>()
+    if operator.gt.is_some() && operator.gte.is_some()
+        || operator.lt.is_some() && operator.lte.is_some()
+    {
+        return None;
     }
 
-    stream! {
+    if let Some(gt) = &operator.gt {
+        operators.push((">", gt));
+    }
+    if let Some(lt) = &operator.lt {
+        operators.push(("<", lt));
+    }
+    if let Some(gte) = &operator.gte {
+        operators.push((">=", gte));
+    }
+    if let Some(lte) = &operator.lte {
+        operators.push(("<=", lte));
+    }
+    if let Some(equals) = &operator.equals
+        && !is_datetime::<T>()
+    {
+        operators.push(("=", equals));
+    }
+    if !operators.is_empty() {
+        Some(operators)
+    } else {
+        None
+    }
+}
 
-    let mut rows = query.build().persistent(false).fetch(&pool);
+// Helper function to check if T is Datetime
+fn is_datetime<T: 'static>() -> bool {
+    std::any::TypeId::of::<T>() == std::any::TypeId::of::<DateTime<Utc>>()
+}
 
-    while let Some(row) = rows.try_next().await.unwrap_or(None)
+/// Builds the query selecting the records matching `filters`, sorted by the requested sort key
+/// and then by `record_id`. At most `limit` records are selected.
+pub(crate) fn records_query(filters: &Filters, limit: Option<i64>) -> QueryBuilder<'_, Postgres> {
+    let mut query = QueryBuilder::new(
+        "SELECT record_id,
+                  meta,
+                  components,
+                  start_time,
+                  stop_time,
+                  runtime
+           FROM auditor_accounting
+               ",
+    );
 
-        {
-            let beam_object = Record {
-                record_id: row.try_get("record_id").unwrap(),
-                meta: row.try_get("meta").ok().and_then(|value| serde_json::from_value(value).ok()),
-                components: row.try_get("components").ok().and_then(|value| serde_json::from_value(value).ok()),
-                start_time: row.try_get("start_time").ok(),
-                stop_time: row.try_get("stop_time").ok(),
-                runtime: row.try_get("runtime").ok(),
-            };
+    push_filters(&mut query, filters);
 
-            let json_bytes = serde_json::to_vec(&beam_object).unwrap().into();
-            yield Ok(json_bytes);
-        }
+    // record_id is used as tie breaker to get a stable order which cursors can refer to
+    let (sort_by, descending) = sort_order(&filters.sort_by);
+    let direction = if descending { "DESC" } else { "ASC" };
+    if sort_by == SortField::RecordId {
+        query.push(format!(" ORDER BY record_id {direction}"));
+    } else {
+        query.push(format!(
+            " ORDER BY {sort_by} {direction}, record_id {direction}"
+        ));
+    }
+
+    if let Some(limit) = limit {
+        query.push(" LIMIT ".to_string());
+        query.push_bind(limit);
+    }
+    query
+}
+
+pub(crate) fn record_from_row(row: &PgRow) -> Record {
+    Record {
+        record_id: row.try_get("record_id").unwrap(),
+        meta: row
+            .try_get("meta")
+            .ok()
+            .and_then(|value| serde_json::from_value(value).ok()),
+        components: row
+            .try_get("components")
+            .ok()
+            .and_then(|value| serde_json::from_value(value).ok()),
+        start_time: row.try_get("start_time").ok(),
+        stop_time: row.try_get("stop_time").ok(),
+        runtime: row.try_get("runtime").ok(),
     }
 }
 
+/// Returns the page of at most `limit` records matching `filters`, together with the cursor
+/// pointing at the last record of the page if more records follow.
+#[tracing::instrument(
+    name = "Getting a page of records using custom query",
+    skip(filters, pool)
+)]
+pub async fn get_records_page(
+    filters: &Filters,
+    limit: ValidAmount,
+    pool: &PgPool,
+) -> Result<(Vec<Record>, Option<Cursor>), GetRecordsPageError> {
+    let limit = *limit.as_ref();
+    // One record more than requested is fetched to find out whether another page follows
+    let rows = records_query(filters, Some(limit.saturating_add(1)))
+        .build()
+        .persistent(false)
+        .fetch_all(pool)
+        .await
+        .map_err(GetRecordsPageError)?;
+
+    let mut records: Vec<Record> = rows.iter().map(record_from_row).collect();
+    let next = if records.len() as i64 > limit {
+        records.truncate(limit as usize);
+        records
+            .last()
+            .map(|record| Cursor::after(record, &filters.sort_by))
+    } else {
+        None
+    };
+    Ok((records, next))
+}
+
+pub struct GetRecordsPageError(sqlx::Error);
+
+debug_for_error!(GetRecordsPageError);
+error_for_error!(GetRecordsPageError);
+display_for_error!(
+    GetRecordsPageError,
+    "A database error was encountered while trying to get a page of records from the database"
+);
+
 #[tracing::instrument(name = "Getting one record using record_id", skip(record_id, pool))]
 pub async fn get_one_record(
     record_id: String,
@@ -306,11 +589,10 @@ pub async fn get_one_record(
         "#,
             &record_id,
         )
-        .fetch_one(pool)
+        .fetch_optional(pool)
         .await
-        .map(Record::try_from)
         .map_err(GetRecordError)?
-        .ok())
+        .and_then(|record| Record::try_from(record).ok()))
     } else {
         return Ok(None);
     }
@@ -0,0 +1,286 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::Principal;
+use crate::domain::{ComponentAggregate, ScoreAggregate, UsageAggregate, ValidName};
+use crate::routes::{ErrorResponse, Filters, GetFilterError, push_filters, split_query_string};
+use actix_web::{HttpRequest, HttpResponse, web};
+use sqlx::postgres::PgRow;
+use sqlx::{PgConnection, PgPool, Postgres, QueryBuilder, Row, Transaction};
+use std::collections::BTreeMap;
+
+#[derive(serde::Deserialize, Debug, Clone, Default, utoipa::IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct AggregateOptions {
+    /// Meta keys whose values the records are grouped by, e.g. `group_by[]=site_id`.
+    pub group_by: Option<Vec<ValidName>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/aggregate",
+    tag = "usage",
+    params(AggregateOptions, Filters),
+    responses(
+        (status = 200, description = "The summed usage per group. `sort_by`, `limit` and \
+            `after` are not supported", body = Vec<UsageAggregate>),
+        (status = 400, description = "The query is invalid", body = ErrorResponse),
+        (status = 500, description = "An unexpected error occurred", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Aggregating records", skip(query, pool))]
+pub async fn query_aggregate(
+    query: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, GetFilterError> {
+    let (options, filters) = split_query_string(query.query_string(), &["group_by"]);
+
+    let options: AggregateOptions = serde_qs::from_str(&options)
+        .map_err(|err| GetFilterError::InvalidQuery(err.to_string()))?;
+    let mut filters = Filters::parse(&filters).map_err(GetFilterError::InvalidQuery)?;
+    if filters.sort_by.is_some() || filters.limit.is_some() || filters.after.is_some() {
+        return Err(GetFilterError::InvalidQuery(
+            "sort_by, limit and after are not supported by aggregations".to_string(),
+        ));
+    }
+    filters.sites = Principal::of(&query).and_then(|principal| principal.sites);
+
+    let aggregates = aggregate_records(
+        &filters,
+        options.group_by.as_deref().unwrap_or_default(),
+        &pool,
+    )
+    .await
+    .map_err(|err| GetFilterError::UnexpectedError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(aggregates))
+}
+
+/// Sums up the resource usage of all records matching `filters`, grouped by the values of the
+/// meta keys in `group_by`.
+#[tracing::instrument(name = "Aggregating records in the database", skip(filters, pool))]
+pub async fn aggregate_records(
+    filters: &Filters,
+    group_by: &[ValidName],
+    pool: &PgPool,
+) -> Result<Vec<UsageAggregate>, AggregateRecordsError> {
+    let mut transaction = begin_snapshot(pool).await?;
+
+    let aggregates = sum_usage(
+        &mut transaction,
+        || {
+            let mut query = QueryBuilder::new(
+                "WITH filtered AS (
+                     SELECT meta, components, runtime
+                     FROM auditor_accounting
+                 ",
+            );
+            push_filters(&mut query, filters);
+            query.push(")");
+            push_grouped(&mut query, "filtered", &["components", "runtime"], group_by);
+            query
+        },
+        "",
+        group_by,
+        |_| Ok(()),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(AggregateRecordsError)?;
+
+    Ok(aggregates.into_values().collect())
+}
+
+/// Starts a read only transaction in which all queries see the same snapshot of the table.
+pub(crate) async fn begin_snapshot(
+    pool: &PgPool,
+) -> Result<Transaction<'static, Postgres>, AggregateRecordsError> {
+    let mut transaction = pool.begin().await.map_err(AggregateRecordsError)?;
+    sqlx::query("SET TRANSACTION ISOLATION LEVEL REPEATABLE READ READ ONLY")
+        .execute(&mut *transaction)
+        .await
+        .map_err(AggregateRecordsError)?;
+    Ok(transaction)
+}
+
+/// Sums up the usage in the common table expression `grouped`, which is built by `grouped` and
+/// has to provide the columns `components` and `runtime`, the columns listed in `key_columns`
+/// and one column `g<i>` per meta key in `group_by` (see [`push_grouped`]).
+///
+/// `key_columns` is either empty or a list of column names, each followed by `", "`. The
+/// values of these columns are extracted with `key` and become part of the key of the
+/// returned map.
+pub(crate) async fn sum_usage<K: Ord + Clone>(
+    connection: &mut PgConnection,
+    grouped: impl Fn() -> QueryBuilder<'static, Postgres>,
+    key_columns: &str,
+    group_by: &[ValidName],
+    key: impl Fn(&PgRow) -> Result<K, sqlx::Error>,
+) -> Result<BTreeMap<(K, Vec<Option<String>>), UsageAggregate>, AggregateRecordsError> {
+    let group_columns: String = std::iter::once(key_columns.to_string())
+        .chain((0..group_by.len()).map(|i| format!("g{i}, ")))
+        .collect();
+    let group_clause = group_columns.trim_end_matches(", ");
+
+    let mut query = grouped();
+    query.push(format!(
+        "SELECT {group_columns} count(*) AS record_count,
+                COALESCE(round(sum(runtime)), 0)::bigint AS runtime
+         FROM grouped"
+    ));
+    if !group_clause.is_empty() {
+        query.push(format!(" GROUP BY {group_clause}"));
+    }
+    let record_rows = query
+        .build()
+        .persistent(false)
+        .fetch_all(&mut *connection)
+        .await
+        .map_err(AggregateRecordsError)?;
+
+    let mut query = grouped();
+    query.push(format!(
+        "SELECT {group_columns} c.value->>'name' AS name,
+                COALESCE(sum((c.value->>'amount')::numeric), 0)::double precision AS amount,
+                COALESCE(sum((c.value->>'amount')::numeric * runtime), 0)::double precision
+                    AS amount_runtime
+         FROM grouped
+         CROSS JOIN LATERAL jsonb_array_elements(grouped.components) AS c(value)
+         GROUP BY {group_columns} c.value->>'name'
+         ORDER BY name"
+    ));
+    let component_rows = query
+        .build()
+        .persistent(false)
+        .fetch_all(&mut *connection)
+        .await
+        .map_err(AggregateRecordsError)?;
+
+    let mut query = grouped();
+    query.push(format!(
+        "SELECT {group_columns} c.value->>'name' AS component, s.value->>'name' AS name,
+                COALESCE(
+                    sum((c.value->>'amount')::numeric * runtime * (s.value->>'value')::numeric),
+                    0
+                )::double precision AS amount_runtime_score
+         FROM grouped
+         CROSS JOIN LATERAL jsonb_array_elements(grouped.components) AS c(value)
+         CROSS JOIN LATERAL jsonb_array_elements(c.value->'scores') AS s(value)
+         GROUP BY {group_columns} c.value->>'name', s.value->>'name'
+         ORDER BY name"
+    ));
+    let score_rows = query
+        .build()
+        .persistent(false)
+        .fetch_all(&mut *connection)
+        .await
+        .map_err(AggregateRecordsError)?;
+
+    let row_key = |row: &PgRow| -> Result<(K, Vec<Option<String>>), AggregateRecordsError> {
+        Ok((
+            key(row).map_err(AggregateRecordsError)?,
+            group_key(row, group_by.len())?,
+        ))
+    };
+
+    let mut aggregates = BTreeMap::new();
+    for row in record_rows {
+        let key = row_key(&row)?;
+        let aggregate = UsageAggregate {
+            group: group_by
+                .iter()
+                .map(|k| k.as_ref().to_string())
+                .zip(key.1.iter().cloned())
+                .collect(),
+            record_count: row.try_get("record_count").map_err(AggregateRecordsError)?,
+            runtime: row.try_get("runtime").map_err(AggregateRecordsError)?,
+            components: vec![],
+        };
+        aggregates.insert(key, aggregate);
+    }
+
+    for row in component_rows {
+        let key = row_key(&row)?;
+        if let Some(aggregate) = aggregates.get_mut(&key) {
+            aggregate.components.push(ComponentAggregate {
+                name: row.try_get("name").map_err(AggregateRecordsError)?,
+                amount: row.try_get("amount").map_err(AggregateRecordsError)?,
+                amount_runtime: row
+                    .try_get("amount_runtime")
+                    .map_err(AggregateRecordsError)?,
+                scores: vec![],
+            });
+        }
+    }
+
+    for row in score_rows {
+        let key = row_key(&row)?;
+        let component: String = row.try_get("component").map_err(AggregateRecordsError)?;
+        if let Some(component) = aggregates
+            .get_mut(&key)
+            .and_then(|a| a.components.iter_mut().find(|c| c.name == component))
+        {
+            component.scores.push(ScoreAggregate {
+                name: row.try_get("name").map_err(AggregateRecordsError)?,
+                amount_runtime_score: row
+                    .try_get("amount_runtime_score")
+                    .map_err(AggregateRecordsError)?,
+            });
+        }
+    }
+
+    Ok(aggregates)
+}
+
+/// Appends the common table expression `grouped` to `query`, which contains `columns` of the
+/// common table expression `source`, together with one column `g<i>` per meta key in
+/// `group_by`. `source` has to provide the column `meta`.
+pub(crate) fn push_grouped(
+    query: &mut QueryBuilder<'static, Postgres>,
+    source: &str,
+    columns: &[&str],
+    group_by: &[ValidName],
+) {
+    query.push(", grouped AS ( SELECT ");
+    query.push(
+        columns
+            .iter()
+            .map(|c| format!("{source}.{c}"))
+            .collect::<Vec<_>>()
+            .join(", "),
+    );
+    for i in 0..group_by.len() {
+        query.push(format!(", g{i}.value AS g{i}"));
+    }
+    query.push(format!(" FROM {source}"));
+    for (i, key) in group_by.iter().enumerate() {
+        // A record without the meta key still ends up in the group `NULL`.
+        query.push(format!(
+            " LEFT JOIN LATERAL jsonb_array_elements_text({source}.meta -> "
+        ));
+        query.push_bind(key.clone());
+        query.push(format!(") AS g{i}(value) ON true"));
+    }
+    query.push(") ");
+}
+
+fn group_key(row: &PgRow, len: usize) -> Result<Vec<Option<String>>, AggregateRecordsError> {
+    (0..len)
+        .map(|i| row.try_get(format!("g{i}").as_str()))
+        .collect::<Result<_, _>>()
+        .map_err(AggregateRecordsError)
+}
+
+pub struct AggregateRecordsError(pub(crate) sqlx::Error);
+
+debug_for_error!(AggregateRecordsError);
+error_for_error!(AggregateRecordsError);
+display_for_error!(
+    AggregateRecordsError,
+    "A database error was encountered while trying to aggregate records."
+);
@@ -0,0 +1,196 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::Principal;
+use crate::domain::{ConcurrentAggregate, ConcurrentUsage, ValidName};
+use crate::routes::{
+    AggregateRecordsError, ErrorResponse, Filters, GetFilterError, begin_snapshot,
+    push_filters_including_open, push_grouped, split_query_string, sum_usage,
+};
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::{DateTime, TimeDelta, Utc};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+
+/// Upper limit for the number of sample points of a single query.
+const MAX_SAMPLES: usize = 50_000;
+
+#[derive(serde::Deserialize, Debug, Clone, utoipa::IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct ConcurrencyOptions {
+    /// Single sample point. Either `at` or all of `from`, `to` and `step` have to be given.
+    pub at: Option<DateTime<Utc>>,
+    /// First sample point (inclusive).
+    pub from: Option<DateTime<Utc>>,
+    /// End of the sample points (inclusive).
+    pub to: Option<DateTime<Utc>>,
+    /// Distance between two sample points in seconds.
+    pub step: Option<i64>,
+    /// Meta keys whose values the records are grouped by, e.g. `group_by[]=site_id`.
+    pub group_by: Option<Vec<ValidName>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/concurrency",
+    tag = "usage",
+    params(ConcurrencyOptions, Filters),
+    responses(
+        (status = 200, description = "The usage of the running records per sample point and \
+            group. `sort_by`, `limit` and `after` are not supported", body = Vec<ConcurrentUsage>),
+        (status = 400, description = "The query is invalid", body = ErrorResponse),
+        (status = 500, description = "An unexpected error occurred", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Computing concurrent usage", skip(query, pool))]
+pub async fn query_concurrency(
+    query: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, GetFilterError> {
+    let (options, filters) = split_query_string(
+        query.query_string(),
+        &["at", "from", "to", "step", "group_by"],
+    );
+
+    let options: ConcurrencyOptions = serde_qs::from_str(&options)
+        .map_err(|err| GetFilterError::InvalidQuery(err.to_string()))?;
+    let mut filters = Filters::parse(&filters).map_err(GetFilterError::InvalidQuery)?;
+    if filters.sort_by.is_some() || filters.limit.is_some() || filters.after.is_some() {
+        return Err(GetFilterError::InvalidQuery(
+            "sort_by, limit and after are not supported by concurrency queries".to_string(),
+        ));
+    }
+    filters.sites = Principal::of(&query).and_then(|principal| principal.sites);
+
+    let samples = sample_points(&options).map_err(GetFilterError::InvalidQuery)?;
+
+    let usage = concurrent_records(
+        &filters,
+        &samples,
+        options.group_by.as_deref().unwrap_or_default(),
+        &pool,
+    )
+    .await
+    .map_err(|err| GetFilterError::UnexpectedError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+/// Returns the sample points described by `options`: either the single point in time `at`, or
+/// the points from `from` to `to` (both inclusive) that are `step` seconds apart.
+pub fn sample_points(options: &ConcurrencyOptions) -> Result<Vec<DateTime<Utc>>, String> {
+    match (options.at, options.from, options.to, options.step) {
+        (Some(at), None, None, None) => Ok(vec![at]),
+        (None, Some(from), Some(to), Some(step)) => {
+            if from > to {
+                return Err("`from` must not be later than `to`".to_string());
+            }
+            if step <= 0 {
+                return Err("`step` has to be a positive number of seconds".to_string());
+            }
+            let step = TimeDelta::try_seconds(step)
+                .ok_or_else(|| "`step` is out of bounds".to_string())?;
+
+            let mut samples = vec![];
+            let mut time = Some(from);
+            while let Some(t) = time
+                && t <= to
+            {
+                if samples.len() == MAX_SAMPLES {
+                    return Err(format!(
+                        "The time range must not contain more than {MAX_SAMPLES} sample points"
+                    ));
+                }
+                samples.push(t);
+                time = t.checked_add_signed(step);
+            }
+            Ok(samples)
+        }
+        _ => Err("Either `at` or all of `from`, `to` and `step` have to be given".to_string()),
+    }
+}
+
+/// Sums up the usage of all records matching `filters` that were running at each of the
+/// `samples`, grouped by the values of the meta keys in `group_by`.
+///
+/// A record is running at time `t` if `start_time <= t < stop_time`. Records without a
+/// `stop_time` are considered to be still running.
+#[tracing::instrument(
+    name = "Computing concurrent usage in the database",
+    skip(filters, samples, pool)
+)]
+pub async fn concurrent_records(
+    filters: &Filters,
+    samples: &[DateTime<Utc>],
+    group_by: &[ValidName],
+    pool: &PgPool,
+) -> Result<Vec<ConcurrentUsage>, AggregateRecordsError> {
+    let (Some(first), Some(last)) = (samples.iter().min(), samples.iter().max()) else {
+        return Ok(vec![]);
+    };
+
+    let mut transaction = begin_snapshot(pool).await?;
+
+    let usage = sum_usage(
+        &mut transaction,
+        || {
+            let mut query = QueryBuilder::<Postgres>::new(
+                "WITH filtered AS (
+                     SELECT meta, components, start_time, stop_time
+                     FROM auditor_accounting
+                 ",
+            );
+            push_filters_including_open(&mut query, filters);
+            query.push(
+                "), samples AS (
+                     SELECT * FROM UNNEST(",
+            );
+            query.push_bind(samples.to_vec());
+            // Records are summed up at a point in time, hence they do not have a runtime
+            query.push(
+                "::timestamptz[]) AS s(sample)
+                 ), running AS (
+                     SELECT samples.sample, filtered.meta, filtered.components,
+                            NULL::bigint AS runtime
+                     FROM filtered
+                     JOIN samples
+                       ON filtered.start_time <= samples.sample
+                      AND (filtered.stop_time IS NULL OR samples.sample < filtered.stop_time)
+                     WHERE filtered.start_time <= ",
+            );
+            query.push_bind(*last);
+            query.push(" AND (filtered.stop_time IS NULL OR filtered.stop_time > ");
+            query.push_bind(*first);
+            query.push("))");
+            push_grouped(
+                &mut query,
+                "running",
+                &["sample", "components", "runtime"],
+                group_by,
+            );
+            query
+        },
+        "sample, ",
+        group_by,
+        |row| row.try_get::<DateTime<Utc>, _>("sample"),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(AggregateRecordsError)?;
+
+    Ok(samples
+        .iter()
+        .map(|time| ConcurrentUsage {
+            time: *time,
+            usage: usage
+                .range((*time, vec![])..)
+                .take_while(|((sample, _), _)| sample == time)
+                .map(|(_, usage)| ConcurrentAggregate::from(usage.clone()))
+                .collect(),
+        })
+        .collect())
+}
@@ -0,0 +1,172 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::domain::Record;
+use crate::routes::{SortField, SortOption};
+use base64::Engine;
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use chrono::{DateTime, Utc};
+use sqlx::{Postgres, QueryBuilder};
+
+/// Position of the last record of a page of `GET /records`.
+///
+/// The cursor holds the sort key and the `record_id` of the record. It is handed out to clients
+/// as URL-safe base64 encoded JSON and has to be treated as opaque. Passing it back as
+/// `after=<cursor>` returns the records following this record in the same sort order.
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+pub struct Cursor {
+    sort_by: SortField,
+    descending: bool,
+    key: Option<CursorKey>,
+    record_id: String,
+}
+
+#[derive(serde::Serialize, serde::Deserialize, Debug, Clone, PartialEq)]
+#[serde(untagged)]
+enum CursorKey {
+    Runtime(i64),
+    Time(DateTime<Utc>),
+}
+
+impl Cursor {
+    /// Returns the cursor pointing at `record` for records sorted by `sort_by`.
+    pub fn after(record: &Record, sort_by: &Option<SortOption>) -> Self {
+        let (sort_by, descending) = sort_order(sort_by);
+        let key = match sort_by {
+            SortField::StartTime => record.start_time.map(CursorKey::Time),
+            SortField::StopTime => record.stop_time.map(CursorKey::Time),
+            SortField::Runtime => record.runtime.map(CursorKey::Runtime),
+            SortField::RecordId => None,
+        };
+        Cursor {
+            sort_by,
+            descending,
+            key,
+            record_id: record.record_id.clone(),
+        }
+    }
+
+    /// Returns whether the cursor was created for records sorted by `sort_by`.
+    pub fn matches(&self, sort_by: &Option<SortOption>) -> bool {
+        sort_order(sort_by) == (self.sort_by.clone(), self.descending)
+    }
+
+    pub fn encode(&self) -> String {
+        URL_SAFE_NO_PAD.encode(serde_json::to_vec(self).expect("Cursor is always serializable"))
+    }
+
+    pub fn decode(cursor: &str) -> Result<Self, String> {
+        URL_SAFE_NO_PAD
+            .decode(cursor)
+            .ok()
+            .and_then(|json| serde_json::from_slice(&json).ok())
+            .ok_or_else(|| "Invalid cursor".to_string())
+    }
+
+    /// Appends a condition which is true for all records following the cursor.
+    ///
+    /// Records are sorted by the sort key and then by `record_id`. Postgres sorts `NULL` values
+    /// last in ascending and first in descending order, which has to be taken into account for
+    /// `stop_time` and `runtime`.
+    pub(crate) fn push_condition(&self, query: &mut QueryBuilder<'_, Postgres>) {
+        let column = self.sort_by.to_string();
+        let comparison = if self.descending { "<" } else { ">" };
+
+        if self.sort_by == SortField::RecordId {
+            query.push(format!(" record_id {comparison} "));
+            query.push_bind(self.record_id.clone());
+            return;
+        }
+
+        query.push(" (");
+        match (&self.key, self.descending) {
+            (Some(key), descending) => {
+                query.push(format!("{column} {comparison} "));
+                push_key(query, key);
+                query.push(format!(" OR ({column} = "));
+                push_key(query, key);
+                query.push(format!(" AND record_id {comparison} "));
+                query.push_bind(self.record_id.clone());
+                query.push(")");
+                if !descending {
+                    query.push(format!(" OR {column} IS NULL"));
+                }
+            }
+            (None, false) => {
+                query.push(format!("{column} IS NULL AND record_id > "));
+                query.push_bind(self.record_id.clone());
+            }
+            (None, true) => {
+                query.push(format!("{column} IS NOT NULL OR record_id < "));
+                query.push_bind(self.record_id.clone());
+            }
+        }
+        query.push(")");
+    }
+}
+
+fn push_key(query: &mut QueryBuilder<'_, Postgres>, key: &CursorKey) {
+    match key {
+        CursorKey::Runtime(runtime) => query.push_bind(*runtime),
+        CursorKey::Time(time) => query.push_bind(*time),
+    };
+}
+
+/// Returns the sort key and whether records are sorted in descending order. Records are sorted
+/// by `stop_time` in ascending order if nothing else is requested.
+pub(crate) fn sort_order(sort_by: &Option<SortOption>) -> (SortField, bool) {
+    match sort_by {
+        Some(SortOption::ASC(field)) => (field.clone(), false),
+        Some(SortOption::DESC(field)) => (field.clone(), true),
+        None => (SortField::StopTime, false),
+    }
+}
+
+/// Deserializes an encoded cursor of a query string.
+pub(crate) fn deserialize_cursor<'de, D>(deserializer: D) -> Result<Option<Cursor>, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    let cursor: Option<String> = serde::Deserialize::deserialize(deserializer)?;
+    cursor
+        .map(|cursor| Cursor::decode(&cursor))
+        .transpose()
+        .map_err(serde::de::Error::custom)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cursor_roundtrips() {
+        let record = Record {
+            record_id: "r1".to_string(),
+            meta: None,
+            components: None,
+            start_time: Some(Utc::now()),
+            stop_time: None,
+            runtime: Some(10),
+        };
+        for sort_by in [
+            None,
+            Some(SortOption::ASC(SortField::StartTime)),
+            Some(SortOption::DESC(SortField::Runtime)),
+            Some(SortOption::DESC(SortField::RecordId)),
+        ] {
+            let cursor = Cursor::after(&record, &sort_by);
+            assert_eq!(Cursor::decode(&cursor.encode()), Ok(cursor.clone()));
+            assert!(cursor.matches(&sort_by));
+        }
+    }
+
+    #[test]
+    fn invalid_cursor_is_rejected() {
+        assert!(Cursor::decode("not a cursor").is_err());
+        assert!(Cursor::decode(&URL_SAFE_NO_PAD.encode("{}")).is_err());
+    }
+}
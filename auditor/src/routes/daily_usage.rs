@@ -0,0 +1,101 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::Principal;
+use crate::domain::{BucketSize, DailyUsage, DailyUsageQuery};
+use crate::routes::{AggregateRecordsError, ErrorResponse, GetFilterError};
+use actix_web::{HttpRequest, HttpResponse, web};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+
+#[utoipa::path(
+    get,
+    path = "/daily_usage",
+    tag = "usage",
+    params(DailyUsageQuery),
+    responses(
+        (status = 200, description = "The summed usage per day or month", body = Vec<DailyUsage>),
+        (status = 400, description = "The query is invalid", body = ErrorResponse),
+        (status = 500, description = "An unexpected error occurred", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Querying daily usage", skip(query, pool))]
+pub async fn query_daily_usage(
+    query: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, GetFilterError> {
+    let options: DailyUsageQuery = serde_qs::from_str(query.query_string())
+        .map_err(|err| GetFilterError::InvalidQuery(err.to_string()))?;
+    if options.from >= options.to {
+        return Err(GetFilterError::InvalidQuery(
+            "`from` has to be earlier than `to`".to_string(),
+        ));
+    }
+    if options.bucket == Some(BucketSize::Hour) {
+        return Err(GetFilterError::InvalidQuery(
+            "The daily usage can only be summed up per day or month".to_string(),
+        ));
+    }
+    let sites = Principal::of(&query).and_then(|principal| principal.sites);
+
+    let usage = get_daily_usage(&options, sites.as_deref(), &pool)
+        .await
+        .map_err(|err| GetFilterError::UnexpectedError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(usage))
+}
+
+/// Returns the daily usage matching `query`. If `sites` is given, only the usage of these sites
+/// is returned.
+#[tracing::instrument(name = "Getting daily usage from the database", skip(pool))]
+pub async fn get_daily_usage(
+    query: &DailyUsageQuery,
+    sites: Option<&[String]>,
+    pool: &PgPool,
+) -> Result<Vec<DailyUsage>, AggregateRecordsError> {
+    let unit = match query.bucket {
+        Some(BucketSize::Month) => "month",
+        _ => "day",
+    };
+    let mut builder: QueryBuilder<Postgres> = QueryBuilder::new(format!(
+        "SELECT date_trunc('{unit}', day::timestamp)::date AS date,
+                site_id, group_id, user_id, component,
+                sum(amount_runtime) AS amount_runtime,
+                sum(record_count)::bigint AS record_count
+         FROM daily_usage
+         WHERE day >= "
+    ));
+    builder.push_bind(query.from);
+    builder.push(" AND day < ");
+    builder.push_bind(query.to);
+    for (column, value) in [
+        ("site_id", &query.site_id),
+        ("group_id", &query.group_id),
+        ("user_id", &query.user_id),
+        ("component", &query.component),
+    ] {
+        if let Some(value) = value {
+            builder.push(format!(" AND {column} = "));
+            builder.push_bind(value.clone());
+        }
+    }
+    if let Some(sites) = sites {
+        builder.push(" AND site_id = ANY(");
+        builder.push_bind(sites.to_vec());
+        builder.push(")");
+    }
+    builder.push(
+        " GROUP BY 1, site_id, group_id, user_id, component
+          ORDER BY 1, site_id, group_id, user_id, component",
+    );
+
+    builder
+        .build_query_as()
+        .persistent(false)
+        .fetch_all(pool)
+        .await
+        .map_err(AggregateRecordsError)
+}
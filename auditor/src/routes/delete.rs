@@ -0,0 +1,195 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::Principal;
+use crate::domain::DeletedRecords;
+use crate::routes::{ErrorResponse, Filters, push_filters_including_open, set_history_client};
+use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
+use serde_json::json;
+use sqlx::{PgPool, Postgres, QueryBuilder};
+
+#[derive(thiserror::Error)]
+pub enum DeleteError {
+    #[error("Deleting unknown record {0} not possible.")]
+    UnknownRecord(String),
+    #[error("Invalid query parameters: {0}")]
+    InvalidQuery(String),
+    #[error("Deleting record {0} is not permitted.")]
+    Forbidden(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+debug_for_error!(DeleteError);
+
+impl ResponseError for DeleteError {
+    fn status_code(&self) -> actix_web::http::StatusCode {
+        match self {
+            DeleteError::UnknownRecord(_) => actix_web::http::StatusCode::NOT_FOUND,
+            DeleteError::InvalidQuery(_) => actix_web::http::StatusCode::BAD_REQUEST,
+            DeleteError::Forbidden(_) => actix_web::http::StatusCode::FORBIDDEN,
+            DeleteError::UnexpectedError(_) => actix_web::http::StatusCode::INTERNAL_SERVER_ERROR,
+        }
+    }
+
+    fn error_response(&self) -> HttpResponse {
+        match self {
+            DeleteError::InvalidQuery(msg) => {
+                HttpResponse::BadRequest().json(json!({ "error": msg }))
+            }
+            _ => HttpResponse::build(self.status_code()).body(self.to_string()),
+        }
+    }
+}
+
+#[utoipa::path(
+    delete,
+    path = "/record/{record_id}",
+    tag = "records",
+    params(("record_id" = String, Path, description = "`record_id` of the record")),
+    responses(
+        (status = 200, description = "The record was deleted"),
+        (status = 403, description = "The client may not delete the record", body = String),
+        (status = 404, description = "No record with this `record_id` exists", body = String),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
+#[tracing::instrument(name = "Deleting a record", skip(request, record_id, pool))]
+pub async fn delete(
+    request: HttpRequest,
+    record_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, DeleteError> {
+    if let Some(principal) = Principal::of(&request)
+        && !principal
+            .permits_stored_records(&[record_id.as_str()], &pool)
+            .await
+            .map_err(|e| DeleteError::UnexpectedError(e.into()))?
+    {
+        return Err(DeleteError::Forbidden(record_id.to_string()));
+    }
+
+    let client = Principal::of(&request).map(|principal| principal.name);
+    let deleted = delete_record(&record_id, client.as_deref(), &pool)
+        .await
+        .map_err(|e| DeleteError::UnexpectedError(e.into()))?;
+
+    if !deleted {
+        return Err(DeleteError::UnknownRecord(record_id.to_string()));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
+
+#[utoipa::path(
+    delete,
+    path = "/records",
+    tag = "records",
+    params(Filters),
+    responses(
+        (status = 200, description = "The matching records were deleted. `sort_by`, `limit` and \
+            `after` are not supported and at least one filter is required", body = DeletedRecords),
+        (status = 400, description = "The query is invalid", body = ErrorResponse),
+        (status = 403, description = "The client may not delete records", body = String),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
+#[tracing::instrument(name = "Deleting records", skip(query, pool))]
+pub async fn bulk_delete(
+    query: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, DeleteError> {
+    let mut filters = Filters::parse(query.query_string()).map_err(DeleteError::InvalidQuery)?;
+
+    if filters.sort_by.is_some() || filters.limit.is_some() || filters.after.is_some() {
+        return Err(DeleteError::InvalidQuery(
+            "sort_by, limit and after are not supported when deleting records".to_string(),
+        ));
+    }
+
+    let principal = Principal::of(&query);
+    let client = principal.as_ref().map(|principal| principal.name.as_str());
+    filters.sites = principal
+        .as_ref()
+        .and_then(|principal| principal.sites.clone());
+    let deleted = delete_records(&filters, client, &pool)
+        .await
+        .map_err(|e| match e {
+            DeleteRecordsError::NoFilter => DeleteError::InvalidQuery(e.to_string()),
+            DeleteRecordsError::Database(_) => DeleteError::UnexpectedError(e.into()),
+        })?;
+
+    Ok(HttpResponse::Ok().json(DeletedRecords { deleted }))
+}
+
+/// Deletes the record with `record_id`. Returns whether the record existed.
+///
+/// The deletion is attributed to `client` in the history of the record.
+#[tracing::instrument(name = "Deleting a record from the database", skip(pool))]
+pub async fn delete_record(
+    record_id: &str,
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<bool, DeleteRecordError> {
+    let mut transaction = pool.begin().await.map_err(DeleteRecordError)?;
+    set_history_client(&mut transaction, client)
+        .await
+        .map_err(DeleteRecordError)?;
+    let result = sqlx::query("DELETE FROM auditor_accounting WHERE record_id = $1")
+        .bind(record_id)
+        .execute(&mut *transaction)
+        .await
+        .map_err(DeleteRecordError)?;
+    transaction.commit().await.map_err(DeleteRecordError)?;
+    Ok(result.rows_affected() > 0)
+}
+
+/// Deletes all records matching `filters`, including records without a `stop_time`. Returns
+/// the number of deleted records.
+///
+/// Fails with [`DeleteRecordsError::NoFilter`] if `filters` do not restrict the records, e.g.
+/// if only a site restriction is set, so that the table is never emptied by accident.
+///
+/// The deletions are attributed to `client` in the history of the records.
+#[tracing::instrument(name = "Deleting records from the database", skip(filters, pool))]
+pub async fn delete_records(
+    filters: &Filters,
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<u64, DeleteRecordsError> {
+    let mut query: QueryBuilder<Postgres> = QueryBuilder::new("DELETE FROM auditor_accounting");
+    if !push_filters_including_open(&mut query, filters) {
+        return Err(DeleteRecordsError::NoFilter);
+    }
+    let mut transaction = pool.begin().await?;
+    set_history_client(&mut transaction, client).await?;
+    let result = query
+        .build()
+        .persistent(false)
+        .execute(&mut *transaction)
+        .await?;
+    transaction.commit().await?;
+    Ok(result.rows_affected())
+}
+
+pub struct DeleteRecordError(sqlx::Error);
+
+debug_for_error!(DeleteRecordError);
+error_for_error!(DeleteRecordError);
+display_for_error!(
+    DeleteRecordError,
+    "A database error was encountered while trying to delete records."
+);
+
+#[derive(thiserror::Error)]
+pub enum DeleteRecordsError {
+    #[error("At least one filter is required when deleting records")]
+    NoFilter,
+    #[error("A database error was encountered while trying to delete records.")]
+    Database(#[from] sqlx::Error),
+}
+
+debug_for_error!(DeleteRecordsError);
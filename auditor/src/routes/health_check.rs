@@ -5,12 +5,119 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
+use crate::metrics::DatabaseMetricsWatcher;
+use crate::migrations::{latest_applied_version, latest_version};
 use actix_web::{HttpResponse, web};
+use serde::Serialize;
 use sqlx::PgPool;
 
+#[utoipa::path(
+    get,
+    path = "/health_check",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "The server is running and can reach the database"),
+        (status = 500, description = "The database cannot be reached"),
+    )
+)]
 pub async fn health_check(pool: web::Data<PgPool>) -> HttpResponse {
     if pool.acquire().await.is_err() {
         return HttpResponse::InternalServerError().finish();
     }
     HttpResponse::Ok().finish()
 }
+
+/// Outcome of a single check of [`readyz`].
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct Check {
+    pub ok: bool,
+    /// Reason why the check failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+impl Check {
+    fn passed() -> Self {
+        Check {
+            ok: true,
+            error: None,
+        }
+    }
+
+    fn failed(error: impl ToString) -> Self {
+        Check {
+            ok: false,
+            error: Some(error.to_string()),
+        }
+    }
+}
+
+/// Response of [`readyz`].
+#[derive(Serialize, Debug, utoipa::ToSchema)]
+pub struct Readiness {
+    /// Whether all checks passed.
+    pub ready: bool,
+    /// The database can be reached.
+    pub database: Check,
+    /// The latest migration known to the server has been applied to the database.
+    pub migrations: Check,
+    /// The database metrics are updated periodically.
+    pub database_metrics: Check,
+}
+
+#[utoipa::path(
+    get,
+    path = "/livez",
+    tag = "health",
+    security(()),
+    responses((status = 200, description = "The server is running")),
+)]
+pub async fn livez() -> HttpResponse {
+    HttpResponse::Ok().finish()
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    security(()),
+    responses(
+        (status = 200, description = "The server is ready to handle requests", body = Readiness),
+        (status = 503, description = "At least one check failed", body = Readiness),
+    )
+)]
+pub async fn readyz(
+    pool: web::Data<PgPool>,
+    db_watcher: web::Data<DatabaseMetricsWatcher>,
+) -> HttpResponse {
+    let database = match pool.acquire().await {
+        Ok(_) => Check::passed(),
+        Err(e) => Check::failed(e),
+    };
+    let migrations = match (latest_applied_version(&pool).await, latest_version()) {
+        (Err(e), _) => Check::failed(e),
+        (Ok(applied), Some(latest)) if applied < Some(latest) => Check::failed(format!(
+            "Migration {latest} has not been applied, the latest applied migration is {}",
+            applied.map_or("none".to_string(), |applied| applied.to_string())
+        )),
+        (Ok(_), _) => Check::passed(),
+    };
+    let database_metrics = if db_watcher.is_alive() {
+        Check::passed()
+    } else {
+        Check::failed("The database metrics have not been updated recently")
+    };
+
+    let readiness = Readiness {
+        ready: database.ok && migrations.ok && database_metrics.ok,
+        database,
+        migrations,
+        database_metrics,
+    };
+    if readiness.ready {
+        HttpResponse::Ok().json(readiness)
+    } else {
+        HttpResponse::ServiceUnavailable().json(readiness)
+    }
+}
@@ -0,0 +1,196 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::Principal;
+use crate::domain::{BucketSize, UsageBucket, ValidName};
+use crate::routes::{
+    AggregateRecordsError, ErrorResponse, Filters, GetFilterError, begin_snapshot, push_filters,
+    push_grouped, split_query_string, sum_usage,
+};
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, QueryBuilder, Row};
+
+/// Upper limit for the number of buckets of a single histogram.
+const MAX_BUCKETS: usize = 50_000;
+
+/// Start (inclusive) and end (exclusive) of a bucket.
+pub type Bucket = (DateTime<Utc>, DateTime<Utc>);
+
+#[derive(serde::Deserialize, Debug, Clone, utoipa::IntoParams)]
+#[serde(deny_unknown_fields)]
+#[into_params(parameter_in = Query)]
+pub struct HistogramOptions {
+    /// Size of the buckets.
+    pub bucket: BucketSize,
+    /// Start of the time range (inclusive).
+    pub from: DateTime<Utc>,
+    /// End of the time range (exclusive).
+    pub to: DateTime<Utc>,
+    /// Meta keys whose values the records are grouped by, e.g. `group_by[]=site_id`.
+    pub group_by: Option<Vec<ValidName>>,
+}
+
+#[utoipa::path(
+    get,
+    path = "/histogram",
+    tag = "usage",
+    params(HistogramOptions, Filters),
+    responses(
+        (status = 200, description = "The usage per bucket and group. `sort_by`, `limit` and \
+            `after` are not supported", body = Vec<UsageBucket>),
+        (status = 400, description = "The query is invalid", body = ErrorResponse),
+        (status = 500, description = "An unexpected error occurred", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Computing usage histogram", skip(query, pool))]
+pub async fn query_histogram(
+    query: HttpRequest,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, GetFilterError> {
+    let (options, filters) =
+        split_query_string(query.query_string(), &["bucket", "from", "to", "group_by"]);
+
+    let options: HistogramOptions = serde_qs::from_str(&options)
+        .map_err(|err| GetFilterError::InvalidQuery(err.to_string()))?;
+    let mut filters = Filters::parse(&filters).map_err(GetFilterError::InvalidQuery)?;
+    if filters.sort_by.is_some() || filters.limit.is_some() || filters.after.is_some() {
+        return Err(GetFilterError::InvalidQuery(
+            "sort_by, limit and after are not supported by histograms".to_string(),
+        ));
+    }
+    filters.sites = Principal::of(&query).and_then(|principal| principal.sites);
+
+    let buckets = bucket_boundaries(options.bucket, options.from, options.to)
+        .map_err(GetFilterError::InvalidQuery)?;
+
+    let histogram = histogram_records(
+        &filters,
+        &buckets,
+        options.group_by.as_deref().unwrap_or_default(),
+        &pool,
+    )
+    .await
+    .map_err(|err| GetFilterError::UnexpectedError(err.to_string()))?;
+
+    Ok(HttpResponse::Ok().json(histogram))
+}
+
+/// Returns the start and the end of all buckets of size `bucket` that overlap with the time
+/// range from `from` (inclusive) to `to` (exclusive).
+pub fn bucket_boundaries(
+    bucket: BucketSize,
+    from: DateTime<Utc>,
+    to: DateTime<Utc>,
+) -> Result<Vec<Bucket>, String> {
+    if from >= to {
+        return Err("`from` has to be earlier than `to`".to_string());
+    }
+
+    let mut buckets = vec![];
+    let mut start = bucket.truncate(from);
+    while start < to {
+        if buckets.len() == MAX_BUCKETS {
+            return Err(format!(
+                "The time range must not span more than {MAX_BUCKETS} buckets"
+            ));
+        }
+        let stop = bucket
+            .next(start)
+            .ok_or_else(|| "The time range is out of bounds".to_string())?;
+        buckets.push((start, stop));
+        start = stop;
+    }
+    Ok(buckets)
+}
+
+/// Sums up the resource usage of all records matching `filters` within each of the `buckets`,
+/// grouped by the values of the meta keys in `group_by`.
+///
+/// Records that overlap with several buckets are accounted for in each of these buckets with
+/// the part of their runtime that lies within the respective bucket. Records without a
+/// `stop_time` are ignored.
+#[tracing::instrument(
+    name = "Computing usage histogram in the database",
+    skip(filters, buckets, pool)
+)]
+pub async fn histogram_records(
+    filters: &Filters,
+    buckets: &[Bucket],
+    group_by: &[ValidName],
+    pool: &PgPool,
+) -> Result<Vec<UsageBucket>, AggregateRecordsError> {
+    let (starts, stops): (Vec<_>, Vec<_>) = buckets.iter().cloned().unzip();
+    let (Some(from), Some(to)) = (starts.first().cloned(), stops.last().cloned()) else {
+        return Ok(vec![]);
+    };
+
+    let mut transaction = begin_snapshot(pool).await?;
+
+    let mut usage = sum_usage(
+        &mut transaction,
+        || {
+            let mut query = QueryBuilder::new(
+                "WITH filtered AS (
+                     SELECT meta, components, start_time, stop_time
+                     FROM auditor_accounting
+                 ",
+            );
+            push_filters(&mut query, filters);
+            query.push(
+                "), buckets AS (
+                     SELECT * FROM UNNEST(",
+            );
+            query.push_bind(starts.clone());
+            query.push("::timestamptz[], ");
+            query.push_bind(stops.clone());
+            query.push(
+                "::timestamptz[]) AS b(bucket, bucket_end)
+                 ), overlapping AS (
+                     SELECT buckets.bucket, filtered.meta, filtered.components,
+                            EXTRACT(EPOCH FROM
+                                LEAST(filtered.stop_time, buckets.bucket_end)
+                                - GREATEST(filtered.start_time, buckets.bucket)
+                            ) AS runtime
+                     FROM filtered
+                     JOIN buckets
+                       ON filtered.start_time < buckets.bucket_end
+                      AND filtered.stop_time > buckets.bucket
+                     WHERE filtered.start_time < ",
+            );
+            query.push_bind(to);
+            query.push(" AND filtered.stop_time > ");
+            query.push_bind(from);
+            query.push(")");
+            push_grouped(
+                &mut query,
+                "overlapping",
+                &["bucket", "components", "runtime"],
+                group_by,
+            );
+            query
+        },
+        "bucket, ",
+        group_by,
+        |row| row.try_get::<DateTime<Utc>, _>("bucket"),
+    )
+    .await?;
+
+    transaction.commit().await.map_err(AggregateRecordsError)?;
+
+    Ok(buckets
+        .iter()
+        .map(|(start, stop)| {
+            let rest = usage.split_off(&(*stop, vec![]));
+            UsageBucket {
+                start: *start,
+                stop: *stop,
+                usage: std::mem::replace(&mut usage, rest).into_values().collect(),
+            }
+        })
+        .collect())
+}
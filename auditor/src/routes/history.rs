@@ -0,0 +1,151 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::{Principal, SITE_META_KEY};
+use crate::domain::{ChangeOperation, RecordChange};
+use actix_web::{HttpRequest, HttpResponse, web};
+use serde_json::{Map, Value};
+use sqlx::{PgConnection, PgPool, Row};
+
+#[derive(thiserror::Error)]
+pub enum HistoryError {
+    #[error("No history of record {0} found.")]
+    UnknownRecord(String),
+    #[error("Access to the history of record {0} is not permitted.")]
+    Forbidden(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+debug_for_error!(HistoryError);
+responseerror_for_error!(
+    HistoryError,
+    UnknownRecord => NOT_FOUND;
+    Forbidden => FORBIDDEN;
+    UnexpectedError => INTERNAL_SERVER_ERROR;
+);
+
+/// Stores the name of the client in the current transaction, so that the changes of the
+/// transaction are attributed to it in the history of the records.
+pub(crate) async fn set_history_client(
+    connection: &mut PgConnection,
+    client: Option<&str>,
+) -> Result<(), sqlx::Error> {
+    if let Some(client) = client {
+        sqlx::query("SELECT set_config('auditor.client', $1, true)")
+            .bind(client)
+            .execute(connection)
+            .await?;
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/record/{record_id}/history",
+    tag = "records",
+    params(("record_id" = String, Path, description = "`record_id` of the record")),
+    responses(
+        (status = 200, description = "The changes of the record, oldest first",
+            body = Vec<RecordChange>),
+        (status = 403, description = "The client may not read the record", body = String),
+        (status = 404, description = "There is no history of this `record_id`", body = String),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
+#[tracing::instrument(name = "Getting the history of a record", skip(request, pool))]
+pub async fn query_record_history(
+    request: HttpRequest,
+    record_id: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, HistoryError> {
+    let history = get_record_history(&record_id, &pool)
+        .await
+        .map_err(|e| HistoryError::UnexpectedError(e.into()))?;
+    if history.is_empty() {
+        return Err(HistoryError::UnknownRecord(record_id.into_inner()));
+    }
+
+    // The record may have been deleted or moved to another site, hence the sites of all
+    // versions of the record are checked. The values of records deleted by a retention policy
+    // are purged, their history is only accessible to unrestricted clients.
+    if let Some(principal) = Principal::of(&request) {
+        let metas: Vec<&Value> = history
+            .iter()
+            .flat_map(|change| change.old.iter().chain(std::iter::once(&change.new)))
+            .filter_map(|values| values.get("meta"))
+            .collect();
+        let permitted = if metas.is_empty() {
+            principal.permits_sites::<&str>(&[])
+        } else {
+            metas.into_iter().all(|meta| {
+                let sites: Vec<&str> = meta
+                    .get(SITE_META_KEY)
+                    .and_then(Value::as_array)
+                    .map(|sites| sites.iter().filter_map(Value::as_str).collect())
+                    .unwrap_or_default();
+                principal.permits_sites(&sites)
+            })
+        };
+        if !permitted {
+            return Err(HistoryError::Forbidden(record_id.into_inner()));
+        }
+    }
+
+    Ok(HttpResponse::Ok().json(history))
+}
+
+/// Returns the changes of the record with `record_id`, oldest first.
+#[tracing::instrument(
+    name = "Retrieving the history of a record from the database",
+    skip(pool)
+)]
+pub async fn get_record_history(
+    record_id: &str,
+    pool: &PgPool,
+) -> Result<Vec<RecordChange>, sqlx::Error> {
+    let rows = sqlx::query(
+        "SELECT operation, old_values, new_values, changed_at, changed_by
+         FROM auditor_accounting_history
+         WHERE record_id = $1
+         ORDER BY id",
+    )
+    .bind(record_id)
+    .fetch_all(pool)
+    .await?;
+
+    rows.iter()
+        .map(|row| {
+            let operation = match row.try_get::<&str, _>("operation")? {
+                "insert" => ChangeOperation::Insert,
+                "update" => ChangeOperation::Update,
+                "delete" => ChangeOperation::Delete,
+                other => {
+                    return Err(sqlx::Error::Decode(
+                        format!("Unknown operation {other}").into(),
+                    ));
+                }
+            };
+            Ok(RecordChange {
+                operation,
+                old: row
+                    .try_get::<Option<Value>, _>("old_values")?
+                    .map(into_object),
+                new: into_object(row.try_get("new_values")?),
+                changed_at: row.try_get("changed_at")?,
+                changed_by: row.try_get("changed_by")?,
+            })
+        })
+        .collect()
+}
+
+fn into_object(values: Value) -> Map<String, Value> {
+    match values {
+        Value::Object(values) => values,
+        _ => Map::new(),
+    }
+}
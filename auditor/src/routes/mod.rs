@@ -7,14 +7,38 @@
 
 mod add;
 mod advanced_record_filters;
+mod aggregate;
+mod concurrency;
+mod cursor;
+mod daily_usage;
+mod delete;
+mod export;
 mod get;
 mod health_check;
+mod histogram;
+mod history;
+mod openapi;
 mod record_handlers;
+mod subscribe;
+mod tokens;
 mod update;
+mod versions;
 
 pub use add::*;
 pub use advanced_record_filters::*;
+pub use aggregate::*;
+pub use concurrency::*;
+pub use cursor::*;
+pub use daily_usage::*;
+pub use delete::*;
+pub use export::*;
 pub use get::*;
 pub use health_check::*;
+pub use histogram::*;
+pub use history::*;
+pub use openapi::*;
 pub use record_handlers::*;
+pub use subscribe::*;
+pub use tokens::*;
 pub use update::*;
+pub use versions::*;
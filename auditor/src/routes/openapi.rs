@@ -0,0 +1,87 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use super::{ComponentOperator, MetaOperator, Operator, SortOption};
+use crate::domain::{BucketSize, OnConflict, ValidAmount};
+use actix_web::HttpResponse;
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+/// OpenAPI 3 description of the REST API, which is generated from the route handlers and the
+/// types of the requests and responses.
+#[derive(OpenApi)]
+#[openapi(
+    info(title = "AUDITOR"),
+    modifiers(&SecurityAddon),
+    security(("api_token" = [])),
+    paths(
+        super::health_check,
+        super::livez,
+        super::readyz,
+        super::add,
+        super::update,
+        super::query_one_record,
+        super::delete,
+        super::bulk_add,
+        super::bulk_update,
+        super::query_records,
+        super::bulk_delete,
+        super::subscribe_records,
+        super::query_record_history,
+        super::query_aggregate,
+        super::query_histogram,
+        super::query_concurrency,
+        super::query_daily_usage,
+        super::create_token,
+        super::list_tokens,
+        super::delete_token,
+    ),
+    // Schemas of query parameters are not collected automatically
+    components(schemas(
+        BucketSize,
+        ComponentOperator,
+        MetaOperator,
+        OnConflict,
+        Operator<String>,
+        Operator<ValidAmount>,
+        SortOption,
+    )),
+    tags(
+        (name = "health", description = "Status of the server"),
+        (name = "records", description = "Adding, updating, querying and deleting records"),
+        (name = "usage", description = "Resource usage summed up over records"),
+        (name = "tokens", description = "Managing API tokens"),
+    )
+)]
+pub struct ApiDoc;
+
+/// Adds the API tokens, which are sent as bearer tokens, as security scheme. Requests without
+/// token are only accepted if authentication is disabled or a client certificate is used.
+struct SecurityAddon;
+
+impl Modify for SecurityAddon {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "api_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+    }
+}
+
+/// Body of responses to failed requests.
+#[derive(serde::Serialize, utoipa::ToSchema)]
+pub struct ErrorResponse {
+    /// Description of the error.
+    pub error: String,
+}
+
+pub async fn openapi_json() -> HttpResponse {
+    HttpResponse::Ok().json(ApiDoc::openapi())
+}
@@ -1,4 +1,10 @@
-use crate::routes::{Filters, advanced_record_filtering, get_one_record};
+use crate::auth::{Principal, SITE_META_KEY};
+use crate::constants::HEADER_NEXT_CURSOR;
+use crate::domain::{ApiVersion, Record};
+use crate::routes::{
+    ErrorResponse, Filters, RecordFormat, api_version, encode_records, export_records,
+    get_one_record, get_records_page,
+};
 use actix_web::{HttpRequest, HttpResponse, ResponseError, web};
 use serde_json::json;
 use sqlx::PgPool;
@@ -9,41 +15,118 @@ pub struct RecordQuery {
     pub record_id: String,
 }
 
+#[utoipa::path(
+    get,
+    path = "/records",
+    tag = "records",
+    params(Filters),
+    responses(
+        (status = 200, description = "The matching records. The format is negotiated with the \
+            `Accept` header. If `limit` is given, the cursor of the next page is returned",
+            headers(("X-Next-Cursor" = String, description = "Value of `after` for the next page")),
+            content(
+                (Vec<Record> = "application/json"),
+                (Record = "application/x-ndjson"),
+                (String = "text/csv"),
+                (Vec<u8> = "application/vnd.apache.parquet"),
+            )
+        ),
+        (status = 400, description = "The query is invalid", body = ErrorResponse),
+        (status = 406, description = "None of the accepted formats is supported",
+            body = ErrorResponse),
+        (status = 500, description = "An unexpected error occurred", body = ErrorResponse),
+    )
+)]
 #[tracing::instrument(name = "Getting records", skip(query, pool))]
 pub async fn query_records(
     query: HttpRequest,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, GetFilterError> {
     let query_string = query.query_string();
+    let format = RecordFormat::from_request(&query).map_err(GetFilterError::NotAcceptable)?;
 
-    let filters: Filters = match serde_qs::from_str(query_string) {
-        Ok(filters) => filters,
-        Err(err) => return Err(GetFilterError::InvalidQuery(err.to_string())),
-    };
+    let mut filters = Filters::parse(query_string).map_err(GetFilterError::InvalidQuery)?;
+    filters.sites = Principal::of(&query).and_then(|principal| principal.sites);
 
-    if query_string.is_empty() {
-        // This case explicitly checks if the query is empty. Then it returns all records.
+    if let Some(cursor) = &filters.after
+        && !cursor.matches(&filters.sort_by)
+    {
+        return Err(GetFilterError::InvalidQuery(
+            "The cursor was created for a different sort order".to_string(),
+        ));
+    }
 
-        let stream = advanced_record_filtering(filters, pool.as_ref().clone()).await;
-        return Ok(HttpResponse::Ok()
-            .content_type("application/json")
-            .streaming(stream));
+    if let Some(limit) = filters.limit {
+        // A page of records is returned together with the cursor of the next page, which is
+        // only known once the page has been read from the database.
+        let (records, next) = get_records_page(&filters, limit, &pool)
+            .await
+            .map_err(|err| GetFilterError::UnexpectedError(err.to_string()))?;
+        let mut response = HttpResponse::Ok();
+        if let Some(next) = next {
+            response.insert_header((HEADER_NEXT_CURSOR, next.encode()));
+        }
+        if format == RecordFormat::Json {
+            return Ok(response.json(records));
+        }
+        let body = encode_records(records, &filters, format, api_version(&query), &pool)
+            .await
+            .map_err(|err| GetFilterError::UnexpectedError(err.to_string()))?;
+        return Ok(response.content_type(format.content_type()).body(body));
     }
 
-    let stream = advanced_record_filtering(filters, pool.as_ref().clone()).await;
+    // Errors while streaming abort the response, so that a truncated response cannot be
+    // mistaken for a complete one.
+    let stream = export_records(filters, format, api_version(&query), pool.as_ref().clone());
     Ok(HttpResponse::Ok()
-        .content_type("application/json")
+        .content_type(format.content_type())
         .streaming(stream))
 }
 
-#[tracing::instrument(name = "Getting one record", skip(record_query, pool))]
+#[utoipa::path(
+    get,
+    path = "/record/{record_id}",
+    tag = "records",
+    params(("record_id" = String, Path, description = "`record_id` of the record")),
+    responses(
+        (status = 200, description = "The record, or `null` if it does not exist (only in v1)",
+            body = Option<Record>),
+        (status = 403, description = "The client may not read the record", body = ErrorResponse),
+        (status = 404, description = "The record does not exist (only in v2)",
+            body = ErrorResponse),
+        (status = 500, description = "An unexpected error occurred", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Getting one record", skip(request, record_query, pool))]
 pub async fn query_one_record(
+    request: HttpRequest,
     record_query: web::Path<String>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, GetFilterError> {
     let record = get_one_record(record_query.to_string(), &pool)
         .await
         .map_err(|err| GetFilterError::UnexpectedError(err.to_string()))?;
+    if let (Some(principal), Some(record)) = (Principal::of(&request), &record)
+        && !principal.permits_sites(
+            record
+                .meta
+                .as_ref()
+                .and_then(|meta| meta.get(SITE_META_KEY))
+                .map(Vec::as_slice)
+                .unwrap_or_default(),
+        )
+    {
+        return Err(GetFilterError::Forbidden(format!(
+            "Access to record {} is not permitted",
+            record_query.as_str()
+        )));
+    }
+    if record.is_none() && api_version(&request) >= ApiVersion::V2 {
+        return Err(GetFilterError::NotFound(format!(
+            "Record {} does not exist",
+            record_query.as_str()
+        )));
+    }
     Ok(HttpResponse::Ok().json(record))
 }
 
@@ -52,6 +135,15 @@ pub enum GetFilterError {
     #[error("Invalid query parameters")]
     InvalidQuery(String),
 
+    #[error("Not acceptable")]
+    NotAcceptable(String),
+
+    #[error("Forbidden")]
+    Forbidden(String),
+
+    #[error("Not found")]
+    NotFound(String),
+
     #[error("Unexpected error: {0}")]
     UnexpectedError(String),
 }
@@ -62,6 +154,13 @@ impl ResponseError for GetFilterError {
             GetFilterError::InvalidQuery(msg) => {
                 HttpResponse::BadRequest().json(json!({ "error": msg }))
             }
+            GetFilterError::NotAcceptable(msg) => {
+                HttpResponse::NotAcceptable().json(json!({ "error": msg }))
+            }
+            GetFilterError::Forbidden(msg) => {
+                HttpResponse::Forbidden().json(json!({ "error": msg }))
+            }
+            GetFilterError::NotFound(msg) => HttpResponse::NotFound().json(json!({ "error": msg })),
             GetFilterError::UnexpectedError(err) => {
                 HttpResponse::InternalServerError().json(json!({ "error": err }))
             }
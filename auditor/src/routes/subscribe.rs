@@ -0,0 +1,149 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::Principal;
+use crate::domain::Record;
+use crate::notifications::{RecordNotification, RecordNotifications};
+use crate::routes::{ErrorResponse, Filters, GetFilterError, push_filters, record_from_row};
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse, web};
+use sqlx::{PgPool, Postgres, QueryBuilder};
+use std::time::Duration;
+use tokio::sync::broadcast::error::TryRecvError;
+
+/// Interval of comments sent to keep idle connections open.
+const KEEP_ALIVE_INTERVAL: Duration = Duration::from_secs(15);
+
+/// Streams inserted and updated records matching the filters of the query string as
+/// Server-Sent Events.
+///
+/// Each record is sent as `record` event, with the values it has when the event is sent. Records
+/// which are changed several times in quick succession may therefore only be sent once. If
+/// changes might have been missed, because the client is too slow or the connection to the
+/// database was lost, an `error` event is sent and the stream ends. Clients should then query the
+/// records they missed and subscribe again.
+#[utoipa::path(
+    get,
+    path = "/records/subscribe",
+    tag = "records",
+    params(Filters),
+    responses(
+        (status = 200, description = "Server-Sent Events with the inserted and updated records \
+            as `record` events. `sort_by`, `limit` and `after` are not supported",
+            content_type = "text/event-stream", body = String),
+        (status = 400, description = "The query is invalid", body = ErrorResponse),
+        (status = 500, description = "An unexpected error occurred", body = ErrorResponse),
+    )
+)]
+#[tracing::instrument(name = "Subscribing to records", skip(request, pool, notifications))]
+pub async fn subscribe_records(
+    request: HttpRequest,
+    pool: web::Data<PgPool>,
+    notifications: web::Data<RecordNotifications>,
+) -> Result<HttpResponse, GetFilterError> {
+    let mut filters =
+        Filters::parse(request.query_string()).map_err(GetFilterError::InvalidQuery)?;
+    if filters.sort_by.is_some() || filters.limit.is_some() || filters.after.is_some() {
+        return Err(GetFilterError::InvalidQuery(
+            "sort_by, limit and after are not supported by subscriptions".to_string(),
+        ));
+    }
+    filters.sites = Principal::of(&request).and_then(|principal| principal.sites);
+
+    let mut receiver = notifications.subscribe();
+    let notifications = notifications.get_ref().clone();
+    let pool = pool.get_ref().clone();
+    let stream = async_stream::stream! {
+        notifications.listening().await;
+        // Tells the client that all following changes are sent
+        yield Ok::<_, actix_web::Error>(Bytes::from_static(b": subscribed\n\n"));
+
+        let mut keep_alive = tokio::time::interval(KEEP_ALIVE_INTERVAL);
+        keep_alive.reset();
+        loop {
+            let batch = tokio::select! {
+                batch = receiver.recv() => batch,
+                _ = keep_alive.tick() => {
+                    yield Ok(Bytes::from_static(b": keep-alive\n\n"));
+                    continue;
+                }
+            };
+            // Notifications which are already waiting are handled together
+            let mut batches = vec![];
+            let mut lost = batch.map(|batch| batches.push(batch)).is_err();
+            while !lost {
+                match receiver.try_recv() {
+                    Ok(batch) => batches.push(batch),
+                    Err(TryRecvError::Empty) => break,
+                    Err(_) => lost = true,
+                }
+            }
+            let mut record_ids = vec![];
+            for notification in batches.iter().flat_map(|batch| batch.iter()) {
+                match notification {
+                    RecordNotification::Changed(record_id) => record_ids.push(record_id.clone()),
+                    RecordNotification::Lost => lost = true,
+                }
+            }
+
+            if !record_ids.is_empty() {
+                match changed_records(&filters, &record_ids, &pool).await {
+                    Ok(records) => {
+                        for record in records {
+                            yield Ok(record_event(&record));
+                        }
+                    }
+                    Err(e) => {
+                        tracing::error!("Failed to get changed records: {e:?}");
+                        yield Ok(error_event("Failed to get changed records"));
+                        break;
+                    }
+                }
+            }
+            if lost {
+                yield Ok(error_event("Changes of records might have been missed"));
+                break;
+            }
+        }
+    };
+
+    Ok(HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(("Cache-Control", "no-cache"))
+        .streaming(stream))
+}
+
+/// Returns the records with the given `record_ids` which match `filters`, in the order they were
+/// changed.
+async fn changed_records(
+    filters: &Filters,
+    record_ids: &[String],
+    pool: &PgPool,
+) -> Result<Vec<Record>, sqlx::Error> {
+    let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+        "SELECT * FROM (
+             SELECT record_id, meta, components, start_time, stop_time, runtime, updated_at
+             FROM auditor_accounting
+             WHERE record_id = ANY(",
+    );
+    query.push_bind(record_ids);
+    query.push(")) AS changed");
+    push_filters(&mut query, filters);
+    query.push(" ORDER BY updated_at, record_id");
+
+    let rows = query.build().persistent(false).fetch_all(pool).await?;
+    Ok(rows.iter().map(record_from_row).collect())
+}
+
+fn record_event(record: &Record) -> Bytes {
+    let data = serde_json::to_string(record).unwrap_or_default();
+    Bytes::from(format!("event: record\ndata: {data}\n\n"))
+}
+
+fn error_event(message: &str) -> Bytes {
+    Bytes::from(format!("event: error\ndata: {message}\n\n"))
+}
@@ -0,0 +1,167 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::auth::{Role, generate_token, hash_token};
+use crate::configuration::validate_sites;
+use actix_web::{HttpResponse, web};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Row};
+
+/// Request body of `POST /tokens`.
+#[derive(serde::Deserialize, Debug, utoipa::ToSchema)]
+#[serde(deny_unknown_fields)]
+pub struct TokenAdd {
+    pub name: String,
+    pub role: Role,
+    /// Restricts the token to the records of these sites.
+    #[serde(default)]
+    pub sites: Option<Vec<String>>,
+}
+
+/// Response of `POST /tokens`. This is the only time the token is shown.
+#[derive(serde::Serialize, serde::Deserialize, Debug, utoipa::ToSchema)]
+pub struct NewToken {
+    pub name: String,
+    pub role: Role,
+    pub sites: Option<Vec<String>>,
+    pub token: String,
+}
+
+/// A token stored in the database, as returned by `GET /tokens`.
+#[derive(serde::Serialize, serde::Deserialize, Debug, utoipa::ToSchema)]
+pub struct StoredToken {
+    pub name: String,
+    pub role: Role,
+    /// Sites the token is restricted to. `None` if the token is not restricted.
+    pub sites: Option<Vec<String>>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(thiserror::Error)]
+pub enum TokenError {
+    #[error("{0}")]
+    InvalidToken(String),
+    #[error("A token with the name {0} already exists.")]
+    TokenExists(String),
+    #[error("Token {0} not found.")]
+    UnknownToken(String),
+    #[error(transparent)]
+    UnexpectedError(#[from] anyhow::Error),
+}
+
+debug_for_error!(TokenError);
+responseerror_for_error!(
+    TokenError,
+    InvalidToken => BAD_REQUEST;
+    TokenExists => CONFLICT;
+    UnknownToken => NOT_FOUND;
+    UnexpectedError => INTERNAL_SERVER_ERROR;
+);
+
+#[utoipa::path(
+    post,
+    path = "/tokens",
+    tag = "tokens",
+    request_body = TokenAdd,
+    responses(
+        (status = 200, description = "The token was created. The secret is only returned once",
+            body = NewToken),
+        (status = 400, description = "The token is invalid", body = String),
+        (status = 409, description = "A token with this name already exists", body = String),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
+#[tracing::instrument(name = "Creating an API token", skip(token, pool), fields(name = %token.name))]
+pub async fn create_token(
+    token: web::Json<TokenAdd>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, TokenError> {
+    validate_sites(&token.sites).map_err(TokenError::InvalidToken)?;
+
+    let secret = generate_token();
+    sqlx::query("INSERT INTO api_tokens (name, token_hash, role, sites) VALUES ($1, $2, $3, $4)")
+        .bind(&token.name)
+        .bind(hash_token(&secret))
+        .bind(token.role.as_str())
+        .bind(&token.sites)
+        .execute(pool.get_ref())
+        .await
+        .map_err(|e| match e {
+            sqlx::Error::Database(e) if e.is_unique_violation() => {
+                TokenError::TokenExists(token.name.clone())
+            }
+            e => TokenError::UnexpectedError(e.into()),
+        })?;
+
+    let token = token.into_inner();
+    Ok(HttpResponse::Ok().json(NewToken {
+        name: token.name,
+        role: token.role,
+        sites: token.sites,
+        token: secret,
+    }))
+}
+
+#[utoipa::path(
+    get,
+    path = "/tokens",
+    tag = "tokens",
+    responses(
+        (status = 200, description = "All tokens stored in the database", body = Vec<StoredToken>),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
+#[tracing::instrument(name = "Listing API tokens", skip(pool))]
+pub async fn list_tokens(pool: web::Data<PgPool>) -> Result<HttpResponse, TokenError> {
+    let rows = sqlx::query("SELECT name, role, sites, created_at FROM api_tokens ORDER BY name")
+        .fetch_all(pool.get_ref())
+        .await
+        .map_err(|e| TokenError::UnexpectedError(e.into()))?;
+
+    let tokens = rows
+        .iter()
+        .map(|row| {
+            let role: String = row.try_get("role").map_err(anyhow::Error::from)?;
+            Ok(StoredToken {
+                name: row.try_get("name").map_err(anyhow::Error::from)?,
+                role: Role::try_from(role).map_err(anyhow::Error::msg)?,
+                sites: row.try_get("sites").map_err(anyhow::Error::from)?,
+                created_at: row.try_get("created_at").map_err(anyhow::Error::from)?,
+            })
+        })
+        .collect::<Result<Vec<_>, TokenError>>()?;
+
+    Ok(HttpResponse::Ok().json(tokens))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/tokens/{name}",
+    tag = "tokens",
+    params(("name" = String, Path, description = "Name of the token")),
+    responses(
+        (status = 200, description = "The token was revoked"),
+        (status = 404, description = "No token with this name exists", body = String),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
+#[tracing::instrument(name = "Revoking an API token", skip(pool))]
+pub async fn delete_token(
+    name: web::Path<String>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, TokenError> {
+    let result = sqlx::query("DELETE FROM api_tokens WHERE name = $1")
+        .bind(name.as_str())
+        .execute(pool.get_ref())
+        .await
+        .map_err(|e| TokenError::UnexpectedError(e.into()))?;
+
+    if result.rows_affected() == 0 {
+        return Err(TokenError::UnknownToken(name.into_inner()));
+    }
+    Ok(HttpResponse::Ok().finish())
+}
@@ -5,15 +5,25 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use crate::domain::RecordUpdate;
-use actix_web::{HttpResponse, web};
-use chrono::Utc;
-use sqlx::PgPool;
+use crate::auth::{Principal, SITE_META_KEY};
+use crate::domain::{
+    BulkUpdateReport, Component, MetaMode, RecordUpdate, UpdateResult, UpdateStatus, ValidMeta,
+};
+use crate::routes::set_history_client;
+use actix_web::{HttpRequest, HttpResponse, web};
+use chrono::{DateTime, Utc};
+use serde_json::{Map, Value};
+use sqlx::{PgPool, Row};
+use std::collections::HashMap;
 
 #[derive(thiserror::Error)]
 pub enum UpdateError {
     #[error("Updating unknown record {0} not possible.")]
     UnknownRecord(String),
+    #[error("{0}")]
+    InvalidRecord(String),
+    #[error("{0} is not permitted to update records of other sites.")]
+    Forbidden(String),
     #[error(transparent)]
     UnexpectedError(#[from] anyhow::Error),
 }
@@ -22,38 +32,78 @@ debug_for_error!(UpdateError);
 responseerror_for_error!(
     UpdateError,
     UnknownRecord => NOT_FOUND;
+    InvalidRecord => BAD_REQUEST;
+    Forbidden => FORBIDDEN;
     UnexpectedError => INTERNAL_SERVER_ERROR;
 );
 
+#[utoipa::path(
+    put,
+    path = "/record",
+    tag = "records",
+    request_body = RecordUpdate,
+    responses(
+        (status = 200, description = "The record was updated"),
+        (status = 400, description = "The record is invalid", body = String),
+        (status = 403, description = "The client may not update the record", body = String),
+        (status = 404, description = "No record with this `record_id` exists", body = String),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
 #[tracing::instrument(
     name = "Updating a record",
-    skip(record, pool),
+    skip(request, record, pool),
     fields(record_id = %record.record_id)
 )]
 pub async fn update(
+    request: HttpRequest,
     record: web::Json<RecordUpdate>,
     pool: web::Data<PgPool>,
 ) -> Result<HttpResponse, UpdateError> {
-    update_record(&record, &pool).await.map_err(|e| match e {
-        UpdateRecordError::RowNotFoundError(s) => UpdateError::UnknownRecord(s),
-        UpdateRecordError::OtherError(err) => UpdateError::UnexpectedError(err.into()),
-    })?;
+    authorize_update(&request, std::slice::from_ref(&record), &pool).await?;
+    let client = Principal::of(&request).map(|principal| principal.name);
+    update_record(&record, client.as_deref(), &pool)
+        .await
+        .map_err(|e| match e {
+            UpdateRecordError::RowNotFoundError(s) => UpdateError::UnknownRecord(s),
+            e @ UpdateRecordError::StartAfterStop(_) => UpdateError::InvalidRecord(e.to_string()),
+            UpdateRecordError::OtherError(err) => UpdateError::UnexpectedError(err.into()),
+        })?;
 
     Ok(HttpResponse::Ok().finish())
 }
 
+/// Updates the record with the `record_id` of `record` in the database.
+///
+/// The update is applied with PATCH semantics:
+/// * `stop_time` is always set and the runtime is recomputed.
+/// * `start_time` is only corrected if it is set.
+/// * With [`MetaMode::Merge`], the meta keys of `record` are merged into the stored meta
+///   information. Values of keys which are already present are replaced, all other keys are
+///   left untouched. With [`MetaMode::Replace`], the stored meta information is replaced.
+/// * Components replace the stored components with the same name. Components with new names
+///   are appended.
+///
+/// Fails with [`UpdateRecordError::StartAfterStop`] if the resulting `start_time` lies after the
+/// `stop_time`. The change is attributed to `client` in the history of the record.
 #[tracing::instrument(name = "Updating a record in the database", skip(record, pool))]
-pub async fn update_record(record: &RecordUpdate, pool: &PgPool) -> Result<(), UpdateRecordError> {
+pub async fn update_record(
+    record: &RecordUpdate,
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<(), UpdateRecordError> {
     let mut transaction = match pool.begin().await {
         Ok(transaction) => transaction,
         Err(e) => return Err(UpdateRecordError::OtherError(e)),
     };
+    set_history_client(&mut transaction, client).await?;
 
-    let start_time = sqlx::query!(
+    let stored = sqlx::query!(
         r#"
-        SELECT start_time
+        SELECT start_time, meta, components
         FROM auditor_accounting
         WHERE record_id = $1
+        FOR UPDATE
         "#,
         record.record_id.as_ref(),
     )
@@ -64,23 +114,39 @@ pub async fn update_record(record: &RecordUpdate, pool: &PgPool) -> Result<(), U
             UpdateRecordError::RowNotFoundError(record.record_id.as_ref().into())
         }
         e => UpdateRecordError::OtherError(e),
-    })?
-    .start_time;
+    })?;
 
-    sqlx::query_unchecked!(
-        r#"
-        UPDATE auditor_accounting
-        SET stop_time = $2,
-            runtime = $3,
-            updated_at = $4
-        WHERE
-            record_id = $1
-        "#,
-        record.record_id.as_ref(),
-        record.stop_time,
-        (record.stop_time - start_time).num_seconds(),
-        Utc::now()
+    let start_time = record.start_time.unwrap_or(stored.start_time);
+    if start_time > record.stop_time {
+        return Err(UpdateRecordError::StartAfterStop(
+            record.record_id.as_ref().into(),
+        ));
+    }
+    let meta = update_meta(stored.meta, record);
+    let components = if record.components.is_empty() {
+        None
+    } else {
+        Some(merge_components(stored.components, &record.components))
+    };
+
+    // The meta information is always written, since replacing it may also remove it
+    sqlx::query(
+        "UPDATE auditor_accounting
+         SET start_time = $2,
+             stop_time = $3,
+             runtime = $4,
+             meta = $5,
+             components = COALESCE($6, components),
+             updated_at = $7
+         WHERE record_id = $1",
     )
+    .bind(record.record_id.as_ref())
+    .bind(start_time)
+    .bind(record.stop_time)
+    .bind((record.stop_time - start_time).num_seconds())
+    .bind(meta)
+    .bind(components)
+    .bind(Utc::now())
     .execute(&mut *transaction)
     .await
     .map_err(UpdateRecordError::OtherError)?;
@@ -92,10 +158,262 @@ pub async fn update_record(record: &RecordUpdate, pool: &PgPool) -> Result<(), U
     }
 }
 
+#[utoipa::path(
+    put,
+    path = "/records",
+    tag = "records",
+    request_body = Vec<RecordUpdate>,
+    responses(
+        (status = 200, description = "The records were updated. Unknown records are reported",
+            body = BulkUpdateReport),
+        (status = 400, description = "The records are invalid", body = String),
+        (status = 403, description = "The client may not update the records", body = String),
+        (status = 500, description = "An unexpected error occurred", body = String),
+    )
+)]
+#[tracing::instrument(name = "Updating multiple records", skip(request, records, pool))]
+pub async fn bulk_update(
+    request: HttpRequest,
+    records: web::Json<Vec<RecordUpdate>>,
+    pool: web::Data<PgPool>,
+) -> Result<HttpResponse, UpdateError> {
+    authorize_update(&request, &records, &pool).await?;
+    let client = Principal::of(&request).map(|principal| principal.name);
+    let report = bulk_update_records(&records, client.as_deref(), &pool)
+        .await
+        .map_err(|e| match e {
+            e @ UpdateRecordError::StartAfterStop(_) => UpdateError::InvalidRecord(e.to_string()),
+            e => UpdateError::UnexpectedError(e.into()),
+        })?;
+
+    Ok(HttpResponse::Ok().json(report))
+}
+
+/// Checks that the client of `request` may update the stored records and, if the updates change
+/// the site of a record, that the new site is permitted as well.
+async fn authorize_update(
+    request: &HttpRequest,
+    records: &[RecordUpdate],
+    pool: &PgPool,
+) -> Result<(), UpdateError> {
+    let Some(principal) = Principal::of(request) else {
+        return Ok(());
+    };
+    let permits_new_sites = records
+        .iter()
+        .all(|record| match (&record.meta, record.meta_mode) {
+            (meta, MetaMode::Replace) => principal.permits_meta(meta.as_ref()),
+            (Some(meta), MetaMode::Merge)
+                if meta.0.keys().any(|key| key.as_ref() == SITE_META_KEY) =>
+            {
+                principal.permits_meta(Some(meta))
+            }
+            _ => true,
+        });
+    let record_ids: Vec<&str> = records.iter().map(|r| r.record_id.as_ref()).collect();
+    if !permits_new_sites
+        || !principal
+            .permits_stored_records(&record_ids, pool)
+            .await
+            .map_err(|e| UpdateError::UnexpectedError(e.into()))?
+    {
+        return Err(UpdateError::Forbidden(principal.name));
+    }
+    Ok(())
+}
+
+/// Values of a stored record which can be changed by an update.
+struct StoredRecord {
+    start_time: DateTime<Utc>,
+    stop_time: Option<DateTime<Utc>>,
+    meta: Option<Value>,
+    components: Option<Value>,
+    updated: bool,
+}
+
+/// Applies `records` in a single transaction and reports the status of every update.
+///
+/// Each update is applied with the same semantics as [`update_record`]. Several updates of the
+/// same record are applied in order. Updates of unknown records are reported as
+/// [`UpdateStatus::Unknown`] and do not affect the other updates. If the `start_time` of any
+/// record would lie after its `stop_time`, none of the updates is applied.
+#[tracing::instrument(
+    name = "Updating multiple records in the database",
+    skip(records, pool)
+)]
+pub async fn bulk_update_records(
+    records: &[RecordUpdate],
+    client: Option<&str>,
+    pool: &PgPool,
+) -> Result<BulkUpdateReport, UpdateRecordError> {
+    let mut transaction = pool.begin().await?;
+    set_history_client(&mut transaction, client).await?;
+
+    let record_ids: Vec<&str> = records.iter().map(|r| r.record_id.as_ref()).collect();
+    // Rows are locked in a fixed order to prevent deadlocks between concurrent bulk updates
+    let rows = sqlx::query(
+        "SELECT record_id, start_time, stop_time, meta, components
+         FROM auditor_accounting
+         WHERE record_id = ANY($1)
+         ORDER BY record_id
+         FOR UPDATE",
+    )
+    .bind(&record_ids)
+    .fetch_all(&mut *transaction)
+    .await?;
+
+    let mut stored = rows
+        .iter()
+        .map(|row| {
+            Ok((
+                row.try_get::<String, _>("record_id")?,
+                StoredRecord {
+                    start_time: row.try_get("start_time")?,
+                    stop_time: row.try_get("stop_time")?,
+                    meta: row.try_get("meta")?,
+                    components: row.try_get("components")?,
+                    updated: false,
+                },
+            ))
+        })
+        .collect::<Result<HashMap<_, _>, sqlx::Error>>()?;
+
+    let mut results = Vec::with_capacity(records.len());
+    for record in records {
+        let status = match stored.get_mut(record.record_id.as_ref()) {
+            Some(stored) => {
+                if let Some(start_time) = record.start_time {
+                    stored.start_time = start_time;
+                }
+                if stored.start_time > record.stop_time {
+                    return Err(UpdateRecordError::StartAfterStop(
+                        record.record_id.as_ref().into(),
+                    ));
+                }
+                stored.stop_time = Some(record.stop_time);
+                stored.meta = update_meta(stored.meta.take(), record);
+                if !record.components.is_empty() {
+                    stored.components = Some(merge_components(
+                        stored.components.take(),
+                        &record.components,
+                    ));
+                }
+                stored.updated = true;
+                UpdateStatus::Updated
+            }
+            None => UpdateStatus::Unknown,
+        };
+        results.push(UpdateResult {
+            record_id: record.record_id.as_ref().to_string(),
+            status,
+        });
+    }
+
+    let updated: Vec<_> = stored.into_iter().filter(|(_, s)| s.updated).collect();
+    sqlx::query(
+        "UPDATE auditor_accounting AS a
+         SET start_time = u.start_time,
+             stop_time = u.stop_time,
+             runtime = u.runtime,
+             meta = u.meta,
+             components = u.components,
+             updated_at = $7
+         FROM UNNEST($1::text[], $2::timestamptz[], $3::timestamptz[], $4::bigint[], $5::jsonb[],
+                     $6::jsonb[])
+             AS u(record_id, start_time, stop_time, runtime, meta, components)
+         WHERE a.record_id = u.record_id",
+    )
+    .bind(
+        updated
+            .iter()
+            .map(|(id, _)| id.as_str())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        updated
+            .iter()
+            .map(|(_, s)| s.start_time)
+            .collect::<Vec<_>>(),
+    )
+    .bind(updated.iter().map(|(_, s)| s.stop_time).collect::<Vec<_>>())
+    .bind(
+        updated
+            .iter()
+            .map(|(_, s)| s.stop_time.map(|stop| (stop - s.start_time).num_seconds()))
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        updated
+            .iter()
+            .map(|(_, s)| s.meta.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(
+        updated
+            .iter()
+            .map(|(_, s)| s.components.clone())
+            .collect::<Vec<_>>(),
+    )
+    .bind(Utc::now())
+    .execute(&mut *transaction)
+    .await?;
+
+    transaction.commit().await?;
+    Ok(BulkUpdateReport { records: results })
+}
+
+/// Applies the meta information of `record` to the `stored` one according to its
+/// [`MetaMode`].
+fn update_meta(stored: Option<Value>, record: &RecordUpdate) -> Option<Value> {
+    match (record.meta_mode, &record.meta) {
+        (MetaMode::Merge, None) => stored,
+        (MetaMode::Merge, Some(meta)) => Some(merge_meta(stored, meta)),
+        (MetaMode::Replace, meta) => meta
+            .as_ref()
+            .map(|meta| serde_json::to_value(meta).unwrap_or(Value::Null)),
+    }
+}
+
+/// Merges `update` into the `stored` meta information. The values of keys present in `update`
+/// replace the stored values.
+fn merge_meta(stored: Option<Value>, update: &ValidMeta) -> Value {
+    let mut meta = match stored {
+        Some(Value::Object(meta)) => meta,
+        _ => Map::new(),
+    };
+    for (key, values) in update.to_vec() {
+        meta.insert(key, Value::from(values));
+    }
+    Value::Object(meta)
+}
+
+/// Merges `update` into the `stored` components. Stored components with the same name as a
+/// component in `update` are replaced, all other components of `update` are appended.
+fn merge_components(stored: Option<Value>, update: &[Component]) -> Value {
+    let mut components = match stored {
+        Some(Value::Array(components)) => components,
+        _ => vec![],
+    };
+    for component in update {
+        let name = component.name.as_ref();
+        let component = serde_json::to_value(component).unwrap_or(Value::Null);
+        match components
+            .iter_mut()
+            .find(|c| c.get("name").and_then(Value::as_str) == Some(name))
+        {
+            Some(stored) => *stored = component,
+            None => components.push(component),
+        }
+    }
+    Value::Array(components)
+}
+
 #[derive(thiserror::Error)]
 pub enum UpdateRecordError {
     #[error("Entry {0} not found in database")]
     RowNotFoundError(String),
+    #[error("start_time of record {0} lies after its stop_time")]
+    StartAfterStop(String),
     #[error(transparent)]
     OtherError(#[from] sqlx::Error),
 }
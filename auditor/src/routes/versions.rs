@@ -0,0 +1,75 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::domain::ApiVersion;
+use crate::routes::ErrorResponse;
+use actix_web::body::{BoxBody, MessageBody, to_bytes};
+use actix_web::dev::{ServiceRequest, ServiceResponse};
+use actix_web::error::InternalError;
+use actix_web::http::header::{CONTENT_LENGTH, CONTENT_TYPE};
+use actix_web::middleware::Next;
+use actix_web::{HttpRequest, HttpResponse};
+
+/// Returns the version of the API addressed by `request`.
+pub fn api_version(request: &HttpRequest) -> ApiVersion {
+    ApiVersion::from_path(request.path()).0
+}
+
+/// Middleware which returns all errors of [`ApiVersion::V2`] as [`ErrorResponse`].
+///
+/// Errors of `V1` are passed on unchanged, as deployed clients depend on their plain text
+/// bodies.
+pub async fn json_errors(
+    request: ServiceRequest,
+    next: Next<impl MessageBody + 'static>,
+) -> Result<ServiceResponse<BoxBody>, actix_web::Error> {
+    if ApiVersion::from_path(request.path()).0 < ApiVersion::V2 {
+        return next
+            .call(request)
+            .await
+            .map(ServiceResponse::map_into_boxed_body);
+    }
+    match next.call(request).await {
+        Ok(response) => {
+            let (request, response) = response.into_parts();
+            let response = error_as_json(response.map_into_boxed_body()).await;
+            Ok(ServiceResponse::new(request, response))
+        }
+        // Errors of inner middleware, e.g. failed authentication
+        Err(e) => {
+            let response = error_as_json(e.error_response()).await;
+            Err(InternalError::from_response(e, response).into())
+        }
+    }
+}
+
+/// Replaces the body of an error `response` by an [`ErrorResponse`] holding the original body.
+/// Successful responses and errors which are JSON already are returned unchanged.
+async fn error_as_json(response: HttpResponse) -> HttpResponse {
+    let status = response.status();
+    let is_json = response
+        .headers()
+        .get(CONTENT_TYPE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.starts_with("application/json"));
+    if !(status.is_client_error() || status.is_server_error()) || is_json {
+        return response;
+    }
+
+    let mut builder = HttpResponse::build(status);
+    for (name, value) in response.headers() {
+        if name != CONTENT_TYPE && name != CONTENT_LENGTH {
+            builder.append_header((name.clone(), value.clone()));
+        }
+    }
+    let body = to_bytes(response.into_body()).await.unwrap_or_default();
+    let error = match String::from_utf8_lossy(&body).trim() {
+        "" => status.canonical_reason().unwrap_or_default().to_string(),
+        message => message.to_string(),
+    };
+    builder.json(ErrorResponse { error })
+}
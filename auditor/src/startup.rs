@@ -5,16 +5,76 @@
 // http://opensource.org/licenses/MIT>, at your option. This file may not be
 // copied, modified, or distributed except according to those terms.
 
-use crate::configuration::TLSParams;
+use crate::auth::{Authenticator, authorize, extract_client_certificate};
+use crate::configuration::{AuthSettings, TLSParams};
+use crate::domain::ApiVersion;
 use crate::metrics::{DatabaseMetricsWatcher, PrometheusExporterBuilder, PrometheusExporterConfig};
-use crate::routes::{add, bulk_add, health_check, query_one_record, query_records, update};
+use crate::notifications::RecordNotifications;
+use crate::routes::{
+    add, bulk_add, bulk_delete, bulk_update, create_token, delete, delete_token, health_check,
+    json_errors, list_tokens, livez, openapi_json, query_aggregate, query_concurrency,
+    query_daily_usage, query_histogram, query_one_record, query_record_history, query_records,
+    readyz, subscribe_records, update,
+};
 use actix_web::dev::Server;
+use actix_web::middleware::from_fn;
 use actix_web::{App, HttpResponse, HttpServer, web};
 use actix_web_opentelemetry::{PrometheusMetricsHandler, RequestMetrics};
 use opentelemetry::global;
 use sqlx::PgPool;
 use tracing_actix_web::TracingLogger;
 
+/// Serves the Swagger UI of `/openapi.json` at `/swagger-ui/`.
+#[cfg(feature = "swagger-ui")]
+fn swagger_ui(config: &mut web::ServiceConfig) {
+    config.service(
+        utoipa_swagger_ui::SwaggerUi::new("/swagger-ui/{_:.*}")
+            .config(utoipa_swagger_ui::Config::new(["/openapi.json"])),
+    );
+}
+
+#[cfg(not(feature = "swagger-ui"))]
+fn swagger_ui(_config: &mut web::ServiceConfig) {}
+
+/// Routes of the REST API, which are served by every [`ApiVersion`]. The handlers look up the
+/// version of a request with [`crate::routes::api_version`].
+fn api_routes(config: &mut web::ServiceConfig) {
+    config
+        .route("/health_check", web::get().to(health_check))
+        .service(
+            web::resource("/record")
+                .route(web::post().to(add))
+                .route(web::put().to(update)),
+        )
+        .service(
+            web::resource("/record/{record_id}")
+                .route(web::get().to(query_one_record))
+                .route(web::delete().to(delete)),
+        )
+        .route(
+            "/record/{record_id}/history",
+            web::get().to(query_record_history),
+        )
+        .service(
+            web::resource("/records")
+                .route(web::post().to(bulk_add))
+                .route(web::put().to(bulk_update))
+                .route(web::get().to(query_records))
+                .route(web::delete().to(bulk_delete)),
+        )
+        .route("/records/subscribe", web::get().to(subscribe_records))
+        .route("/aggregate", web::get().to(query_aggregate))
+        .route("/histogram", web::get().to(query_histogram))
+        .route("/concurrency", web::get().to(query_concurrency))
+        .route("/daily_usage", web::get().to(query_daily_usage))
+        .service(
+            web::resource("/tokens")
+                .route(web::post().to(create_token))
+                .route(web::get().to(list_tokens)),
+        )
+        .route("/tokens/{name}", web::delete().to(delete_token));
+}
+
 /// Configures and starts the HttpServer
 pub fn run(
     addrs: Vec<String>,
@@ -23,16 +83,23 @@ pub fn run(
     db_pool: PgPool,
     db_watcher: DatabaseMetricsWatcher,
     tls_params: Option<TLSParams>,
+    auth: AuthSettings,
 ) -> Result<Server, anyhow::Error> {
     let request_metrics: PrometheusExporterConfig = PrometheusExporterBuilder::new()
-        .with_database_watcher(db_watcher)
+        .with_database_watcher(db_watcher.clone())
         .build()?;
     global::set_meter_provider(request_metrics.provider);
 
+    let authenticator = web::Data::new(Authenticator::new(db_pool.clone(), &auth)?);
+    let notifications = web::Data::new(RecordNotifications::start(db_pool.clone()));
     let db_pool = web::Data::new(db_pool);
+    let db_watcher = web::Data::new(db_watcher);
 
     let app_config = move || {
         App::new()
+            // Authentication middleware
+            .wrap(from_fn(authorize))
+            .wrap(from_fn(json_errors))
             // Logging middleware
             .wrap(TracingLogger::default())
             .wrap(RequestMetrics::default())
@@ -43,26 +110,27 @@ pub fn run(
                 )),
             )
             // Routes
-            .route("/health_check", web::get().to(health_check))
-            .service(
-                web::resource("/record")
-                    .route(web::post().to(add))
-                    .route(web::put().to(update)),
-            )
-            .route("/record/{record_id}", web::get().to(query_one_record))
+            .route("/livez", web::get().to(livez))
+            .route("/readyz", web::get().to(readyz))
+            .route("/openapi.json", web::get().to(openapi_json))
+            .configure(swagger_ui)
+            // Unversioned paths are answered by v1, which is what deployed clients use
+            .configure(api_routes)
+            .service(web::scope(ApiVersion::V1.prefix()).configure(api_routes))
+            .service(web::scope(ApiVersion::V2.prefix()).configure(api_routes))
             // DB connection pool
-            .service(
-                web::resource("/records")
-                    .route(web::post().to(bulk_add))
-                    .route(web::get().to(query_records)),
-            )
             .app_data(db_pool.clone())
+            .app_data(db_watcher.clone())
+            .app_data(authenticator.clone())
+            .app_data(notifications.clone())
             .default_service(web::route().to(|| async {
                 HttpResponse::NotFound().body("The requested resource was not found. 404 Not Found")
             }))
     };
 
-    let mut server = HttpServer::new(app_config).workers(web_workers);
+    let mut server = HttpServer::new(app_config)
+        .on_connect(extract_client_certificate)
+        .workers(web_workers);
 
     for addr in &addrs {
         let address = format!("{}:{}", addr, port);
@@ -0,0 +1,327 @@
+// Copyright 2021-2022 AUDITOR developers
+//
+// Licensed under the Apache License, Version 2.0 <LICENSE-APACHE or
+// http://apache.org/licenses/LICENSE-2.0> or the MIT license <LICENSE-MIT or
+// http://opensource.org/licenses/MIT>, at your option. This file may not be
+// copied, modified, or distributed except according to those terms.
+
+use crate::configuration::{Settings, WebhookTarget};
+use crate::domain::Record;
+use crate::routes::{Filters, push_filters_including_open};
+use sqlx::{PgPool, Postgres, QueryBuilder, Row};
+
+/// Number of events or deliveries which are handled at once.
+const BATCH_SIZE: i64 = 100;
+/// Upper bound of the delay between two attempts of a delivery.
+const MAX_BACKOFF_SECONDS: f64 = 3600.0;
+
+/// Events of records which are posted to webhooks.
+#[derive(serde::Serialize, serde::Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "snake_case")]
+pub enum WebhookEvent {
+    /// A record was added.
+    RecordAdded,
+    /// The `stop_time` of a record was set by an update.
+    RecordCompleted,
+}
+
+impl WebhookEvent {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            WebhookEvent::RecordAdded => "record_added",
+            WebhookEvent::RecordCompleted => "record_completed",
+        }
+    }
+}
+
+/// `WebhookDispatcher` posts records to the configured webhooks.
+///
+/// Events are collected by a trigger in the database, in the same transaction as the change of
+/// the record. They are then matched against the filters of the webhooks and stored in an outbox,
+/// from which they are delivered. Failed deliveries are retried with exponential backoff, so
+/// events are not lost if a webhook or AUDITOR itself is unavailable for some time. Deliveries
+/// are deleted from the outbox once they succeeded or failed `max_attempts` times.
+#[derive(Clone)]
+pub struct WebhookDispatcher {
+    db_pool: PgPool,
+    client: reqwest::Client,
+    frequency: chrono::Duration,
+    max_attempts: u32,
+    targets: Vec<(WebhookTarget, Filters)>,
+}
+
+impl WebhookDispatcher {
+    pub fn new(pool: PgPool, config: &Settings) -> Result<WebhookDispatcher, anyhow::Error> {
+        let mut targets = Vec::with_capacity(config.webhooks.targets.len());
+        for target in config.webhooks.targets.iter() {
+            if targets
+                .iter()
+                .any(|(t, _): &(WebhookTarget, _)| t.name == target.name)
+            {
+                anyhow::bail!("The name {} is used by several webhooks", target.name);
+            }
+            reqwest::Url::parse(&target.url)
+                .map_err(|e| anyhow::anyhow!("Invalid url of webhook {}: {e}", target.name))?;
+            let filters = Filters::parse(target.filter.as_deref().unwrap_or(""))
+                .map_err(|e| anyhow::anyhow!("Invalid filter of webhook {}: {e}", target.name))?;
+            if filters.sort_by.is_some() || filters.limit.is_some() || filters.after.is_some() {
+                anyhow::bail!(
+                    "The filter of webhook {} must not contain sort_by, limit or after",
+                    target.name
+                );
+            }
+            targets.push((target.clone(), filters));
+        }
+
+        Ok(WebhookDispatcher {
+            db_pool: pool,
+            client: reqwest::Client::builder()
+                .timeout(config.webhooks.timeout.to_std()?)
+                .build()?,
+            frequency: config.webhooks.frequency,
+            max_attempts: config.webhooks.max_attempts,
+            targets,
+        })
+    }
+
+    /// Registers the webhooks and delivers events every `frequency`. Returns after registering if
+    /// no webhooks are configured.
+    #[tracing::instrument(name = "Dispatching webhooks", skip(self))]
+    pub async fn dispatch(&self) -> Result<(), anyhow::Error> {
+        self.register().await?;
+        if self.targets.is_empty() {
+            return Ok(());
+        }
+
+        let mut interval = tokio::time::interval(self.frequency.to_std()?);
+        loop {
+            interval.tick().await;
+            if let Err(e) = self.enqueue().await {
+                tracing::error!("Failed to match events against webhooks: {e:?}");
+            }
+            if let Err(e) = self.deliver().await {
+                tracing::error!("Failed to deliver events to webhooks: {e:?}");
+            }
+        }
+    }
+
+    /// Registers the configured webhooks in the database. Events are only collected while
+    /// webhooks are registered.
+    ///
+    /// Webhooks which are not configured are kept, since they may be configured in other
+    /// instances of AUDITOR using the same database. They are removed by
+    /// [`WebhookDispatcher::prune`].
+    #[tracing::instrument(name = "Registering webhooks", skip(self))]
+    pub async fn register(&self) -> Result<(), sqlx::Error> {
+        let names: Vec<&str> = self.targets.iter().map(|(t, _)| t.name.as_str()).collect();
+        sqlx::query(
+            "INSERT INTO webhooks (name) SELECT * FROM UNNEST($1::text[]) ON CONFLICT DO NOTHING",
+        )
+        .bind(&names)
+        .execute(&self.db_pool)
+        .await?;
+        Ok(())
+    }
+
+    /// Removes the registered webhooks which are not configured, together with their pending
+    /// deliveries. Collected events are dropped if no webhooks remain. Returns the number of
+    /// removed webhooks.
+    #[tracing::instrument(name = "Pruning webhooks", skip(self))]
+    pub async fn prune(&self) -> Result<u64, sqlx::Error> {
+        let names: Vec<&str> = self.targets.iter().map(|(t, _)| t.name.as_str()).collect();
+        let mut transaction = self.db_pool.begin().await?;
+        let removed = sqlx::query("DELETE FROM webhooks WHERE name <> ALL($1)")
+            .bind(&names)
+            .execute(&mut *transaction)
+            .await?
+            .rows_affected();
+        sqlx::query("DELETE FROM webhook_events WHERE NOT EXISTS (SELECT 1 FROM webhooks)")
+            .execute(&mut *transaction)
+            .await?;
+        transaction.commit().await?;
+        Ok(removed)
+    }
+
+    /// Matches the collected events against the filters of the webhooks and moves them to the
+    /// outbox. Returns the number of handled events.
+    #[tracing::instrument(name = "Matching events against webhooks", skip(self))]
+    pub async fn enqueue(&self) -> Result<u64, sqlx::Error> {
+        let mut handled = 0;
+        loop {
+            let mut transaction = self.db_pool.begin().await?;
+            let ids: Vec<i64> = sqlx::query_scalar(
+                "SELECT id FROM webhook_events ORDER BY id LIMIT $1 FOR UPDATE SKIP LOCKED",
+            )
+            .bind(BATCH_SIZE)
+            .fetch_all(&mut *transaction)
+            .await?;
+            if ids.is_empty() {
+                return Ok(handled);
+            }
+
+            for (target, filters) in self.targets.iter() {
+                let events: Vec<&str> = target.events.iter().map(WebhookEvent::as_str).collect();
+                // The filters are applied to the columns of the record stored with the event
+                let mut query: QueryBuilder<Postgres> = QueryBuilder::new(
+                    "INSERT INTO webhook_outbox (webhook, event, record) SELECT ",
+                );
+                query.push_bind(&target.name);
+                query.push(
+                    ", event, record FROM (
+                         SELECT e.id, e.event, e.record, r.*
+                         FROM webhook_events AS e
+                         CROSS JOIN LATERAL jsonb_to_record(e.record) AS r(
+                             record_id TEXT, meta JSONB, components JSONB,
+                             start_time TIMESTAMPTZ, stop_time TIMESTAMPTZ, runtime BIGINT
+                         )
+                         WHERE e.id = ANY(",
+                );
+                query.push_bind(&ids);
+                query.push(") AND e.event = ANY(");
+                query.push_bind(events);
+                query.push(")) AS events");
+                push_filters_including_open(&mut query, filters);
+                query.push(" ORDER BY id");
+                query
+                    .build()
+                    .persistent(false)
+                    .execute(&mut *transaction)
+                    .await?;
+            }
+
+            sqlx::query("DELETE FROM webhook_events WHERE id = ANY($1)")
+                .bind(&ids)
+                .execute(&mut *transaction)
+                .await?;
+            transaction.commit().await?;
+            handled += ids.len() as u64;
+        }
+    }
+
+    /// Delivers the pending events of the outbox whose next attempt is due. Returns the number of
+    /// successful deliveries.
+    #[tracing::instrument(name = "Delivering events to webhooks", skip(self))]
+    pub async fn deliver(&self) -> Result<u64, anyhow::Error> {
+        let mut delivered = 0;
+        for (target, _) in self.targets.iter() {
+            delivered += self.deliver_to(target).await?;
+        }
+        Ok(delivered)
+    }
+
+    async fn deliver_to(&self, target: &WebhookTarget) -> Result<u64, anyhow::Error> {
+        let max_attempts = i32::try_from(self.max_attempts).unwrap_or(i32::MAX);
+        // Deliveries whose last attempt was interrupted, or which exceeded a lowered
+        // `max_attempts`, would otherwise never be attempted nor removed.
+        let exhausted =
+            sqlx::query("DELETE FROM webhook_outbox WHERE webhook = $1 AND attempts >= $2")
+                .bind(&target.name)
+                .bind(max_attempts)
+                .execute(&self.db_pool)
+                .await?
+                .rows_affected();
+        if exhausted > 0 {
+            tracing::error!(
+                "Gave up {exhausted} deliveries to webhook {} after {max_attempts} attempts",
+                target.name
+            );
+        }
+
+        let mut delivered = 0;
+        loop {
+            // Deliveries are claimed by scheduling their next attempt, so that they are not sent
+            // twice by several instances of AUDITOR.
+            let mut rows = sqlx::query(
+                "UPDATE webhook_outbox
+                 SET attempts = attempts + 1,
+                     next_attempt_at = now()
+                         + make_interval(secs => LEAST($2 * power(2, attempts), $3))
+                 WHERE id IN (
+                     SELECT id FROM webhook_outbox
+                     WHERE webhook = $1 AND attempts < $4 AND next_attempt_at <= now()
+                     ORDER BY id
+                     LIMIT $5
+                     FOR UPDATE SKIP LOCKED
+                 )
+                 RETURNING id, event, record, attempts",
+            )
+            .bind(&target.name)
+            .bind(self.frequency.num_milliseconds() as f64 / 1000.0)
+            .bind(MAX_BACKOFF_SECONDS)
+            .bind(max_attempts)
+            .bind(BATCH_SIZE)
+            .fetch_all(&self.db_pool)
+            .await?;
+            if rows.is_empty() {
+                return Ok(delivered);
+            }
+            rows.sort_by_key(|row| row.try_get::<i64, _>("id").unwrap_or_default());
+
+            for (index, row) in rows.iter().enumerate() {
+                let id: i64 = row.try_get("id")?;
+                let event: String = row.try_get("event")?;
+                let record: Record = serde_json::from_value(row.try_get("record")?)?;
+                match self.post(target, id, &event, &record).await {
+                    Ok(()) => {
+                        sqlx::query("DELETE FROM webhook_outbox WHERE id = $1")
+                            .bind(id)
+                            .execute(&self.db_pool)
+                            .await?;
+                        delivered += 1;
+                    }
+                    Err(e) => {
+                        let attempts: i32 = row.try_get("attempts")?;
+                        if attempts >= max_attempts {
+                            tracing::error!(
+                                "Giving up delivery {id} to webhook {} after {attempts} attempts: {e}",
+                                target.name
+                            );
+                            sqlx::query("DELETE FROM webhook_outbox WHERE id = $1")
+                                .bind(id)
+                                .execute(&self.db_pool)
+                                .await?;
+                        } else {
+                            tracing::warn!("Delivery {id} to webhook {} failed: {e}", target.name);
+                            sqlx::query("UPDATE webhook_outbox SET last_error = $2 WHERE id = $1")
+                                .bind(id)
+                                .bind(e.to_string())
+                                .execute(&self.db_pool)
+                                .await?;
+                        }
+                        // The webhook is probably unavailable, hence the remaining deliveries
+                        // are postponed without counting an attempt.
+                        let remaining: Vec<i64> = rows[index + 1..]
+                            .iter()
+                            .map(|row| row.try_get("id"))
+                            .collect::<Result<_, _>>()?;
+                        sqlx::query(
+                            "UPDATE webhook_outbox SET attempts = attempts - 1 WHERE id = ANY($1)",
+                        )
+                        .bind(&remaining)
+                        .execute(&self.db_pool)
+                        .await?;
+                        return Ok(delivered);
+                    }
+                }
+            }
+        }
+    }
+
+    async fn post(
+        &self,
+        target: &WebhookTarget,
+        id: i64,
+        event: &str,
+        record: &Record,
+    ) -> Result<(), reqwest::Error> {
+        self.client
+            .post(&target.url)
+            .header("X-Auditor-Event", event)
+            .header("X-Auditor-Delivery", id.to_string())
+            .json(record)
+            .send()
+            .await?
+            .error_for_status()?;
+        Ok(())
+    }
+}
@@ -1,5 +1,7 @@
 use crate::helpers::spawn_app;
-use auditor::domain::{RecordDatabase, RecordTest};
+use auditor::domain::{
+    BulkInsertReport, InsertResult, InsertStatus, Record, RecordDatabase, RecordTest,
+};
 use fake::{Fake, Faker};
 
 #[tokio::test]
@@ -96,6 +98,69 @@ async fn add_returns_a_500_for_duplicate_records() {
     assert_eq!(500, response.status().as_u16());
 }
 
+#[tokio::test]
+async fn add_with_on_conflict_accepts_identical_records() {
+    let app = spawn_app().await;
+
+    let record: RecordTest = Faker.fake();
+
+    for (on_conflict, status) in [
+        ("skip", InsertStatus::Inserted),
+        ("skip", InsertStatus::Duplicate),
+        ("update", InsertStatus::Duplicate),
+    ] {
+        let response = app.add_record_with(&record, on_conflict).await;
+        assert_eq!(200, response.status().as_u16());
+
+        let result: InsertResult = response.json().await.unwrap();
+        assert_eq!(result.record_id, record.record_id);
+        assert_eq!(result.status, status);
+    }
+}
+
+#[tokio::test]
+async fn add_with_skip_returns_a_409_with_the_stored_record() {
+    let app = spawn_app().await;
+
+    let stored: RecordTest = Faker.fake::<RecordTest>().with_record_id("r1");
+    let response = app.add_record(&stored).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let different: RecordTest = Faker.fake::<RecordTest>().with_record_id("r1");
+    let response = app.add_record_with(&different, "skip").await;
+    assert_eq!(409, response.status().as_u16());
+
+    let conflicting: Record = response.json().await.unwrap();
+    assert_eq!(stored, conflicting);
+    assert_eq!(
+        stored.meta,
+        conflicting.meta.map(|m| m.to_vec().into_iter().collect())
+    );
+
+    let response = app.get_single_record("r1").await;
+    let saved: Record = response.json().await.unwrap();
+    assert_eq!(stored, saved);
+}
+
+#[tokio::test]
+async fn add_with_update_replaces_different_records() {
+    let app = spawn_app().await;
+
+    let stored: RecordTest = Faker.fake::<RecordTest>().with_record_id("r1");
+    let response = app.add_record(&stored).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let different: RecordTest = Faker.fake::<RecordTest>().with_record_id("r1");
+    let response = app.add_record_with(&different, "update").await;
+    assert_eq!(200, response.status().as_u16());
+    let result: InsertResult = response.json().await.unwrap();
+    assert_eq!(result.status, InsertStatus::Updated);
+
+    let response = app.get_single_record("r1").await;
+    let saved: Record = response.json().await.unwrap();
+    assert_eq!(different, saved);
+}
+
 #[tokio::test]
 async fn bulk_insert_records() {
     let app = spawn_app().await;
@@ -197,3 +262,95 @@ async fn bulk_insert_returns_a_500_for_duplicate_records() {
     let response = app.bulk_insert(&records).await;
     assert_eq!(500, response.status().as_u16());
 }
+
+#[tokio::test]
+async fn bulk_insert_skips_duplicates_and_reports_status() {
+    let app = spawn_app().await;
+
+    let existing: RecordTest = Faker.fake::<RecordTest>().with_record_id("existing");
+    let response = app.add_record(&existing).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let new: RecordTest = Faker.fake::<RecordTest>().with_record_id("new");
+    let invalid = RecordTest {
+        start_time: None,
+        ..Faker.fake::<RecordTest>().with_record_id("invalid")
+    };
+    let records = vec![
+        Faker.fake::<RecordTest>().with_record_id("existing"),
+        new.clone(),
+        invalid,
+        Faker.fake::<RecordTest>().with_record_id("new"),
+    ];
+
+    let response = app.bulk_insert_with(&records, "skip").await;
+    assert_eq!(200, response.status().as_u16());
+
+    let report: BulkInsertReport = response.json().await.unwrap();
+    let statuses = report
+        .records
+        .iter()
+        .map(|r| (r.record_id.as_deref().unwrap(), r.status))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        vec![
+            ("existing", InsertStatus::Duplicate),
+            ("new", InsertStatus::Inserted),
+            ("invalid", InsertStatus::Invalid),
+            ("new", InsertStatus::Duplicate),
+        ]
+    );
+    assert!(report.records[2].error.is_some());
+
+    // Existing records are left untouched
+    let response = app.get_single_record("existing").await;
+    let saved: Record = response.json().await.unwrap();
+    assert_eq!(existing, saved);
+    let response = app.get_single_record("new").await;
+    let saved: Record = response.json().await.unwrap();
+    assert_eq!(new, saved);
+
+    let count = sqlx::query!(r#"SELECT record_id FROM auditor_accounting"#)
+        .fetch_all(&app.db_pool)
+        .await
+        .expect("Failed to fetch data")
+        .len();
+    assert_eq!(count, 2);
+}
+
+#[tokio::test]
+async fn bulk_insert_updates_existing_records() {
+    let app = spawn_app().await;
+
+    let existing: RecordTest = Faker.fake::<RecordTest>().with_record_id("existing");
+    let response = app.add_record(&existing).await;
+    assert_eq!(200, response.status().as_u16());
+
+    let replacement: RecordTest = Faker.fake::<RecordTest>().with_record_id("existing");
+    let new: RecordTest = Faker.fake::<RecordTest>().with_record_id("new");
+
+    let response = app
+        .bulk_insert_with(&vec![replacement.clone(), new], "update")
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    let report: BulkInsertReport = response.json().await.unwrap();
+    assert_eq!(report.count(InsertStatus::Updated), 1);
+    assert_eq!(report.count(InsertStatus::Inserted), 1);
+    assert_eq!(report.records[0].status, InsertStatus::Updated);
+
+    let response = app.get_single_record("existing").await;
+    let saved: Record = response.json().await.unwrap();
+    assert_eq!(replacement, saved);
+}
+
+#[tokio::test]
+async fn bulk_insert_returns_a_400_for_invalid_on_conflict() {
+    let app = spawn_app().await;
+
+    let records: Vec<RecordTest> = (0..2).map(|_| Faker.fake()).collect();
+
+    let response = app.bulk_insert_with(&records, "overwrite").await;
+    assert_eq!(400, response.status().as_u16());
+}
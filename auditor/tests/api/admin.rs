@@ -0,0 +1,114 @@
+use crate::helpers::spawn_app;
+use auditor::admin::{check_config, stats, vacuum_retention};
+use auditor::configuration::{RetentionPolicy, get_configuration};
+use auditor::domain::RecordTest;
+use auditor::migrations::{self, latest_version};
+use chrono::{Duration, SecondsFormat, Utc};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use uuid::Uuid;
+
+fn record(record_id: &str, days_ago: i64) -> RecordTest {
+    let stop_time = Utc::now() - Duration::days(days_ago);
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_component("CPU", 1, vec![])
+        .with_start_time(
+            (stop_time - Duration::hours(1)).to_rfc3339_opts(SecondsFormat::Secs, true),
+        )
+        .with_stop_time(stop_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+#[tokio::test]
+async fn migrate_creates_and_migrates_the_database() {
+    // Arrange
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.database.database_name = Uuid::new_v4().to_string();
+
+    // Act
+    let version = migrations::run(&configuration.database).await.unwrap();
+    // Running the migrations again is a no-op
+    let again = migrations::run(&configuration.database).await.unwrap();
+
+    // Assert
+    assert_eq!(latest_version(), version);
+    assert_eq!(version, again);
+    let pool = PgPool::connect_with(configuration.database.with_db())
+        .await
+        .unwrap();
+    let records: i64 = sqlx::query_scalar("SELECT count(*) FROM auditor_accounting")
+        .fetch_one(&pool)
+        .await
+        .unwrap();
+    assert_eq!(0, records);
+}
+
+#[tokio::test]
+async fn check_config_rejects_invalid_configurations() {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    assert!(check_config(&configuration).is_ok());
+
+    configuration.retention.frequency = Duration::zero();
+    assert!(check_config(&configuration).is_err());
+}
+
+#[tokio::test]
+async fn stats_count_the_stored_records() {
+    // Arrange
+    let app = spawn_app().await;
+    let empty = stats(&app.db_pool).await.unwrap();
+    for r in [
+        record("r1", 1),
+        record("r2", 2),
+        RecordTest::new()
+            .with_record_id("open")
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2022-01-01T00:00:00Z"),
+    ] {
+        assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    }
+
+    // Act
+    let stats = stats(&app.db_pool).await.unwrap();
+
+    // Assert
+    assert_eq!(0, empty.records);
+    assert_eq!(None, empty.earliest_start_time);
+    assert_eq!(3, stats.records);
+    assert_eq!(1, stats.open_records);
+    assert_eq!(
+        Some("2022-01-01T00:00:00+00:00".to_string()),
+        stats.earliest_start_time.map(|time| time.to_rfc3339())
+    );
+    assert!(stats.partitions > 0);
+    assert!(stats.size_bytes > 0);
+    assert_eq!(stats.latest_migration, stats.applied_migration);
+}
+
+#[tokio::test]
+async fn vacuum_retention_deletes_old_records() {
+    // Arrange
+    let app = spawn_app().await;
+    for r in [record("old", 100), record("recent", 1)] {
+        assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    }
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.retention.policies = vec![RetentionPolicy {
+        older_than_months: 0,
+        older_than_days: 30,
+        meta: HashMap::new(),
+    }];
+
+    // Act
+    let deleted = vacuum_retention(&app.db_pool, &configuration)
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(1, deleted);
+    let (records, _) = app.get_records().await.unwrap();
+    assert_eq!(
+        vec!["recent".to_string()],
+        records.into_iter().map(|r| r.record_id).collect::<Vec<_>>()
+    );
+}
@@ -1,5 +1,5 @@
 use crate::helpers::spawn_app;
-use auditor::domain::{Record, RecordTest};
+use auditor::domain::{Record, RecordTest, ScoreTest};
 use chrono::{TimeZone, Utc};
 use fake::{Fake, Faker};
 use std::collections::HashMap;
@@ -97,7 +97,7 @@ async fn get_started_since_returns_a_list_of_sorted_records() {
             .skip(usize::try_from(i).unwrap() - 1)
             .cloned()
             .collect::<Vec<_>>();
-        tmp_test_cases.sort_by(|a, b| a.stop_time.cmp(&b.stop_time));
+        tmp_test_cases.sort_by_key(|a| a.stop_time);
 
         for (j, (record, received)) in tmp_test_cases
             .iter()
@@ -226,7 +226,7 @@ async fn get_stopped_since_returns_a_list_of_sorted_records() {
             .skip(usize::try_from(i).unwrap() - 1)
             .cloned()
             .collect::<Vec<_>>();
-        tmp_test_cases.sort_by(|a, b| a.stop_time.cmp(&b.stop_time));
+        tmp_test_cases.sort_by_key(|a| a.stop_time);
 
         for (j, (record, received)) in tmp_test_cases
             .iter()
@@ -353,6 +353,7 @@ async fn get_component_query_returns_a_200_and_list_of_records() {
         let (mut received_records, status) = app.advanced_queries(query).await.unwrap();
 
         assert_eq!(200, status);
+        assert_eq!(test_cases.len(), received_records.len());
 
         // make sure they are both sorted
         received_records.sort_by(|a, b| a.record_id.cmp(&b.record_id));
@@ -369,6 +370,187 @@ async fn get_component_query_returns_a_200_and_list_of_records() {
     }
 }
 
+#[tokio::test]
+async fn component_query_matches_any_component() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = [
+        RecordTest::new()
+            .with_record_id("r1")
+            .with_component("CPU", 2, vec![])
+            .with_component("MEM", 1024, vec![]),
+        RecordTest::new()
+            .with_record_id("r2")
+            .with_component("MEM", 4096, vec![])
+            .with_component("CPU", 8, vec![]),
+        RecordTest::new()
+            .with_record_id("r3")
+            .with_component("GPU", 1, vec![])
+            .with_component("CPU", 4, vec![])
+            .with_component("CPU", 16, vec![]),
+    ]
+    .map(|r| {
+        r.with_start_time("2022-10-01T12:00:00-00:00")
+            .with_stop_time("2022-10-01T13:00:00-00:00")
+    });
+
+    for case in test_cases.iter() {
+        let response = app.add_record(&case).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    for (query, expected) in [
+        ("component[MEM][gte]=2048", vec!["r2"]),
+        ("component[CPU][gte]=4", vec!["r2", "r3"]),
+        ("component[CPU][equals]=16", vec!["r3"]),
+        // Both bounds have to be satisfied by the same component
+        ("component[CPU][gt]=4&component[CPU][lt]=16", vec!["r2"]),
+        ("component[CPU][gte]=2&component[MEM][lt]=2048", vec!["r1"]),
+        ("component[CPU][gte]=2&component[GPU][gte]=1", vec!["r3"]),
+        ("component[DISK][gte]=0", vec![]),
+    ] {
+        // Act
+        let (received_records, status) = app.advanced_queries(query).await.unwrap();
+
+        // Assert
+        assert_eq!(200, status);
+        let mut record_ids: Vec<_> = received_records.into_iter().map(|r| r.record_id).collect();
+        record_ids.sort();
+        assert_eq!(
+            expected, record_ids,
+            "Query {query} returned wrong records."
+        );
+    }
+}
+
+#[tokio::test]
+async fn score_query_returns_records_with_matching_scores() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let score = |name: &str, value: f64| {
+        ScoreTest::new()
+            .with_name(name.to_string())
+            .with_value(value)
+    };
+    let test_cases = [
+        RecordTest::new().with_record_id("r1").with_component(
+            "CPU",
+            4,
+            vec![score("HEPSPEC06", 8.0)],
+        ),
+        RecordTest::new().with_record_id("r2").with_component(
+            "CPU",
+            8,
+            vec![score("HEPSPEC06", 12.5), score("HEPscore23", 15.0)],
+        ),
+        RecordTest::new()
+            .with_record_id("r3")
+            .with_component("CPU", 2, vec![score("HEPSPEC06", 20.0)])
+            .with_component("MEM", 1024, vec![score("HEPSPEC06", 1.0)]),
+        RecordTest::new()
+            .with_record_id("r4")
+            .with_component("CPU", 16, vec![]),
+    ]
+    .map(|r| {
+        r.with_start_time("2022-10-01T12:00:00-00:00")
+            .with_stop_time("2022-10-01T13:00:00-00:00")
+    });
+
+    for case in test_cases.iter() {
+        let response = app.add_record(&case).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    for (query, expected) in [
+        ("component[CPU][score][HEPSPEC06][gte]=10", vec!["r2", "r3"]),
+        ("component[CPU][score][HEPSPEC06][lt]=10", vec!["r1"]),
+        ("component[CPU][score][HEPSPEC06][equals]=12.5", vec!["r2"]),
+        ("component[MEM][score][HEPSPEC06][gte]=1", vec!["r3"]),
+        ("component[CPU][score][HEPscore23][gt]=0", vec!["r2"]),
+        // Amount and score have to be satisfied by the same component
+        (
+            "component[CPU][gte]=4&component[CPU][score][HEPSPEC06][gte]=10",
+            vec!["r2"],
+        ),
+        (
+            "component[CPU][score][HEPSPEC06][gt]=10&component[CPU][score][HEPscore23][gt]=10",
+            vec!["r2"],
+        ),
+        (
+            "component[CPU][score][HEPSPEC06][gt]=8&component[CPU][score][HEPSPEC06][lt]=20",
+            vec!["r2"],
+        ),
+        ("component[GPU][score][HEPSPEC06][gte]=0", vec![]),
+    ] {
+        // Act
+        let (received_records, status) = app.advanced_queries(query).await.unwrap();
+
+        // Assert
+        assert_eq!(200, status);
+        let mut record_ids: Vec<_> = received_records.into_iter().map(|r| r.record_id).collect();
+        record_ids.sort();
+        assert_eq!(
+            expected, record_ids,
+            "Query {query} returned wrong records."
+        );
+    }
+}
+
+#[tokio::test]
+async fn invalid_score_query_returns_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for query in [
+        "component[CPU][score][HEPSPEC06][gte]=-1",
+        "component[CPU][score][HEPSPEC06][between]=1",
+        "component[CPU][scores][HEPSPEC06][gte]=1",
+    ] {
+        // Act
+        let response = reqwest::Client::new()
+            .get(format!("{}/records?{}", &app.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "Query {query} was accepted."
+        );
+    }
+}
+
+#[tokio::test]
+async fn filters_without_condition_return_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for query in [
+        "start_time[equals]=2022-10-01T12:00:00Z",
+        "runtime[gt]=1&runtime[gte]=2",
+        "meta[site_id][foo]=site1",
+        "component[CPU][score][HEPSPEC06][lt]=1&component[CPU][score][HEPSPEC06][lte]=2",
+    ] {
+        // Act
+        let response = reqwest::Client::new()
+            .get(format!("{}/records?{}", &app.address, query))
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "Query {query} was accepted."
+        );
+    }
+}
+
 #[tokio::test]
 async fn sort_by_returns_a_200_and_list_of_records() {
     // Arrange
@@ -507,3 +689,38 @@ async fn exact_record_id_returns_a_200_and_list_of_records() {
 
     assert_eq!(received_record.record_id, "r3".to_string());
 }
+
+#[tokio::test]
+async fn runtime_query_returns_a_200_and_list_of_records() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Records with a runtime of 1, 2, ..., 9 hours
+    let test_cases = (1..10)
+        .map(|i| {
+            Faker
+                .fake::<RecordTest>()
+                .with_record_id(format!("r{i}"))
+                .with_start_time("2022-10-01T00:00:00-00:00")
+                .with_stop_time(format!("2022-10-01T0{i}:00:00-00:00"))
+        })
+        .collect::<Vec<_>>();
+
+    for case in test_cases.iter() {
+        let response = app.add_record(&case).await;
+
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    let query = "runtime[gt]=7200&runtime[lte]=18000".to_string();
+
+    let (received_records, status) = app.advanced_queries(query).await.unwrap();
+
+    assert_eq!(200, status);
+
+    let received_ids = received_records
+        .iter()
+        .map(|r| r.record_id.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(vec!["r3", "r4", "r5"], received_ids);
+}
@@ -0,0 +1,152 @@
+use crate::helpers::spawn_app;
+use auditor::domain::{RecordTest, ScoreTest, UsageAggregate};
+use std::collections::HashMap;
+
+fn record(
+    record_id: &str,
+    site: &str,
+    cpu: i64,
+    hepspec: f64,
+    start_time: &str,
+    stop_time: &str,
+) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec![site])]))
+        .with_component(
+            "CPU",
+            cpu,
+            vec![
+                ScoreTest::new()
+                    .with_name("HEPSPEC06".to_string())
+                    .with_value(hepspec),
+            ],
+        )
+        .with_component("MEM", 1024, vec![])
+        .with_start_time(start_time)
+        .with_stop_time(stop_time)
+}
+
+#[tokio::test]
+async fn aggregate_returns_a_200_and_sums_per_meta_key() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let records = [
+        record(
+            "r1",
+            "site1",
+            2,
+            10.0,
+            "2022-10-01T12:00:00-00:00",
+            "2022-10-01T13:00:00-00:00",
+        ),
+        record(
+            "r2",
+            "site1",
+            4,
+            5.0,
+            "2022-10-01T12:00:00-00:00",
+            "2022-10-01T12:30:00-00:00",
+        ),
+        record(
+            "r3",
+            "site2",
+            8,
+            1.0,
+            "2022-10-02T12:00:00-00:00",
+            "2022-10-02T12:00:10-00:00",
+        ),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app.aggregate("group_by[]=site_id").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let aggregates: Vec<UsageAggregate> = response.json().await.unwrap();
+    assert_eq!(2, aggregates.len());
+
+    let site1 = &aggregates[0];
+    assert_eq!(Some(&Some("site1".to_string())), site1.group.get("site_id"));
+    assert_eq!(2, site1.record_count);
+    assert_eq!(3600 + 1800, site1.runtime);
+    assert_eq!(2, site1.components.len());
+    let cpu = &site1.components[0];
+    assert_eq!("CPU", cpu.name);
+    assert_eq!(6.0, cpu.amount);
+    assert_eq!((2 * 3600 + 4 * 1800) as f64, cpu.amount_runtime);
+    assert_eq!(1, cpu.scores.len());
+    assert_eq!("HEPSPEC06", cpu.scores[0].name);
+    assert_eq!(
+        2.0 * 3600.0 * 10.0 + 4.0 * 1800.0 * 5.0,
+        cpu.scores[0].amount_runtime_score
+    );
+    let mem = &site1.components[1];
+    assert_eq!("MEM", mem.name);
+    assert_eq!(2048.0, mem.amount);
+    assert!(mem.scores.is_empty());
+
+    let site2 = &aggregates[1];
+    assert_eq!(Some(&Some("site2".to_string())), site2.group.get("site_id"));
+    assert_eq!(1, site2.record_count);
+    assert_eq!(10, site2.runtime);
+    assert_eq!(80.0, site2.components[0].amount_runtime);
+}
+
+#[tokio::test]
+async fn aggregate_applies_filters() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for (i, site) in ["site1", "site2", "site1"].iter().enumerate() {
+        let r = record(
+            &format!("r{i}"),
+            site,
+            1,
+            1.0,
+            &format!("2022-10-0{}T12:00:00-00:00", i + 1),
+            &format!("2022-10-0{}T13:00:00-00:00", i + 1),
+        );
+        assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app.aggregate("meta[site_id][c]=site1").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let aggregates: Vec<UsageAggregate> = response.json().await.unwrap();
+    assert_eq!(1, aggregates.len());
+    assert!(aggregates[0].group.is_empty());
+    assert_eq!(2, aggregates[0].record_count);
+    assert_eq!(7200, aggregates[0].runtime);
+}
+
+#[tokio::test]
+async fn aggregate_returns_a_400_for_invalid_query() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for query in [
+        "group_by=site_id",
+        "foo=bar",
+        "group_by[]=site_id&foo=bar",
+        // Aggregations cannot be paginated
+        "group_by[]=site_id&limit=10",
+        "sort_by[asc]=start_time",
+    ] {
+        // Act
+        let response = app.aggregate(query).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request for query {query}."
+        );
+    }
+}
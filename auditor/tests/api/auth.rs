@@ -0,0 +1,283 @@
+use crate::helpers::{TestApp, spawn_app_with};
+use auditor::auth::{Role, hash_token};
+use auditor::configuration::ApiTokenSettings;
+use auditor::domain::RecordTest;
+use fake::{Fake, Faker};
+
+async fn spawn_app_with_tokens() -> TestApp {
+    spawn_app_with(|config| {
+        config.auth.enabled = true;
+        config.auth.tokens = [
+            ("admin", Role::Admin),
+            ("collector", Role::Collector),
+            ("reader", Role::Reader),
+        ]
+        .into_iter()
+        .map(|(name, role)| ApiTokenSettings {
+            name: name.to_string(),
+            role,
+            token_sha256: hash_token(&format!("{name}-token")),
+            sites: None,
+        })
+        .collect();
+    })
+    .await
+}
+
+fn request(
+    app: &TestApp,
+    method: reqwest::Method,
+    path: &str,
+    token: Option<&str>,
+) -> reqwest::RequestBuilder {
+    let request = reqwest::Client::new().request(method, format!("{}{}", app.address, path));
+    match token {
+        Some(token) => request.bearer_auth(token),
+        None => request,
+    }
+}
+
+#[tokio::test]
+async fn requests_without_valid_token_are_rejected() {
+    // Arrange
+    let app = spawn_app_with_tokens().await;
+
+    for token in [None, Some("unknown")] {
+        // Act
+        let response = request(&app, reqwest::Method::GET, "/records", token)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(401, response.status().as_u16());
+        assert_eq!(
+            Some("Bearer"),
+            response
+                .headers()
+                .get("WWW-Authenticate")
+                .and_then(|v| v.to_str().ok())
+        );
+    }
+
+    // The health check does not require a token
+    assert!(app.health_check().await.status().is_success());
+}
+
+#[tokio::test]
+async fn roles_restrict_access() {
+    // Arrange
+    let app = spawn_app_with_tokens().await;
+    let record = Faker.fake::<RecordTest>().with_record_id("r1");
+
+    // Act & Assert
+    let response = request(&app, reqwest::Method::POST, "/record", Some("reader-token"))
+        .json(&record)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+
+    let response = request(
+        &app,
+        reqwest::Method::POST,
+        "/record",
+        Some("collector-token"),
+    )
+    .json(&record)
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = request(
+        &app,
+        reqwest::Method::GET,
+        "/records",
+        Some("collector-token"),
+    )
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+
+    let response = request(&app, reqwest::Method::GET, "/records", Some("reader-token"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = request(
+        &app,
+        reqwest::Method::DELETE,
+        "/record/r1",
+        Some("collector-token"),
+    )
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+
+    let response = request(
+        &app,
+        reqwest::Method::DELETE,
+        "/record/r1",
+        Some("admin-token"),
+    )
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn tokens_stored_in_database_can_be_created_and_revoked() {
+    // Arrange
+    let app = spawn_app_with_tokens().await;
+
+    let response = request(&app, reqwest::Method::POST, "/tokens", Some("reader-token"))
+        .json(&serde_json::json!({"name": "site1", "role": "reader"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+
+    // Act
+    let response = request(&app, reqwest::Method::POST, "/tokens", Some("admin-token"))
+        .json(&serde_json::json!({"name": "site1", "role": "reader"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let created: serde_json::Value = response.json().await.unwrap();
+    let token = created["token"].as_str().unwrap().to_string();
+
+    // Assert
+    let stored: (String, String) =
+        sqlx::query_as("SELECT token_hash, role FROM api_tokens WHERE name = 'site1'")
+            .fetch_one(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(stored, (hash_token(&token), "reader".to_string()));
+
+    let response = request(&app, reqwest::Method::GET, "/records", Some(&token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = request(&app, reqwest::Method::POST, "/tokens", Some("admin-token"))
+        .json(&serde_json::json!({"name": "site1", "role": "admin"}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(409, response.status().as_u16());
+
+    let response = request(&app, reqwest::Method::GET, "/tokens", Some("admin-token"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let tokens: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(tokens[0]["name"], "site1");
+    assert_eq!(tokens[0]["role"], "reader");
+
+    let response = request(
+        &app,
+        reqwest::Method::DELETE,
+        "/tokens/site1",
+        Some("admin-token"),
+    )
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = request(&app, reqwest::Method::GET, "/records", Some(&token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(401, response.status().as_u16());
+
+    let response = request(
+        &app,
+        reqwest::Method::DELETE,
+        "/tokens/site1",
+        Some("admin-token"),
+    )
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn tokens_stored_in_database_can_be_restricted_to_sites() {
+    // Arrange
+    let app = spawn_app_with_tokens().await;
+    let records = ["site-a", "site-b"].map(|site| {
+        RecordTest::new()
+            .with_record_id(format!("{site}-record"))
+            .with_meta(std::collections::HashMap::from([("site_id", vec![site])]))
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2024-01-01T00:00:00Z")
+            .with_stop_time("2024-01-01T01:00:00Z")
+    });
+    let response = request(
+        &app,
+        reqwest::Method::POST,
+        "/records",
+        Some("collector-token"),
+    )
+    .json(&records)
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = request(&app, reqwest::Method::POST, "/tokens", Some("admin-token"))
+        .json(&serde_json::json!({"name": "reader-a", "role": "reader", "sites": []}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(400, response.status().as_u16());
+
+    // Act
+    let response = request(&app, reqwest::Method::POST, "/tokens", Some("admin-token"))
+        .json(&serde_json::json!({"name": "reader-a", "role": "reader", "sites": ["site-a"]}))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let created: serde_json::Value = response.json().await.unwrap();
+    let token = created["token"].as_str().unwrap().to_string();
+
+    // Assert
+    assert_eq!(created["sites"], serde_json::json!(["site-a"]));
+    let response = request(&app, reqwest::Method::GET, "/records", Some(&token))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+    let stored: Vec<serde_json::Value> = response.json().await.unwrap();
+    assert_eq!(stored.len(), 1);
+    assert_eq!(stored[0]["record_id"], "site-a-record");
+
+    let response = request(
+        &app,
+        reqwest::Method::GET,
+        "/record/site-b-record",
+        Some(&token),
+    )
+    .send()
+    .await
+    .expect("Failed to execute request.");
+    assert_eq!(403, response.status().as_u16());
+
+    let response = request(&app, reqwest::Method::GET, "/tokens", Some("admin-token"))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    let tokens: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(tokens[0]["name"], "reader-a");
+    assert_eq!(tokens[0]["sites"], serde_json::json!(["site-a"]));
+}
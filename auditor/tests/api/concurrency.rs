@@ -0,0 +1,128 @@
+use crate::helpers::spawn_app;
+use auditor::domain::{ConcurrentUsage, RecordTest};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+fn record(record_id: &str, site: &str, cpu: i64, start_time: &str) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec![site])]))
+        .with_component("CPU", cpu, vec![])
+        .with_start_time(start_time)
+}
+
+fn time(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+#[tokio::test]
+async fn concurrency_returns_a_200_and_running_records_per_sample_point() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let records = [
+        record("r1", "site1", 2, "2022-10-01T12:00:00-00:00")
+            .with_stop_time("2022-10-01T13:00:00-00:00"),
+        record("r2", "site1", 4, "2022-10-01T12:30:00-00:00")
+            .with_stop_time("2022-10-01T14:00:00-00:00"),
+        // Still running
+        record("r3", "site2", 8, "2022-10-01T13:00:00-00:00"),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app
+        .concurrency(
+            "from=2022-10-01T12:00:00Z&to=2022-10-01T14:00:00Z&step=3600&group_by[]=site_id",
+        )
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let usage: Vec<ConcurrentUsage> = response.json().await.unwrap();
+    assert_eq!(3, usage.len());
+
+    assert_eq!(time("2022-10-01T12:00:00Z"), usage[0].time);
+    assert_eq!(1, usage[0].usage.len());
+    assert_eq!(1, usage[0].usage[0].record_count);
+    assert_eq!(2.0, usage[0].usage[0].components[0].amount);
+
+    // r1 stopped at 13:00, r3 started at 13:00
+    assert_eq!(time("2022-10-01T13:00:00Z"), usage[1].time);
+    assert_eq!(2, usage[1].usage.len());
+    let site1 = &usage[1].usage[0];
+    assert_eq!(Some(&Some("site1".to_string())), site1.group.get("site_id"));
+    assert_eq!(1, site1.record_count);
+    assert_eq!(4.0, site1.components[0].amount);
+    let site2 = &usage[1].usage[1];
+    assert_eq!(Some(&Some("site2".to_string())), site2.group.get("site_id"));
+    assert_eq!(1, site2.record_count);
+    assert_eq!(8.0, site2.components[0].amount);
+
+    // r2 stopped at 14:00
+    assert_eq!(1, usage[2].usage.len());
+    assert_eq!(
+        Some(&Some("site2".to_string())),
+        usage[2].usage[0].group.get("site_id")
+    );
+}
+
+#[tokio::test]
+async fn concurrency_at_single_point_in_time_applies_filters() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let records = [
+        record("r1", "site1", 2, "2022-10-01T12:00:00-00:00")
+            .with_stop_time("2022-10-01T13:00:00-00:00"),
+        record("r2", "site1", 4, "2022-10-01T11:00:00-00:00"),
+        record("r3", "site2", 8, "2022-10-01T11:00:00-00:00"),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app
+        .concurrency("at=2022-10-01T12:30:00Z&meta[site_id][c]=site1")
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let usage: Vec<ConcurrentUsage> = response.json().await.unwrap();
+    assert_eq!(1, usage.len());
+    assert_eq!(1, usage[0].usage.len());
+    assert!(usage[0].usage[0].group.is_empty());
+    assert_eq!(2, usage[0].usage[0].record_count);
+    assert_eq!(6.0, usage[0].usage[0].components[0].amount);
+}
+
+#[tokio::test]
+async fn concurrency_returns_a_400_for_invalid_query() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for query in [
+        "",
+        "from=2022-01-01T00:00:00Z&to=2022-02-01T00:00:00Z",
+        "at=2022-01-01T00:00:00Z&from=2022-01-01T00:00:00Z",
+        "from=2022-02-01T00:00:00Z&to=2022-01-01T00:00:00Z&step=60",
+        "from=2022-01-01T00:00:00Z&to=2022-02-01T00:00:00Z&step=0",
+        "from=2000-01-01T00:00:00Z&to=2022-02-01T00:00:00Z&step=1",
+        "at=2022-01-01T00:00:00Z&foo=bar",
+        "at=2022-01-01T00:00:00Z&limit=10",
+        "at=2022-01-01T00:00:00Z&sort_by[desc]=stop_time",
+    ] {
+        // Act
+        let response = app.concurrency(query).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request for query {query}."
+        );
+    }
+}
@@ -0,0 +1,107 @@
+use crate::helpers::{TestApp, spawn_app};
+use auditor::configuration::get_configuration;
+use auditor::domain::{DailyUsage, RecordTest};
+use auditor::rollup::DailyUsageRollup;
+use chrono::NaiveDate;
+use std::collections::HashMap;
+
+fn record(record_id: &str, site: &str, stop_time: &str, cores: i64) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([
+            ("site_id", vec![site]),
+            ("user_id", vec!["alice"]),
+        ]))
+        .with_component("CPU", cores, vec![])
+        .with_start_time("2024-01-01T00:00:00Z")
+        .with_stop_time(stop_time)
+}
+
+async fn daily_usage(app: &TestApp, query: &str) -> Vec<DailyUsage> {
+    let response = app.daily_usage(query).await;
+    assert_eq!(200, response.status().as_u16());
+    response.json().await.unwrap()
+}
+
+#[tokio::test]
+async fn daily_usage_follows_changes_of_records() {
+    // Arrange
+    let app = spawn_app().await;
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    let rollup = DailyUsageRollup::new(app.db_pool.clone(), &configuration).unwrap();
+    let records = [
+        record("r1", "site1", "2024-01-01T01:00:00Z", 2),
+        record("r2", "site1", "2024-01-01T02:00:00Z", 1),
+        record("r3", "site2", "2024-01-02T01:00:00Z", 4),
+        // Open records are not included
+        RecordTest::new()
+            .with_record_id("open")
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2024-01-01T00:00:00Z"),
+    ];
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    // Act
+    assert_eq!(2, rollup.update().await.unwrap());
+
+    // Assert
+    let usage = daily_usage(&app, "from=2024-01-01&to=2024-02-01").await;
+    let summary: Vec<_> = usage
+        .iter()
+        .map(|u| {
+            (
+                u.date.to_string(),
+                u.site_id.clone().unwrap(),
+                u.record_count,
+                u.amount_runtime,
+            )
+        })
+        .collect();
+    assert_eq!(
+        summary,
+        vec![
+            ("2024-01-01".to_string(), "site1".to_string(), 2, 14400.0),
+            ("2024-01-02".to_string(), "site2".to_string(), 1, 360000.0),
+        ]
+    );
+    assert_eq!(usage[0].user_id.as_deref(), Some("alice"));
+    assert_eq!(usage[0].group_id, None);
+    assert_eq!(usage[0].component.as_deref(), Some("CPU"));
+
+    // Updates and deletions are reflected after the next update
+    let response = app
+        .bulk_update(&[record("r2", "site1", "2024-01-02T02:00:00Z", 1)])
+        .await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(200, app.delete_record("r3").await.status().as_u16());
+    assert_eq!(2, rollup.update().await.unwrap());
+    assert_eq!(0, rollup.update().await.unwrap());
+
+    let usage = daily_usage(&app, "from=2024-01-01&to=2024-02-01&bucket=month").await;
+    assert_eq!(1, usage.len());
+    assert_eq!(usage[0].date, NaiveDate::from_ymd_opt(2024, 1, 1).unwrap());
+    assert_eq!(usage[0].record_count, 2);
+    assert_eq!(usage[0].amount_runtime, 7200.0 + 93600.0);
+
+    let usage = daily_usage(&app, "from=2024-01-01&to=2024-02-01&site_id=site2").await;
+    assert!(usage.is_empty());
+}
+
+#[tokio::test]
+async fn daily_usage_rejects_invalid_queries() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for query in [
+        "from=2024-02-01&to=2024-01-01",
+        "from=2024-01-01&to=2024-02-01&bucket=hour",
+        "from=2024-01-01",
+        "from=2024-01-01&to=2024-02-01&unknown=1",
+    ] {
+        // Act
+        let response = app.daily_usage(query).await;
+
+        // Assert
+        assert_eq!(400, response.status().as_u16(), "{query}");
+    }
+}
@@ -0,0 +1,153 @@
+use crate::helpers::spawn_app;
+use auditor::domain::{DeletedRecords, RecordTest};
+use auditor::routes::{DeleteRecordsError, Filters, delete_records};
+use std::collections::HashMap;
+
+fn record(record_id: &str, site: &str) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec![site])]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time("2022-10-01T12:00:00-00:00")
+}
+
+#[tokio::test]
+async fn delete_returns_a_200_and_removes_the_record() {
+    // Arrange
+    let app = spawn_app().await;
+    for r in [record("r1", "site1"), record("r2", "site1")].iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app.delete_record("r1").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let (records, _) = app.get_records().await.unwrap();
+    assert_eq!(1, records.len());
+    assert_eq!("r2", records[0].record_id);
+}
+
+#[tokio::test]
+async fn delete_returns_a_404_for_non_existing_record() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.delete_record("r1").await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn bulk_delete_removes_matching_records_including_open_ones() {
+    // Arrange
+    let app = spawn_app().await;
+    let records = [
+        record("r1", "site1").with_stop_time("2022-10-01T13:00:00-00:00"),
+        record("r2", "site1"),
+        record("r3", "site2").with_stop_time("2022-10-01T13:00:00-00:00"),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app.delete_records("meta[site_id][c]=site1").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let deleted: DeletedRecords = response.json().await.unwrap();
+    assert_eq!(2, deleted.deleted);
+    let (records, _) = app.get_records().await.unwrap();
+    assert_eq!(1, records.len());
+    assert_eq!("r3", records[0].record_id);
+}
+
+#[tokio::test]
+async fn bulk_delete_returns_a_400_for_invalid_query() {
+    // Arrange
+    let app = spawn_app().await;
+    assert_eq!(
+        200,
+        app.add_record(&record("r1", "site1"))
+            .await
+            .status()
+            .as_u16()
+    );
+
+    for query in ["", "foo=bar", "limit=1", "sort_by[asc]=start_time"] {
+        // Act
+        let response = app.delete_records(query).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request for query {query}."
+        );
+    }
+    let (records, _) = app.get_records().await.unwrap();
+    assert_eq!(1, records.len());
+}
+
+#[tokio::test]
+async fn bulk_delete_rejects_filters_without_condition() {
+    // Arrange
+    let app = spawn_app().await;
+    let records = [
+        record("r1", "site1").with_stop_time("2022-10-01T13:00:00-00:00"),
+        record("r2", "site1"),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // All of these used to be ignored, which deleted all records
+    for query in [
+        "start_time[equals]=2022-10-01T12:00:00Z",
+        "stop_time[equals]=2022-10-01T13:00:00Z",
+        "runtime[gt]=1&runtime[gte]=2",
+        "runtime[lt]=1&runtime[lte]=2",
+        "meta[site_id][foo]=site1",
+        "component[CPU][score]=1",
+        "component[CPU][score][HEPSPEC06][gt]=1&component[CPU][score][HEPSPEC06][gte]=2",
+    ] {
+        // Act
+        let response = app.delete_records(query).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request for query {query}."
+        );
+        let (records, _) = app.get_records().await.unwrap();
+        assert_eq!(2, records.len(), "Records were deleted by query {query}.");
+    }
+}
+
+#[tokio::test]
+async fn delete_records_requires_a_filter() {
+    // Arrange
+    let app = spawn_app().await;
+    assert_eq!(
+        200,
+        app.add_record(&record("r1", "site1"))
+            .await
+            .status()
+            .as_u16()
+    );
+    let mut filters = Filters::parse("").unwrap();
+    filters.sites = Some(vec!["site1".to_string()]);
+
+    // Act
+    let result = delete_records(&filters, None, &app.db_pool).await;
+
+    // Assert
+    assert!(matches!(result, Err(DeleteRecordsError::NoFilter)));
+    let (records, _) = app.get_records().await.unwrap();
+    assert_eq!(1, records.len());
+}
@@ -0,0 +1,273 @@
+use crate::helpers::{TestApp, spawn_app};
+use auditor::constants::HEADER_NEXT_CURSOR;
+use auditor::domain::{Record, RecordTest, ScoreTest};
+use parquet::file::reader::{FileReader, SerializedFileReader};
+use parquet::record::RowAccessor;
+use std::collections::HashMap;
+
+async fn get_records_as(app: &TestApp, query: &str, accept: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!("{}/records?{}", &app.address, query))
+        .header("Accept", accept)
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Adds three records with different meta information and components.
+async fn add_records(app: &TestApp) {
+    let records = [
+        RecordTest::new()
+            .with_record_id("r1")
+            .with_meta(HashMap::from([
+                ("site_id", vec!["site1"]),
+                ("group_id", vec!["g1", "g2"]),
+            ]))
+            .with_component(
+                "cpu",
+                2,
+                vec![
+                    ScoreTest::new()
+                        .with_name("hepspec".to_string())
+                        .with_value(9.5),
+                ],
+            )
+            .with_component("cpu", 3, vec![])
+            .with_start_time("2022-10-01T12:00:00-00:00")
+            .with_stop_time("2022-10-01T13:00:00-00:00"),
+        RecordTest::new()
+            .with_record_id("r2")
+            .with_meta(HashMap::from([("site_id", vec!["site2"])]))
+            .with_component("mem", 1024, vec![])
+            .with_start_time("2022-10-02T12:00:00-00:00")
+            .with_stop_time("2022-10-02T12:30:00-00:00"),
+        RecordTest::new()
+            .with_record_id("r3")
+            .with_component("cpu", 1, vec![])
+            .with_start_time("2022-10-03T12:00:00-00:00")
+            .with_stop_time("2022-10-03T12:00:10-00:00"),
+    ];
+    for record in records.iter() {
+        let response = app.add_record(record).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+}
+
+#[tokio::test]
+async fn ndjson_returns_one_record_per_line() {
+    // Arrange
+    let app = spawn_app().await;
+    add_records(&app).await;
+
+    // Act
+    let response = get_records_as(&app, "", "application/x-ndjson").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        response.headers()["Content-Type"].to_str().unwrap(),
+        "application/x-ndjson"
+    );
+    let body = response.text().await.unwrap();
+    let record_ids = body
+        .lines()
+        .map(|line| serde_json::from_str::<Record>(line).unwrap().record_id)
+        .collect::<Vec<_>>();
+    assert_eq!(record_ids, vec!["r1", "r2", "r3"]);
+}
+
+#[tokio::test]
+async fn csv_flattens_meta_and_components() {
+    // Arrange
+    let app = spawn_app().await;
+    add_records(&app).await;
+
+    for query in ["", "limit=10"] {
+        // Act
+        let response = get_records_as(&app, query, "text/csv").await;
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!(
+            response.headers()["Content-Type"].to_str().unwrap(),
+            "text/csv"
+        );
+        let body = response.text().await.unwrap();
+        let lines = body.lines().collect::<Vec<_>>();
+        assert_eq!(
+            lines,
+            vec![
+                "record_id,start_time,stop_time,runtime,meta.group_id,meta.site_id,\
+                 component.cpu,component.mem,component.cpu.hepspec",
+                "r1,2022-10-01T12:00:00+00:00,2022-10-01T13:00:00+00:00,3600,g1;g2,site1,5,,9.5",
+                "r2,2022-10-02T12:00:00+00:00,2022-10-02T12:30:00+00:00,1800,,site2,,1024,",
+                "r3,2022-10-03T12:00:00+00:00,2022-10-03T12:00:10+00:00,10,,,1,,",
+            ]
+        );
+    }
+}
+
+#[tokio::test]
+async fn csv_only_contains_columns_of_filtered_records() {
+    // Arrange
+    let app = spawn_app().await;
+    add_records(&app).await;
+
+    // Act
+    let response = get_records_as(&app, "meta[site_id][c]=site2", "text/csv").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let body = response.text().await.unwrap();
+    let lines = body.lines().collect::<Vec<_>>();
+    assert_eq!(
+        lines,
+        vec![
+            "record_id,start_time,stop_time,runtime,meta.site_id,component.mem",
+            "r2,2022-10-02T12:00:00+00:00,2022-10-02T12:30:00+00:00,1800,site2,1024",
+        ]
+    );
+}
+
+#[tokio::test]
+async fn csv_pages_share_the_columns_of_all_records() {
+    // Arrange
+    let app = spawn_app().await;
+    add_records(&app).await;
+
+    // Act
+    let mut pages = Vec::new();
+    let mut query = "limit=1".to_string();
+    loop {
+        let response = get_records_as(&app, &query, "text/csv").await;
+        assert_eq!(200, response.status().as_u16());
+        let next = response
+            .headers()
+            .get(HEADER_NEXT_CURSOR)
+            .map(|cursor| cursor.to_str().unwrap().to_string());
+        pages.push(response.text().await.unwrap());
+        match next {
+            Some(cursor) => query = format!("limit=1&after={cursor}"),
+            None => break,
+        }
+    }
+
+    // Assert
+    let header = "record_id,start_time,stop_time,runtime,meta.group_id,meta.site_id,\
+                  component.cpu,component.mem,component.cpu.hepspec";
+    assert_eq!(pages.len(), 3);
+    for page in &pages {
+        assert_eq!(page.lines().next(), Some(header));
+    }
+    assert_eq!(
+        pages[2].lines().nth(1),
+        Some("r3,2022-10-03T12:00:00+00:00,2022-10-03T12:00:10+00:00,10,,,1,,")
+    );
+}
+
+#[tokio::test]
+async fn parquet_contains_all_records() {
+    // Arrange
+    let app = spawn_app().await;
+    add_records(&app).await;
+
+    // Act
+    let response = get_records_as(&app, "", "application/vnd.apache.parquet").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let body = response.bytes().await.unwrap();
+    assert!(body.starts_with(b"PAR1"));
+
+    let reader = SerializedFileReader::new(body).unwrap();
+    let columns = reader
+        .metadata()
+        .file_metadata()
+        .schema_descr()
+        .columns()
+        .iter()
+        .map(|column| column.name().to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(
+        columns,
+        vec![
+            "record_id",
+            "start_time",
+            "stop_time",
+            "runtime",
+            "meta.group_id",
+            "meta.site_id",
+            "component.cpu",
+            "component.mem",
+            "component.cpu.hepspec",
+        ]
+    );
+
+    let rows = reader
+        .get_row_iter(None)
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(rows.len(), 3);
+    assert_eq!(rows[0].get_string(0).unwrap(), "r1");
+    assert_eq!(rows[0].get_long(3).unwrap(), 3600);
+    assert_eq!(rows[0].get_string(4).unwrap(), "g1;g2");
+    assert_eq!(rows[0].get_long(6).unwrap(), 5);
+    assert_eq!(rows[0].get_double(8).unwrap(), 9.5);
+    assert_eq!(rows[1].get_string(0).unwrap(), "r2");
+    assert!(rows[1].get_long(6).is_err());
+    assert_eq!(rows[1].get_long(7).unwrap(), 1024);
+    assert_eq!(rows[2].get_string(0).unwrap(), "r3");
+}
+
+#[tokio::test]
+async fn json_is_returned_by_default() {
+    // Arrange
+    let app = spawn_app().await;
+    add_records(&app).await;
+
+    for accept in ["*/*", "application/json", "text/html, */*;q=0.1"] {
+        // Act
+        let response = get_records_as(&app, "", accept).await;
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!(
+            response.headers()["Content-Type"].to_str().unwrap(),
+            "application/json"
+        );
+        let records: Vec<Record> = response.json().await.unwrap();
+        assert_eq!(records.len(), 3);
+    }
+}
+
+#[tokio::test]
+async fn json_without_records_is_an_empty_array() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = get_records_as(&app, "", "application/json").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(response.text().await.unwrap(), "[]");
+}
+
+#[tokio::test]
+async fn unsupported_media_type_returns_a_406() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for accept in ["text/html", "application/xml", "application/json;q=0"] {
+        // Act
+        let response = get_records_as(&app, "", accept).await;
+
+        // Assert
+        assert_eq!(
+            406,
+            response.status().as_u16(),
+            "The API did not fail with 406 Not Acceptable for {accept}"
+        );
+    }
+}
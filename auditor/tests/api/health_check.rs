@@ -1,4 +1,5 @@
 use crate::helpers::spawn_app;
+use serde_json::{Value, json};
 
 #[tokio::test]
 async fn health_check_works() {
@@ -9,3 +10,63 @@ async fn health_check_works() {
     assert!(response.status().is_success());
     assert_eq!(Some(0), response.content_length());
 }
+
+#[tokio::test]
+async fn livez_works() {
+    let app = spawn_app().await;
+
+    let response = reqwest::Client::new()
+        .get(format!("{}/livez", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn readyz_returns_a_200_if_all_checks_pass() {
+    let app = spawn_app().await;
+
+    // The database metrics watcher reports on its first iteration
+    let mut response = app.readyz().await;
+    for _ in 0..50 {
+        if response.status().is_success() {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        response = app.readyz().await;
+    }
+
+    assert_eq!(200, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(
+        json!({
+            "ready": true,
+            "database": { "ok": true },
+            "migrations": { "ok": true },
+            "database_metrics": { "ok": true },
+        }),
+        body
+    );
+}
+
+#[tokio::test]
+async fn readyz_returns_a_503_if_migrations_are_missing() {
+    let app = spawn_app().await;
+    sqlx::query(
+        "DELETE FROM _sqlx_migrations WHERE version = (SELECT max(version) FROM _sqlx_migrations)",
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    let response = app.readyz().await;
+
+    assert_eq!(503, response.status().as_u16());
+    let body: Value = response.json().await.unwrap();
+    assert_eq!(json!(false), body["ready"]);
+    assert_eq!(json!(true), body["database"]["ok"]);
+    assert_eq!(json!(false), body["migrations"]["ok"]);
+    assert!(body["migrations"]["error"].is_string(), "{body}");
+}
@@ -1,8 +1,9 @@
-use auditor::configuration::{DatabaseSettings, get_configuration};
+use auditor::configuration::{DatabaseSettings, Settings, TLSParams, get_configuration};
 use auditor::metrics::DatabaseMetricsWatcher;
 use auditor::telemetry::{get_subscriber, init_subscriber};
 use futures::TryStreamExt;
 use once_cell::sync::Lazy;
+use rustls::ServerConfig;
 use sqlx::{Connection, Executor, PgConnection, PgPool};
 use std::net::TcpListener;
 use tracing_subscriber::filter::LevelFilter;
@@ -29,6 +30,8 @@ static TRACING: Lazy<()> = Lazy::new(|| {
 pub struct TestApp {
     pub address: String,
     pub db_pool: PgPool,
+    /// Address of the TLS listener, if the app was spawned with TLS.
+    pub https_address: Option<String>,
 }
 
 impl TestApp {
@@ -40,6 +43,14 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn readyz(&self) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/readyz", self.address))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn add_record<T: serde::Serialize>(&self, record: &T) -> reqwest::Response {
         reqwest::Client::new()
             .post(format!("{}/record", &self.address))
@@ -60,6 +71,50 @@ impl TestApp {
             .expect("Failed to execute request.")
     }
 
+    pub async fn add_record_with<T: serde::Serialize>(
+        &self,
+        record: &T,
+        on_conflict: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/record?on_conflict={}",
+                &self.address, on_conflict
+            ))
+            .header("Content-Type", "application/json")
+            .json(record)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn bulk_insert_with<T: serde::Serialize>(
+        &self,
+        record: &T,
+        on_conflict: &str,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .post(format!(
+                "{}/records?on_conflict={}",
+                &self.address, on_conflict
+            ))
+            .header("Content-Type", "application/json")
+            .json(record)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn bulk_update<T: serde::Serialize>(&self, records: &T) -> reqwest::Response {
+        reqwest::Client::new()
+            .put(format!("{}/records", &self.address))
+            .header("Content-Type", "application/json")
+            .json(records)
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_records(&self) -> Result<(Vec<Record>, u16), anyhow::Error> {
         let response = reqwest::Client::new()
             .get(format!("{}/records", &self.address))
@@ -153,6 +208,83 @@ impl TestApp {
         Ok((items1, status))
     }
 
+    pub async fn aggregate<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        query_string: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/aggregate?{}", &self.address, query_string))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn concurrency<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        query_string: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/concurrency?{}", &self.address, query_string))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn histogram<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        query_string: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/histogram?{}", &self.address, query_string))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn daily_usage<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        query_string: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/daily_usage?{}", &self.address, query_string))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_record<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        record_id: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("{}/record/{}", &self.address, record_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn delete_records<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        query_string: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .delete(format!("{}/records?{}", &self.address, query_string))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
+    pub async fn get_records_page<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        query_string: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/records?{}", &self.address, query_string))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
+
     pub async fn get_single_record<T: AsRef<str> + std::fmt::Display>(
         &self,
         record_id: T,
@@ -163,33 +295,79 @@ impl TestApp {
             .await
             .expect("Failed to execute queries.")
     }
+
+    pub async fn get_record_history<T: AsRef<str> + std::fmt::Display>(
+        &self,
+        record_id: T,
+    ) -> reqwest::Response {
+        reqwest::Client::new()
+            .get(format!("{}/record/{}/history", &self.address, record_id))
+            .send()
+            .await
+            .expect("Failed to execute request.")
+    }
 }
 
 pub async fn spawn_app() -> TestApp {
-    Lazy::force(&TRACING);
+    spawn_app_with(|_| {}).await
+}
+
+/// Spawns an app whose configuration is adjusted by `configure` first.
+pub async fn spawn_app_with(configure: impl FnOnce(&mut Settings)) -> TestApp {
+    spawn(configure, None).await
+}
+
+/// Same as [`spawn_app_with`], but the app additionally listens for TLS connections.
+pub async fn spawn_app_with_tls(
+    configure: impl FnOnce(&mut Settings),
+    tls_config: ServerConfig,
+) -> TestApp {
+    spawn(configure, Some(tls_config)).await
+}
 
+fn random_port() -> u16 {
     let listener = TcpListener::bind("127.0.0.1:0").expect("Failed to bind random port");
-    let port = listener.local_addr().unwrap().port();
+    listener.local_addr().unwrap().port()
+}
+
+async fn spawn(configure: impl FnOnce(&mut Settings), tls_config: Option<ServerConfig>) -> TestApp {
+    Lazy::force(&TRACING);
+
+    let port = random_port();
     let address = format!("http://127.0.0.1:{port}");
-    drop(listener);
+    let https_port = random_port();
 
     let mut configuration = get_configuration().expect("Failed to read configuration.");
     configuration.database.database_name = Uuid::new_v4().to_string();
+    configure(&mut configuration);
     let connection_pool = configure_database(&configuration.database).await;
     let db_watcher = DatabaseMetricsWatcher::new(connection_pool.clone(), &configuration).unwrap();
+    let db_watcher_task = db_watcher.clone();
+    tokio::spawn(async move { db_watcher_task.monitor().await });
+    let https_address = tls_config
+        .as_ref()
+        .map(|_| format!("https://localhost:{https_port}"));
+    let tls_params = tls_config.map(|config| TLSParams {
+        config,
+        https_addr: None,
+        https_port,
+        use_tls: true,
+    });
     let server = auditor::startup::run(
         vec!["127.0.0.1".to_string()],
         port,
         4,
         connection_pool.clone(),
         db_watcher,
-        None,
+        tls_params,
+        configuration.auth,
     )
     .expect("Failed to bind address");
     tokio::spawn(server);
     TestApp {
         address,
         db_pool: connection_pool,
+        https_address,
     }
 }
 
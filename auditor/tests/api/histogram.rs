@@ -0,0 +1,156 @@
+use crate::helpers::spawn_app;
+use auditor::domain::{RecordTest, UsageBucket};
+use chrono::{DateTime, Utc};
+use std::collections::HashMap;
+
+fn record(record_id: &str, site: &str, start_time: &str, stop_time: &str) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec![site])]))
+        .with_component("CPU", 2, vec![])
+        .with_start_time(start_time)
+        .with_stop_time(stop_time)
+}
+
+fn time(s: &str) -> DateTime<Utc> {
+    DateTime::parse_from_rfc3339(s).unwrap().with_timezone(&Utc)
+}
+
+#[tokio::test]
+async fn histogram_splits_records_across_bucket_boundaries() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let records = [
+        record(
+            "r1",
+            "site1",
+            "2022-10-01T23:00:00-00:00",
+            "2022-10-02T01:00:00-00:00",
+        ),
+        record(
+            "r2",
+            "site2",
+            "2022-10-01T12:00:00-00:00",
+            "2022-10-01T12:30:00-00:00",
+        ),
+        record(
+            "r3",
+            "site1",
+            "2022-09-30T12:00:00-00:00",
+            "2022-09-30T13:00:00-00:00",
+        ),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app
+        .histogram(
+            "bucket=day&from=2022-10-01T00:00:00Z&to=2022-10-04T00:00:00Z&group_by[]=site_id",
+        )
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let histogram: Vec<UsageBucket> = response.json().await.unwrap();
+    assert_eq!(3, histogram.len());
+    assert_eq!(time("2022-10-01T00:00:00Z"), histogram[0].start);
+    assert_eq!(time("2022-10-02T00:00:00Z"), histogram[0].stop);
+    assert_eq!(time("2022-10-03T00:00:00Z"), histogram[2].start);
+
+    let day1 = &histogram[0].usage;
+    assert_eq!(2, day1.len());
+    assert_eq!(
+        Some(&Some("site1".to_string())),
+        day1[0].group.get("site_id")
+    );
+    assert_eq!(1, day1[0].record_count);
+    assert_eq!(3600, day1[0].runtime);
+    assert_eq!(2.0 * 3600.0, day1[0].components[0].amount_runtime);
+    assert_eq!(
+        Some(&Some("site2".to_string())),
+        day1[1].group.get("site_id")
+    );
+    assert_eq!(1800, day1[1].runtime);
+
+    let day2 = &histogram[1].usage;
+    assert_eq!(1, day2.len());
+    assert_eq!(1, day2[0].record_count);
+    assert_eq!(3600, day2[0].runtime);
+    assert_eq!(2.0 * 3600.0, day2[0].components[0].amount_runtime);
+
+    assert!(histogram[2].usage.is_empty());
+}
+
+#[tokio::test]
+async fn histogram_aligns_buckets_and_applies_filters() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let records = [
+        record(
+            "r1",
+            "site1",
+            "2022-01-31T23:30:00-00:00",
+            "2022-02-01T00:30:00-00:00",
+        ),
+        record(
+            "r2",
+            "site2",
+            "2022-01-15T12:00:00-00:00",
+            "2022-01-15T13:00:00-00:00",
+        ),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    // Act
+    let response = app
+        .histogram(
+            "bucket=month&from=2022-01-20T00:00:00Z&to=2022-02-02T00:00:00Z&meta[site_id][c]=site1",
+        )
+        .await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let histogram: Vec<UsageBucket> = response.json().await.unwrap();
+    assert_eq!(2, histogram.len());
+    assert_eq!(time("2022-01-01T00:00:00Z"), histogram[0].start);
+    assert_eq!(time("2022-02-01T00:00:00Z"), histogram[0].stop);
+    assert_eq!(time("2022-03-01T00:00:00Z"), histogram[1].stop);
+    for bucket in histogram.iter() {
+        assert_eq!(1, bucket.usage.len());
+        assert!(bucket.usage[0].group.is_empty());
+        assert_eq!(1, bucket.usage[0].record_count);
+        assert_eq!(1800, bucket.usage[0].runtime);
+    }
+}
+
+#[tokio::test]
+async fn histogram_returns_a_400_for_invalid_query() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for query in [
+        "from=2022-01-01T00:00:00Z&to=2022-02-01T00:00:00Z",
+        "bucket=week&from=2022-01-01T00:00:00Z&to=2022-02-01T00:00:00Z",
+        "bucket=day&from=2022-02-01T00:00:00Z&to=2022-01-01T00:00:00Z",
+        "bucket=hour&from=2000-01-01T00:00:00Z&to=2022-01-01T00:00:00Z",
+        "bucket=day&from=2022-01-01T00:00:00Z&to=2022-02-01T00:00:00Z&foo=bar",
+        "bucket=day&from=2022-01-01T00:00:00Z&to=2022-02-01T00:00:00Z&limit=10",
+        "bucket=day&from=2022-01-01T00:00:00Z&to=2022-02-01T00:00:00Z&sort_by[asc]=start_time",
+    ] {
+        // Act
+        let response = app.histogram(query).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "The API did not fail with 400 Bad Request for query {query}."
+        );
+    }
+}
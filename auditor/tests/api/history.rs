@@ -0,0 +1,150 @@
+use crate::helpers::{spawn_app, spawn_app_with};
+use auditor::auth::{Role, hash_token};
+use auditor::configuration::ApiTokenSettings;
+use auditor::domain::{ChangeOperation, RecordChange, RecordTest};
+use serde_json::json;
+use std::collections::HashMap;
+
+fn record() -> RecordTest {
+    RecordTest::new()
+        .with_record_id("r1")
+        .with_meta(HashMap::from([("site_id", vec!["site-a"])]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time("2024-01-01T00:00:00Z")
+        .with_stop_time("2024-01-01T01:00:00Z")
+}
+
+#[tokio::test]
+async fn history_contains_inserts_and_changed_values() {
+    // Arrange
+    let app = spawn_app().await;
+    assert_eq!(200, app.add_record(&record()).await.status().as_u16());
+
+    let update = record().with_stop_time("2024-01-01T03:00:00Z");
+    let response = reqwest::Client::new()
+        .put(format!("{}/record", app.address))
+        .json(&update)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    // An update without changes is not part of the history
+    assert_eq!(200, app.bulk_update(&[update]).await.status().as_u16());
+
+    // Act
+    let response = app.get_record_history("r1").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let history: Vec<RecordChange> = response.json().await.unwrap();
+    assert_eq!(history.len(), 2);
+
+    assert_eq!(history[0].operation, ChangeOperation::Insert);
+    assert_eq!(history[0].old, None);
+    assert_eq!(history[0].new["runtime"], json!(3600));
+    assert_eq!(history[0].new["meta"], json!({"site_id": ["site-a"]}));
+    assert_eq!(history[0].changed_by, None);
+
+    assert_eq!(history[1].operation, ChangeOperation::Update);
+    let old = history[1].old.as_ref().unwrap();
+    let mut keys: Vec<&String> = old.keys().collect();
+    keys.sort();
+    assert_eq!(keys, vec!["runtime", "stop_time"]);
+    assert_eq!(old["runtime"], json!(3600));
+    assert_eq!(history[1].new["runtime"], json!(10800));
+    assert_eq!(
+        history[1].new["stop_time"]
+            .as_str()
+            .unwrap()
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap(),
+        "2024-01-01T03:00:00Z"
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .unwrap()
+    );
+    assert!(history[0].changed_at <= history[1].changed_at);
+}
+
+#[tokio::test]
+async fn history_of_unknown_record_returns_a_404() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app.get_record_history("unknown").await;
+
+    // Assert
+    assert_eq!(404, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn history_records_the_authenticated_client() {
+    // Arrange
+    let app = spawn_app_with(|config| {
+        config.auth.enabled = true;
+        config.auth.tokens = [
+            ("collector", Role::Collector, None),
+            ("reader", Role::Reader, None),
+            ("reader-b", Role::Reader, Some(vec!["site-b".to_string()])),
+        ]
+        .into_iter()
+        .map(|(name, role, sites)| ApiTokenSettings {
+            name: name.to_string(),
+            role,
+            token_sha256: hash_token(&format!("{name}-token")),
+            sites,
+        })
+        .collect();
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let response = client
+        .post(format!("{}/record?on_conflict=update", app.address))
+        .bearer_auth("collector-token")
+        .json(&record())
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    // Act
+    let history = |token: &'static str| {
+        client
+            .get(format!("{}/record/r1/history", app.address))
+            .bearer_auth(token)
+            .send()
+    };
+    let response = history("reader-token").await.unwrap();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let changes: Vec<RecordChange> = response.json().await.unwrap();
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].changed_by.as_deref(), Some("collector"));
+
+    // The history of records of other sites is not accessible
+    let response = history("reader-b-token").await.unwrap();
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn history_contains_deletions() {
+    // Arrange
+    let app = spawn_app().await;
+    assert_eq!(200, app.add_record(&record()).await.status().as_u16());
+
+    // Act
+    assert_eq!(200, app.delete_record("r1").await.status().as_u16());
+    let response = app.get_record_history("r1").await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let history: Vec<RecordChange> = response.json().await.unwrap();
+    assert_eq!(history.len(), 2);
+    assert_eq!(history[1].operation, ChangeOperation::Delete);
+    let old = history[1].old.as_ref().unwrap();
+    assert_eq!(old["runtime"], json!(3600));
+    assert_eq!(old["meta"], json!({"site_id": ["site-a"]}));
+    assert!(history[1].new.is_empty());
+}
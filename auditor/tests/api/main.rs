@@ -1,8 +1,26 @@
 mod add;
+mod admin;
 mod advanced_queries;
+mod aggregate;
+mod auth;
+mod concurrency;
+mod daily_usage;
+mod delete;
+mod export;
 mod get;
 mod get_one_record;
 mod get_since;
 mod health_check;
 mod helpers;
+mod histogram;
+mod history;
+mod metrics;
+mod mtls;
+mod openapi;
+mod pagination;
+mod partitions;
+mod retention;
+mod subscribe;
 mod update;
+mod versions;
+mod webhooks;
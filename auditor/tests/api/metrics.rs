@@ -0,0 +1,261 @@
+use crate::helpers::spawn_app;
+use auditor::configuration::{DatabaseMetricsSettings, get_configuration};
+use auditor::domain::{RecordTest, ScoreTest};
+use auditor::metrics::{DatabaseMetric, DatabaseMetricAggregation, DatabaseMetricsWatcher};
+use chrono::{Duration, Utc};
+use prometheus::core::Collector;
+use prometheus::{Encoder, TextEncoder};
+use std::collections::HashMap;
+
+fn record(record_id: &str, vo: &str, queue: &str, hours_ago: i64) -> RecordTest {
+    let stop_time = Utc::now() - Duration::hours(hours_ago);
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([
+            ("site_id", vec!["site1"]),
+            ("vo", vec![vo]),
+            ("queue", vec![queue]),
+        ]))
+        .with_component(
+            "CPU",
+            2,
+            vec![
+                ScoreTest::new()
+                    .with_name("HEPSPEC06".to_string())
+                    .with_value(10.0),
+            ],
+        )
+        .with_start_time((stop_time - Duration::hours(1)).to_rfc3339())
+        .with_stop_time(stop_time.to_rfc3339())
+}
+
+fn metric(name: &str, aggregation: DatabaseMetricAggregation) -> DatabaseMetric {
+    DatabaseMetric {
+        name: name.to_string(),
+        help: None,
+        aggregation,
+        component: None,
+        score: None,
+        group_by: vec![],
+        labels: None,
+        window: None,
+    }
+}
+
+fn encode(watcher: &DatabaseMetricsWatcher) -> String {
+    let mut buffer = vec![];
+    TextEncoder::new()
+        .encode(&watcher.collect(), &mut buffer)
+        .unwrap();
+    String::from_utf8(buffer).unwrap()
+}
+
+#[tokio::test]
+async fn predefined_database_metrics_count_records() {
+    // Arrange
+    let app = spawn_app().await;
+    let configuration = get_configuration().expect("Failed to read configuration.");
+    let watcher = DatabaseMetricsWatcher::new(app.db_pool.clone(), &configuration).unwrap();
+    assert_eq!(encode(&watcher), "");
+
+    let records = [
+        record("r1", "atlas", "short", 1),
+        record("r2", "cms", "long", 2),
+    ];
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    // Act
+    watcher.update().await.unwrap();
+
+    // Assert
+    let metrics = encode(&watcher);
+    assert!(metrics.contains("num_records_database 2\n"), "{metrics}");
+    assert!(
+        metrics.contains("num_records_database_per_site{site=\"site1\"} 2\n"),
+        "{metrics}"
+    );
+    assert!(
+        !metrics.contains("num_records_database_per_user"),
+        "{metrics}"
+    );
+}
+
+#[tokio::test]
+async fn configured_database_metrics_are_exported_as_labelled_gauges() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.metrics.database.metrics = vec![
+        DatabaseMetric {
+            group_by: vec!["vo".to_string(), "queue".to_string()],
+            ..metric("records_per_vo", DatabaseMetricAggregation::Count)
+        },
+        DatabaseMetric {
+            group_by: vec!["vo".to_string()],
+            window: Some(Duration::hours(24)),
+            ..metric("runtime_per_vo_24h", DatabaseMetricAggregation::Runtime)
+        },
+        DatabaseMetric {
+            group_by: vec!["queue".to_string()],
+            labels: Some(vec!["batch_queue".to_string()]),
+            component: Some("CPU".to_string()),
+            ..metric("cpu_usage", DatabaseMetricAggregation::ComponentRuntime)
+        },
+        DatabaseMetric {
+            help: Some("HEPSPEC06 hours".to_string()),
+            component: Some("CPU".to_string()),
+            score: Some("HEPSPEC06".to_string()),
+            ..metric(
+                "hepspec_usage",
+                DatabaseMetricAggregation::ScoreComponentRuntime,
+            )
+        },
+    ];
+    let watcher = DatabaseMetricsWatcher::new(app.db_pool.clone(), &configuration).unwrap();
+
+    let records = [
+        record("r1", "atlas", "short", 1),
+        record("r2", "atlas", "short", 48),
+        record("r3", "cms", "long", 2),
+        // Records without the meta keys are not included in the grouped metrics
+        RecordTest::new()
+            .with_record_id("r4")
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2024-01-01T00:00:00Z")
+            .with_stop_time("2024-01-01T01:00:00Z"),
+    ];
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    // Act
+    watcher.update().await.unwrap();
+
+    // Assert
+    let metrics = encode(&watcher);
+    for line in [
+        "records_per_vo{queue=\"short\",vo=\"atlas\"} 2\n",
+        "records_per_vo{queue=\"long\",vo=\"cms\"} 1\n",
+        "runtime_per_vo_24h{vo=\"atlas\"} 3600\n",
+        "runtime_per_vo_24h{vo=\"cms\"} 3600\n",
+        "cpu_usage{batch_queue=\"short\"} 14400\n",
+        "cpu_usage{batch_queue=\"long\"} 7200\n",
+        "# HELP hepspec_usage HEPSPEC06 hours\n",
+        "# TYPE hepspec_usage gauge\n",
+        "hepspec_usage 216000\n",
+    ] {
+        assert!(metrics.contains(line), "{line} missing in {metrics}");
+    }
+}
+
+#[tokio::test]
+async fn failing_database_metrics_do_not_prevent_updates_of_other_metrics() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.metrics.database.metrics = vec![
+        DatabaseMetric {
+            group_by: vec!["vo".to_string()],
+            ..metric("records_per_vo", DatabaseMetricAggregation::Count)
+        },
+        metric("records", DatabaseMetricAggregation::Count),
+    ];
+    let watcher = DatabaseMetricsWatcher::new(app.db_pool.clone(), &configuration).unwrap();
+
+    let records = [record("r1", "atlas", "short", 1)];
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+    // Meta values which are not arrays cannot be grouped by
+    sqlx::query("UPDATE auditor_accounting SET meta = '{\"vo\": \"atlas\"}'")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+
+    // Act
+    let result = watcher.update().await;
+
+    // Assert
+    assert!(result.is_err());
+    let metrics = encode(&watcher);
+    assert!(metrics.contains("records 1\n"), "{metrics}");
+    assert!(!metrics.contains("records_per_vo"), "{metrics}");
+}
+
+#[tokio::test]
+async fn invalid_database_metrics_are_rejected() {
+    let app = spawn_app().await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+
+    for metrics in [
+        vec![metric("usage", DatabaseMetricAggregation::ComponentRuntime)],
+        vec![DatabaseMetric {
+            component: Some("CPU".to_string()),
+            ..metric("usage", DatabaseMetricAggregation::ScoreComponentRuntime)
+        }],
+        vec![DatabaseMetric {
+            group_by: vec!["site-id".to_string()],
+            ..metric("records", DatabaseMetricAggregation::Count)
+        }],
+        vec![DatabaseMetric {
+            group_by: vec!["site_id".to_string()],
+            labels: Some(vec![]),
+            ..metric("records", DatabaseMetricAggregation::Count)
+        }],
+        vec![DatabaseMetric {
+            window: Some(Duration::zero()),
+            ..metric("records", DatabaseMetricAggregation::Count)
+        }],
+        vec![
+            metric("records", DatabaseMetricAggregation::Count),
+            metric("records", DatabaseMetricAggregation::Runtime),
+        ],
+    ] {
+        configuration.metrics.database.metrics = metrics;
+        assert!(DatabaseMetricsWatcher::new(app.db_pool.clone(), &configuration).is_err());
+    }
+}
+
+#[test]
+fn database_metrics_are_read_from_definitions_and_names() {
+    let settings: DatabaseMetricsSettings = config::Config::builder()
+        .add_source(config::File::from_str(
+            r#"
+            frequency: 60
+            metrics:
+              - RecordCount
+              - name: usage_per_vo
+                aggregation: component_runtime
+                component: CPU
+                group_by: [vo]
+                window: 86400
+            "#,
+            config::FileFormat::Yaml,
+        ))
+        .build()
+        .unwrap()
+        .try_deserialize()
+        .unwrap();
+
+    assert_eq!(
+        settings.metrics,
+        vec![
+            DatabaseMetric {
+                help: Some("Number of records in the Auditor database".to_string()),
+                ..metric("num_records_database", DatabaseMetricAggregation::Count)
+            },
+            DatabaseMetric {
+                component: Some("CPU".to_string()),
+                group_by: vec!["vo".to_string()],
+                window: Some(Duration::days(1)),
+                ..metric("usage_per_vo", DatabaseMetricAggregation::ComponentRuntime)
+            },
+        ]
+    );
+
+    let result = config::Config::builder()
+        .add_source(config::File::from_str(
+            "metrics: [RecordCountPerVo]",
+            config::FileFormat::Yaml,
+        ))
+        .build()
+        .unwrap()
+        .try_deserialize::<DatabaseMetricsSettings>();
+    assert!(result.is_err());
+}
@@ -0,0 +1,282 @@
+use crate::helpers::{TestApp, spawn_app_with_tls};
+use auditor::auth::{Role, hash_token};
+use auditor::configuration::{ApiTokenSettings, ClientCertificateSettings};
+use auditor::domain::{Record, RecordTest};
+use rcgen::{
+    BasicConstraints, Certificate, CertificateParams, DnType, ExtendedKeyUsagePurpose, IsCa,
+    KeyPair,
+};
+use rustls::ServerConfig;
+use rustls::pki_types::{PrivateKeyDer, PrivatePkcs8KeyDer};
+use rustls::server::WebPkiClientVerifier;
+use std::collections::HashMap;
+use std::sync::Arc;
+
+struct TestCa {
+    certificate: Certificate,
+    key: KeyPair,
+}
+
+impl TestCa {
+    fn new() -> TestCa {
+        let key = KeyPair::generate().unwrap();
+        let mut params = CertificateParams::new(Vec::<String>::new()).unwrap();
+        params.is_ca = IsCa::Ca(BasicConstraints::Unconstrained);
+        params
+            .distinguished_name
+            .push(DnType::CommonName, "AUDITOR test CA");
+        TestCa {
+            certificate: params.self_signed(&key).unwrap(),
+            key,
+        }
+    }
+
+    fn issue(
+        &self,
+        common_name: &str,
+        alt_names: &[&str],
+        usage: ExtendedKeyUsagePurpose,
+    ) -> (Certificate, KeyPair) {
+        let key = KeyPair::generate().unwrap();
+        let alt_names: Vec<String> = alt_names.iter().map(|name| name.to_string()).collect();
+        let mut params = CertificateParams::new(alt_names).unwrap();
+        params
+            .distinguished_name
+            .push(DnType::CommonName, common_name);
+        params.extended_key_usages = vec![usage];
+        let certificate = params
+            .signed_by(&key, &self.certificate, &self.key)
+            .unwrap();
+        (certificate, key)
+    }
+
+    fn server_config(&self) -> ServerConfig {
+        let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
+        let mut roots = rustls::RootCertStore::empty();
+        roots.add(self.certificate.der().clone()).unwrap();
+        let verifier = WebPkiClientVerifier::builder(Arc::new(roots))
+            .build()
+            .unwrap();
+        let (certificate, key) = self.issue(
+            "localhost",
+            &["localhost"],
+            ExtendedKeyUsagePurpose::ServerAuth,
+        );
+        ServerConfig::builder()
+            .with_client_cert_verifier(verifier)
+            .with_single_cert(
+                vec![certificate.der().clone()],
+                PrivateKeyDer::Pkcs8(PrivatePkcs8KeyDer::from(key.serialize_der())),
+            )
+            .unwrap()
+    }
+
+    fn client(&self, common_name: &str, alt_names: &[&str]) -> reqwest::Client {
+        let (certificate, key) =
+            self.issue(common_name, alt_names, ExtendedKeyUsagePurpose::ClientAuth);
+        let identity = reqwest::Identity::from_pem(
+            format!("{}{}", certificate.pem(), key.serialize_pem()).as_bytes(),
+        )
+        .unwrap();
+        reqwest::Client::builder()
+            .add_root_certificate(
+                reqwest::Certificate::from_pem(self.certificate.pem().as_bytes()).unwrap(),
+            )
+            .identity(identity)
+            .build()
+            .unwrap()
+    }
+}
+
+async fn spawn_app_with_certificates(ca: &TestCa) -> TestApp {
+    spawn_app_with_tls(
+        |config| {
+            config.auth.enabled = true;
+            config.auth.tokens = vec![ApiTokenSettings {
+                name: "admin".to_string(),
+                role: Role::Admin,
+                token_sha256: hash_token("admin-token"),
+                sites: None,
+            }];
+            config.auth.certificates = vec![
+                ClientCertificateSettings {
+                    subject: Some("CN=site-a-collector".to_string()),
+                    san: None,
+                    role: Role::Collector,
+                    sites: Some(vec!["site-a".to_string()]),
+                },
+                ClientCertificateSettings {
+                    subject: None,
+                    san: Some("reader.site-a.example.org".to_string()),
+                    role: Role::Reader,
+                    sites: Some(vec!["site-a".to_string()]),
+                },
+            ];
+        },
+        ca.server_config(),
+    )
+    .await
+}
+
+fn record(record_id: &str, sites: &[&str]) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", sites.to_vec())]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time("2024-01-01T00:00:00Z")
+        .with_stop_time("2024-01-01T01:00:00Z")
+}
+
+/// Adds records as admin over the plain HTTP listener
+async fn seed(app: &TestApp, records: &[RecordTest]) {
+    let response = reqwest::Client::new()
+        .post(format!("{}/records", app.address))
+        .bearer_auth("admin-token")
+        .json(records)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn collector_certificates_only_write_records_of_their_site() {
+    // Arrange
+    let ca = TestCa::new();
+    let app = spawn_app_with_certificates(&ca).await;
+    let https = app.https_address.clone().unwrap();
+    let client = ca.client("site-a-collector", &[]);
+    seed(&app, &[record("b1", &["site-b"])]).await;
+
+    // Act & Assert
+    let post =
+        |path: &str, record: RecordTest| client.post(format!("{https}{path}")).json(&record).send();
+    let response = post("/record", record("a1", &["site-a"])).await.unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    let response = post("/record", record("a2", &["site-b"])).await.unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = post("/record", record("a3", &["site-a", "site-b"]))
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = post("/record", record("a4", &[])).await.unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    // Overwriting a record of another site is not possible either
+    let response = post("/record?on_conflict=update", record("b1", &["site-a"]))
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = client
+        .put(format!("{https}/record"))
+        .json(&record("b1", &["site-b"]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = client
+        .put(format!("{https}/records"))
+        .json(&[record("a1", &["site-b"])])
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = client
+        .put(format!("{https}/record"))
+        .json(&record("a1", &["site-a"]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+
+    // Collectors cannot read records
+    let response = client.get(format!("{https}/records")).send().await.unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let stored: Vec<String> =
+        sqlx::query_scalar("SELECT record_id FROM auditor_accounting ORDER BY record_id")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap();
+    assert_eq!(stored, vec!["a1", "b1"]);
+}
+
+#[tokio::test]
+async fn reader_certificates_only_read_records_of_their_site() {
+    // Arrange
+    let ca = TestCa::new();
+    let app = spawn_app_with_certificates(&ca).await;
+    let https = app.https_address.clone().unwrap();
+    let client = ca.client("reader", &["reader.site-a.example.org"]);
+    seed(
+        &app,
+        &[
+            record("a1", &["site-a"]),
+            record("b1", &["site-b"]),
+            record("ab", &["site-a", "site-b"]),
+        ],
+    )
+    .await;
+
+    // Act
+    let response = client.get(format!("{https}/records")).send().await.unwrap();
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let records: Vec<Record> = response.json().await.unwrap();
+    let record_ids: Vec<&str> = records.iter().map(|r| r.record_id.as_str()).collect();
+    assert_eq!(record_ids, vec!["a1"]);
+
+    let response = client
+        .get(format!("{https}/record/b1"))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+
+    let response = client
+        .get(format!("{https}/aggregate"))
+        .send()
+        .await
+        .unwrap();
+    let aggregate: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(aggregate[0]["record_count"], 1);
+
+    let response = client
+        .post(format!("{https}/record"))
+        .json(&record("a2", &["site-a"]))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(403, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn unknown_certificates_are_rejected() {
+    // Arrange
+    let ca = TestCa::new();
+    let app = spawn_app_with_certificates(&ca).await;
+    let https = app.https_address.clone().unwrap();
+    let client = ca.client("someone", &["someone.example.org"]);
+
+    // Act
+    let response = client.get(format!("{https}/records")).send().await.unwrap();
+
+    // Assert
+    assert_eq!(401, response.status().as_u16());
+
+    // A bearer token takes precedence over the certificate
+    let response = client
+        .get(format!("{https}/records"))
+        .bearer_auth("admin-token")
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+}
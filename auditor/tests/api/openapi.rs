@@ -0,0 +1,92 @@
+use crate::helpers::spawn_app_with;
+use serde_json::Value;
+
+#[tokio::test]
+async fn openapi_json_describes_the_record_endpoints() {
+    // Arrange
+    // The specification is available without authentication
+    let app = spawn_app_with(|config| config.auth.enabled = true).await;
+
+    // Act
+    let response = reqwest::Client::new()
+        .get(format!("{}/openapi.json", app.address))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let spec: Value = response.json().await.unwrap();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3."));
+
+    let paths = &spec["paths"];
+    for (path, methods) in [
+        ("/health_check", &["get"][..]),
+        ("/record", &["post", "put"]),
+        ("/record/{record_id}", &["get", "delete"]),
+        ("/records", &["get", "post", "put", "delete"]),
+        ("/records/subscribe", &["get"]),
+        ("/record/{record_id}/history", &["get"]),
+        ("/aggregate", &["get"]),
+        ("/histogram", &["get"]),
+        ("/concurrency", &["get"]),
+        ("/daily_usage", &["get"]),
+        ("/tokens", &["get", "post"]),
+        ("/tokens/{name}", &["delete"]),
+    ] {
+        for method in methods {
+            assert!(paths[path][method].is_object(), "{method} {path} missing");
+        }
+    }
+
+    let schemas = &spec["components"]["schemas"];
+    for schema in ["RecordAdd", "RecordUpdate", "Record", "Component", "Score"] {
+        assert!(schemas[schema].is_object(), "{schema} missing");
+    }
+    assert_eq!(
+        paths["/record"]["post"]["requestBody"]["content"]["application/json"]["schema"]["$ref"],
+        "#/components/schemas/RecordAdd"
+    );
+
+    // API tokens are sent as bearer tokens, except for the health checks
+    let scheme = &spec["components"]["securitySchemes"]["api_token"];
+    assert_eq!(scheme["type"], "http");
+    assert_eq!(scheme["scheme"], "bearer");
+    assert_eq!(spec["security"][0]["api_token"], serde_json::json!([]));
+    assert_eq!(
+        paths["/health_check"]["get"]["security"],
+        serde_json::json!([{}])
+    );
+
+    // The filters are query parameters in the syntax of serde_qs
+    let parameters = paths["/records"]["get"]["parameters"].as_array().unwrap();
+    let meta = parameters.iter().find(|p| p["name"] == "meta").unwrap();
+    assert_eq!(meta["in"], "query");
+    assert_eq!(meta["style"], "deepObject");
+    assert!(parameters.iter().all(|p| p["name"] != "sites"));
+
+    // All referenced schemas are part of the specification
+    let mut references = vec![];
+    collect_references(&spec, &mut references);
+    for reference in references {
+        let name = reference.strip_prefix("#/components/schemas/").unwrap();
+        assert!(schemas[name].is_object(), "{reference} missing");
+    }
+}
+
+fn collect_references<'a>(value: &'a Value, references: &mut Vec<&'a str>) {
+    match value {
+        Value::Object(object) => {
+            for (key, value) in object {
+                match (key.as_str(), value) {
+                    ("$ref", Value::String(reference)) => references.push(reference),
+                    _ => collect_references(value, references),
+                }
+            }
+        }
+        Value::Array(values) => values
+            .iter()
+            .for_each(|value| collect_references(value, references)),
+        _ => {}
+    }
+}
@@ -0,0 +1,181 @@
+use crate::helpers::{TestApp, spawn_app};
+use auditor::constants::HEADER_NEXT_CURSOR;
+use auditor::domain::{Record, RecordTest};
+use fake::{Fake, Faker};
+use std::collections::HashMap;
+
+/// Follows the cursors of `GET /records` until the last page and returns the record ids of all
+/// pages.
+async fn read_all_pages(app: &TestApp, query: &str) -> Vec<String> {
+    let mut record_ids = vec![];
+    let mut cursor: Option<String> = None;
+    loop {
+        let query = match &cursor {
+            Some(cursor) => format!("{query}&after={cursor}"),
+            None => query.to_string(),
+        };
+        let response = app.get_records_page(&query).await;
+        assert_eq!(200, response.status().as_u16());
+
+        cursor = response
+            .headers()
+            .get(HEADER_NEXT_CURSOR)
+            .map(|cursor| cursor.to_str().unwrap().to_string());
+        let page: Vec<Record> = response.json().await.unwrap();
+        assert!(page.len() <= 4);
+        if cursor.is_some() {
+            assert_eq!(page.len(), 4);
+        }
+        record_ids.extend(page.into_iter().map(|r| r.record_id));
+
+        if cursor.is_none() {
+            return record_ids;
+        }
+    }
+}
+
+#[tokio::test]
+async fn following_cursors_returns_all_records_in_order() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Records share sort keys to make sure the record_id is used as tie breaker
+    let test_cases = (0..11)
+        .map(|i| {
+            let record = Faker
+                .fake::<RecordTest>()
+                .with_record_id(format!("r{i:02}"))
+                .with_start_time(format!("2022-10-0{}T12:00:00-00:00", 1 + i % 3));
+            if i % 5 == 0 {
+                RecordTest {
+                    stop_time: None,
+                    ..record
+                }
+            } else {
+                record.with_stop_time(format!("2022-10-0{}T13:00:00-00:00", 4 + i % 4))
+            }
+        })
+        .collect::<Vec<_>>();
+
+    for case in test_cases.iter() {
+        let response = app.add_record(&case).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    for sort_by in [
+        "",
+        "sort_by[asc]=start_time&",
+        "sort_by[desc]=start_time&",
+        "sort_by[asc]=stop_time&",
+        "sort_by[desc]=stop_time&",
+        "sort_by[asc]=runtime&",
+        "sort_by[desc]=runtime&",
+        "sort_by[asc]=record_id&",
+        "sort_by[desc]=record_id&",
+    ] {
+        // The unpaginated query returns all records in the expected order
+        let (expected, status) = app
+            .advanced_queries(sort_by.trim_end_matches('&'))
+            .await
+            .unwrap();
+        assert_eq!(200, status);
+        let expected: Vec<_> = expected.into_iter().map(|r| r.record_id).collect();
+        assert_eq!(expected.len(), test_cases.len());
+
+        // Act
+        let received = read_all_pages(&app, &format!("{sort_by}limit=4")).await;
+
+        // Assert
+        assert_eq!(expected, received, "Pages of {sort_by} do not match.");
+    }
+}
+
+#[tokio::test]
+async fn cursors_are_combined_with_filters() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let test_cases = (0..10)
+        .map(|i| {
+            Faker
+                .fake::<RecordTest>()
+                .with_record_id(format!("r{i}"))
+                .with_meta(HashMap::from([(
+                    "site_id",
+                    vec![if i % 2 == 0 { "site1" } else { "site2" }],
+                )]))
+                .with_stop_time(format!("2022-10-0{}T12:00:00-00:00", i % 3 + 1))
+                .with_start_time("2022-09-01T12:00:00-00:00")
+        })
+        .collect::<Vec<_>>();
+
+    for case in test_cases.iter() {
+        let response = app.add_record(&case).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    // Act
+    let received = read_all_pages(&app, "meta[site_id][c]=site1&limit=4").await;
+
+    // Assert
+    assert_eq!(received, vec!["r0", "r6", "r4", "r2", "r8"]);
+}
+
+#[tokio::test]
+async fn last_page_has_no_cursor() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for i in 0..3 {
+        let record = Faker.fake::<RecordTest>().with_record_id(format!("r{i}"));
+        let response = app.add_record(&record).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    for (limit, has_next) in [(2, true), (3, false), (4, false)] {
+        // Act
+        let response = app.get_records_page(format!("limit={limit}")).await;
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+        assert_eq!(
+            has_next,
+            response.headers().contains_key(HEADER_NEXT_CURSOR),
+            "Unexpected cursor for limit {limit}"
+        );
+    }
+}
+
+#[tokio::test]
+async fn invalid_cursor_returns_a_400() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for i in 0..3 {
+        let record = Faker.fake::<RecordTest>().with_record_id(format!("r{i}"));
+        let response = app.add_record(&record).await;
+        assert_eq!(200, response.status().as_u16());
+    }
+
+    let response = app.get_records_page("limit=1").await;
+    let cursor = response.headers()[HEADER_NEXT_CURSOR]
+        .to_str()
+        .unwrap()
+        .to_string();
+
+    for query in [
+        "limit=1&after=invalid".to_string(),
+        // The cursor was created for records sorted by stop_time
+        format!("limit=1&sort_by[desc]=runtime&after={cursor}"),
+    ] {
+        // Act
+        let response = app.get_records_page(&query).await;
+
+        // Assert
+        assert_eq!(
+            400,
+            response.status().as_u16(),
+            "Query {query} was accepted."
+        );
+    }
+}
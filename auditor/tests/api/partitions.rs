@@ -0,0 +1,154 @@
+use crate::helpers::{TestApp, spawn_app};
+use auditor::configuration::{RetentionPolicy, get_configuration};
+use auditor::domain::{ChangeOperation, RecordTest};
+use auditor::partitions::PartitionManager;
+use auditor::retention::{RETENTION_CLIENT, RetentionWatcher};
+use auditor::routes::get_record_history;
+use chrono::{Months, Utc};
+use std::collections::HashMap;
+
+fn record(record_id: &str) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec!["site1"])]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time("2024-01-10T00:00:00Z")
+}
+
+async fn partition_of(app: &TestApp, record_id: &str) -> String {
+    sqlx::query_scalar(
+        "SELECT tableoid::regclass::text FROM auditor_accounting WHERE record_id = $1",
+    )
+    .bind(record_id)
+    .fetch_one(&app.db_pool)
+    .await
+    .unwrap()
+}
+
+#[tokio::test]
+async fn partitions_are_created_for_upcoming_months_and_stored_records() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.partitioning.months_ahead = 2;
+    let manager = PartitionManager::new(app.db_pool.clone(), &configuration).unwrap();
+    let r = record("r1").with_stop_time("2024-01-10T01:00:00Z");
+    assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    assert_eq!("auditor_accounting_default", partition_of(&app, "r1").await);
+
+    // Act
+    let created = manager.create_partitions().await.unwrap();
+
+    // Assert
+    let upcoming = |months: u32| {
+        (Utc::now() + Months::new(months))
+            .format("auditor_accounting_%Y_%m")
+            .to_string()
+    };
+    // The partition of the current month is created by the migration
+    assert_eq!(
+        created,
+        vec![
+            "auditor_accounting_2024_01".to_string(),
+            upcoming(1),
+            upcoming(2)
+        ]
+    );
+    assert_eq!("auditor_accounting_2024_01", partition_of(&app, "r1").await);
+    assert!(manager.create_partitions().await.unwrap().is_empty());
+
+    // Record ids stay unique after moving the record
+    let response = app.add_record(&r).await;
+    assert_eq!(500, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn completing_a_record_moves_it_to_its_partition() {
+    // Arrange
+    let app = spawn_app().await;
+    sqlx::query("SELECT auditor_accounting_create_partition('2024-01-01T00:00:00Z')")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(200, app.add_record(&record("r1")).await.status().as_u16());
+
+    // Act
+    let completed = [record("r1").with_stop_time("2024-01-10T01:00:00Z")];
+    assert_eq!(200, app.bulk_update(&completed).await.status().as_u16());
+
+    // Assert
+    assert_eq!("auditor_accounting_2024_01", partition_of(&app, "r1").await);
+    let history: serde_json::Value = app.get_record_history("r1").await.json().await.unwrap();
+    let operations: Vec<&str> = history
+        .as_array()
+        .unwrap()
+        .iter()
+        .map(|change| change["operation"].as_str().unwrap())
+        .collect();
+    assert_eq!(operations, vec!["insert", "update"]);
+    assert_eq!(history[1]["new"]["runtime"], 3600);
+
+    // Adding the record again is still rejected or skipped
+    assert_eq!(500, app.add_record(&completed[0]).await.status().as_u16());
+    let response = app.add_record_with(&completed[0], "skip").await;
+    assert_eq!(200, response.status().as_u16());
+    let result: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(result["status"], "duplicate");
+}
+
+#[tokio::test]
+async fn retention_policies_drop_partitions_of_old_months() {
+    // Arrange
+    let app = spawn_app().await;
+    for month in ["2000-01-01T00:00:00Z", "2000-02-01T00:00:00Z"] {
+        sqlx::query("SELECT auditor_accounting_create_partition($1::timestamptz)")
+            .bind(month)
+            .execute(&app.db_pool)
+            .await
+            .unwrap();
+    }
+    let records = [
+        record("r1")
+            .with_start_time("2000-01-10T00:00:00Z")
+            .with_stop_time("2000-01-10T01:00:00Z"),
+        record("r2")
+            .with_start_time("2000-02-10T00:00:00Z")
+            .with_stop_time("2000-02-10T01:00:00Z"),
+        record("open").with_start_time("2000-01-10T00:00:00Z"),
+    ];
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.retention.policies = vec![RetentionPolicy {
+        older_than_months: 24,
+        older_than_days: 0,
+        meta: HashMap::new(),
+    }];
+    let watcher = RetentionWatcher::new(app.db_pool.clone(), &configuration).unwrap();
+
+    // Act
+    let deleted = watcher.apply().await.unwrap();
+
+    // Assert
+    assert_eq!(2, deleted);
+    let partitions: Vec<String> = sqlx::query_scalar(
+        "SELECT relname::text FROM pg_class WHERE relname LIKE 'auditor_accounting_2000_%'",
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert!(partitions.is_empty());
+    let (stored, _) = app.get_records().await.unwrap();
+    let record_ids: Vec<_> = stored.into_iter().map(|r| r.record_id).collect();
+    assert_eq!(record_ids, vec!["open"]);
+
+    // Only the deletion is kept in the history of dropped records
+    let history = get_record_history("r1", &app.db_pool).await.unwrap();
+    assert_eq!(history.len(), 1);
+    assert_eq!(history[0].operation, ChangeOperation::Delete);
+    assert_eq!(history[0].old, None);
+    assert_eq!(history[0].changed_by.as_deref(), Some(RETENTION_CLIENT));
+
+    // The record ids of dropped records can be used again
+    assert_eq!(200, app.add_record(&records[0]).await.status().as_u16());
+}
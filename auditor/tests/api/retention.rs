@@ -0,0 +1,124 @@
+use crate::helpers::spawn_app;
+use auditor::configuration::{RetentionPolicy, get_configuration};
+use auditor::domain::{ChangeOperation, RecordTest};
+use auditor::retention::{RETENTION_CLIENT, RetentionWatcher};
+use auditor::routes::get_record_history;
+use chrono::{Duration, SecondsFormat, Utc};
+use std::collections::HashMap;
+
+fn record(record_id: &str, site: &str, days_ago: i64) -> RecordTest {
+    let stop_time = Utc::now() - Duration::days(days_ago);
+    let start_time = stop_time - Duration::hours(1);
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec![site])]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time(start_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+        .with_stop_time(stop_time.to_rfc3339_opts(SecondsFormat::Secs, true))
+}
+
+#[tokio::test]
+async fn retention_policies_delete_old_records() {
+    // Arrange
+    let app = spawn_app().await;
+    let records = [
+        record("old", "site1", 800),
+        record("recent", "site1", 10),
+        record("site2_old", "site2", 100),
+        record("site2_recent", "site2", 10),
+        // Open records are never deleted by a retention policy
+        RecordTest::new()
+            .with_record_id("open")
+            .with_meta(HashMap::from([("site_id", vec!["site2"])]))
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2000-01-01T00:00:00Z"),
+    ];
+    for r in records.iter() {
+        assert_eq!(200, app.add_record(r).await.status().as_u16());
+    }
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.retention.policies = vec![
+        RetentionPolicy {
+            older_than_months: 24,
+            older_than_days: 0,
+            meta: HashMap::new(),
+        },
+        RetentionPolicy {
+            older_than_months: 0,
+            older_than_days: 30,
+            meta: HashMap::from([("site_id".to_string(), vec!["site2".to_string()])]),
+        },
+    ];
+    let watcher = RetentionWatcher::new(app.db_pool.clone(), &configuration).unwrap();
+
+    // Act
+    let deleted = watcher.apply().await.unwrap();
+
+    // Assert
+    assert_eq!(2, deleted);
+    let (records, _) = app.get_records().await.unwrap();
+    let mut record_ids: Vec<_> = records.into_iter().map(|r| r.record_id).collect();
+    record_ids.sort();
+    assert_eq!(vec!["open", "recent", "site2_recent"], record_ids);
+}
+
+#[tokio::test]
+async fn retention_policies_purge_the_history_of_deleted_records() {
+    // Arrange
+    let app = spawn_app().await;
+    for r in [
+        record("old", "site1", 800),
+        record("site2_old", "site2", 100),
+    ] {
+        assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    }
+    let update = record("old", "site1", 799);
+    assert_eq!(200, app.bulk_update(&[update]).await.status().as_u16());
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.retention.policies = vec![
+        RetentionPolicy {
+            older_than_months: 24,
+            older_than_days: 0,
+            meta: HashMap::new(),
+        },
+        RetentionPolicy {
+            older_than_months: 0,
+            older_than_days: 30,
+            meta: HashMap::from([("site_id".to_string(), vec!["site2".to_string()])]),
+        },
+    ];
+    let watcher = RetentionWatcher::new(app.db_pool.clone(), &configuration).unwrap();
+
+    // Act
+    assert_eq!(2, watcher.apply().await.unwrap());
+
+    // Assert
+    for record_id in ["old", "site2_old"] {
+        let history = get_record_history(record_id, &app.db_pool).await.unwrap();
+        assert_eq!(history.len(), 1, "{record_id}");
+        assert_eq!(history[0].operation, ChangeOperation::Delete);
+        assert_eq!(history[0].old, None);
+        assert!(history[0].new.is_empty());
+        assert_eq!(history[0].changed_by.as_deref(), Some(RETENTION_CLIENT));
+    }
+}
+
+#[tokio::test]
+async fn retention_policy_without_age_is_rejected() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.retention.policies = vec![RetentionPolicy {
+        older_than_months: 0,
+        older_than_days: 0,
+        meta: HashMap::new(),
+    }];
+
+    // Act
+    let watcher = RetentionWatcher::new(app.db_pool.clone(), &configuration);
+
+    // Assert
+    assert!(watcher.is_err());
+}
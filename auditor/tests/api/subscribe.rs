@@ -0,0 +1,107 @@
+use crate::helpers::{TestApp, spawn_app};
+use auditor::domain::{Record, RecordTest};
+use std::collections::HashMap;
+use std::time::Duration;
+
+fn record(record_id: &str, site: &str) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec![site])]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time("2024-01-01T00:00:00Z")
+        .with_stop_time("2024-01-01T01:00:00Z")
+}
+
+async fn subscribe(app: &TestApp, query_string: &str) -> reqwest::Response {
+    reqwest::Client::new()
+        .get(format!(
+            "{}/records/subscribe?{}",
+            app.address, query_string
+        ))
+        .send()
+        .await
+        .expect("Failed to execute request.")
+}
+
+/// Reads events of the stream until the received records satisfy `done`
+async fn next_records(
+    response: &mut reqwest::Response,
+    done: impl Fn(&[Record]) -> bool,
+) -> Vec<Record> {
+    let mut buffer = String::new();
+    let mut records: Vec<Record> = vec![];
+    while !done(&records) {
+        let chunk = tokio::time::timeout(Duration::from_secs(10), response.chunk())
+            .await
+            .expect("Timed out waiting for events")
+            .unwrap()
+            .expect("Stream ended");
+        buffer.push_str(std::str::from_utf8(&chunk).unwrap());
+        while let Some(end) = buffer.find("\n\n") {
+            let event: String = buffer.drain(..end + 2).collect();
+            if let Some(data) = event
+                .strip_prefix("event: record\ndata: ")
+                .map(str::trim_end)
+            {
+                records.push(serde_json::from_str(data).unwrap());
+            }
+        }
+    }
+    records
+}
+
+#[tokio::test]
+async fn subscription_streams_inserted_and_updated_records_matching_the_filters() {
+    // Arrange
+    let app = spawn_app().await;
+    let mut response = subscribe(&app, "meta[site_id][c]=site-a").await;
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(
+        Some("text/event-stream"),
+        response
+            .headers()
+            .get("Content-Type")
+            .and_then(|v| v.to_str().ok())
+    );
+    // The comment is sent once the subscription is active
+    let chunk = response.chunk().await.unwrap().unwrap();
+    assert_eq!(&chunk[..], b": subscribed\n\n");
+
+    // Act
+    assert_eq!(200, app.add_record(&record("a1", "site-a")).await.status());
+    assert_eq!(
+        200,
+        app.bulk_insert(&[record("b1", "site-b"), record("a2", "site-a")])
+            .await
+            .status()
+    );
+    let update = record("a1", "site-a").with_stop_time("2024-01-01T02:00:00Z");
+    assert_eq!(200, app.bulk_update(&[update]).await.status());
+
+    // Assert
+    let records = next_records(&mut response, |records| {
+        records.iter().any(|r| r.record_id == "a2")
+            && records.iter().any(|r| r.runtime == Some(7200))
+    })
+    .await;
+    // Records are sent with their values at the time of sending, hence changes in quick
+    // succession may be sent only once
+    let mut record_ids: Vec<&str> = records.iter().map(|r| r.record_id.as_str()).collect();
+    record_ids.sort();
+    record_ids.dedup();
+    assert_eq!(record_ids, vec!["a1", "a2"]);
+}
+
+#[tokio::test]
+async fn subscription_rejects_sorting_and_pagination() {
+    // Arrange
+    let app = spawn_app().await;
+
+    for query_string in ["limit=10", "sort_by[asc]=stop_time", "unknown=1"] {
+        // Act
+        let response = subscribe(&app, query_string).await;
+
+        // Assert
+        assert_eq!(400, response.status().as_u16(), "{query_string}");
+    }
+}
@@ -1,6 +1,9 @@
 use crate::helpers::spawn_app;
-use auditor::domain::{Record, RecordDatabase, RecordTest};
+use auditor::domain::{
+    BulkUpdateReport, Record, RecordDatabase, RecordTest, ScoreTest, UpdateStatus,
+};
 use fake::{Fake, Faker};
+use std::collections::HashMap;
 
 #[tokio::test]
 async fn update_returns_a_404_for_non_existing_record() {
@@ -78,3 +81,404 @@ async fn update_returns_a_200_for_valid_form_data() {
 
     assert_eq!(saved, body);
 }
+
+#[tokio::test]
+async fn update_merges_meta_and_components_and_corrects_start_time() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let body = RecordTest::new()
+        .with_record_id("r1")
+        .with_meta(HashMap::from([
+            ("site_id", vec!["site1"]),
+            ("user_id", vec!["user1"]),
+        ]))
+        .with_component("CPU", 1, vec![])
+        .with_component("MEM", 10, vec![])
+        .with_start_time("2022-03-01T12:00:00-00:00");
+    assert_eq!(200, app.add_record(&body).await.status().as_u16());
+
+    let updated_at =
+        sqlx::query!(r#"SELECT updated_at FROM auditor_accounting WHERE record_id = 'r1'"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch data.")
+            .updated_at;
+
+    // Act
+    let update = RecordTest::new()
+        .with_record_id("r1")
+        .with_meta(HashMap::from([
+            ("user_id", vec!["user2"]),
+            ("group_id", vec!["group1"]),
+        ]))
+        .with_component(
+            "MEM",
+            20,
+            vec![
+                ScoreTest::new()
+                    .with_name("factor".to_string())
+                    .with_value(2.0),
+            ],
+        )
+        .with_component("GPU", 1, vec![])
+        .with_start_time("2022-03-01T11:00:00-00:00")
+        .with_stop_time("2022-03-01T13:00:00-00:00");
+
+    let response = client
+        .put(format!("{}/record", &app.address))
+        .header("Content-Type", "application/json")
+        .json(&update)
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let saved: Option<Record> = app.get_single_record("r1").await.json().await.unwrap();
+    let saved = saved.unwrap();
+
+    let expected = RecordTest::new()
+        .with_record_id("r1")
+        .with_meta(HashMap::from([
+            ("site_id", vec!["site1"]),
+            ("user_id", vec!["user2"]),
+            ("group_id", vec!["group1"]),
+        ]))
+        .with_component("CPU", 1, vec![])
+        .with_component(
+            "MEM",
+            20,
+            vec![
+                ScoreTest::new()
+                    .with_name("factor".to_string())
+                    .with_value(2.0),
+            ],
+        )
+        .with_component("GPU", 1, vec![])
+        .with_start_time("2022-03-01T11:00:00-00:00")
+        .with_stop_time("2022-03-01T13:00:00-00:00");
+    assert_eq!(expected, saved);
+    assert_eq!(Some(7200), saved.runtime);
+    let meta = saved.meta.unwrap();
+    assert_eq!(3, meta.to_vec().len());
+    assert_eq!(Some(&vec!["site1".to_string()]), meta.get("site_id"));
+    assert_eq!(Some(&vec!["user2".to_string()]), meta.get("user_id"));
+    assert_eq!(Some(&vec!["group1".to_string()]), meta.get("group_id"));
+
+    let new_updated_at =
+        sqlx::query!(r#"SELECT updated_at FROM auditor_accounting WHERE record_id = 'r1'"#)
+            .fetch_one(&app.db_pool)
+            .await
+            .expect("Failed to fetch data.")
+            .updated_at;
+    assert!(new_updated_at > updated_at);
+}
+
+#[tokio::test]
+async fn update_without_meta_and_components_keeps_them() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let body = RecordTest::new()
+        .with_record_id("r1")
+        .with_meta(HashMap::from([("site_id", vec!["site1"])]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time("2022-03-01T12:00:00-00:00");
+    assert_eq!(200, app.add_record(&body).await.status().as_u16());
+
+    // Act
+    let response = client
+        .put(format!("{}/record", &app.address))
+        .header("Content-Type", "application/json")
+        .json(&serde_json::json!({
+            "record_id": "r1",
+            "meta": null,
+            "components": [],
+            "start_time": null,
+            "stop_time": "2022-03-01T13:00:00Z",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+
+    let saved: Option<Record> = app.get_single_record("r1").await.json().await.unwrap();
+    let saved = saved.unwrap();
+    assert_eq!(body.with_stop_time("2022-03-01T13:00:00-00:00"), saved);
+    assert_eq!(Some(3600), saved.runtime);
+    assert_eq!(
+        vec![("site_id".to_string(), vec!["site1".to_string()])],
+        saved.meta.unwrap().to_vec()
+    );
+}
+
+#[tokio::test]
+async fn bulk_update_updates_records_and_reports_unknown_ones() {
+    // Arrange
+    let app = spawn_app().await;
+
+    let records = (0..3)
+        .map(|i| {
+            RecordTest::new()
+                .with_record_id(format!("r{i}"))
+                .with_meta(HashMap::from([("site_id", vec!["site1"])]))
+                .with_component("CPU", 1, vec![])
+                .with_start_time("2022-03-01T12:00:00-00:00")
+        })
+        .collect::<Vec<_>>();
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    // Updates require the list of components, which may be empty
+    let update = |record_id: &str| RecordTest {
+        components: Some(vec![]),
+        ..RecordTest::new().with_record_id(record_id)
+    };
+
+    // Act
+    let updates = vec![
+        update("r0").with_stop_time("2022-03-01T13:00:00-00:00"),
+        update("unknown").with_stop_time("2022-03-01T13:00:00-00:00"),
+        update("r1")
+            .with_meta(HashMap::from([("user_id", vec!["user1"])]))
+            .with_component("GPU", 2, vec![])
+            .with_stop_time("2022-03-01T14:00:00-00:00"),
+        // A second update of the same record is applied on top of the first one
+        update("r1")
+            .with_start_time("2022-03-01T11:00:00-00:00")
+            .with_stop_time("2022-03-01T15:00:00-00:00"),
+    ];
+    let response = app.bulk_update(&updates).await;
+
+    // Assert
+    assert_eq!(200, response.status().as_u16());
+    let report: BulkUpdateReport = response.json().await.unwrap();
+    let statuses = report
+        .records
+        .iter()
+        .map(|r| (r.record_id.as_str(), r.status))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        statuses,
+        vec![
+            ("r0", UpdateStatus::Updated),
+            ("unknown", UpdateStatus::Unknown),
+            ("r1", UpdateStatus::Updated),
+            ("r1", UpdateStatus::Updated),
+        ]
+    );
+
+    let saved: Record = app.get_single_record("r0").await.json().await.unwrap();
+    assert_eq!(
+        records[0]
+            .clone()
+            .with_stop_time("2022-03-01T13:00:00-00:00"),
+        saved
+    );
+    assert_eq!(Some(3600), saved.runtime);
+
+    let saved: Record = app.get_single_record("r1").await.json().await.unwrap();
+    let expected = records[1]
+        .clone()
+        .with_meta(HashMap::from([
+            ("site_id", vec!["site1"]),
+            ("user_id", vec!["user1"]),
+        ]))
+        .with_component("GPU", 2, vec![])
+        .with_start_time("2022-03-01T11:00:00-00:00")
+        .with_stop_time("2022-03-01T15:00:00-00:00");
+    assert_eq!(expected, saved);
+    assert_eq!(Some(14400), saved.runtime);
+    let meta = saved.meta.unwrap();
+    assert_eq!(Some(&vec!["site1".to_string()]), meta.get("site_id"));
+    assert_eq!(Some(&vec!["user1".to_string()]), meta.get("user_id"));
+
+    // Records without updates are left untouched
+    let saved: Record = app.get_single_record("r2").await.json().await.unwrap();
+    assert_eq!(records[2], saved);
+    assert_eq!(None, saved.stop_time);
+}
+
+#[tokio::test]
+async fn bulk_update_returns_a_400_for_invalid_updates() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    let response = app
+        .bulk_update(&serde_json::json!([{ "record_id": "r1" }]))
+        .await;
+
+    // Assert
+    assert_eq!(400, response.status().as_u16());
+}
+
+#[tokio::test]
+async fn update_with_meta_mode_replace_replaces_meta() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let records = ["r1", "r2"].map(|record_id| {
+        RecordTest::new()
+            .with_record_id(record_id)
+            .with_meta(HashMap::from([
+                ("site_id", vec!["site1"]),
+                ("user_id", vec!["user1"]),
+            ]))
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2022-03-01T12:00:00-00:00")
+    });
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    // Act
+    let response = client
+        .put(format!("{}/record", &app.address))
+        .json(&serde_json::json!({
+            "record_id": "r1",
+            "meta": {"group_id": ["group1"]},
+            "meta_mode": "replace",
+            "components": [],
+            "stop_time": "2022-03-01T13:00:00Z",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app
+        .bulk_update(&serde_json::json!([{
+            "record_id": "r2",
+            "meta": null,
+            "meta_mode": "replace",
+            "components": [],
+            "stop_time": "2022-03-01T13:00:00Z",
+        }]))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Assert
+    let saved: Record = app.get_single_record("r1").await.json().await.unwrap();
+    assert_eq!(
+        vec![("group_id".to_string(), vec!["group1".to_string()])],
+        saved.meta.unwrap().to_vec()
+    );
+    assert_eq!(Some(3600), saved.runtime);
+
+    let saved: Record = app.get_single_record("r2").await.json().await.unwrap();
+    assert!(saved.meta.is_none_or(|meta| meta.to_vec().is_empty()));
+}
+
+#[tokio::test]
+async fn update_with_meta_mode_replace_without_meta_removes_meta() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let records = ["r1", "r2"].map(|record_id| {
+        RecordTest::new()
+            .with_record_id(record_id)
+            .with_meta(HashMap::from([("site_id", vec!["site1"])]))
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2022-03-01T12:00:00-00:00")
+    });
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    // Act
+    let response = client
+        .put(format!("{}/record", &app.address))
+        .json(&serde_json::json!({
+            "record_id": "r1",
+            "meta_mode": "replace",
+            "components": [],
+            "stop_time": "2022-03-01T13:00:00Z",
+        }))
+        .send()
+        .await
+        .expect("Failed to execute request.");
+    assert_eq!(200, response.status().as_u16());
+
+    let response = app
+        .bulk_update(&serde_json::json!([{
+            "record_id": "r2",
+            "meta_mode": "replace",
+            "components": [],
+            "stop_time": "2022-03-01T13:00:00Z",
+        }]))
+        .await;
+    assert_eq!(200, response.status().as_u16());
+
+    // Assert
+    let without_meta: Vec<String> = sqlx::query_scalar(
+        "SELECT record_id FROM auditor_accounting WHERE meta IS NULL ORDER BY record_id",
+    )
+    .fetch_all(&app.db_pool)
+    .await
+    .unwrap();
+    assert_eq!(without_meta, vec!["r1", "r2"]);
+}
+
+#[tokio::test]
+async fn update_returns_a_400_if_start_time_is_after_stop_time() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+
+    let records = ["r1", "r2"].map(|record_id| {
+        RecordTest::new()
+            .with_record_id(record_id)
+            .with_component("CPU", 1, vec![])
+            .with_start_time("2022-03-01T12:00:00-00:00")
+    });
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    let update = |record_id: &str| RecordTest {
+        components: Some(vec![]),
+        ..RecordTest::new().with_record_id(record_id)
+    };
+
+    for body in [
+        // The stored start_time lies after the stop_time
+        update("r1").with_stop_time("2022-03-01T11:00:00-00:00"),
+        // The corrected start_time lies after the stop_time
+        update("r1")
+            .with_start_time("2022-03-01T14:00:00-00:00")
+            .with_stop_time("2022-03-01T13:00:00-00:00"),
+    ] {
+        // Act
+        let response = client
+            .put(format!("{}/record", &app.address))
+            .json(&body)
+            .send()
+            .await
+            .expect("Failed to execute request.");
+
+        // Assert
+        assert_eq!(400, response.status().as_u16());
+    }
+
+    // None of the updates of a bulk update is applied
+    let response = app
+        .bulk_update(&[
+            update("r1").with_stop_time("2022-03-01T13:00:00-00:00"),
+            update("r2").with_stop_time("2022-03-01T11:00:00-00:00"),
+        ])
+        .await;
+    assert_eq!(400, response.status().as_u16());
+
+    for record in records {
+        let saved: Record = app
+            .get_single_record(record.record_id.as_deref().unwrap())
+            .await
+            .json()
+            .await
+            .unwrap();
+        assert_eq!(None, saved.stop_time);
+        assert_eq!(None, saved.runtime);
+    }
+}
@@ -0,0 +1,174 @@
+use crate::helpers::{spawn_app, spawn_app_with};
+use auditor::domain::{Record, RecordFrame, RecordTest};
+use fake::{Fake, Faker};
+use serde_json::{Value, json};
+
+#[tokio::test]
+async fn v1_keeps_the_behaviour_of_unversioned_paths() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let record: RecordTest = Faker.fake();
+
+    assert_eq!(200, app.add_record(&record).await.status().as_u16());
+
+    for prefix in ["", "/v1"] {
+        // Act
+        let response = client
+            .post(format!("{}{prefix}/record", app.address))
+            .json(&record)
+            .send()
+            .await
+            .unwrap();
+        // Assert
+        assert_eq!(500, response.status().as_u16(), "{prefix}");
+        assert_eq!("RECORD_EXISTS", response.text().await.unwrap());
+
+        let response = client
+            .get(format!("{}{prefix}/record/unknown", app.address))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(200, response.status().as_u16(), "{prefix}");
+        assert_eq!("null", response.text().await.unwrap());
+    }
+}
+
+#[tokio::test]
+async fn v2_returns_json_errors_with_proper_status_codes() {
+    // Arrange
+    let app = spawn_app().await;
+    let client = reqwest::Client::new();
+    let record = Faker.fake::<RecordTest>().with_record_id("existing");
+    assert_eq!(200, app.add_record(&record).await.status().as_u16());
+
+    // Act
+    let duplicate = client
+        .post(format!("{}/v2/record", app.address))
+        .json(&record)
+        .send()
+        .await
+        .unwrap();
+    let bulk_duplicate = client
+        .post(format!("{}/v2/records", app.address))
+        .json(&[&record])
+        .send()
+        .await
+        .unwrap();
+    let missing = client
+        .get(format!("{}/v2/record/unknown", app.address))
+        .send()
+        .await
+        .unwrap();
+    let invalid = client
+        .post(format!("{}/v2/record", app.address))
+        .json(&json!({ "record_id": "r1" }))
+        .send()
+        .await
+        .unwrap();
+    let unknown_path = client
+        .get(format!("{}/v2/unknown", app.address))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    for (response, status) in [
+        (duplicate, 409),
+        (bulk_duplicate, 409),
+        (missing, 404),
+        (invalid, 400),
+        (unknown_path, 404),
+    ] {
+        assert_eq!(status, response.status().as_u16(), "{}", response.url());
+        let body: Value = response.json().await.unwrap();
+        assert!(body["error"].is_string(), "{body}");
+    }
+
+    // Existing records are returned as in v1
+    let response = client
+        .get(format!("{}/v2/record/existing", app.address))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(200, response.status().as_u16());
+    assert_eq!(record, response.json::<Record>().await.unwrap());
+}
+
+#[tokio::test]
+async fn v2_returns_authentication_errors_as_json() {
+    // Arrange
+    let app = spawn_app_with(|config| config.auth.enabled = true).await;
+    let client = reqwest::Client::new();
+
+    // Act
+    let v1 = client
+        .get(format!("{}/v1/records", app.address))
+        .send()
+        .await
+        .unwrap();
+    let v2 = client
+        .get(format!("{}/v2/records", app.address))
+        .send()
+        .await
+        .unwrap();
+    let health_check = client
+        .get(format!("{}/v2/health_check", app.address))
+        .send()
+        .await
+        .unwrap();
+
+    // Assert
+    assert_eq!(401, v1.status().as_u16());
+    assert_ne!(
+        Some("application/json"),
+        v1.headers()
+            .get("Content-Type")
+            .and_then(|value| value.to_str().ok())
+    );
+    assert_eq!(401, v2.status().as_u16());
+    let body: Value = v2.json().await.unwrap();
+    assert!(body["error"].is_string(), "{body}");
+    assert_eq!(200, health_check.status().as_u16());
+}
+
+#[tokio::test]
+async fn v2_frames_ndjson_streams() {
+    // Arrange
+    let app = spawn_app().await;
+    let records: Vec<RecordTest> = (0..3)
+        .map(|i| {
+            Faker
+                .fake::<RecordTest>()
+                .with_record_id(format!("r{i}"))
+                .with_start_time(format!("2022-10-0{}T12:00:00-00:00", i + 1))
+        })
+        .collect();
+    assert_eq!(200, app.bulk_insert(&records).await.status().as_u16());
+
+    for (query, count) in [("", 3), ("?limit=2", 2)] {
+        // Act
+        let response = reqwest::Client::new()
+            .get(format!("{}/v2/records{query}", app.address))
+            .header("Accept", "application/x-ndjson")
+            .send()
+            .await
+            .unwrap();
+
+        // Assert
+        assert_eq!(200, response.status().as_u16());
+        let body = response.text().await.unwrap();
+        let frames: Vec<RecordFrame> = body
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        let (last, records) = frames.split_last().unwrap();
+        assert_eq!(last, &RecordFrame::End { count });
+        assert_eq!(records.len() as u64, count);
+        assert!(
+            records
+                .iter()
+                .all(|frame| matches!(frame, RecordFrame::Record(_)))
+        );
+    }
+}
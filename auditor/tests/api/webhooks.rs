@@ -0,0 +1,233 @@
+use crate::helpers::spawn_app;
+use auditor::admin::prune_webhooks;
+use auditor::configuration::{WebhookTarget, get_configuration};
+use auditor::domain::{Record, RecordTest};
+use auditor::webhooks::{WebhookDispatcher, WebhookEvent};
+use sqlx::PgPool;
+use std::collections::HashMap;
+use wiremock::matchers::{header, method, path};
+use wiremock::{Mock, MockServer, ResponseTemplate};
+
+fn record(record_id: &str, site: &str) -> RecordTest {
+    RecordTest::new()
+        .with_record_id(record_id)
+        .with_meta(HashMap::from([("site_id", vec![site])]))
+        .with_component("CPU", 1, vec![])
+        .with_start_time("2024-01-01T00:00:00Z")
+}
+
+fn dispatcher(pool: &PgPool, url: String, filter: Option<&str>) -> WebhookDispatcher {
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.webhooks.targets = vec![WebhookTarget {
+        name: "billing".to_string(),
+        url,
+        filter: filter.map(str::to_string),
+        events: vec![WebhookEvent::RecordCompleted],
+    }];
+    WebhookDispatcher::new(pool.clone(), &configuration).unwrap()
+}
+
+async fn outbox(pool: &PgPool) -> Vec<(String, i32, Option<String>)> {
+    sqlx::query_as("SELECT event, attempts, last_error FROM webhook_outbox ORDER BY id")
+        .fetch_all(pool)
+        .await
+        .unwrap()
+}
+
+#[tokio::test]
+async fn completed_records_matching_the_filter_are_posted() {
+    // Arrange
+    let app = spawn_app().await;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .and(path("/hook"))
+        .and(header("X-Auditor-Event", "record_completed"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    let dispatcher = dispatcher(
+        &app.db_pool,
+        format!("{}/hook", mock_server.uri()),
+        Some("meta[site_id][c]=site1"),
+    );
+    dispatcher.register().await.unwrap();
+
+    for r in [record("r1", "site1"), record("r2", "site2")] {
+        assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    }
+    let completed: Vec<_> = [record("r1", "site1"), record("r2", "site2")]
+        .into_iter()
+        .map(|r| r.with_stop_time("2024-01-01T01:00:00Z"))
+        .collect();
+    assert_eq!(200, app.bulk_update(&completed).await.status().as_u16());
+
+    // Act
+    // Two additions and two completions, of which only the completion of r1 matches
+    assert_eq!(4, dispatcher.enqueue().await.unwrap());
+    assert_eq!(1, outbox(&app.db_pool).await.len());
+    assert_eq!(1, dispatcher.deliver().await.unwrap());
+
+    // Assert
+    let requests = mock_server.received_requests().await.unwrap();
+    let posted: Record = serde_json::from_slice(&requests[0].body).unwrap();
+    assert_eq!(posted.record_id, "r1");
+    assert_eq!(posted.runtime, Some(3600));
+    assert!(outbox(&app.db_pool).await.is_empty());
+}
+
+#[tokio::test]
+async fn failed_deliveries_are_retried() {
+    // Arrange
+    let app = spawn_app().await;
+    let mock_server = MockServer::start().await;
+    let dispatcher = dispatcher(&app.db_pool, format!("{}/hook", mock_server.uri()), None);
+    dispatcher.register().await.unwrap();
+
+    let r = record("r1", "site1");
+    assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    let r = r.with_stop_time("2024-01-01T01:00:00Z");
+    assert_eq!(200, app.bulk_update(&[r]).await.status().as_u16());
+    dispatcher.enqueue().await.unwrap();
+
+    // Act
+    let failure = Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(1)
+        .mount_as_scoped(&mock_server)
+        .await;
+    assert_eq!(0, dispatcher.deliver().await.unwrap());
+    drop(failure);
+
+    // Assert
+    let pending = outbox(&app.db_pool).await;
+    assert_eq!(1, pending.len());
+    assert_eq!(1, pending[0].1);
+    assert!(pending[0].2.as_ref().unwrap().contains("500"));
+
+    // The next attempt is only made after the backoff
+    assert_eq!(0, dispatcher.deliver().await.unwrap());
+
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(200))
+        .expect(1)
+        .mount(&mock_server)
+        .await;
+    sqlx::query("UPDATE webhook_outbox SET next_attempt_at = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(1, dispatcher.deliver().await.unwrap());
+    assert!(outbox(&app.db_pool).await.is_empty());
+}
+
+#[tokio::test]
+async fn deliveries_are_deleted_after_the_last_attempt() {
+    // Arrange
+    let app = spawn_app().await;
+    let mock_server = MockServer::start().await;
+    Mock::given(method("POST"))
+        .respond_with(ResponseTemplate::new(500))
+        .expect(2)
+        .mount(&mock_server)
+        .await;
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.webhooks.max_attempts = 2;
+    configuration.webhooks.targets = vec![WebhookTarget {
+        name: "billing".to_string(),
+        url: format!("{}/hook", mock_server.uri()),
+        filter: None,
+        events: vec![WebhookEvent::RecordAdded],
+    }];
+    let dispatcher = WebhookDispatcher::new(app.db_pool.clone(), &configuration).unwrap();
+    dispatcher.register().await.unwrap();
+
+    for r in [record("r1", "site1"), record("r2", "site1")] {
+        assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    }
+    dispatcher.enqueue().await.unwrap();
+    // The attempt of r2 was interrupted after it had been claimed for the last time
+    sqlx::query(
+        "UPDATE webhook_outbox SET attempts = 2, next_attempt_at = now() + interval '1 hour'
+         WHERE record->>'record_id' = 'r2'",
+    )
+    .execute(&app.db_pool)
+    .await
+    .unwrap();
+
+    // Act
+    assert_eq!(0, dispatcher.deliver().await.unwrap());
+    assert_eq!(1, outbox(&app.db_pool).await.len());
+    sqlx::query("UPDATE webhook_outbox SET next_attempt_at = now()")
+        .execute(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, dispatcher.deliver().await.unwrap());
+
+    // Assert
+    assert!(outbox(&app.db_pool).await.is_empty());
+}
+
+#[tokio::test]
+async fn webhooks_which_are_not_configured_are_only_removed_by_pruning() {
+    // Arrange
+    let app = spawn_app().await;
+    let billing = dispatcher(&app.db_pool, "http://127.0.0.1:1/hook".to_string(), None);
+    billing.register().await.unwrap();
+    let r = record("r1", "site1");
+    assert_eq!(200, app.add_record(&r).await.status().as_u16());
+    let r = r.with_stop_time("2024-01-01T01:00:00Z");
+    assert_eq!(200, app.bulk_update(&[r]).await.status().as_u16());
+    billing.enqueue().await.unwrap();
+
+    let mut configuration = get_configuration().expect("Failed to read configuration.");
+    configuration.webhooks.targets = vec![WebhookTarget {
+        name: "monitoring".to_string(),
+        url: "http://127.0.0.1:1/hook".to_string(),
+        filter: None,
+        events: vec![WebhookEvent::RecordAdded],
+    }];
+    let monitoring = WebhookDispatcher::new(app.db_pool.clone(), &configuration).unwrap();
+
+    // Act
+    monitoring.register().await.unwrap();
+
+    // Assert
+    let webhooks = || async {
+        sqlx::query_scalar::<_, String>("SELECT name FROM webhooks ORDER BY name")
+            .fetch_all(&app.db_pool)
+            .await
+            .unwrap()
+    };
+    assert_eq!(webhooks().await, vec!["billing", "monitoring"]);
+    assert_eq!(1, outbox(&app.db_pool).await.len());
+
+    assert_eq!(
+        1,
+        prune_webhooks(&app.db_pool, &configuration).await.unwrap()
+    );
+    assert_eq!(webhooks().await, vec!["monitoring"]);
+    assert!(outbox(&app.db_pool).await.is_empty());
+}
+
+#[tokio::test]
+async fn events_are_only_collected_while_webhooks_are_configured() {
+    // Arrange
+    let app = spawn_app().await;
+
+    // Act
+    assert_eq!(
+        200,
+        app.add_record(&record("r1", "site1"))
+            .await
+            .status()
+            .as_u16()
+    );
+
+    // Assert
+    let events: i64 = sqlx::query_scalar("SELECT count(*) FROM webhook_events")
+        .fetch_one(&app.db_pool)
+        .await
+        .unwrap();
+    assert_eq!(0, events);
+}
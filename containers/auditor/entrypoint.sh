@@ -6,23 +6,14 @@ run_auditor() {
   ./auditor "$@"
 }
 
-run_sqlx_migration() {
-
-  AUDITOR_DATABASE__USERNAME="${AUDITOR_DATABASE__USERNAME:=postgres}"
-  AUDITOR_DATABASE__PASSWORD="${AUDITOR_DATABASE__PASSWORD:=password}"
-  AUDITOR_DATABASE__HOST="${AUDITOR_DATABASE__HOST:=localhost}"
-  AUDITOR_DATABASE__PORT="${AUDITOR_DATABASE__PORT:=5432}"
-  AUDITOR_DATABASE__DATABASE_NAME="${AUDITOR_DATABASE__DATABASE_NAME:=auditor}"
-
-  export DATABASE_URL=postgres://${AUDITOR_DATABASE__USERNAME}:${AUDITOR_DATABASE__PASSWORD}@${AUDITOR_DATABASE__HOST}:${AUDITOR_DATABASE__PORT}/${AUDITOR_DATABASE__DATABASE_NAME}
-  ./sqlx database create
-  ./sqlx migrate run
+run_migration() {
+  ./auditor migrate "$@"
 }
 
 help() {
   echo "Available commands:"
   echo "  auditor: Run AUDITOR"
-  echo "  migrate: Run sqlx migrations"
+  echo "  migrate: Create the database and run migrations"
   echo "  shell: Start a shell session (for debugging)"
   echo "  help: Show this help message"
 }
@@ -37,7 +28,7 @@ shift
 if [ "$command" = "auditor" ]; then
   run_auditor "$@"
 elif [ "$command" = "migrate" ]; then
-  run_sqlx_migration
+  run_migration "$@"
 elif [ "$command" = "shell" ]; then
   /bin/bash
 elif [ "$command" = "help" ]; then
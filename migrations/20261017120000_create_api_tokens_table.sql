@@ -0,0 +1,8 @@
+-- Create table for API tokens. Only the SHA-256 hash of a token is stored.
+CREATE TABLE api_tokens (
+    name        TEXT NOT NULL,
+    PRIMARY KEY (name),
+    token_hash  TEXT NOT NULL UNIQUE,
+    role        TEXT NOT NULL CHECK (role IN ('collector', 'reader', 'admin')),
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
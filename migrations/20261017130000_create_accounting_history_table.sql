@@ -0,0 +1,64 @@
+-- History of the changes of records. For inserts, `new_values` contains all values of the
+-- record. For updates, `old_values` and `new_values` only contain the values which changed.
+CREATE TABLE auditor_accounting_history (
+    id          BIGINT GENERATED ALWAYS AS IDENTITY,
+    PRIMARY KEY (id),
+    record_id   TEXT NOT NULL,
+    operation   TEXT NOT NULL CHECK (operation IN ('insert', 'update')),
+    old_values  JSONB,
+    new_values  JSONB NOT NULL,
+    changed_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    -- Name of the authenticated client, set with `SET LOCAL auditor.client` by the server
+    changed_by  TEXT
+);
+
+CREATE INDEX auditor_accounting_history_record_id_idx
+    ON auditor_accounting_history (record_id, id);
+
+CREATE FUNCTION auditor_accounting_record_history() RETURNS trigger AS $$
+DECLARE
+    old_values JSONB;
+    new_values JSONB;
+BEGIN
+    new_values := jsonb_build_object(
+        'start_time', NEW.start_time,
+        'stop_time', NEW.stop_time,
+        'runtime', NEW.runtime,
+        'meta', NEW.meta,
+        'components', NEW.components
+    );
+
+    IF TG_OP = 'UPDATE' THEN
+        old_values := jsonb_build_object(
+            'start_time', OLD.start_time,
+            'stop_time', OLD.stop_time,
+            'runtime', OLD.runtime,
+            'meta', OLD.meta,
+            'components', OLD.components
+        );
+        SELECT jsonb_object_agg(o.key, o.value), jsonb_object_agg(o.key, new_values -> o.key)
+        INTO old_values, new_values
+        FROM jsonb_each(old_values) AS o
+        WHERE o.value IS DISTINCT FROM new_values -> o.key;
+
+        -- Only `updated_at` changed
+        IF old_values IS NULL THEN
+            RETURN NULL;
+        END IF;
+    END IF;
+
+    INSERT INTO auditor_accounting_history (record_id, operation, old_values, new_values, changed_by)
+    VALUES (
+        NEW.record_id,
+        lower(TG_OP),
+        old_values,
+        new_values,
+        NULLIF(current_setting('auditor.client', true), '')
+    );
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auditor_accounting_history
+    AFTER INSERT OR UPDATE ON auditor_accounting
+    FOR EACH ROW EXECUTE FUNCTION auditor_accounting_record_history();
@@ -0,0 +1,12 @@
+-- Notify listeners of the `auditor_records` channel about inserted and updated records. The
+-- payload is the `record_id` of the record.
+CREATE FUNCTION auditor_accounting_notify() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('auditor_records', NEW.record_id);
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auditor_accounting_notify
+    AFTER INSERT OR UPDATE ON auditor_accounting
+    FOR EACH ROW EXECUTE FUNCTION auditor_accounting_notify();
@@ -0,0 +1,65 @@
+-- Names of the configured webhooks. AUDITOR replaces the rows on startup. Events are only
+-- collected if at least one webhook is configured.
+CREATE TABLE webhooks (
+    name  TEXT NOT NULL,
+    PRIMARY KEY (name)
+);
+
+-- Events of records which have not been matched against the webhooks yet
+CREATE TABLE webhook_events (
+    id          BIGINT GENERATED ALWAYS AS IDENTITY,
+    PRIMARY KEY (id),
+    event       TEXT NOT NULL CHECK (event IN ('record_added', 'record_completed')),
+    record      JSONB NOT NULL,
+    created_at  TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+-- Outbox of the deliveries of events to webhooks. Deliveries are deleted once they succeeded.
+CREATE TABLE webhook_outbox (
+    id               BIGINT GENERATED ALWAYS AS IDENTITY,
+    PRIMARY KEY (id),
+    webhook          TEXT NOT NULL REFERENCES webhooks (name) ON DELETE CASCADE,
+    event            TEXT NOT NULL,
+    record           JSONB NOT NULL,
+    attempts         INTEGER NOT NULL DEFAULT 0,
+    next_attempt_at  TIMESTAMPTZ NOT NULL DEFAULT now(),
+    last_error       TEXT,
+    created_at       TIMESTAMPTZ NOT NULL DEFAULT now()
+);
+
+CREATE INDEX webhook_outbox_webhook_next_attempt_at_idx
+    ON webhook_outbox (webhook, next_attempt_at);
+
+CREATE FUNCTION auditor_accounting_webhook_event() RETURNS trigger AS $$
+DECLARE
+    kind TEXT;
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        kind := 'record_added';
+    ELSIF OLD.stop_time IS NULL AND NEW.stop_time IS NOT NULL THEN
+        kind := 'record_completed';
+    ELSE
+        RETURN NULL;
+    END IF;
+
+    IF EXISTS (SELECT 1 FROM webhooks) THEN
+        INSERT INTO webhook_events (event, record)
+        VALUES (
+            kind,
+            jsonb_build_object(
+                'record_id', NEW.record_id,
+                'meta', NEW.meta,
+                'components', NEW.components,
+                'start_time', NEW.start_time,
+                'stop_time', NEW.stop_time,
+                'runtime', NEW.runtime
+            )
+        );
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auditor_accounting_webhook_event
+    AFTER INSERT OR UPDATE ON auditor_accounting
+    FOR EACH ROW EXECUTE FUNCTION auditor_accounting_webhook_event();
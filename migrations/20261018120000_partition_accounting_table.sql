@@ -0,0 +1,285 @@
+-- Partition `auditor_accounting` by month of `stop_time`. Partitions are named
+-- `auditor_accounting_YYYY_MM` (in UTC). Open records and records of months without partition
+-- are stored in `auditor_accounting_default`; AUDITOR creates the partitions of upcoming months
+-- and moves records out of the default partition periodically.
+--
+-- The records are copied into the partitioned table, which takes a while for large tables.
+ALTER TABLE auditor_accounting RENAME TO auditor_accounting_unpartitioned;
+
+CREATE TABLE auditor_accounting (
+    id          INT NOT NULL,
+    record_id   TEXT NOT NULL,
+    meta        JSONB,
+    components  JSONB,
+    start_time  TIMESTAMPTZ NOT NULL,
+    stop_time   TIMESTAMPTZ,
+    runtime     BIGINT,
+    updated_at  TIMESTAMPTZ NOT NULL
+) PARTITION BY RANGE (stop_time);
+
+CREATE TABLE auditor_accounting_default PARTITION OF auditor_accounting DEFAULT;
+
+CREATE INDEX auditor_accounting_record_id_idx ON auditor_accounting (record_id);
+CREATE INDEX auditor_accounting_stop_time_idx ON auditor_accounting (stop_time);
+
+-- Unique constraints of partitioned tables have to contain the partition key, hence the
+-- `record_id`s are kept unique with this table, which is maintained by triggers.
+CREATE TABLE auditor_accounting_record_ids (
+    record_id  TEXT NOT NULL,
+    PRIMARY KEY (record_id)
+);
+
+-- Creates the partition of the month of `month_of` unless it exists, and moves the records of the
+-- month out of the default partition. Returns the name of the created partition.
+CREATE FUNCTION auditor_accounting_create_partition(month_of TIMESTAMPTZ) RETURNS TEXT AS $$
+DECLARE
+    lower_bound     TIMESTAMPTZ := date_trunc('month', month_of, 'UTC');
+    -- Added in UTC, as the result of adding a month depends on the time zone
+    upper_bound     TIMESTAMPTZ :=
+        (date_trunc('month', month_of AT TIME ZONE 'UTC') + interval '1 month') AT TIME ZONE 'UTC';
+    partition_name  TEXT := 'auditor_accounting_' || to_char(month_of AT TIME ZONE 'UTC', 'YYYY_MM');
+BEGIN
+    -- Several instances of AUDITOR may manage the partitions at the same time
+    PERFORM pg_advisory_xact_lock(hashtext('auditor_accounting_partitions'));
+    IF to_regclass(partition_name) IS NOT NULL THEN
+        RETURN NULL;
+    END IF;
+
+    EXECUTE format('CREATE TABLE %I (LIKE auditor_accounting INCLUDING DEFAULTS)', partition_name);
+    EXECUTE format(
+        'WITH moved AS (
+             DELETE FROM auditor_accounting_default
+             WHERE stop_time >= $1 AND stop_time < $2
+             RETURNING *
+         )
+         INSERT INTO %I SELECT * FROM moved',
+        partition_name
+    ) USING lower_bound, upper_bound;
+    -- Deleting the records from the default partition released their `record_id`s
+    EXECUTE format(
+        'INSERT INTO auditor_accounting_record_ids (record_id) SELECT record_id FROM %I',
+        partition_name
+    );
+    EXECUTE format(
+        'ALTER TABLE auditor_accounting ATTACH PARTITION %I FOR VALUES FROM (%L) TO (%L)',
+        partition_name, lower_bound, upper_bound
+    );
+    RETURN partition_name;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Drops the partitions which only contain records that stopped before `before`. Returns the
+-- number of dropped records.
+CREATE FUNCTION auditor_accounting_drop_partitions(before TIMESTAMPTZ) RETURNS BIGINT AS $$
+DECLARE
+    part       RECORD;
+    num        BIGINT;
+    dropped    BIGINT := 0;
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('auditor_accounting_partitions'));
+    FOR part IN
+        SELECT c.relname AS name,
+               substring(pg_get_expr(c.relpartbound, c.oid) FROM 'TO \(''([^'']+)''\)')::timestamptz
+                   AS upper_bound
+        FROM pg_inherits AS i
+        JOIN pg_class AS c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'auditor_accounting'::regclass
+    LOOP
+        -- The default partition has no bounds
+        CONTINUE WHEN part.upper_bound IS NULL OR part.upper_bound > before;
+
+        EXECUTE format(
+            'DELETE FROM auditor_accounting_record_ids
+             WHERE record_id IN (SELECT record_id FROM %I)',
+            part.name
+        );
+        GET DIAGNOSTICS num = ROW_COUNT;
+        EXECUTE format('DROP TABLE %I', part.name);
+        dropped := dropped + num;
+    END LOOP;
+    RETURN dropped;
+END;
+$$ LANGUAGE plpgsql;
+
+SELECT auditor_accounting_create_partition(month)
+FROM (
+    SELECT DISTINCT date_trunc('month', stop_time, 'UTC') AS month
+    FROM auditor_accounting_unpartitioned
+    WHERE stop_time IS NOT NULL
+    UNION
+    SELECT date_trunc('month', now(), 'UTC')
+) AS months
+ORDER BY month;
+
+INSERT INTO auditor_accounting
+SELECT id, record_id, meta, components, start_time, stop_time, runtime, updated_at
+FROM auditor_accounting_unpartitioned;
+
+INSERT INTO auditor_accounting_record_ids (record_id)
+SELECT record_id FROM auditor_accounting_unpartitioned;
+
+DROP TABLE auditor_accounting_unpartitioned;
+
+CREATE SEQUENCE auditor_accounting_id_seq AS INT OWNED BY auditor_accounting.id;
+SELECT setval('auditor_accounting_id_seq', COALESCE(max(id), 0) + 1, false)
+FROM auditor_accounting;
+ALTER TABLE auditor_accounting
+    ALTER COLUMN id SET DEFAULT nextval('auditor_accounting_id_seq');
+
+-- Claims the `record_id` of inserted records. Existing records are rejected with a unique
+-- violation, or skipped if `auditor.skip_existing` is set with `SET LOCAL` by the server.
+CREATE FUNCTION auditor_accounting_claim_record_id() RETURNS trigger AS $$
+BEGIN
+    IF current_setting('auditor.skip_existing', true) = 'on' THEN
+        INSERT INTO auditor_accounting_record_ids (record_id) VALUES (NEW.record_id)
+        ON CONFLICT DO NOTHING;
+        IF NOT FOUND THEN
+            RETURN NULL;
+        END IF;
+    ELSE
+        INSERT INTO auditor_accounting_record_ids (record_id) VALUES (NEW.record_id);
+    END IF;
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE FUNCTION auditor_accounting_release_record_id() RETURNS trigger AS $$
+BEGIN
+    DELETE FROM auditor_accounting_record_ids WHERE record_id = OLD.record_id;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+-- An update which changes the partition of a record deletes the record from the old partition
+-- and inserts it into the new one. Both triggers fire in that order, so the `record_id` is
+-- released and claimed again.
+CREATE TRIGGER auditor_accounting_claim_record_id
+    BEFORE INSERT ON auditor_accounting
+    FOR EACH ROW EXECUTE FUNCTION auditor_accounting_claim_record_id();
+
+CREATE TRIGGER auditor_accounting_release_record_id
+    BEFORE DELETE ON auditor_accounting
+    FOR EACH ROW EXECUTE FUNCTION auditor_accounting_release_record_id();
+
+-- Moving a record to another partition does not fire the `AFTER UPDATE` row triggers, but the
+-- `AFTER INSERT` ones. The triggers of the history, the notifications and the webhooks are
+-- therefore statement level triggers, whose transition tables of updates contain moved records.
+CREATE OR REPLACE FUNCTION auditor_accounting_record_history() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO auditor_accounting_history (record_id, operation, new_values, changed_by)
+        SELECT
+            n.record_id,
+            'insert',
+            jsonb_build_object(
+                'start_time', n.start_time,
+                'stop_time', n.stop_time,
+                'runtime', n.runtime,
+                'meta', n.meta,
+                'components', n.components
+            ),
+            NULLIF(current_setting('auditor.client', true), '')
+        FROM new_rows AS n
+        ORDER BY n.id;
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO auditor_accounting_history (record_id, operation, old_values, new_values, changed_by)
+    SELECT
+        v.record_id,
+        'update',
+        changes.old_values,
+        changes.new_values,
+        NULLIF(current_setting('auditor.client', true), '')
+    FROM (
+        SELECT
+            n.id,
+            n.record_id,
+            jsonb_build_object(
+                'start_time', o.start_time,
+                'stop_time', o.stop_time,
+                'runtime', o.runtime,
+                'meta', o.meta,
+                'components', o.components
+            ) AS old_values,
+            jsonb_build_object(
+                'start_time', n.start_time,
+                'stop_time', n.stop_time,
+                'runtime', n.runtime,
+                'meta', n.meta,
+                'components', n.components
+            ) AS new_values
+        FROM old_rows AS o
+        JOIN new_rows AS n ON n.id = o.id
+    ) AS v
+    CROSS JOIN LATERAL (
+        SELECT jsonb_object_agg(c.key, c.value), jsonb_object_agg(c.key, v.new_values -> c.key)
+        FROM jsonb_each(v.old_values) AS c
+        WHERE c.value IS DISTINCT FROM v.new_values -> c.key
+    ) AS changes(old_values, new_values)
+    -- Records of which only `updated_at` changed
+    WHERE changes.old_values IS NOT NULL
+    ORDER BY v.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION auditor_accounting_notify() RETURNS trigger AS $$
+BEGIN
+    PERFORM pg_notify('auditor_records', n.record_id) FROM new_rows AS n ORDER BY n.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE OR REPLACE FUNCTION auditor_accounting_webhook_event() RETURNS trigger AS $$
+BEGIN
+    IF NOT EXISTS (SELECT 1 FROM webhooks) THEN
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO webhook_events (event, record)
+        SELECT 'record_added', to_jsonb(n) - 'id' - 'updated_at'
+        FROM new_rows AS n
+        ORDER BY n.id;
+    ELSE
+        INSERT INTO webhook_events (event, record)
+        SELECT 'record_completed', to_jsonb(n) - 'id' - 'updated_at'
+        FROM old_rows AS o
+        JOIN new_rows AS n ON n.id = o.id
+        WHERE o.stop_time IS NULL AND n.stop_time IS NOT NULL
+        ORDER BY n.id;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auditor_accounting_history_insert
+    AFTER INSERT ON auditor_accounting
+    REFERENCING NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_record_history();
+
+CREATE TRIGGER auditor_accounting_history_update
+    AFTER UPDATE ON auditor_accounting
+    REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_record_history();
+
+CREATE TRIGGER auditor_accounting_notify_insert
+    AFTER INSERT ON auditor_accounting
+    REFERENCING NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_notify();
+
+CREATE TRIGGER auditor_accounting_notify_update
+    AFTER UPDATE ON auditor_accounting
+    REFERENCING NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_notify();
+
+CREATE TRIGGER auditor_accounting_webhook_event_insert
+    AFTER INSERT ON auditor_accounting
+    REFERENCING NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_webhook_event();
+
+CREATE TRIGGER auditor_accounting_webhook_event_update
+    AFTER UPDATE ON auditor_accounting
+    REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_webhook_event();
@@ -0,0 +1,108 @@
+-- Usage per day of `stop_time` (in UTC), site, group, user and component. A record with
+-- several values for `site_id`, `group_id` or `user_id` is accounted for in each combination of
+-- the values. Records without `stop_time` are not included.
+CREATE TABLE daily_usage (
+    day             DATE NOT NULL,
+    site_id         TEXT,
+    group_id        TEXT,
+    user_id         TEXT,
+    component       TEXT,
+    -- Sum of the amounts of the component multiplied by the runtime of the records
+    amount_runtime  DOUBLE PRECISION NOT NULL,
+    record_count    BIGINT NOT NULL
+);
+
+CREATE INDEX daily_usage_day_idx ON daily_usage (day);
+
+-- Days whose usage has to be computed again. Each statement changing records adds the days of
+-- the records, and AUDITOR periodically updates `daily_usage` for these days. Rows are only
+-- deleted once the days have been updated, hence changes of concurrent transactions are not
+-- lost.
+CREATE TABLE daily_usage_changes (
+    id   BIGINT GENERATED ALWAYS AS IDENTITY,
+    PRIMARY KEY (id),
+    day  DATE NOT NULL
+);
+
+CREATE INDEX daily_usage_changes_day_idx ON daily_usage_changes (day);
+
+CREATE FUNCTION auditor_accounting_daily_usage_change() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO daily_usage_changes (day)
+        SELECT DISTINCT (stop_time AT TIME ZONE 'UTC')::date
+        FROM new_rows
+        WHERE stop_time IS NOT NULL;
+    ELSIF TG_OP = 'UPDATE' THEN
+        INSERT INTO daily_usage_changes (day)
+        SELECT DISTINCT (stop_time AT TIME ZONE 'UTC')::date
+        FROM (SELECT stop_time FROM old_rows UNION ALL SELECT stop_time FROM new_rows) AS changed
+        WHERE stop_time IS NOT NULL;
+    ELSE
+        INSERT INTO daily_usage_changes (day)
+        SELECT DISTINCT (stop_time AT TIME ZONE 'UTC')::date
+        FROM old_rows
+        WHERE stop_time IS NOT NULL;
+    END IF;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+CREATE TRIGGER auditor_accounting_daily_usage_insert
+    AFTER INSERT ON auditor_accounting
+    REFERENCING NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_daily_usage_change();
+
+CREATE TRIGGER auditor_accounting_daily_usage_update
+    AFTER UPDATE ON auditor_accounting
+    REFERENCING OLD TABLE AS old_rows NEW TABLE AS new_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_daily_usage_change();
+
+CREATE TRIGGER auditor_accounting_daily_usage_delete
+    AFTER DELETE ON auditor_accounting
+    REFERENCING OLD TABLE AS old_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_daily_usage_change();
+
+-- Dropping partitions does not fire triggers, hence the days of the dropped records are added
+-- to the changes as well.
+CREATE OR REPLACE FUNCTION auditor_accounting_drop_partitions(before TIMESTAMPTZ) RETURNS BIGINT AS $$
+DECLARE
+    part       RECORD;
+    num        BIGINT;
+    dropped    BIGINT := 0;
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('auditor_accounting_partitions'));
+    FOR part IN
+        SELECT c.relname AS name,
+               substring(pg_get_expr(c.relpartbound, c.oid) FROM 'TO \(''([^'']+)''\)')::timestamptz
+                   AS upper_bound
+        FROM pg_inherits AS i
+        JOIN pg_class AS c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'auditor_accounting'::regclass
+    LOOP
+        -- The default partition has no bounds
+        CONTINUE WHEN part.upper_bound IS NULL OR part.upper_bound > before;
+
+        EXECUTE format(
+            'DELETE FROM auditor_accounting_record_ids
+             WHERE record_id IN (SELECT record_id FROM %I)',
+            part.name
+        );
+        GET DIAGNOSTICS num = ROW_COUNT;
+        EXECUTE format(
+            'INSERT INTO daily_usage_changes (day)
+             SELECT DISTINCT (stop_time AT TIME ZONE ''UTC'')::date FROM %I',
+            part.name
+        );
+        EXECUTE format('DROP TABLE %I', part.name);
+        dropped := dropped + num;
+    END LOOP;
+    RETURN dropped;
+END;
+$$ LANGUAGE plpgsql;
+
+-- The usage of existing records is computed by AUDITOR after the migration
+INSERT INTO daily_usage_changes (day)
+SELECT DISTINCT (stop_time AT TIME ZONE 'UTC')::date
+FROM auditor_accounting
+WHERE stop_time IS NOT NULL;
@@ -0,0 +1,164 @@
+-- Deletions of records are recorded in their history as well. `old_values` contains all values
+-- of the deleted record and `new_values` is empty.
+ALTER TABLE auditor_accounting_history
+    DROP CONSTRAINT auditor_accounting_history_operation_check,
+    ADD CONSTRAINT auditor_accounting_history_operation_check
+        CHECK (operation IN ('insert', 'update', 'delete'));
+
+CREATE OR REPLACE FUNCTION auditor_accounting_record_history() RETURNS trigger AS $$
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        INSERT INTO auditor_accounting_history (record_id, operation, new_values, changed_by)
+        SELECT
+            n.record_id,
+            'insert',
+            jsonb_build_object(
+                'start_time', n.start_time,
+                'stop_time', n.stop_time,
+                'runtime', n.runtime,
+                'meta', n.meta,
+                'components', n.components
+            ),
+            NULLIF(current_setting('auditor.client', true), '')
+        FROM new_rows AS n
+        ORDER BY n.id;
+        RETURN NULL;
+    END IF;
+
+    IF TG_OP = 'DELETE' THEN
+        INSERT INTO auditor_accounting_history (record_id, operation, old_values, new_values, changed_by)
+        SELECT
+            o.record_id,
+            'delete',
+            jsonb_build_object(
+                'start_time', o.start_time,
+                'stop_time', o.stop_time,
+                'runtime', o.runtime,
+                'meta', o.meta,
+                'components', o.components
+            ),
+            '{}'::jsonb,
+            NULLIF(current_setting('auditor.client', true), '')
+        FROM old_rows AS o
+        ORDER BY o.id;
+        RETURN NULL;
+    END IF;
+
+    INSERT INTO auditor_accounting_history (record_id, operation, old_values, new_values, changed_by)
+    SELECT
+        v.record_id,
+        'update',
+        changes.old_values,
+        changes.new_values,
+        NULLIF(current_setting('auditor.client', true), '')
+    FROM (
+        SELECT
+            n.id,
+            n.record_id,
+            jsonb_build_object(
+                'start_time', o.start_time,
+                'stop_time', o.stop_time,
+                'runtime', o.runtime,
+                'meta', o.meta,
+                'components', o.components
+            ) AS old_values,
+            jsonb_build_object(
+                'start_time', n.start_time,
+                'stop_time', n.stop_time,
+                'runtime', n.runtime,
+                'meta', n.meta,
+                'components', n.components
+            ) AS new_values
+        FROM old_rows AS o
+        JOIN new_rows AS n ON n.id = o.id
+    ) AS v
+    CROSS JOIN LATERAL (
+        SELECT jsonb_object_agg(c.key, c.value), jsonb_object_agg(c.key, v.new_values -> c.key)
+        FROM jsonb_each(v.old_values) AS c
+        WHERE c.value IS DISTINCT FROM v.new_values -> c.key
+    ) AS changes(old_values, new_values)
+    -- Records of which only `updated_at` changed
+    WHERE changes.old_values IS NOT NULL
+    ORDER BY v.id;
+    RETURN NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Records moved to another partition by an update do not fire statement level delete triggers.
+CREATE TRIGGER auditor_accounting_history_delete
+    AFTER DELETE ON auditor_accounting
+    REFERENCING OLD TABLE AS old_rows
+    FOR EACH STATEMENT EXECUTE FUNCTION auditor_accounting_record_history();
+
+-- Removes the values of the records `record_ids` from their history, e.g. when they are deleted
+-- by a retention policy. Only the deletion entries are kept, without the values of the records,
+-- so that it stays visible when and by whom the records were deleted.
+CREATE FUNCTION auditor_accounting_purge_history(record_ids TEXT[]) RETURNS VOID AS $$
+BEGIN
+    DELETE FROM auditor_accounting_history
+    WHERE record_id = ANY(record_ids) AND operation <> 'delete';
+    UPDATE auditor_accounting_history
+    SET old_values = NULL
+    WHERE record_id = ANY(record_ids) AND operation = 'delete' AND old_values IS NOT NULL;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Dropping partitions does not fire triggers, hence the deletion entries are added and the
+-- history of the dropped records is purged here.
+CREATE OR REPLACE FUNCTION auditor_accounting_drop_partitions(before TIMESTAMPTZ) RETURNS BIGINT AS $$
+DECLARE
+    part       RECORD;
+    num        BIGINT;
+    dropped    BIGINT := 0;
+BEGIN
+    PERFORM pg_advisory_xact_lock(hashtext('auditor_accounting_partitions'));
+    FOR part IN
+        SELECT c.relname AS name,
+               substring(pg_get_expr(c.relpartbound, c.oid) FROM 'TO \(''([^'']+)''\)')::timestamptz
+                   AS upper_bound
+        FROM pg_inherits AS i
+        JOIN pg_class AS c ON c.oid = i.inhrelid
+        WHERE i.inhparent = 'auditor_accounting'::regclass
+    LOOP
+        -- The default partition has no bounds
+        CONTINUE WHEN part.upper_bound IS NULL OR part.upper_bound > before;
+
+        EXECUTE format(
+            'DELETE FROM auditor_accounting_record_ids
+             WHERE record_id IN (SELECT record_id FROM %I)',
+            part.name
+        );
+        GET DIAGNOSTICS num = ROW_COUNT;
+        EXECUTE format(
+            'INSERT INTO daily_usage_changes (day)
+             SELECT DISTINCT (stop_time AT TIME ZONE ''UTC'')::date FROM %I',
+            part.name
+        );
+        EXECUTE format(
+            'DELETE FROM auditor_accounting_history AS h
+             USING %I AS p
+             WHERE h.record_id = p.record_id AND h.operation <> ''delete''',
+            part.name
+        );
+        EXECUTE format(
+            'UPDATE auditor_accounting_history AS h
+             SET old_values = NULL
+             FROM %I AS p
+             WHERE h.record_id = p.record_id AND h.operation = ''delete''
+                 AND h.old_values IS NOT NULL',
+            part.name
+        );
+        EXECUTE format(
+            'INSERT INTO auditor_accounting_history (record_id, operation, new_values, changed_by)
+             SELECT record_id, ''delete'', ''{}''::jsonb,
+                    NULLIF(current_setting(''auditor.client'', true), '''')
+             FROM %I
+             ORDER BY id',
+            part.name
+        );
+        EXECUTE format('DROP TABLE %I', part.name);
+        dropped := dropped + num;
+    END LOOP;
+    RETURN dropped;
+END;
+$$ LANGUAGE plpgsql;
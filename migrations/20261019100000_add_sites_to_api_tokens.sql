@@ -0,0 +1,3 @@
+-- Tokens stored in the database can be restricted to the records of some sites, like tokens in
+-- the configuration. NULL means that the token is not restricted.
+ALTER TABLE api_tokens ADD COLUMN sites TEXT[];
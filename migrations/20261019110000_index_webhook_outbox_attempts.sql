@@ -0,0 +1,7 @@
+-- Deliveries are claimed by webhook, due time and number of attempts. Including the attempts
+-- lets the claim skip exhausted deliveries, which are only deleted by the next round of
+-- deliveries, within the index.
+DROP INDEX webhook_outbox_webhook_next_attempt_at_idx;
+
+CREATE INDEX webhook_outbox_webhook_next_attempt_at_attempts_idx
+    ON webhook_outbox (webhook, next_attempt_at, attempts);
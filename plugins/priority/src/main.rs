@@ -30,6 +30,9 @@ type ResourceValue = f64;
 type PriorityName = String;
 type PriorityValue = i64;
 
+// The resources are still computed from the downloaded records, because the configured
+// components of a record are multiplied with each other, whereas `AuditorClient::aggregate`
+// sums up each component separately. Moving to `aggregate` is left as follow-up work.
 #[tracing::instrument(name = "Extracting resources from records", skip(records, config))]
 fn extract(records: Vec<Record>, config: &Settings) -> HashMap<ResourceName, ResourceValue> {
     if config.components.is_empty() {
@@ -114,15 +117,14 @@ fn extract(records: Vec<Record>, config: &Settings) -> HashMap<ResourceName, Res
         };
         // If no group_id is present in the record, then record will be silently ignored
         if let Some(meta) = r.meta.as_ref() {
-            if let Some(groups) = meta.get("group_id") {
-                if let Some(group_id) = groups.first() {
-                    // Only consider configured groups
-                    if config.group_mapping.contains_key(group_id) {
-                        // we know that the key exists (we filled it beforehand), therefore we can unwrap
-                        *resources.get_mut(group_id).unwrap() += val;
-                        println!("Resources: {resources:?}");
-                    }
-                }
+            if let Some(groups) = meta.get("group_id")
+                && let Some(group_id) = groups.first()
+                // Only consider configured groups
+                && config.group_mapping.contains_key(group_id)
+            {
+                // we know that the key exists (we filled it beforehand), therefore we can unwrap
+                *resources.get_mut(group_id).unwrap() += val;
+                println!("Resources: {resources:?}");
             }
         } else {
             error!(record_id = %r.record_id, "Record without group_id, ignoring.");
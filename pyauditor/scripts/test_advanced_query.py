@@ -220,6 +220,14 @@ async def main():
     records = await client.advanced_query(query_string)
     assert len(records) == 1
 
+    sort_by = SortBy().descending("start_time")
+    query = QueryBuilder().sort_by(sort_by)
+
+    record_ids = [record.record_id async for record in client.paginate(query, 4)]
+    assert len(record_ids) == 34
+    for i in range(0, 10):
+        assert record_ids[i] == f"record2-{9 - i:02d}"
+
 
 if __name__ == "__main__":
     import time
@@ -136,6 +136,17 @@ impl AuditorClientBuilder {
         self_
     }
 
+    /// bearer_token(token: str)
+    /// Set an API token which is sent as bearer token with every request.
+    /// This is required if authentication is enabled on the Auditor server.
+    ///
+    /// :param token: API token
+    /// :type token: str
+    pub fn bearer_token(mut self_: PyRefMut<Self>, token: String) -> PyRefMut<Self> {
+        self_.inner = self_.inner.clone().bearer_token(&token);
+        self_
+    }
+
     /// Build an ``AuditorClient`` from ``AuditorClientBuilder``
     pub fn build(&self) -> Result<AuditorClient, Error> {
         Ok(AuditorClient {
@@ -12,7 +12,9 @@ use pyo3::IntoPyObjectExt;
 use pyo3::ffi::c_str;
 use pyo3::prelude::*;
 use pyo3::types::PyDateTime;
-use std::collections::HashMap;
+use std::collections::{HashMap, VecDeque};
+use std::sync::Arc;
+use tokio::sync::Mutex;
 
 /// The `QueryBuilder` is used to construct `QueryParameters` using the builder pattern.
 #[pyclass]
@@ -103,6 +105,23 @@ impl Value {
             inner: auditor_client::Value::Count(count),
         })
     }
+
+    /// Sets the score value to query
+    ///
+    /// :param score: float
+    /// :type score: float
+    ///
+    /// **Example**
+    ///
+    /// .. code-block:: python
+    ///
+    ///     value = Value.set_score(10.0)
+    #[staticmethod]
+    fn set_score(score: f64) -> Result<Self, Error> {
+        Ok(Value {
+            inner: auditor_client::Value::Score(score),
+        })
+    }
 }
 
 #[pymethods]
@@ -323,9 +342,7 @@ impl ComponentQuery {
     #[new]
     fn new() -> Self {
         ComponentQuery {
-            inner: auditor_client::ComponentQuery {
-                component_query: HashMap::new(),
-            },
+            inner: auditor_client::ComponentQuery::new(),
         }
     }
 
@@ -355,6 +372,39 @@ impl ComponentQuery {
             .insert(query_id, Some(operator.inner));
         self_
     }
+
+    /// Adds a new operator on a score of the component with the given query ID.
+    ///
+    /// :param query_id: Component name
+    /// :type query_id: string
+    ///
+    /// :param score: Score name
+    /// :type score: string
+    ///
+    /// :param operator: score value
+    /// :type operator: `Operator` object
+    ///
+    /// **Example**
+    ///
+    /// .. code-block:: python
+    ///
+    ///     value = Value.set_score(10.0)
+    ///     score_operator = Operator().gte(value)
+    ///     component_query = ComponentQuery().score_operator("cpu", "HEPSPEC06", score_operator)
+    fn score_operator(
+        mut self_: PyRefMut<Self>,
+        query_id: String,
+        score: String,
+        operator: Operator,
+    ) -> PyRefMut<Self> {
+        self_
+            .inner
+            .score_query
+            .entry(query_id)
+            .or_default()
+            .insert(score, operator.inner);
+        self_
+    }
 }
 
 /// SortBy provides options on sorting the query records
@@ -424,6 +474,7 @@ impl QueryBuilder {
                     component: None,
                     sort_by: None,
                     limit: None,
+                    after: None,
                 },
             },
         })
@@ -588,6 +639,16 @@ impl QueryBuilder {
         self_
     }
 
+    /// Only returns the records following the cursor. Usually, the cursors are followed
+    /// automatically with `AuditorClient.paginate`.
+    ///
+    /// :param cursor: Cursor returned by the Auditor instance for a query with the same sort order
+    /// :type cursor: string
+    fn after(mut self_: PyRefMut<Self>, cursor: String) -> PyRefMut<Self> {
+        self_.inner.query_params.after = Some(cursor);
+        self_
+    }
+
     /// Builds the query string for the given query parameters
     fn build(self_: PyRef<Self>, py: Python) -> Py<PyAny> {
         let query_string: String = self_.inner.clone().build();
@@ -759,6 +820,34 @@ impl AuditorClient {
         })
     }
 
+    /// paginate(query: QueryBuilder, page_size: int)
+    /// Returns an asynchronous iterator over all records matching the query. The records are
+    /// fetched in pages of ``page_size`` records, following the cursors returned by the Auditor
+    /// instance. Any limit set in the query is replaced by ``page_size``.
+    ///
+    /// :param query: QueryBuilder object
+    /// :type query: QueryBuilder
+    ///
+    /// :param page_size: Number of records fetched with a single request
+    /// :type page_size: int
+    ///
+    /// **Example**
+    ///
+    /// .. code-block:: python
+    ///
+    ///     query = QueryBuilder().sort_by(SortBy().ascending("start_time"))
+    ///     async for record in client.paginate(query, 1000):
+    ///         print(record.record_id)
+    fn paginate(self_: PyRef<'_, Self>, query: QueryBuilder, page_size: u64) -> RecordPager {
+        RecordPager {
+            state: Arc::new(Mutex::new(RecordPagerState {
+                client: self_.inner.clone(),
+                query: Some(query.inner.limit(page_size)),
+                records: VecDeque::new(),
+            })),
+        }
+    }
+
     /// get_one_record(record_id: string)
     /// Get one record using record_id
     ///
@@ -830,3 +919,46 @@ impl AuditorClient {
     }
 }
 // Ok(Python::with_gil(|py| py.None()))
+
+/// Asynchronous iterator over the records of a query, which are fetched page by page.
+/// It is returned by `AuditorClient.paginate`.
+#[pyclass]
+pub struct RecordPager {
+    state: Arc<Mutex<RecordPagerState>>,
+}
+
+struct RecordPagerState {
+    client: auditor_client::AuditorClient,
+    /// Query of the next page, `None` after the last page has been fetched.
+    query: Option<auditor_client::QueryBuilder>,
+    records: VecDeque<auditor::domain::Record>,
+}
+
+#[pymethods]
+impl RecordPager {
+    fn __aiter__(self_: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        self_
+    }
+
+    fn __anext__<'a>(self_: PyRef<'a, Self>, py: Python<'a>) -> PyResult<Bound<'a, PyAny>> {
+        let state = self_.state.clone();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let mut state = state.lock().await;
+            loop {
+                if let Some(record) = state.records.pop_front() {
+                    return Ok(Record::from(record));
+                }
+                let Some(query) = state.query.take() else {
+                    return Err(pyo3::exceptions::PyStopAsyncIteration::new_err(()));
+                };
+                let page = state
+                    .client
+                    .get_page(query.build())
+                    .await
+                    .map_err(|e| pyo3::exceptions::PyRuntimeError::new_err(format!("{e}")))?;
+                state.query = page.next.map(|cursor| query.after(cursor));
+                state.records.extend(page.records);
+            }
+        })
+    }
+}
@@ -27,6 +27,7 @@ fn pyauditor(_py: Python, m: &Bound<'_, PyModule>) -> PyResult<()> {
     m.add_class::<crate::client::MetaOperator>()?;
     m.add_class::<crate::client::ComponentQuery>()?;
     m.add_class::<crate::client::SortBy>()?;
+    m.add_class::<crate::client::RecordPager>()?;
     m.add_class::<crate::blocking_client::AuditorClientBlocking>()?;
     m.add_class::<crate::queued_client::QueuedAuditorClient>()?;
     m.add_class::<crate::domain::Record>()?;